@@ -0,0 +1,28 @@
+//! Looks for the tools the backend tests run compiled programs with. Tests
+//! that need one that is missing are ignored instead of passing without
+//! checking anything.
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=PATH");
+    println!("cargo:rustc-check-cfg=cfg(has_cc, has_x86_64_cc, has_node)");
+
+    let cc = found("cc");
+    if cc {
+        println!("cargo:rustc-cfg=has_cc");
+    }
+    let x86_64_linux = std::env::var("CARGO_CFG_TARGET_ARCH").as_deref() == Ok("x86_64")
+        && std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("linux");
+    if cc && x86_64_linux {
+        println!("cargo:rustc-cfg=has_x86_64_cc");
+    }
+    if found("node") {
+        println!("cargo:rustc-cfg=has_node");
+    }
+}
+
+fn found(program: &str) -> bool {
+    Command::new(program).arg("--version").output().is_ok()
+}
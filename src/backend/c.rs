@@ -0,0 +1,393 @@
+//! C11 code generation from the IR. The output starts with the runtime in
+//! `runtime.c` and builds on its own with `cc program.c -lm`. Registers
+//! hold tagged values and own a reference to what they point to.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::ir::ir::*;
+
+const RUNTIME: &str = include_str!("runtime.c");
+
+pub fn generate(module: &Module) -> String {
+    let mut generator = Generator {
+        functions: index(module.functions.iter().map(|function| &function.name)),
+        classes: index(module.classes.iter().map(|class| &class.name)),
+        globals: index(module.globals.iter().map(|global| &global.name)),
+        strings: vec![],
+    };
+
+    let mut bodies = String::new();
+    for (index, function) in module.functions.iter().enumerate() {
+        generator.function(&mut bodies, index, function);
+    }
+
+    let mut out = String::from(RUNTIME);
+    out.push_str("\n/* The program. */\n\n");
+    for (index, function) in module.functions.iter().enumerate() {
+        writeln!(
+            out,
+            "static mc_value mc_f{}(int argc, const mc_value *argv); /* {} */",
+            index, function.name
+        )
+        .unwrap();
+    }
+    let functions = module
+        .functions
+        .iter()
+        .enumerate()
+        .map(|(index, function)| {
+            format!(
+                "    {{{}, {}, mc_f{}}},",
+                c_string(&function.name),
+                function.params.len(),
+                index
+            )
+        })
+        .collect::<Vec<String>>();
+    writeln!(
+        out,
+        "static const mc_function mc_functions[] = {{\n{}\n}};",
+        functions.join("\n")
+    )
+    .unwrap();
+
+    if !module.classes.is_empty() {
+        out.push('\n');
+        let mut classes = vec![];
+        for (index, class) in module.classes.iter().enumerate() {
+            let methods = match class.methods.is_empty() {
+                true => String::from("NULL"),
+                false => {
+                    let methods = class
+                        .methods
+                        .iter()
+                        .map(|(name, function)| {
+                            format!(
+                                "{{{}, &mc_functions[{}]}}",
+                                c_string(name),
+                                generator.functions[function]
+                            )
+                        })
+                        .collect::<Vec<String>>();
+                    writeln!(
+                        out,
+                        "static const mc_method mc_methods{}[] = {{{}}};",
+                        index,
+                        methods.join(", ")
+                    )
+                    .unwrap();
+                    format!("mc_methods{}", index)
+                }
+            };
+            let super_class = match &class.super_class {
+                Some(super_class) => format!("&mc_classes[{}]", generator.classes[super_class]),
+                None => String::from("NULL"),
+            };
+            classes.push(format!(
+                "    {{{}, {}, {}, {}}},",
+                c_string(&class.name),
+                super_class,
+                methods,
+                class.methods.len()
+            ));
+        }
+        writeln!(
+            out,
+            "static const mc_class mc_classes[] = {{\n{}\n}};",
+            classes.join("\n")
+        )
+        .unwrap();
+    }
+
+    if !module.globals.is_empty() {
+        out.push('\n');
+        for (index, global) in module.globals.iter().enumerate() {
+            writeln!(out, "/* mc_globals[{}] is {} */", index, global.name).unwrap();
+        }
+        writeln!(out, "static mc_value mc_globals[{}];", module.globals.len()).unwrap();
+    }
+
+    if !generator.strings.is_empty() {
+        out.push('\n');
+        for (index, string) in generator.strings.iter().enumerate() {
+            writeln!(
+                out,
+                "static mc_string mc_s{} = {{{{-1}}, {}, {}}};",
+                index,
+                string.len(),
+                c_string(string)
+            )
+            .unwrap();
+        }
+    }
+
+    out.push_str(&bodies);
+
+    out.push_str("\nint main(void) {\n");
+    if !module.globals.is_empty() {
+        writeln!(
+            out,
+            "    for (size_t i = 0; i < {}; i++) {{\n        mc_globals[i].tag = MC_UNDEFINED;\n    }}",
+            module.globals.len()
+        )
+        .unwrap();
+    }
+    if !module.classes.is_empty() {
+        out.push_str("    (void)mc_classes;\n");
+    }
+    out.push_str("    mc_release(mc_call(&mc_functions[0], 0, NULL));\n    return 0;\n}\n");
+    out
+}
+
+fn index<'a>(names: impl Iterator<Item = &'a String>) -> HashMap<String, usize> {
+    names
+        .enumerate()
+        .map(|(index, name)| (name.clone(), index))
+        .collect()
+}
+
+struct Generator {
+    functions: HashMap<String, usize>,
+    classes: HashMap<String, usize>,
+    globals: HashMap<String, usize>,
+    /// String constants, written out as `mc_s0`, `mc_s1`, ...
+    strings: Vec<String>,
+}
+
+impl Generator {
+    fn function(&mut self, out: &mut String, index: usize, function: &Function) {
+        let count = function.registers.len().max(1);
+        writeln!(out, "\n/* {} */", function.name).unwrap();
+        writeln!(
+            out,
+            "static mc_value mc_f{}(int argc, const mc_value *argv) {{",
+            index
+        )
+        .unwrap();
+        // A `longjmp` back to a handler can lose what was written to local
+        // variables since the `setjmp`, but not what is on the heap.
+        let catches = function.catches();
+        if catches {
+            writeln!(out, "    mc_value *r = mc_alloc(sizeof(mc_value) * {});", count).unwrap();
+        } else {
+            writeln!(out, "    mc_value r[{}];\n    memset(r, 0, sizeof r);", count).unwrap();
+        }
+        let handlers = function
+            .blocks
+            .iter()
+            .filter(|block| matches!(block.terminator, Terminator::Try { .. }))
+            .count();
+        if handlers > 0 {
+            writeln!(out, "    mc_handler handlers[{}];", handlers).unwrap();
+        }
+        out.push_str("    (void)argc;\n    (void)argv;\n");
+        for (position, param) in function.params.iter().enumerate() {
+            writeln!(out, "    r[{}] = mc_retain(argv[{}]);", param.0, position).unwrap();
+        }
+
+        let mut targets = vec![false; function.blocks.len()];
+        for block in &function.blocks {
+            for successor in block.terminator.successors() {
+                targets[successor.0] = true;
+            }
+        }
+
+        let free = if catches { " free(r);" } else { "" };
+        let mut tries = 0;
+        for (block_index, block) in function.blocks.iter().enumerate() {
+            if targets[block_index] {
+                writeln!(out, "{}:", BlockId(block_index)).unwrap();
+            }
+            for instr in &block.instrs {
+                // Where the instruction is, for the trace of a runtime error.
+                let at = match instr.span() {
+                    Some(span) => format!("mc_at({}, {}); ", span.line, span.column),
+                    None => String::new(),
+                };
+                writeln!(out, "    {}{}", at, self.instr(instr)).unwrap();
+            }
+            let terminator = match &block.terminator {
+                Terminator::Jump(target) => format!("goto {};", target),
+                Terminator::Branch {
+                    condition,
+                    then_block,
+                    else_block,
+                } => format!(
+                    "if (mc_truthy(r[{}])) goto {}; else goto {};",
+                    condition.0, then_block, else_block
+                ),
+                Terminator::Return(value) => format!(
+                    "{{ mc_value result = mc_retain(r[{}]); mc_release_all(r, {});{} return result; }}",
+                    value.0, count, free
+                ),
+                Terminator::Try { body, handler } => {
+                    tries += 1;
+                    format!(
+                        "if (setjmp(mc_try(&handlers[{}])->env)) goto {}; else goto {};",
+                        tries - 1,
+                        handler,
+                        body
+                    )
+                }
+                Terminator::Throw { value, span } => format!(
+                    "mc_at({}, {}); mc_throw(mc_retain(r[{}]));",
+                    span.line, span.column, value.0
+                ),
+                Terminator::Rethrow(value) => format!("mc_rethrow(mc_retain(r[{}]));", value.0),
+            };
+            writeln!(out, "    {}", terminator).unwrap();
+        }
+        out.push_str("}\n");
+    }
+
+    fn instr(&mut self, instr: &Instr) -> String {
+        let value = match instr {
+            Instr::Const { value, .. } => self.constant(value),
+            Instr::Copy { src, .. } => format!("mc_retain(r[{}])", src.0),
+            Instr::Unary { op, src, .. } => {
+                let name = match op {
+                    UnaryOp::Neg => "neg",
+                    UnaryOp::Not => "not",
+                    UnaryOp::BitNot => "bitnot",
+                    UnaryOp::ToFloat => "to_float",
+                };
+                format!("mc_{}(r[{}])", name, src.0)
+            }
+            Instr::Binary {
+                op, left, right, ..
+            } => {
+                let name = match op {
+                    BinaryOp::BitAnd => String::from("bitand"),
+                    BinaryOp::BitOr => String::from("bitor"),
+                    BinaryOp::BitXor => String::from("bitxor"),
+                    op => op.to_string(),
+                };
+                format!("mc_{}(r[{}], r[{}])", name, left.0, right.0)
+            }
+            Instr::LoadGlobal { name, .. } => format!(
+                "mc_load_global(mc_globals[{}], {})",
+                self.globals[name],
+                c_string(name)
+            ),
+            Instr::FunctionRef { name, .. } => {
+                format!("mc_function_value(&mc_functions[{}])", self.functions[name])
+            }
+            Instr::Call { function, args, .. } => {
+                format!(
+                    "mc_call(&mc_functions[{}], {})",
+                    self.functions[function],
+                    arguments(args)
+                )
+            }
+            Instr::CallIndirect { callee, args, .. } => {
+                format!("mc_call_value(r[{}], {})", callee.0, arguments(args))
+            }
+            Instr::New { class, args, .. } => {
+                format!("mc_new(&mc_classes[{}], {})", self.classes[class], arguments(args))
+            }
+            Instr::CallMethod {
+                object,
+                method,
+                args,
+                ..
+            } => format!(
+                "mc_call_method(r[{}], {}, {})",
+                object.0,
+                c_string(method),
+                arguments(args)
+            ),
+            Instr::CallSuper {
+                class,
+                method,
+                object,
+                args,
+                ..
+            } => format!(
+                "mc_call_super(&mc_classes[{}], {}, r[{}], {})",
+                self.classes[class],
+                c_string(method),
+                object.0,
+                arguments(args)
+            ),
+            Instr::GetProperty { object, name, .. } => {
+                format!("mc_get(r[{}], {})", object.0, c_string(name))
+            }
+            Instr::StoreGlobal { name, src } => {
+                return format!(
+                    "mc_set(&mc_globals[{}], mc_retain(r[{}]));",
+                    self.globals[name], src.0
+                )
+            }
+            Instr::SetProperty {
+                object, name, src, ..
+            } => {
+                return format!(
+                    "mc_set_property(r[{}], {}, r[{}]);",
+                    object.0,
+                    c_string(name),
+                    src.0
+                )
+            }
+            Instr::Print { src } => return format!("mc_print(r[{}]);", src.0),
+            Instr::EndTry => return String::from("mc_end_try();"),
+            Instr::Caught { .. } => String::from("mc_caught()"),
+            Instr::Phi { .. } => unreachable!("phi outside of SSA form"),
+        };
+
+        format!("mc_set(&r[{}], {});", instr.dest().unwrap().0, value)
+    }
+
+    fn constant(&mut self, value: &Constant) -> String {
+        match value {
+            Constant::Int(i64::MIN) => String::from("mc_int(INT64_MIN)"),
+            Constant::Int(value) => format!("mc_int(INT64_C({}))", value),
+            Constant::Float(value) if value.is_nan() => String::from("mc_float(NAN)"),
+            Constant::Float(value) if value.is_infinite() => {
+                format!("mc_float({}HUGE_VAL)", if *value < 0.0 { "-" } else { "" })
+            }
+            Constant::Float(value) => format!("mc_float({:?})", value),
+            Constant::Str(value) => {
+                let index = match self.strings.iter().position(|string| string == value) {
+                    Some(index) => index,
+                    None => {
+                        self.strings.push(value.clone());
+                        self.strings.len() - 1
+                    }
+                };
+                format!("mc_object_value(MC_STRING, &mc_s{}.header)", index)
+            }
+            Constant::Bool(value) => format!("mc_bool({})", value),
+            Constant::Nil => String::from("mc_nil()"),
+        }
+    }
+}
+
+/// The count and array of arguments for the runtime's call functions.
+fn arguments(args: &[Reg]) -> String {
+    if args.is_empty() {
+        return String::from("0, NULL");
+    }
+    let args = args
+        .iter()
+        .map(|arg| format!("r[{}]", arg.0))
+        .collect::<Vec<String>>();
+    format!("{}, (const mc_value[]){{{}}}", args.len(), args.join(", "))
+}
+
+/// A C string literal for `text`. Bytes outside printable ASCII are written
+/// as octal escapes, which never take more than three digits.
+fn c_string(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for byte in text.bytes() {
+        match byte {
+            b'"' => literal.push_str("\\\""),
+            b'\\' => literal.push_str("\\\\"),
+            b'?' => literal.push_str("\\?"),
+            b' '..=b'~' => literal.push(byte as char),
+            _ => write!(literal, "\\{:03o}", byte).unwrap(),
+        }
+    }
+    literal.push('"');
+    literal
+}
@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+use crate::lexer::Span;
+
+/// Something in the IR a backend cannot translate, located where the IR
+/// keeps a span for it.
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct BackendError {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl BackendError {
+    pub fn new(message: impl Into<String>) -> BackendError {
+        BackendError {
+            message: message.into(),
+            span: None,
+        }
+    }
+}
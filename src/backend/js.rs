@@ -0,0 +1,1419 @@
+//! Translation of the AST to readable ES2020, with a version 3 source map
+//! back to the original program. Names are kept, so code written in this
+//! language can be called from JavaScript. Values follow the IR: integers are
+//! `BigInt`s and floats are numbers, and operators become calls to helpers
+//! that do what `runtime.c` does, written after the program when it uses
+//! them. `nil` becomes `null`.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+use serde_json::json;
+
+use super::error::BackendError;
+use crate::formatter::formatter::float_literal;
+use crate::lexer::{Span, Token, TokenType};
+use crate::parser::expr::*;
+use crate::parser::stmt::Stmt;
+use crate::parser::visitor::{walk_assign, walk_call, walk_class, walk_return, Visitor};
+
+const INDENT: &str = "  ";
+
+/// Words JavaScript reserves that are plain identifiers in this language,
+/// and the globals the translation and its helpers use, which a variable of
+/// the program would hide. They get a `$` appended, which no name in this
+/// language can contain.
+const RESERVED: [&str; 43] = [
+    "arguments",
+    "await",
+    "BigInt",
+    "break",
+    "case",
+    "console",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "Error",
+    "eval",
+    "export",
+    "function",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "Math",
+    "new",
+    "null",
+    "Number",
+    "Object",
+    "package",
+    "private",
+    "process",
+    "protected",
+    "public",
+    "static",
+    "String",
+    "switch",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "with",
+    "yield",
+];
+
+/// Functions the translation calls, in the order they are written out, with
+/// the helpers each one calls. They check and report what the functions of
+/// the same name in `runtime.c` do, with the same messages.
+const HELPERS: [(&str, &[&str], &str); 32] = [
+    ("$add", &["$isNumber", "$checked", "$error"], ADD),
+    ("$sub", &["$numbers", "$checked"], SUB),
+    ("$mul", &["$numbers", "$checked"], MUL),
+    ("$div", &["$numbers", "$checked", "$error"], DIV),
+    ("$rem", &["$numbers", "$error"], REM),
+    ("$pow", &["$numbers", "$checked", "$error"], POW),
+    ("$shl", &["$integers", "$error"], SHL),
+    ("$shr", &["$integers", "$error"], SHR),
+    ("$bitand", &["$integers"], BITAND),
+    ("$bitor", &["$integers"], BITOR),
+    ("$bitxor", &["$integers"], BITXOR),
+    ("$bitnot", &["$type", "$error"], BITNOT),
+    ("$neg", &["$checked", "$type", "$error"], NEG),
+    ("$eq", &["$isNumber"], EQ),
+    ("$ne", &["$eq"], NE),
+    ("$lt", &["$compare"], LT),
+    ("$le", &["$compare"], LE),
+    ("$gt", &["$compare"], GT),
+    ("$ge", &["$compare"], GE),
+    ("$compare", &["$numbers"], COMPARE),
+    ("$truthy", &[], TRUTHY),
+    ("$show", &["$float"], SHOW),
+    ("$float", &[], FLOAT),
+    ("$toFloat", &[], TO_FLOAT),
+    ("$type", &[], TYPE),
+    ("$isNumber", &[], IS_NUMBER),
+    ("$numbers", &["$isNumber", "$error"], NUMBERS),
+    ("$integers", &["$error"], INTEGERS),
+    ("$checked", &["$error"], CHECKED),
+    ("$error", &["$throw"], ERROR),
+    ("$throw", &["$uncaught"], THROW),
+    ("$uncaught", &["$show"], UNCAUGHT),
+];
+
+// The code of `HELPERS`. Integers are exact as `BigInt`s, so an operation
+// overflows when its result does not fit in 64 bits.
+
+const ADD: &str = r#"function $add(a, b) {
+  if (typeof a === "string" && typeof b === "string") {
+    return a + b;
+  }
+  if (typeof a === "bigint" && typeof b === "bigint") {
+    return $checked(a + b, "+");
+  }
+  if (!$isNumber(a) || !$isNumber(b)) {
+    $error("Operands of '+' must be two numbers or two strings");
+  }
+  return Number(a) + Number(b);
+}"#;
+
+const SUB: &str = r#"function $sub(a, b) {
+  $numbers(a, b, "-");
+  if (typeof a === "bigint" && typeof b === "bigint") {
+    return $checked(a - b, "-");
+  }
+  return Number(a) - Number(b);
+}"#;
+
+const MUL: &str = r#"function $mul(a, b) {
+  $numbers(a, b, "*");
+  if (typeof a === "bigint" && typeof b === "bigint") {
+    return $checked(a * b, "*");
+  }
+  return Number(a) * Number(b);
+}"#;
+
+const DIV: &str = r#"function $div(a, b) {
+  $numbers(a, b, "/");
+  if (typeof a === "bigint" && typeof b === "bigint") {
+    if (b === 0n) {
+      $error("Division by zero");
+    }
+    return $checked(a / b, "/");
+  }
+  return Number(a) / Number(b);
+}"#;
+
+const REM: &str = r#"function $rem(a, b) {
+  $numbers(a, b, "%");
+  if (typeof a === "bigint" && typeof b === "bigint") {
+    if (b === 0n) {
+      $error("Division by zero");
+    }
+    if (b === -1n && a === -(2n ** 63n)) {
+      $error("Integer overflow in '%'");
+    }
+    return a % b;
+  }
+  return Number(a) % Number(b);
+}"#;
+
+const POW: &str = r#"function $pow(a, b) {
+  $numbers(a, b, "**");
+  if (typeof a === "bigint" && typeof b === "bigint") {
+    if (b < 0n) {
+      $error("Negative exponent in '**'");
+    }
+    if (b > 63n && (a < -1n || a > 1n)) {
+      $error("Integer overflow in '**'");
+    }
+    return $checked(a ** b, "**");
+  }
+  return Number(a) ** Number(b);
+}"#;
+
+const SHL: &str = r#"function $shl(a, b) {
+  $integers(a, b, "<<");
+  if (b < 0n || b > 63n) {
+    $error("Integer overflow in '<<'");
+  }
+  return BigInt.asIntN(64, a << b);
+}"#;
+
+const SHR: &str = r#"function $shr(a, b) {
+  $integers(a, b, ">>");
+  if (b < 0n || b > 63n) {
+    $error("Integer overflow in '>>'");
+  }
+  return a >> b;
+}"#;
+
+const BITAND: &str = r#"function $bitand(a, b) {
+  $integers(a, b, "&");
+  return a & b;
+}"#;
+
+const BITOR: &str = r#"function $bitor(a, b) {
+  $integers(a, b, "|");
+  return a | b;
+}"#;
+
+const BITXOR: &str = r#"function $bitxor(a, b) {
+  $integers(a, b, "^");
+  return a ^ b;
+}"#;
+
+const BITNOT: &str = r#"function $bitnot(value) {
+  if (typeof value !== "bigint") {
+    $error(`Operand of '~' must be an integer, found ${$type(value)}`);
+  }
+  return ~value;
+}"#;
+
+const NEG: &str = r#"function $neg(value) {
+  if (typeof value === "bigint") {
+    return $checked(-value, "-");
+  }
+  if (typeof value !== "number") {
+    $error(`Operand of '-' must be a number, found ${$type(value)}`);
+  }
+  return -value;
+}"#;
+
+const EQ: &str = r#"function $eq(a, b) {
+  if ($isNumber(a) && $isNumber(b) && typeof a !== typeof b) {
+    return Number(a) === Number(b);
+  }
+  return a === b;
+}"#;
+
+const NE: &str = r#"function $ne(a, b) {
+  return !$eq(a, b);
+}"#;
+
+const LT: &str = r#"function $lt(a, b) {
+  return $compare(a, b, "<") < 0;
+}"#;
+
+const LE: &str = r#"function $le(a, b) {
+  return $compare(a, b, "<=") <= 0;
+}"#;
+
+const GT: &str = r#"function $gt(a, b) {
+  return $compare(a, b, ">") > 0;
+}"#;
+
+const GE: &str = r#"function $ge(a, b) {
+  return $compare(a, b, ">=") >= 0;
+}"#;
+
+const COMPARE: &str = r#"function $compare(a, b, op) {
+  if (typeof a === "string" && typeof b === "string") {
+    return a < b ? -1 : a > b ? 1 : 0;
+  }
+  $numbers(a, b, op);
+  if (typeof a !== typeof b) {
+    a = Number(a);
+    b = Number(b);
+  }
+  return (a > b) - (a < b);
+}"#;
+
+const TRUTHY: &str = r#"function $truthy(value) {
+  return value !== null && value !== undefined && value !== false;
+}"#;
+
+const SHOW: &str = r#"function $show(value) {
+  if (value === null || value === undefined) {
+    return "nil";
+  }
+  switch (typeof value) {
+    case "number":
+      return $float(value);
+    case "function":
+      return `<fn ${value.name}>`;
+    case "object":
+      return `${value.constructor.name} instance`;
+    default:
+      return String(value);
+  }
+}"#;
+
+// `%.15g`, or `%.17g` when that reads back as another number, and `.0`
+// after whole numbers, as `mc_print_float` writes floats.
+const FLOAT: &str = r#"function $float(value) {
+  if (Number.isNaN(value)) {
+    return "nan";
+  }
+  if (!Number.isFinite(value)) {
+    return value < 0 ? "-inf" : "inf";
+  }
+  const trim = (digits) => (digits.includes(".") ? digits.replace(/\.?0+$/, "") : digits);
+  const g = (precision) => {
+    const [mantissa, exponent] = Math.abs(value).toExponential(precision - 1).split("e");
+    const power = Number(exponent);
+    const text =
+      power < -4 || power >= precision
+        ? `${trim(mantissa)}e${power < 0 ? "-" : "+"}${String(Math.abs(power)).padStart(2, "0")}`
+        : trim(Math.abs(value).toFixed(precision - 1 - power));
+    return (value < 0 || Object.is(value, -0) ? "-" : "") + text;
+  };
+  let text = g(15);
+  if (Number(text) !== value) {
+    text = g(17);
+  }
+  return /^-?[0-9]+$/.test(text) ? `${text}.0` : text;
+}"#;
+
+const TO_FLOAT: &str = r#"function $toFloat(value) {
+  return typeof value === "bigint" ? Number(value) : value;
+}"#;
+
+const TYPE: &str = r#"function $type(value) {
+  if (value === null || value === undefined) {
+    return "nil";
+  }
+  switch (typeof value) {
+    case "bigint":
+      return "int";
+    case "number":
+      return "float";
+    case "boolean":
+      return "bool";
+    case "object":
+      return value.constructor.name;
+    default:
+      return typeof value;
+  }
+}"#;
+
+const IS_NUMBER: &str = r#"function $isNumber(value) {
+  return typeof value === "bigint" || typeof value === "number";
+}"#;
+
+const NUMBERS: &str = r#"function $numbers(a, b, op) {
+  if (!$isNumber(a) || !$isNumber(b)) {
+    $error(`Operands of '${op}' must be numbers`);
+  }
+}"#;
+
+const INTEGERS: &str = r#"function $integers(a, b, op) {
+  if (typeof a !== "bigint" || typeof b !== "bigint") {
+    $error(`Operands of '${op}' must be integers`);
+  }
+}"#;
+
+const CHECKED: &str = r#"function $checked(value, op) {
+  if (BigInt.asIntN(64, value) !== value) {
+    $error(`Integer overflow in '${op}'`);
+  }
+  return value;
+}"#;
+
+const ERROR: &str = r#"function $error(message) {
+  $throw(message);
+}"#;
+
+// A value nothing catches ends the program the way it does in C.
+const THROW: &str = r#"function $throw(value) {
+  if (!process.listeners("uncaughtException").includes($uncaught)) {
+    process.on("uncaughtException", $uncaught);
+  }
+  throw value;
+}"#;
+
+const UNCAUGHT: &str = r#"function $uncaught(value) {
+  if (value instanceof Error) {
+    console.error(value);
+    process.exit(1);
+  }
+  console.error(`Runtime error: ${$show(value)}`);
+  process.exit(70);
+}"#;
+
+/// A translated program.
+pub struct JavaScript {
+    pub code: String,
+    /// The source map, as JSON.
+    pub source_map: String,
+}
+
+/// Translates `statements`. `file` is the name the code is written to, and
+/// `source` is the name of the program relative to the source map, which is
+/// expected next to the code as `<file>.map`. Fails on calls to `super.init`
+/// that cannot become a call to `super()`.
+pub fn generate(statements: &[Stmt], source: &str, file: &str) -> Result<JavaScript, BackendError> {
+    let mut calls = SuperInitCalls(Ok(()));
+    calls.visit_block(statements);
+    calls.0?;
+
+    let mut classes = Classes(vec![]);
+    classes.visit_block(statements);
+
+    let mut generator = Generator {
+        classes: classes.0,
+        indent: 0,
+        out: String::new(),
+        line: 0,
+        column: 0,
+        mappings: vec![],
+        constructor: false,
+        scopes: vec![HashMap::new()],
+        float_return: false,
+        renames: 0,
+        helpers: BTreeSet::new(),
+    };
+    for stmt in statements {
+        generator.statement(stmt);
+    }
+    let mut code = generator.out;
+    for (name, _, helper) in HELPERS {
+        if generator.helpers.contains(name) {
+            writeln!(code, "\n{}", helper).unwrap();
+        }
+    }
+    writeln!(code, "//# sourceMappingURL={}.map", file).unwrap();
+
+    let source_map = json!({
+        "version": 3,
+        "file": file,
+        "sources": [source],
+        "names": [],
+        "mappings": mappings(&generator.mappings),
+    });
+    Ok(JavaScript {
+        code,
+        source_map: source_map.to_string(),
+    })
+}
+
+/// A position in the generated code and the one in the source it came from,
+/// all counted from 0.
+struct Mapping {
+    line: usize,
+    column: usize,
+    source_line: usize,
+    source_column: usize,
+}
+
+/// A name declared in a scope.
+struct Name {
+    spelled: String,
+    /// Whether it is declared `float`, so ints stored in it are converted.
+    float: bool,
+}
+
+struct Generator {
+    /// Names of every class in the program, which are called with `new`.
+    classes: Vec<String>,
+    indent: usize,
+    out: String,
+    line: usize,
+    column: usize,
+    mappings: Vec<Mapping>,
+    /// Whether `init` of a class is being translated, where `super.init()`
+    /// becomes `super()`.
+    constructor: bool,
+    /// The names declared in each open scope, innermost last, with what
+    /// they are called in JavaScript.
+    scopes: Vec<HashMap<String, Name>>,
+    /// Whether the function being translated is declared to return `float`.
+    float_return: bool,
+    /// How many variables have been renamed, which numbers the next one.
+    renames: usize,
+    /// The entries of `HELPERS` the code calls.
+    helpers: BTreeSet<&'static str>,
+}
+
+impl Generator {
+    fn statement(&mut self, stmt: &Stmt) {
+        self.start_line();
+        if !matches!(stmt, Stmt::Block { .. }) {
+            self.mark(stmt.start());
+        }
+
+        match stmt {
+            Stmt::Block { statements } => {
+                self.push("{");
+                self.block(&[], &[], statements);
+            }
+            Stmt::Expression(expr) => {
+                self.effect(expr);
+                self.push(";\n");
+            }
+            Stmt::Print(expr) => {
+                self.push("console.log(");
+                self.call("$show");
+                self.expr(expr);
+                self.push("));\n");
+            }
+            Stmt::Let {
+                token,
+                annotation,
+                initilizer,
+            } => {
+                self.binding(token, annotation, initilizer);
+                self.push(";\n");
+            }
+            Stmt::If { condition, branch } => {
+                self.push("if (");
+                self.condition(condition, 0);
+                self.push(")");
+                self.body(branch);
+            }
+            Stmt::While { condition, body } => {
+                self.push("while (");
+                self.condition(condition, 0);
+                self.push(")");
+                self.body(body);
+            }
+            Stmt::For {
+                initilizer,
+                condition,
+                increment,
+                body,
+            } => {
+                self.scopes.push(HashMap::new());
+                self.push("for (");
+                match &**initilizer {
+                    Stmt::Let {
+                        token,
+                        annotation,
+                        initilizer,
+                    } => self.binding(token, annotation, initilizer),
+                    Stmt::Expression(expr) => self.effect(expr),
+                    _ => unreachable!("for initializers are let or expression statements"),
+                }
+                self.push("; ");
+                self.condition(condition, 0);
+                self.push("; ");
+                self.effect(increment);
+                self.push(")");
+                self.body(body);
+                self.scopes.pop();
+            }
+            Stmt::Function {
+                name,
+                params,
+                param_types,
+                return_type,
+                body,
+            } => match self.redeclared(name) {
+                // JavaScript cannot declare a name twice in a scope, so a
+                // function declared again is assigned, like a variable.
+                Some(spelled) => {
+                    self.mark(Some(name.span));
+                    self.push(&spelled);
+                    self.push(" = function ");
+                    self.parameters(params);
+                    self.push(" {");
+                    self.function(params, param_types, return_type, body);
+                    self.continue_block();
+                    self.push(";\n");
+                }
+                None => {
+                    self.push("function ");
+                    self.name(name);
+                    self.declare(name, false);
+                    self.parameters(params);
+                    self.push(" {");
+                    self.function(params, param_types, return_type, body);
+                }
+            },
+            Stmt::Return { value, .. } => match value {
+                Some(value) if self.float_return => {
+                    self.push("return ");
+                    self.float(value);
+                    self.push(";\n");
+                }
+                Some(value) => {
+                    self.push("return ");
+                    self.expr(value);
+                    self.push(";\n");
+                }
+                None => self.push("return;\n"),
+            },
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+            } => {
+                let redeclared = self.redeclared(name);
+                match &redeclared {
+                    Some(spelled) => {
+                        self.mark(Some(name.span));
+                        self.push(spelled);
+                        self.push(" = class");
+                    }
+                    None => {
+                        self.push("class ");
+                        self.name(name);
+                        self.declare(name, false);
+                    }
+                }
+                if let Some(super_class) = super_class {
+                    self.push(" extends ");
+                    self.expr(super_class);
+                }
+                self.push(" {\n");
+                self.indent += 1;
+                for method in methods {
+                    self.method(method, super_class.is_some());
+                }
+                self.indent -= 1;
+                self.start_line();
+                self.push(if redeclared.is_some() { "};\n" } else { "}\n" });
+            }
+            Stmt::Throw { value, .. } => {
+                self.call("$throw");
+                self.expr(value);
+                self.push(");\n");
+            }
+            Stmt::Try {
+                body,
+                catch_name,
+                catch_body,
+                finally_body,
+            } => {
+                self.push("try {");
+                self.block(&[], &[], body);
+                if let (Some(name), Some(catch_body)) = (catch_name, catch_body) {
+                    self.continue_block();
+                    self.push(" catch (");
+                    self.name(name);
+                    self.push(") {");
+                    self.block(std::slice::from_ref(name), &[], catch_body);
+                }
+                if let Some(finally_body) = finally_body {
+                    self.continue_block();
+                    self.push(" finally {");
+                    self.block(&[], &[], finally_body);
+                }
+            }
+            Stmt::Comment { token, .. } => {
+                self.push(&token.kind.to_string());
+                self.push("\n");
+            }
+        }
+    }
+
+    /// A method of a class, with `init` turned into the constructor.
+    fn method(&mut self, method: &Stmt, subclass: bool) {
+        let (name, params, param_types, return_type, body) = match method {
+            Stmt::Function {
+                name,
+                params,
+                param_types,
+                return_type,
+                body,
+            } => (name, params, param_types, return_type, body),
+            _ => return self.statement(method),
+        };
+
+        self.start_line();
+        self.mark(Some(name.span));
+        let constructor = is_init(name);
+        if constructor {
+            self.push("constructor");
+        } else {
+            self.name(name);
+        }
+        self.parameters(params);
+        self.push(" {");
+
+        let enclosing = std::mem::replace(&mut self.constructor, constructor);
+        if constructor && subclass {
+            // JavaScript needs `super()` before `this` can be used.
+            let mut calls = SuperInits(vec![]);
+            calls.visit_block(body);
+            if calls.0.is_empty() {
+                self.push("\n");
+                self.indent += 1;
+                self.start_line();
+                self.push("super();");
+                self.indent -= 1;
+            }
+        }
+        self.function(params, param_types, return_type, body);
+        self.constructor = enclosing;
+    }
+
+    /// Bodies of `if`, `while` and `for`, which open their block on the
+    /// same line when they have one.
+    fn body(&mut self, body: &Stmt) {
+        match body {
+            Stmt::Block { statements } => {
+                self.push(" {");
+                self.block(&[], &[], statements);
+            }
+            _ => {
+                self.push("\n");
+                self.indent += 1;
+                self.statement(body);
+                self.indent -= 1;
+            }
+        }
+    }
+
+    /// The body of a function after its `{`, where ints returned from a
+    /// `-> float` function are converted.
+    fn function(
+        &mut self,
+        params: &[Token],
+        param_types: &[Option<Token>],
+        return_type: &Option<Token>,
+        body: &[Stmt],
+    ) {
+        let enclosing = std::mem::replace(&mut self.float_return, is_float(return_type));
+        self.block(params, param_types, body);
+        self.float_return = enclosing;
+    }
+
+    /// The statements of a block after its `{`, and the closing `}`. `names`
+    /// are declared in the block before them, like parameters, with the
+    /// annotations in `types`.
+    fn block(&mut self, names: &[Token], types: &[Option<Token>], statements: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        let mut floats = vec![];
+        for (index, name) in names.iter().enumerate() {
+            let float = types.get(index).is_some_and(is_float);
+            self.declare(name, float);
+            if float {
+                floats.push(name);
+            }
+        }
+        if statements.is_empty() && floats.is_empty() {
+            self.push("}\n");
+        } else {
+            self.push("\n");
+            self.indent += 1;
+            // Callers can pass an int for a `float` parameter.
+            for name in floats {
+                self.start_line();
+                self.variable(name);
+                self.push(" = ");
+                self.call("$toFloat");
+                self.variable(name);
+                self.push(");\n");
+            }
+            for stmt in statements {
+                self.statement(stmt);
+            }
+            self.indent -= 1;
+            self.start_line();
+            self.push("}\n");
+        }
+        self.scopes.pop();
+    }
+
+    /// Goes back to the end of the `}` that was just written, to continue
+    /// its line as in `} catch (e) {`.
+    fn continue_block(&mut self) {
+        self.out.pop();
+        self.line -= 1;
+        let start = self.out.rfind('\n').map_or(0, |index| index + 1);
+        self.column = self.out[start..].encode_utf16().count();
+    }
+
+    fn parameters(&mut self, params: &[Token]) {
+        self.push("(");
+        for (index, param) in params.iter().enumerate() {
+            if index > 0 {
+                self.push(", ");
+            }
+            self.name(param);
+        }
+        self.push(")");
+    }
+
+    /// `let name = initializer`, or an assignment when `name` is already
+    /// declared in the scope, as JavaScript cannot declare it again and the
+    /// IR keeps a single variable.
+    fn binding(&mut self, name: &Token, annotation: &Option<Token>, initializer: &Expr) {
+        let float = is_float(annotation);
+        if let Some(spelled) = self.redeclared(name) {
+            self.mark(Some(name.span));
+            self.push(&spelled);
+            self.push(" = ");
+            self.stored(initializer, float);
+            self.scopes
+                .last_mut()
+                .unwrap()
+                .insert(name.kind.to_string(), Name { spelled, float });
+            return;
+        }
+
+        // `let x = x + 1;` reads the `x` of an enclosing scope, which the
+        // new `x` would hide in JavaScript, so that one gets another name.
+        let text = name.kind.to_string();
+        let mut mentions = Mentions(&text, false);
+        mentions.visit_expr(initializer);
+        let spelled = if mentions.1 {
+            self.renames += 1;
+            format!("{}${}", text, self.renames)
+        } else {
+            spelling(&text)
+        };
+
+        self.push("let ");
+        self.mark(Some(name.span));
+        self.push(&spelled);
+        self.push(" = ");
+        self.stored(initializer, float);
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(text, Name { spelled, float });
+    }
+
+    fn declare(&mut self, name: &Token, float: bool) {
+        let text = name.kind.to_string();
+        let spelled = spelling(&text);
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(text, Name { spelled, float });
+    }
+
+    /// `value` as it is stored in a variable, converted when the variable
+    /// is declared `float`.
+    fn stored(&mut self, value: &Expr, float: bool) {
+        match float {
+            true => self.float(value),
+            false => self.operand(value, ASSIGNMENT),
+        }
+    }
+
+    /// `value`, or the float it is equal to when it is an int.
+    fn float(&mut self, value: &Expr) {
+        self.call("$toFloat");
+        self.expr(value);
+        self.push(")");
+    }
+
+    /// What `name` is called in JavaScript when the innermost scope already
+    /// declares it.
+    fn redeclared(&self, name: &Token) -> Option<String> {
+        self.scopes
+            .last()
+            .unwrap()
+            .get(&name.kind.to_string())
+            .map(|name| name.spelled.clone())
+    }
+
+    /// An expression whose value is not used, so `x++` needs no copy of `x`.
+    fn effect(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Update(update) => self.update(update, false),
+            expr => self.expr(expr),
+        }
+    }
+
+    /// `expr` as a condition, where only `nil` and `false` are false.
+    fn condition(&mut self, expr: &Expr, minimum: u8) {
+        if boolean(expr) {
+            self.operand(expr, minimum);
+        } else {
+            self.call("$truthy");
+            self.expr(expr);
+            self.push(")");
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::BinaryExpr(binary) => {
+                self.mark(Some(binary.op.span));
+                self.call(helper(&binary.op));
+                self.operand(&binary.left, ASSIGNMENT);
+                self.push(", ");
+                self.operand(&binary.right, ASSIGNMENT);
+                self.push(")");
+            }
+            Expr::UnaryExpr(unary) => {
+                self.mark(Some(unary.op.span));
+                match unary.op.kind.to_string().as_str() {
+                    "!" => {
+                        self.push("!");
+                        self.condition(&unary.right, UNARY);
+                    }
+                    // A number written in the source cannot overflow.
+                    "-" if unsigned_literal(&unary.right) => {
+                        self.push("-");
+                        self.expr(&unary.right);
+                    }
+                    op => {
+                        self.call(if op == "-" { "$neg" } else { "$bitnot" });
+                        self.operand(&unary.right, ASSIGNMENT);
+                        self.push(")");
+                    }
+                }
+            }
+            // Parentheses are written where JavaScript needs them.
+            Expr::Grouping(grouping) => self.expr(&grouping.expr),
+            Expr::Conditional(conditional) => {
+                self.condition(&conditional.condition, CONDITIONAL + 1);
+                self.push(" ? ");
+                self.operand(&conditional.then_branch, CONDITIONAL);
+                self.push(" : ");
+                self.operand(&conditional.else_branch, CONDITIONAL);
+            }
+            Expr::Literal(literal) => {
+                let text = match literal {
+                    Literal::Integer(value) => format!("{}n", value),
+                    Literal::FloatingPoint(value) => float_literal(*value),
+                    Literal::String(value) => serde_json::to_string(value).unwrap(),
+                    Literal::Boolean(value) => value.to_string(),
+                    Literal::Terminal(terminal) => match terminal.value.downcast_ref::<&str>() {
+                        Some(&"nil") | None => String::from("null"),
+                        Some(value) => value.to_string(),
+                    },
+                };
+                self.push(&text);
+            }
+            Expr::Variable(token) => self.variable(token),
+            Expr::Assign(assign) => {
+                self.variable(&assign.name);
+                self.push(" = ");
+                let float = self.lookup(&assign.name).is_some_and(|name| name.float);
+                self.stored(&assign.value, float);
+            }
+            Expr::Update(update) => self.update(update, true),
+            Expr::Call(call) => {
+                match &*call.callee {
+                    Expr::Super(callee) if self.constructor && is_init(&callee.method) => {
+                        self.mark(Some(callee.keyword.span));
+                        self.push("super");
+                    }
+                    Expr::Variable(name) if self.classes.contains(&name.kind.to_string()) => {
+                        self.push("new ");
+                        self.variable(name);
+                    }
+                    callee => self.operand(callee, CALL),
+                }
+                self.mark(Some(call.paren.span));
+                self.push("(");
+                for (index, arg) in call.args.iter().enumerate() {
+                    if index > 0 {
+                        self.push(", ");
+                    }
+                    self.operand(arg, ASSIGNMENT);
+                }
+                self.push(")");
+            }
+            Expr::Lambda(lambda) => {
+                // Arrow functions keep the `this` of where they are written,
+                // the way functions in this language do.
+                self.mark(Some(lambda.keyword.span));
+                self.parameters(&lambda.params);
+                self.push(" => {");
+                self.function(
+                    &lambda.params,
+                    &lambda.param_types,
+                    &lambda.return_type,
+                    &lambda.body,
+                );
+                // `block` ends the line after the `}`, which the expression
+                // has to continue.
+                self.continue_block();
+            }
+            Expr::Get(get) => {
+                self.operand(&get.object, CALL);
+                self.push(".");
+                self.name(&get.name);
+            }
+            Expr::Set(set) => {
+                self.operand(&set.object, CALL);
+                self.push(".");
+                self.name(&set.name);
+                self.push(" = ");
+                self.operand(&set.value, ASSIGNMENT);
+            }
+            Expr::This(this) => {
+                self.mark(Some(this.keyword.span));
+                self.push("this");
+            }
+            Expr::Super(super_expr) => {
+                self.mark(Some(super_expr.keyword.span));
+                self.push("super.");
+                self.name(&super_expr.method);
+            }
+        }
+    }
+
+    /// `x += 1` as `x = $add(x, 1n)`. An object more than a name is
+    /// evaluated once, as the argument of an arrow function, and the old
+    /// value of `x++` is kept in a parameter of one when it is `used`.
+    fn update(&mut self, update: &UpdateExpr, used: bool) {
+        let object = bound_object(update);
+        let old = used && update.kind == UpdateKind::Postfix;
+        let arrow = object.is_some() || old;
+
+        if arrow {
+            self.push("((");
+            if object.is_some() {
+                self.push("$object");
+                if old {
+                    self.push(", ");
+                }
+            }
+            if old {
+                self.push("$old = ");
+                self.target(update);
+            }
+            self.push(") => ");
+            if old {
+                self.push("(");
+            }
+        }
+        self.target(update);
+        self.push(" = ");
+        self.mark(Some(update.op.span));
+        self.call(helper(&update.op));
+        if old {
+            self.push("$old");
+        } else {
+            self.target(update);
+        }
+        self.push(", ");
+        self.operand(&update.change, ASSIGNMENT);
+        self.push(")");
+        if arrow {
+            if old {
+                self.push(", $old)");
+            }
+            self.push(")(");
+            if let Some(object) = object {
+                self.operand(object, ASSIGNMENT);
+            }
+            self.push(")");
+        }
+    }
+
+    /// The variable or property an update changes.
+    fn target(&mut self, update: &UpdateExpr) {
+        match &*update.target {
+            Expr::Variable(token) => self.variable(token),
+            Expr::Get(get) => {
+                match bound_object(update) {
+                    Some(_) => self.push("$object"),
+                    None => self.operand(&get.object, CALL),
+                }
+                self.push(".");
+                self.name(&get.name);
+            }
+            _ => unreachable!("the parser only makes updates of variables and properties"),
+        }
+    }
+
+    /// `expr`, in parentheses when it binds less tightly than `minimum`.
+    fn operand(&mut self, expr: &Expr, minimum: u8) {
+        if expr_precedence(expr) < minimum {
+            self.push("(");
+            self.expr(expr);
+            self.push(")");
+        } else {
+            self.expr(expr);
+        }
+    }
+
+    /// Writes `helper(`, and the helper after the program.
+    fn call(&mut self, helper: &'static str) {
+        self.require(helper);
+        self.push(helper);
+        self.push("(");
+    }
+
+    fn require(&mut self, helper: &'static str) {
+        if self.helpers.insert(helper) {
+            let (_, calls, _) = HELPERS.iter().find(|(name, ..)| *name == helper).unwrap();
+            for call in calls.iter() {
+                self.require(call);
+            }
+        }
+    }
+
+    fn name(&mut self, token: &Token) {
+        self.mark(Some(token.span));
+        self.push(&spelling(&token.kind.to_string()));
+    }
+
+    /// A use of a variable, by the name of its innermost declaration.
+    fn variable(&mut self, token: &Token) {
+        let spelled = match self.lookup(token) {
+            Some(name) => name.spelled.clone(),
+            None => spelling(&token.kind.to_string()),
+        };
+        self.mark(Some(token.span));
+        self.push(&spelled);
+    }
+
+    /// The innermost declaration of `token`.
+    fn lookup(&self, token: &Token) -> Option<&Name> {
+        let name = token.kind.to_string();
+        self.scopes.iter().rev().find_map(|scope| scope.get(&name))
+    }
+
+    fn start_line(&mut self) {
+        self.push(&INDENT.repeat(self.indent));
+    }
+
+    /// Maps the current position in the code to `span`.
+    fn mark(&mut self, span: Option<Span>) {
+        if let Some(span) = span {
+            self.mappings.push(Mapping {
+                line: self.line,
+                column: self.column,
+                source_line: span.line - 1,
+                source_column: span.column - 1,
+            });
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        for c in text.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 0;
+            } else {
+                // Source map columns count UTF-16 code units.
+                self.column += c.len_utf16();
+            }
+        }
+        self.out.push_str(text);
+    }
+}
+
+const ASSIGNMENT: u8 = 2;
+const CONDITIONAL: u8 = 3;
+const UNARY: u8 = 15;
+const CALL: u8 = 17;
+const PRIMARY: u8 = 18;
+
+/// How tightly the translation of `expr` binds, as in the MDN table.
+fn expr_precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::UnaryExpr(unary) => match unary.op.kind.to_string().as_str() {
+            "!" => UNARY,
+            "-" if unsigned_literal(&unary.right) => UNARY,
+            _ => CALL,
+        },
+        Expr::Grouping(grouping) => expr_precedence(&grouping.expr),
+        Expr::Conditional(_) => CONDITIONAL,
+        Expr::Assign(_) | Expr::Set(_) | Expr::Lambda(_) => ASSIGNMENT,
+        Expr::Update(update) => {
+            if update.kind == UpdateKind::Postfix || bound_object(update).is_some() {
+                CALL
+            } else {
+                ASSIGNMENT
+            }
+        }
+        Expr::BinaryExpr(_) | Expr::Call(_) | Expr::Get(_) => CALL,
+        Expr::Literal(_) | Expr::Variable(_) | Expr::This(_) | Expr::Super(_) => PRIMARY,
+    }
+}
+
+/// The entry of `HELPERS` a binary operator, or an update with it, calls.
+fn helper(op: &Token) -> &'static str {
+    match op.kind.to_string().as_str() {
+        "+" => "$add",
+        "-" => "$sub",
+        "*" => "$mul",
+        "/" => "$div",
+        "%" => "$rem",
+        "**" => "$pow",
+        "<<" => "$shl",
+        ">>" => "$shr",
+        "&" => "$bitand",
+        "|" => "$bitor",
+        "^" => "$bitxor",
+        "==" => "$eq",
+        "!=" => "$ne",
+        "<" => "$lt",
+        "<=" => "$le",
+        ">" => "$gt",
+        ">=" => "$ge",
+        op => unreachable!("'{}' is not a binary operator", op),
+    }
+}
+
+/// The object of a property an update changes, when it is more than a name
+/// and so cannot be evaluated twice.
+fn bound_object(update: &UpdateExpr) -> Option<&Expr> {
+    match &*update.target {
+        Expr::Get(get) if !matches!(*get.object, Expr::Variable(_) | Expr::This(_)) => {
+            Some(&get.object)
+        }
+        _ => None,
+    }
+}
+
+/// Whether `expr` is a JavaScript boolean, which conditions can use as is.
+fn boolean(expr: &Expr) -> bool {
+    match expr {
+        Expr::BinaryExpr(binary) => matches!(
+            binary.op.kind.to_string().as_str(),
+            "==" | "!=" | "<" | "<=" | ">" | ">="
+        ),
+        Expr::UnaryExpr(unary) => unary.op.kind.to_string() == "!",
+        Expr::Grouping(grouping) => boolean(&grouping.expr),
+        Expr::Literal(Literal::Boolean(_)) => true,
+        Expr::Literal(Literal::Terminal(terminal)) => matches!(
+            terminal.value.downcast_ref::<&str>(),
+            Some(&"true") | Some(&"false")
+        ),
+        _ => false,
+    }
+}
+
+fn unsigned_literal(expr: &Expr) -> bool {
+    match expr {
+        Expr::Grouping(grouping) => unsigned_literal(&grouping.expr),
+        Expr::Literal(Literal::Integer(value)) => *value >= 0,
+        Expr::Literal(Literal::FloatingPoint(value)) => !value.is_sign_negative(),
+        _ => false,
+    }
+}
+
+fn spelling(name: &str) -> String {
+    match RESERVED.contains(&name) {
+        true => format!("{}$", name),
+        false => name.to_string(),
+    }
+}
+
+fn is_float(annotation: &Option<Token>) -> bool {
+    annotation.as_ref().map(|token| &token.kind)
+        == Some(&TokenType::Identifier(String::from("float")))
+}
+
+fn is_init(name: &Token) -> bool {
+    name.kind == TokenType::Identifier(String::from("init"))
+}
+
+fn error(span: Span, message: String) -> BackendError {
+    BackendError {
+        span: Some(span),
+        ..BackendError::new(message)
+    }
+}
+
+/// Encodes `mappings` in the base 64 VLQ format of source maps.
+fn mappings(mappings: &[Mapping]) -> String {
+    let mut out = String::new();
+    let (mut line, mut column) = (0, 0);
+    let (mut source_line, mut source_column) = (0, 0);
+
+    for mapping in mappings {
+        if mapping.line > line {
+            out.push_str(&";".repeat(mapping.line - line));
+            line = mapping.line;
+            column = 0;
+        } else if !out.is_empty() && !out.ends_with(';') {
+            out.push(',');
+        }
+
+        vlq(&mut out, mapping.column as i64 - column as i64);
+        vlq(&mut out, 0);
+        vlq(&mut out, mapping.source_line as i64 - source_line as i64);
+        vlq(
+            &mut out,
+            mapping.source_column as i64 - source_column as i64,
+        );
+        column = mapping.column;
+        source_line = mapping.source_line;
+        source_column = mapping.source_column;
+    }
+    out
+}
+
+fn vlq(out: &mut String, value: i64) {
+    const DIGITS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut value = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    loop {
+        let mut digit = value & 0b11111;
+        value >>= 5;
+        if value > 0 {
+            digit |= 0b100000;
+        }
+        out.push(DIGITS[digit as usize] as char);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+struct Classes(Vec<String>);
+
+impl Visitor for Classes {
+    fn visit_class(&mut self, name: &Token, super_class: Option<&Expr>, methods: &[Stmt]) {
+        self.0.push(name.kind.to_string());
+        walk_class(self, name, super_class, methods);
+    }
+}
+
+/// Whether a variable is used or assigned.
+struct Mentions<'a>(&'a str, bool);
+
+impl Visitor for Mentions<'_> {
+    fn visit_variable(&mut self, name: &Token) {
+        self.1 |= name.kind.to_string() == self.0;
+    }
+
+    fn visit_assign(&mut self, expr: &AssignExpr) {
+        self.1 |= expr.name.kind.to_string() == self.0;
+        walk_assign(self, expr);
+    }
+}
+
+/// Where `super.init` is called, leaving out nested classes.
+struct SuperInits(Vec<Span>);
+
+impl Visitor for SuperInits {
+    fn visit_call(&mut self, expr: &CallExpr) {
+        if let Expr::Super(callee) = &*expr.callee {
+            if is_init(&callee.method) {
+                self.0.push(callee.keyword.span);
+            }
+        }
+        walk_call(self, expr);
+    }
+
+    fn visit_class(&mut self, _name: &Token, _super_class: Option<&Expr>, _methods: &[Stmt]) {}
+}
+
+/// Checks that every `super.init` can become `super()`: it is called once in
+/// `init` of a subclass, as a statement, before `this` is used.
+struct SuperInitCalls(Result<(), BackendError>);
+
+impl Visitor for SuperInitCalls {
+    fn visit_class(&mut self, name: &Token, super_class: Option<&Expr>, methods: &[Stmt]) {
+        for method in methods {
+            if let (Ok(()), Stmt::Function { name, body, .. }) = (&self.0, method) {
+                self.0 = super_init(body, super_class.is_some() && is_init(name));
+            }
+        }
+        walk_class(self, name, super_class, methods);
+    }
+}
+
+fn super_init(body: &[Stmt], constructor: bool) -> Result<(), BackendError> {
+    let mut calls = SuperInits(vec![]);
+    calls.visit_block(body);
+    let last = match calls.0.last() {
+        Some(span) => *span,
+        None => return Ok(()),
+    };
+    if !constructor {
+        let message = "'super.init' can only be called from 'init' of a subclass for the js target";
+        return Err(error(last, String::from(message)));
+    }
+
+    let call = body.iter().position(|stmt| super_init_call(stmt).is_some());
+    let index = match call {
+        Some(index) if calls.0.len() == 1 => index,
+        _ => {
+            let message =
+                "'super.init' needs to be called once, as a statement of 'init', for the js target";
+            return Err(error(last, String::from(message)));
+        }
+    };
+
+    let mut before = BeforeSuper(None);
+    before.visit_block(&body[..index]);
+    for arg in &super_init_call(&body[index]).unwrap().args {
+        before.visit_expr(arg);
+    }
+    match before.0 {
+        Some((span, word)) => Err(error(
+            span,
+            format!(
+                "'{}' cannot come before 'super.init' for the js target",
+                word
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn super_init_call(stmt: &Stmt) -> Option<&CallExpr> {
+    match stmt {
+        Stmt::Expression(Expr::Call(call)) => match &*call.callee {
+            Expr::Super(callee) if is_init(&callee.method) => Some(call),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The first `this`, `super` or `return`, which JavaScript only allows in a
+/// constructor after `super()`, leaving out nested classes.
+struct BeforeSuper(Option<(Span, &'static str)>);
+
+impl Visitor for BeforeSuper {
+    fn visit_this(&mut self, expr: &ThisExpr) {
+        self.0.get_or_insert((expr.keyword.span, "this"));
+    }
+
+    fn visit_super(&mut self, expr: &SuperExpr) {
+        self.0.get_or_insert((expr.keyword.span, "super"));
+    }
+
+    fn visit_return(&mut self, keyword: &Token, value: Option<&Expr>) {
+        self.0.get_or_insert((keyword.span, "return"));
+        walk_return(self, keyword, value);
+    }
+
+    fn visit_class(&mut self, _name: &Token, _super_class: Option<&Expr>, _methods: &[Stmt]) {}
+}
@@ -0,0 +1,5 @@
+pub mod c;
+pub mod error;
+pub mod js;
+pub mod wasm;
+pub mod x86_64;
@@ -0,0 +1,810 @@
+/* Runtime for programs compiled by mini-comp to C. Every value is tagged,
+ * and strings, instances and bound methods are reference counted. Cycles
+ * between instances are never freed, and neither is what the frames a throw
+ * unwinds hold. */
+
+#include <math.h>
+#include <setjmp.h>
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef enum {
+    MC_NIL,
+    MC_BOOL,
+    MC_INT,
+    MC_FLOAT,
+    MC_STRING,
+    MC_FUNCTION,
+    MC_INSTANCE,
+    MC_BOUND,
+    /* A global that has not been written yet. */
+    MC_UNDEFINED
+} mc_tag;
+
+typedef struct mc_object {
+    /* Negative for objects that live as long as the program. */
+    long refs;
+} mc_object;
+
+typedef struct mc_function mc_function;
+
+typedef struct {
+    mc_tag tag;
+    union {
+        bool b;
+        int64_t i;
+        double f;
+        const mc_function *fn;
+        mc_object *o;
+    } as;
+} mc_value;
+
+struct mc_function {
+    const char *name;
+    int arity;
+    mc_value (*code)(int argc, const mc_value *argv);
+};
+
+typedef struct {
+    mc_object header;
+    size_t length;
+    char *chars;
+} mc_string;
+
+typedef struct {
+    const char *name;
+    const mc_function *function;
+} mc_method;
+
+typedef struct mc_class {
+    const char *name;
+    const struct mc_class *super_class;
+    const mc_method *methods;
+    size_t method_count;
+} mc_class;
+
+typedef struct {
+    const char *name;
+    mc_value value;
+} mc_field;
+
+typedef struct {
+    mc_object header;
+    const mc_class *class;
+    mc_field *fields;
+    size_t field_count;
+} mc_instance;
+
+typedef struct {
+    mc_object header;
+    mc_value receiver;
+    const mc_function *function;
+} mc_bound;
+
+/* A call that is running, in a list from the innermost one out, with the
+ * line and column of what it last did that can fail. */
+typedef struct mc_frame {
+    const char *name;
+    int line;
+    int column;
+    struct mc_frame *caller;
+} mc_frame;
+
+static mc_frame *mc_frames;
+
+/* A `try` that is running, in a list from the innermost one out. */
+typedef struct mc_handler {
+    jmp_buf env;
+    mc_frame *frames;
+    struct mc_handler *next;
+} mc_handler;
+
+static mc_handler *mc_handlers;
+
+/* Throws the message as a string. */
+static _Noreturn void mc_error(const char *format, ...);
+
+static _Noreturn void mc_out_of_memory(void) {
+    fflush(stdout);
+    fputs("Runtime error: out of memory\n", stderr);
+    exit(70);
+}
+
+static inline void *mc_alloc(size_t size) {
+    void *memory = calloc(1, size);
+    if (memory == NULL) {
+        mc_out_of_memory();
+    }
+    return memory;
+}
+
+static inline mc_value mc_nil(void) {
+    mc_value value = {MC_NIL, {0}};
+    return value;
+}
+
+static inline mc_value mc_bool(bool b) {
+    mc_value value = {MC_BOOL, {0}};
+    value.as.b = b;
+    return value;
+}
+
+static inline mc_value mc_int(int64_t i) {
+    mc_value value = {MC_INT, {0}};
+    value.as.i = i;
+    return value;
+}
+
+static inline mc_value mc_float(double f) {
+    mc_value value = {MC_FLOAT, {0}};
+    value.as.f = f;
+    return value;
+}
+
+static inline mc_value mc_function_value(const mc_function *fn) {
+    mc_value value = {MC_FUNCTION, {0}};
+    value.as.fn = fn;
+    return value;
+}
+
+static inline mc_value mc_object_value(mc_tag tag, mc_object *o) {
+    mc_value value = {tag, {0}};
+    value.as.o = o;
+    return value;
+}
+
+static inline bool mc_is_object(mc_value value) {
+    return value.tag == MC_STRING || value.tag == MC_INSTANCE || value.tag == MC_BOUND;
+}
+
+static inline mc_value mc_retain(mc_value value) {
+    if (mc_is_object(value) && value.as.o->refs >= 0) {
+        value.as.o->refs++;
+    }
+    return value;
+}
+
+static inline void mc_release(mc_value value) {
+    if (!mc_is_object(value) || value.as.o->refs < 0 || --value.as.o->refs > 0) {
+        return;
+    }
+    switch (value.tag) {
+    case MC_STRING:
+        free(((mc_string *)value.as.o)->chars);
+        break;
+    case MC_INSTANCE: {
+        mc_instance *instance = (mc_instance *)value.as.o;
+        for (size_t i = 0; i < instance->field_count; i++) {
+            mc_release(instance->fields[i].value);
+        }
+        free(instance->fields);
+        break;
+    }
+    case MC_BOUND:
+        mc_release(((mc_bound *)value.as.o)->receiver);
+        break;
+    default:
+        break;
+    }
+    free(value.as.o);
+}
+
+static inline void mc_release_all(mc_value *values, size_t count) {
+    for (size_t i = 0; i < count; i++) {
+        mc_release(values[i]);
+    }
+}
+
+/* Stores an owned value in `slot`, releasing what was there. */
+static inline void mc_set(mc_value *slot, mc_value value) {
+    mc_value old = *slot;
+    *slot = value;
+    mc_release(old);
+}
+
+static inline mc_value mc_string_value(const char *chars, size_t length) {
+    mc_string *string = mc_alloc(sizeof(mc_string));
+    string->header.refs = 1;
+    string->length = length;
+    string->chars = mc_alloc(length + 1);
+    memcpy(string->chars, chars, length);
+    return mc_object_value(MC_STRING, &string->header);
+}
+
+static inline mc_string *mc_as_string(mc_value value) {
+    return (mc_string *)value.as.o;
+}
+
+static inline mc_instance *mc_as_instance(mc_value value) {
+    return (mc_instance *)value.as.o;
+}
+
+static inline bool mc_truthy(mc_value value) {
+    return !(value.tag == MC_NIL || (value.tag == MC_BOOL && !value.as.b));
+}
+
+static inline const char *mc_type_name(mc_value value) {
+    switch (value.tag) {
+    case MC_NIL:
+        return "nil";
+    case MC_BOOL:
+        return "bool";
+    case MC_INT:
+        return "int";
+    case MC_FLOAT:
+        return "float";
+    case MC_STRING:
+        return "string";
+    case MC_FUNCTION:
+    case MC_BOUND:
+        return "function";
+    case MC_INSTANCE:
+        return mc_as_instance(value)->class->name;
+    default:
+        return "undefined";
+    }
+}
+
+static inline void mc_write_float(FILE *out, double f) {
+    char text[64];
+    snprintf(text, sizeof text, "%.15g", f);
+    if (strtod(text, NULL) != f) {
+        snprintf(text, sizeof text, "%.17g", f);
+    }
+    if (strspn(text, "-0123456789") == strlen(text)) {
+        strcat(text, ".0");
+    }
+    fputs(text, out);
+}
+
+/* Writes `value` the way `print` shows it, without the newline. */
+static inline void mc_write(FILE *out, mc_value value) {
+    switch (value.tag) {
+    case MC_NIL:
+        fputs("nil", out);
+        break;
+    case MC_BOOL:
+        fputs(value.as.b ? "true" : "false", out);
+        break;
+    case MC_INT:
+        fprintf(out, "%lld", (long long)value.as.i);
+        break;
+    case MC_FLOAT:
+        mc_write_float(out, value.as.f);
+        break;
+    case MC_STRING:
+        fwrite(mc_as_string(value)->chars, 1, mc_as_string(value)->length, out);
+        break;
+    case MC_FUNCTION:
+        fprintf(out, "<fn %s>", value.as.fn->name);
+        break;
+    case MC_BOUND:
+        fprintf(out, "<fn %s>", ((mc_bound *)value.as.o)->function->name);
+        break;
+    case MC_INSTANCE:
+        fprintf(out, "%s instance", mc_as_instance(value)->class->name);
+        break;
+    default:
+        break;
+    }
+}
+
+static inline void mc_print(mc_value value) {
+    mc_write(stdout, value);
+    fputc('\n', stdout);
+}
+
+static inline void mc_at(int line, int column) {
+    mc_frames->line = line;
+    mc_frames->column = column;
+}
+
+/* What was thrown, until the handler takes it. */
+static mc_value mc_thrown;
+
+/* The calls that were running when the last value was thrown, innermost
+ * first, up to `MC_TRACE` of them. */
+#define MC_TRACE 32
+static mc_frame mc_trace[MC_TRACE];
+static int mc_trace_depth;
+
+/* Starts a `try`, whose code runs once `setjmp` on the `env` of `handler`
+ * returns 0. */
+static inline mc_handler *mc_try(mc_handler *handler) {
+    handler->frames = mc_frames;
+    handler->next = mc_handlers;
+    mc_handlers = handler;
+    return handler;
+}
+
+static inline void mc_end_try(void) {
+    mc_handlers = mc_handlers->next;
+}
+
+/* Throws `value` again from a `finally` block, keeping the trace of where
+ * it was first thrown. */
+static _Noreturn void mc_rethrow(mc_value value) {
+    mc_handler *handler = mc_handlers;
+    if (handler == NULL) {
+        /* What was printed comes before the error. */
+        fflush(stdout);
+        fputs("Runtime error: ", stderr);
+        mc_write(stderr, value);
+        fputc('\n', stderr);
+        for (int i = 0; i < mc_trace_depth && i < MC_TRACE; i++) {
+            fprintf(stderr, "    at %s (%d:%d)\n", mc_trace[i].name, mc_trace[i].line,
+                    mc_trace[i].column);
+        }
+        if (mc_trace_depth > MC_TRACE) {
+            fprintf(stderr, "    ... %d more\n", mc_trace_depth - MC_TRACE);
+        }
+        exit(70);
+    }
+    mc_handlers = handler->next;
+    mc_frames = handler->frames;
+    mc_thrown = value;
+    longjmp(handler->env, 1);
+}
+
+/* Ends the innermost `try` and jumps back to it with `value`, which it owns.
+ * Without a `try` the program ends with the value and the trace. */
+static _Noreturn void mc_throw(mc_value value) {
+    mc_trace_depth = 0;
+    for (mc_frame *frame = mc_frames; frame != NULL; frame = frame->caller) {
+        if (mc_trace_depth < MC_TRACE) {
+            mc_trace[mc_trace_depth] = *frame;
+        }
+        mc_trace_depth++;
+    }
+    mc_rethrow(value);
+}
+
+static inline mc_value mc_caught(void) {
+    mc_value value = mc_thrown;
+    mc_thrown = mc_nil();
+    return value;
+}
+
+static _Noreturn void mc_error(const char *format, ...) {
+    va_list args;
+    va_start(args, format);
+    int length = vsnprintf(NULL, 0, format, args);
+    va_end(args);
+
+    mc_string *string = mc_alloc(sizeof(mc_string));
+    string->header.refs = 1;
+    string->length = (size_t)length;
+    string->chars = mc_alloc((size_t)length + 1);
+    va_start(args, format);
+    vsnprintf(string->chars, (size_t)length + 1, format, args);
+    va_end(args);
+    mc_throw(mc_object_value(MC_STRING, &string->header));
+}
+
+static inline bool mc_is_number(mc_value value) {
+    return value.tag == MC_INT || value.tag == MC_FLOAT;
+}
+
+static inline double mc_as_float(mc_value value) {
+    return value.tag == MC_INT ? (double)value.as.i : value.as.f;
+}
+
+static inline void mc_numbers(mc_value left, mc_value right, const char *op) {
+    if (!mc_is_number(left) || !mc_is_number(right)) {
+        mc_error("Operands of '%s' must be numbers", op);
+    }
+}
+
+static inline void mc_integers(mc_value left, mc_value right, const char *op) {
+    if (left.tag != MC_INT || right.tag != MC_INT) {
+        mc_error("Operands of '%s' must be integers", op);
+    }
+}
+
+static inline mc_value mc_overflow(const char *op) {
+    mc_error("Integer overflow in '%s'", op);
+    return mc_nil();
+}
+
+static inline mc_value mc_add(mc_value left, mc_value right) {
+    if (left.tag == MC_STRING && right.tag == MC_STRING) {
+        mc_string *a = mc_as_string(left);
+        mc_string *b = mc_as_string(right);
+        mc_value result = mc_string_value(a->chars, a->length);
+        mc_string *string = mc_as_string(result);
+        string->chars = realloc(string->chars, a->length + b->length + 1);
+        if (string->chars == NULL) {
+            mc_out_of_memory();
+        }
+        memcpy(string->chars + a->length, b->chars, b->length);
+        string->length += b->length;
+        string->chars[string->length] = '\0';
+        return result;
+    }
+    if (left.tag == MC_INT && right.tag == MC_INT) {
+        int64_t a = left.as.i, b = right.as.i;
+        if ((b > 0 && a > INT64_MAX - b) || (b < 0 && a < INT64_MIN - b)) {
+            return mc_overflow("+");
+        }
+        return mc_int(a + b);
+    }
+    if (!mc_is_number(left) || !mc_is_number(right)) {
+        mc_error("%s", "Operands of '+' must be two numbers or two strings");
+    }
+    return mc_float(mc_as_float(left) + mc_as_float(right));
+}
+
+static inline mc_value mc_sub(mc_value left, mc_value right) {
+    mc_numbers(left, right, "-");
+    if (left.tag == MC_INT && right.tag == MC_INT) {
+        int64_t a = left.as.i, b = right.as.i;
+        if ((b < 0 && a > INT64_MAX + b) || (b > 0 && a < INT64_MIN + b)) {
+            return mc_overflow("-");
+        }
+        return mc_int(a - b);
+    }
+    return mc_float(mc_as_float(left) - mc_as_float(right));
+}
+
+static inline bool mc_mul_overflows(int64_t a, int64_t b) {
+    if (a == 0 || b == 0) {
+        return false;
+    }
+    if ((a == -1 && b == INT64_MIN) || (b == -1 && a == INT64_MIN)) {
+        return true;
+    }
+    if (a == -1 || b == -1) {
+        return false;
+    }
+    int64_t product = (int64_t)((uint64_t)a * (uint64_t)b);
+    return product / b != a;
+}
+
+static inline mc_value mc_mul(mc_value left, mc_value right) {
+    mc_numbers(left, right, "*");
+    if (left.tag == MC_INT && right.tag == MC_INT) {
+        if (mc_mul_overflows(left.as.i, right.as.i)) {
+            return mc_overflow("*");
+        }
+        return mc_int(left.as.i * right.as.i);
+    }
+    return mc_float(mc_as_float(left) * mc_as_float(right));
+}
+
+static inline mc_value mc_div(mc_value left, mc_value right) {
+    mc_numbers(left, right, "/");
+    if (left.tag == MC_INT && right.tag == MC_INT) {
+        if (right.as.i == 0) {
+            mc_error("%s", "Division by zero");
+        }
+        if (left.as.i == INT64_MIN && right.as.i == -1) {
+            return mc_overflow("/");
+        }
+        return mc_int(left.as.i / right.as.i);
+    }
+    return mc_float(mc_as_float(left) / mc_as_float(right));
+}
+
+static inline mc_value mc_rem(mc_value left, mc_value right) {
+    mc_numbers(left, right, "%");
+    if (left.tag == MC_INT && right.tag == MC_INT) {
+        if (right.as.i == 0) {
+            mc_error("%s", "Division by zero");
+        }
+        if (left.as.i == INT64_MIN && right.as.i == -1) {
+            return mc_overflow("%");
+        }
+        return mc_int(left.as.i % right.as.i);
+    }
+    return mc_float(fmod(mc_as_float(left), mc_as_float(right)));
+}
+
+static inline mc_value mc_pow(mc_value left, mc_value right) {
+    mc_numbers(left, right, "**");
+    if (left.tag == MC_INT && right.tag == MC_INT) {
+        if (right.as.i < 0) {
+            mc_error("%s", "Negative exponent in '**'");
+        }
+        int64_t result = 1;
+        for (int64_t i = 0; i < right.as.i; i++) {
+            if (mc_mul_overflows(result, left.as.i)) {
+                return mc_overflow("**");
+            }
+            result *= left.as.i;
+            if (left.as.i == 0 || left.as.i == 1) {
+                break;
+            }
+        }
+        return mc_int(result);
+    }
+    return mc_float(pow(mc_as_float(left), mc_as_float(right)));
+}
+
+static inline mc_value mc_bitand(mc_value left, mc_value right) {
+    mc_integers(left, right, "&");
+    return mc_int(left.as.i & right.as.i);
+}
+
+static inline mc_value mc_bitor(mc_value left, mc_value right) {
+    mc_integers(left, right, "|");
+    return mc_int(left.as.i | right.as.i);
+}
+
+static inline mc_value mc_bitxor(mc_value left, mc_value right) {
+    mc_integers(left, right, "^");
+    return mc_int(left.as.i ^ right.as.i);
+}
+
+static inline mc_value mc_shl(mc_value left, mc_value right) {
+    mc_integers(left, right, "<<");
+    if (right.as.i < 0 || right.as.i > 63) {
+        return mc_overflow("<<");
+    }
+    return mc_int((int64_t)((uint64_t)left.as.i << right.as.i));
+}
+
+static inline mc_value mc_shr(mc_value left, mc_value right) {
+    mc_integers(left, right, ">>");
+    if (right.as.i < 0 || right.as.i > 63) {
+        return mc_overflow(">>");
+    }
+    int64_t a = left.as.i;
+    return mc_int(a >= 0 ? a >> right.as.i : ~(~a >> right.as.i));
+}
+
+static inline bool mc_equal(mc_value left, mc_value right) {
+    if (mc_is_number(left) && mc_is_number(right)) {
+        if (left.tag == MC_INT && right.tag == MC_INT) {
+            return left.as.i == right.as.i;
+        }
+        return mc_as_float(left) == mc_as_float(right);
+    }
+    if (left.tag != right.tag) {
+        return false;
+    }
+    switch (left.tag) {
+    case MC_NIL:
+        return true;
+    case MC_BOOL:
+        return left.as.b == right.as.b;
+    case MC_STRING:
+        return mc_as_string(left)->length == mc_as_string(right)->length &&
+               memcmp(mc_as_string(left)->chars, mc_as_string(right)->chars,
+                      mc_as_string(left)->length) == 0;
+    case MC_FUNCTION:
+        return left.as.fn == right.as.fn;
+    default:
+        return left.as.o == right.as.o;
+    }
+}
+
+static inline mc_value mc_eq(mc_value left, mc_value right) {
+    return mc_bool(mc_equal(left, right));
+}
+
+static inline mc_value mc_ne(mc_value left, mc_value right) {
+    return mc_bool(!mc_equal(left, right));
+}
+
+/* Negative, zero or positive as `left` is less than, equal to or greater
+ * than `right`. */
+static inline int mc_compare(mc_value left, mc_value right, const char *op) {
+    if (left.tag == MC_STRING && right.tag == MC_STRING) {
+        mc_string *a = mc_as_string(left);
+        mc_string *b = mc_as_string(right);
+        size_t length = a->length < b->length ? a->length : b->length;
+        int order = memcmp(a->chars, b->chars, length);
+        if (order != 0) {
+            return order;
+        }
+        return (a->length > b->length) - (a->length < b->length);
+    }
+    mc_numbers(left, right, op);
+    if (left.tag == MC_INT && right.tag == MC_INT) {
+        return (left.as.i > right.as.i) - (left.as.i < right.as.i);
+    }
+    double a = mc_as_float(left), b = mc_as_float(right);
+    return (a > b) - (a < b);
+}
+
+static inline mc_value mc_lt(mc_value left, mc_value right) {
+    return mc_bool(mc_compare(left, right, "<") < 0);
+}
+
+static inline mc_value mc_le(mc_value left, mc_value right) {
+    return mc_bool(mc_compare(left, right, "<=") <= 0);
+}
+
+static inline mc_value mc_gt(mc_value left, mc_value right) {
+    return mc_bool(mc_compare(left, right, ">") > 0);
+}
+
+static inline mc_value mc_ge(mc_value left, mc_value right) {
+    return mc_bool(mc_compare(left, right, ">=") >= 0);
+}
+
+static inline mc_value mc_neg(mc_value value) {
+    if (value.tag == MC_INT) {
+        if (value.as.i == INT64_MIN) {
+            return mc_overflow("-");
+        }
+        return mc_int(-value.as.i);
+    }
+    if (value.tag != MC_FLOAT) {
+        mc_error("Operand of '-' must be a number, found %s", mc_type_name(value));
+    }
+    return mc_float(-value.as.f);
+}
+
+static inline mc_value mc_not(mc_value value) {
+    return mc_bool(!mc_truthy(value));
+}
+
+static inline mc_value mc_bitnot(mc_value value) {
+    if (value.tag != MC_INT) {
+        mc_error("Operand of '~' must be an integer, found %s", mc_type_name(value));
+    }
+    return mc_int(~value.as.i);
+}
+
+/* Makes an int a float, for a slot declared `float`. */
+static inline mc_value mc_to_float(mc_value value) {
+    if (value.tag == MC_INT) {
+        return mc_float((double)value.as.i);
+    }
+    return mc_retain(value);
+}
+
+static inline void mc_check_arity(const mc_function *fn, int argc) {
+    if (argc != fn->arity) {
+        mc_error("Expected %d arguments for '%s', found %d", fn->arity, fn->name, argc);
+    }
+}
+
+static inline mc_value mc_call(const mc_function *fn, int argc, const mc_value *argv) {
+    mc_check_arity(fn, argc);
+    mc_frame frame = {fn->name, 0, 0, mc_frames};
+    mc_frames = &frame;
+    mc_value result = fn->code(argc, argv);
+    mc_frames = frame.caller;
+    return result;
+}
+
+/* Calls `fn` with `receiver` as `this` in front of the arguments. */
+static inline mc_value mc_call_with(const mc_function *fn, mc_value receiver, int argc,
+                             const mc_value *argv) {
+    mc_value *args = mc_alloc(sizeof(mc_value) * (size_t)(argc + 1));
+    args[0] = receiver;
+    for (int i = 0; i < argc; i++) {
+        args[i + 1] = argv[i];
+    }
+    mc_value result = mc_call(fn, argc + 1, args);
+    free(args);
+    return result;
+}
+
+static inline mc_value mc_call_value(mc_value callee, int argc, const mc_value *argv) {
+    switch (callee.tag) {
+    case MC_FUNCTION:
+        return mc_call(callee.as.fn, argc, argv);
+    case MC_BOUND: {
+        mc_bound *bound = (mc_bound *)callee.as.o;
+        return mc_call_with(bound->function, bound->receiver, argc, argv);
+    }
+    default:
+        mc_error("Cannot call a value of type %s", mc_type_name(callee));
+        return mc_nil();
+    }
+}
+
+static inline const mc_function *mc_find_method(const mc_class *class, const char *name) {
+    for (; class != NULL; class = class->super_class) {
+        for (size_t i = 0; i < class->method_count; i++) {
+            if (strcmp(class->methods[i].name, name) == 0) {
+                return class->methods[i].function;
+            }
+        }
+    }
+    return NULL;
+}
+
+static inline mc_value mc_new(const mc_class *class, int argc, const mc_value *argv) {
+    mc_instance *instance = mc_alloc(sizeof(mc_instance));
+    instance->header.refs = 1;
+    instance->class = class;
+    mc_value value = mc_object_value(MC_INSTANCE, &instance->header);
+
+    const mc_function *init = mc_find_method(class, "init");
+    if (init != NULL) {
+        mc_release(mc_call_with(init, value, argc, argv));
+    } else if (argc != 0) {
+        mc_error("Expected 0 arguments for '%s', found %d", class->name, argc);
+    }
+    return value;
+}
+
+static inline mc_instance *mc_instance_of(mc_value value, const char *name) {
+    if (value.tag != MC_INSTANCE) {
+        mc_error("%s has no property '%s'", mc_type_name(value), name);
+    }
+    return mc_as_instance(value);
+}
+
+static inline mc_field *mc_find_field(mc_instance *instance, const char *name) {
+    for (size_t i = 0; i < instance->field_count; i++) {
+        if (strcmp(instance->fields[i].name, name) == 0) {
+            return &instance->fields[i];
+        }
+    }
+    return NULL;
+}
+
+static inline mc_value mc_get(mc_value object, const char *name) {
+    mc_instance *instance = mc_instance_of(object, name);
+    mc_field *field = mc_find_field(instance, name);
+    if (field != NULL) {
+        return mc_retain(field->value);
+    }
+
+    const mc_function *method = mc_find_method(instance->class, name);
+    if (method == NULL) {
+        mc_error("%s has no property '%s'", instance->class->name, name);
+    }
+    mc_bound *bound = mc_alloc(sizeof(mc_bound));
+    bound->header.refs = 1;
+    bound->receiver = mc_retain(object);
+    bound->function = method;
+    return mc_object_value(MC_BOUND, &bound->header);
+}
+
+static inline void mc_set_property(mc_value object, const char *name, mc_value value) {
+    mc_instance *instance = mc_instance_of(object, name);
+    mc_field *field = mc_find_field(instance, name);
+    if (field != NULL) {
+        mc_set(&field->value, mc_retain(value));
+        return;
+    }
+
+    mc_field *fields = realloc(instance->fields, sizeof(mc_field) * (instance->field_count + 1));
+    if (fields == NULL) {
+        mc_out_of_memory();
+    }
+    fields[instance->field_count].name = name;
+    fields[instance->field_count].value = mc_retain(value);
+    instance->fields = fields;
+    instance->field_count++;
+}
+
+static inline mc_value mc_call_method(mc_value object, const char *name, int argc, const mc_value *argv) {
+    mc_instance *instance = mc_instance_of(object, name);
+    mc_field *field = mc_find_field(instance, name);
+    if (field != NULL) {
+        return mc_call_value(field->value, argc, argv);
+    }
+
+    const mc_function *method = mc_find_method(instance->class, name);
+    if (method == NULL) {
+        mc_error("%s has no method '%s'", instance->class->name, name);
+    }
+    return mc_call_with(method, object, argc, argv);
+}
+
+static inline mc_value mc_call_super(const mc_class *class, const char *name, mc_value object, int argc,
+                              const mc_value *argv) {
+    const mc_function *method = mc_find_method(class, name);
+    if (method == NULL) {
+        mc_error("%s has no method '%s'", class->name, name);
+    }
+    return mc_call_with(method, object, argc, argv);
+}
+
+static inline mc_value mc_load_global(mc_value value, const char *name) {
+    if (value.tag == MC_UNDEFINED) {
+        mc_error("Undefined variable '%s'", name);
+    }
+    return mc_retain(value);
+}
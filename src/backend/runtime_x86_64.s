@@ -0,0 +1,87 @@
+# Runtime of programs compiled with --target=x86_64-asm, written against
+# libc. Every helper is entered with the stack 8 bytes off a 16 byte
+# boundary, like any function.
+
+    .section .rodata
+mc_int_format:
+    .string "%lld\n"
+mc_true:
+    .string "true"
+mc_false:
+    .string "false"
+mc_nil:
+    .string "nil"
+mc_error_format:
+    .string "Runtime error: %s\n"
+mc_pow_overflow:
+    .string "Integer overflow in '**'"
+mc_negative_exponent:
+    .string "Negative exponent in '**'"
+
+    .text
+# Prints the integer in %rdi and a newline.
+mc_print_int:
+    subq $8, %rsp
+    movq %rdi, %rsi
+    leaq mc_int_format(%rip), %rdi
+    xorl %eax, %eax
+    call printf@PLT
+    addq $8, %rsp
+    ret
+
+# Prints `true` when %rdi is not zero, `false` otherwise.
+mc_print_bool:
+    subq $8, %rsp
+    leaq mc_false(%rip), %rax
+    leaq mc_true(%rip), %rcx
+    testq %rdi, %rdi
+    cmovne %rcx, %rax
+    movq %rax, %rdi
+    call puts@PLT
+    addq $8, %rsp
+    ret
+
+mc_print_nil:
+    subq $8, %rsp
+    leaq mc_nil(%rip), %rdi
+    call puts@PLT
+    addq $8, %rsp
+    ret
+
+# Reports the message in %rdi and exits with status 70.
+mc_error:
+    subq $8, %rsp
+    movq %rdi, %rdx
+    movq stderr@GOTPCREL(%rip), %rax
+    movq (%rax), %rdi
+    leaq mc_error_format(%rip), %rsi
+    xorl %eax, %eax
+    call fprintf@PLT
+    movl $70, %edi
+    call exit@PLT
+
+# %rdi raised to the power %rsi, in %rax.
+mc_pow:
+    subq $8, %rsp
+    testq %rsi, %rsi
+    js .Lmc_pow_negative
+    movl $1, %eax
+.Lmc_pow_loop:
+    testq %rsi, %rsi
+    jz .Lmc_pow_done
+    imulq %rdi, %rax
+    jo .Lmc_pow_overflow
+    # Powers of 0 and 1 are the base itself.
+    cmpq $1, %rdi
+    jbe .Lmc_pow_done
+    decq %rsi
+    jmp .Lmc_pow_loop
+.Lmc_pow_done:
+    addq $8, %rsp
+    ret
+.Lmc_pow_negative:
+    leaq mc_negative_exponent(%rip), %rdi
+    call mc_error
+.Lmc_pow_overflow:
+    leaq mc_pow_overflow(%rip), %rdi
+    call mc_error
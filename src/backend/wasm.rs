@@ -0,0 +1,935 @@
+//! WebAssembly code generation from the IR. Only programs whose registers
+//! all have a known type can be translated: `int` becomes `i64`, `float`
+//! becomes `f64`, and `bool`, `nil` and string constants become `i32`. The
+//! host provides `print` through imports from the `env` module, and the
+//! top level code is exported as `main`. Runtime errors are reported through
+//! the `error` import, after which the module traps.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+use super::error::BackendError;
+use crate::ir::ir::*;
+use crate::ir::types::binary;
+use crate::lexer::Span;
+
+/// Functions the host has to provide, with their wasm signatures.
+const IMPORTS: [(&str, &str); 7] = [
+    ("print_i64", "(param i64)"),
+    ("print_f64", "(param f64)"),
+    ("print_bool", "(param i32)"),
+    ("print_nil", ""),
+    ("print_str", "(param i32)"),
+    ("pow", "(param f64 f64) (result f64)"),
+    ("error", "(param i32)"),
+];
+
+/// Integer operations that check for the errors the other runtimes report,
+/// in the order they are written out, with the helpers each one calls.
+const HELPERS: [(&str, &[&str]); 10] = [
+    ("add_i64", &[]),
+    ("sub_i64", &[]),
+    ("mul_i64", &["mul_overflows"]),
+    ("div_i64", &[]),
+    ("rem_i64", &[]),
+    ("pow_i64", &["mul_overflows"]),
+    ("shl_i64", &[]),
+    ("shr_i64", &[]),
+    ("neg_i64", &[]),
+    ("mul_overflows", &[]),
+];
+
+// The bodies of `HELPERS`, one instruction per line. `error <message>`
+// reports the message and traps.
+
+const ADD_I64: &str = "(local $result i64)
+local.get $a
+local.get $b
+i64.add
+local.set $result
+local.get $a
+local.get $result
+i64.xor
+local.get $b
+local.get $result
+i64.xor
+i64.and
+i64.const 0
+i64.lt_s
+if
+error Integer overflow in '+'
+end
+local.get $result";
+
+const SUB_I64: &str = "(local $result i64)
+local.get $a
+local.get $b
+i64.sub
+local.set $result
+local.get $a
+local.get $b
+i64.xor
+local.get $a
+local.get $result
+i64.xor
+i64.and
+i64.const 0
+i64.lt_s
+if
+error Integer overflow in '-'
+end
+local.get $result";
+
+const MUL_I64: &str = "local.get $a
+local.get $b
+call $<mul_overflows>
+if
+error Integer overflow in '*'
+end
+local.get $a
+local.get $b
+i64.mul";
+
+const DIV_I64: &str = "local.get $b
+i64.eqz
+if
+error Division by zero
+end
+local.get $a
+i64.const -9223372036854775808
+i64.eq
+local.get $b
+i64.const -1
+i64.eq
+i32.and
+if
+error Integer overflow in '/'
+end
+local.get $a
+local.get $b
+i64.div_s";
+
+const REM_I64: &str = "local.get $b
+i64.eqz
+if
+error Division by zero
+end
+local.get $a
+i64.const -9223372036854775808
+i64.eq
+local.get $b
+i64.const -1
+i64.eq
+i32.and
+if
+error Integer overflow in '%'
+end
+local.get $a
+local.get $b
+i64.rem_s";
+
+const POW_I64: &str = "(local $result i64)
+local.get $exp
+i64.const 0
+i64.lt_s
+if
+error Negative exponent in '**'
+end
+i64.const 1
+local.set $result
+block $done
+loop $next
+local.get $exp
+i64.eqz
+br_if $done
+local.get $result
+local.get $base
+call $<mul_overflows>
+if
+error Integer overflow in '**'
+end
+local.get $result
+local.get $base
+i64.mul
+local.set $result
+local.get $base
+i64.const 1
+i64.le_u
+br_if $done
+local.get $exp
+i64.const 1
+i64.sub
+local.set $exp
+br $next
+end
+end
+local.get $result";
+
+const SHL_I64: &str = "local.get $b
+i64.const 63
+i64.gt_u
+if
+error Integer overflow in '<<'
+end
+local.get $a
+local.get $b
+i64.shl";
+
+const SHR_I64: &str = "local.get $b
+i64.const 63
+i64.gt_u
+if
+error Integer overflow in '>>'
+end
+local.get $a
+local.get $b
+i64.shr_s";
+
+const NEG_I64: &str = "local.get $a
+i64.const -9223372036854775808
+i64.eq
+if
+error Integer overflow in '-'
+end
+i64.const 0
+local.get $a
+i64.sub";
+
+/// Whether `a * b` does not fit in 64 bits: when dividing the wrapped
+/// product by `a` does not give `b` back.
+const MUL_OVERFLOWS: &str = "local.get $a
+i64.const -1
+i64.eq
+if
+local.get $b
+i64.const -9223372036854775808
+i64.eq
+return
+end
+local.get $a
+i64.eqz
+if
+i32.const 0
+return
+end
+local.get $a
+local.get $b
+i64.mul
+local.get $a
+i64.div_s
+local.get $b
+i64.ne";
+
+/// The module in the WebAssembly text format.
+pub fn generate(module: &Module) -> Result<String, BackendError> {
+    if let Some(class) = module.classes.first() {
+        return Err(BackendError::new(format!(
+            "Class '{}' is not supported by the wasm target",
+            class.name
+        )));
+    }
+
+    // A parameter without a type is what leaves most registers without one,
+    // so it is reported first.
+    for function in &module.functions {
+        for param in &function.params {
+            if function.ty(*param) == Ty::Any {
+                return Err(BackendError::new(format!(
+                    "Parameter '{}' of '{}' needs a type annotation for the wasm target",
+                    function.registers[param.0]
+                        .name
+                        .as_deref()
+                        .unwrap_or_default(),
+                    signature(function)
+                )));
+            }
+        }
+    }
+
+    let mut generator = Generator {
+        module,
+        imports: BTreeSet::new(),
+        helpers: BTreeSet::new(),
+        data: vec![],
+        strings: HashMap::new(),
+    };
+
+    let mut functions = String::new();
+    for function in &module.functions {
+        generator.function(&mut functions, function)?;
+    }
+    for (name, _) in HELPERS {
+        if generator.helpers.contains(name) {
+            let helper = generator.helper(name);
+            functions.push_str(&helper);
+        }
+    }
+
+    let mut out = String::from("(module\n");
+    for (name, signature) in IMPORTS {
+        if generator.imports.contains(name) {
+            let signature = match signature {
+                "" => String::new(),
+                signature => format!(" {}", signature),
+            };
+            writeln!(
+                out,
+                "  (import \"env\" \"{}\" (func $env.{}{}))",
+                name, name, signature
+            )
+            .unwrap();
+        }
+    }
+    if !generator.data.is_empty() {
+        let pages = generator.data.len().div_ceil(65536);
+        writeln!(out, "  (memory (export \"memory\") {})", pages).unwrap();
+        let data = generator
+            .data
+            .iter()
+            .map(|byte| format!("\\{:02x}", byte))
+            .collect::<String>();
+        writeln!(out, "  (data (i32.const 0) \"{}\")", data).unwrap();
+    }
+    for global in &module.globals {
+        let ty = value_type(global.ty)
+            .ok_or_else(|| unsupported_type(&format!("global '{}'", global.name), global.ty))?;
+        writeln!(
+            out,
+            "  (global ${} (mut {}) ({}.const 0))",
+            identifier(&global.name),
+            ty,
+            ty
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  (global {} (mut i32) (i32.const 0))",
+            defined(&global.name)
+        )
+        .unwrap();
+    }
+    out.push_str(&functions);
+    writeln!(
+        out,
+        "  (export \"main\" (func ${}))",
+        identifier(&module.functions[0].name)
+    )
+    .unwrap();
+    out.push_str(")\n");
+    Ok(out)
+}
+
+/// The binary form of a module in the text format.
+#[cfg(feature = "wat")]
+pub fn assemble(text: &str) -> Result<Vec<u8>, BackendError> {
+    wat::parse_str(text).map_err(|err| BackendError::new(err.to_string()))
+}
+
+#[cfg(not(feature = "wat"))]
+pub fn assemble(_text: &str) -> Result<Vec<u8>, BackendError> {
+    Err(BackendError::new(String::from(
+        "Built without the 'wat' feature, use --target=wat and an external assembler",
+    )))
+}
+
+fn value_type(ty: Ty) -> Option<&'static str> {
+    match ty {
+        Ty::Int => Some("i64"),
+        Ty::Float => Some("f64"),
+        Ty::Bool | Ty::Nil | Ty::Str => Some("i32"),
+        Ty::Any => None,
+    }
+}
+
+fn unsupported_type(what: &str, ty: Ty) -> BackendError {
+    BackendError::new(format!(
+        "The wasm target cannot represent {} of type {}",
+        what, ty
+    ))
+}
+
+fn unsupported(what: &str, function: &Function) -> BackendError {
+    BackendError::new(format!(
+        "{} in '{}' is not supported by the wasm target",
+        what, function.name
+    ))
+}
+
+/// `try` and `throw`, which need a way to unwind the stack that the target
+/// does not have.
+fn exceptions(function: &Function) -> Result<(), BackendError> {
+    for block in &function.blocks {
+        match block.terminator {
+            Terminator::Try { .. } => return Err(unsupported("'try'", function)),
+            Terminator::Throw { span, .. } => {
+                let mut err = unsupported("'throw'", function);
+                err.span = Some(span);
+                return Err(err);
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Names in the text format cannot have spaces.
+fn identifier(name: &str) -> String {
+    name.replace(' ', "_")
+}
+
+/// The global that says whether the global `name` has been assigned yet.
+fn defined(name: &str) -> String {
+    format!("$<defined>.{}", identifier(name))
+}
+
+/// Where a value written to `reg` comes from, found by following copies
+/// back to an instruction that keeps its span.
+fn origin(function: &Function, reg: Reg) -> Option<Span> {
+    let mut seen = vec![reg];
+    let mut work = vec![reg];
+    while let Some(reg) = work.pop() {
+        for instr in function.blocks.iter().flat_map(|block| &block.instrs) {
+            if instr.dest() != Some(reg) {
+                continue;
+            }
+            if let Some(span) = instr.span() {
+                return Some(span);
+            }
+            if let Instr::Copy { src, .. } = instr {
+                if !seen.contains(src) {
+                    seen.push(*src);
+                    work.push(*src);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// How messages refer to a function: its name and parameters.
+fn signature(function: &Function) -> String {
+    let params = function
+        .params
+        .iter()
+        .map(|param| function.reg_name(*param).replacen('%', "", 1))
+        .collect::<Vec<String>>();
+    format!("{}({})", function.name, params.join(", "))
+}
+
+struct Generator<'a> {
+    module: &'a Module,
+    imports: BTreeSet<&'static str>,
+    /// The entries of `HELPERS` that are called.
+    helpers: BTreeSet<&'static str>,
+    /// The linear memory: every string is its length as 4 little endian
+    /// bytes followed by its UTF-8 bytes.
+    data: Vec<u8>,
+    strings: HashMap<String, usize>,
+}
+
+impl Generator<'_> {
+    fn function(&mut self, out: &mut String, function: &Function) -> Result<(), BackendError> {
+        exceptions(function)?;
+        for (index, register) in function.registers.iter().enumerate() {
+            if value_type(register.ty).is_none() {
+                let name = function.reg_name(Reg(index));
+                let mut err =
+                    unsupported_type(&format!("{} in '{}'", name, function.name), register.ty);
+                err.span = origin(function, Reg(index));
+                return Err(err);
+            }
+        }
+
+        let local = |reg: Reg| function.reg_name(reg).replacen('%', "$", 1);
+        let mut header = format!("  (func ${}", identifier(&function.name));
+        for param in &function.params {
+            write!(
+                header,
+                " (param {} {})",
+                local(*param),
+                value_type(function.ty(*param)).unwrap()
+            )
+            .unwrap();
+        }
+        let ret = value_type(function.ret).ok_or_else(|| {
+            unsupported_type(&format!("the result of '{}'", function.name), function.ret)
+        })?;
+        write!(header, " (result {})", ret).unwrap();
+        writeln!(out, "{}", header).unwrap();
+
+        for (index, register) in function.registers.iter().enumerate() {
+            if !function.params.contains(&Reg(index)) {
+                writeln!(
+                    out,
+                    "    (local {} {})",
+                    local(Reg(index)),
+                    value_type(register.ty).unwrap()
+                )
+                .unwrap();
+            }
+        }
+        out.push_str("    (local $<block> i32)\n");
+
+        // Blocks are entered through a `br_table` on `$<block>` inside a loop,
+        // with the code of each block after the `end` of its wasm block, so
+        // that jumping to the next block needs no branch.
+        let count = function.blocks.len();
+        let mut body = Body {
+            lines: vec![],
+            depth: 2,
+        };
+        body.push("loop $dispatch");
+        for index in (0..count).rev() {
+            body.push(&format!("block $bb{}", index));
+        }
+        body.push("local.get $<block>");
+        let labels = (0..count)
+            .map(|index| format!("$bb{}", index))
+            .collect::<Vec<String>>();
+        body.push(&format!("br_table {}", labels.join(" ")));
+
+        for (index, block) in function.blocks.iter().enumerate() {
+            body.push("end");
+            for instr in &block.instrs {
+                self.instr(&mut body, function, instr)?;
+            }
+            match &block.terminator {
+                Terminator::Jump(target) => {
+                    if target.0 != index + 1 {
+                        body.jump(*target);
+                    }
+                }
+                Terminator::Branch {
+                    condition,
+                    then_block,
+                    else_block,
+                } => {
+                    body.push(&format!("i32.const {}", then_block.0));
+                    body.push(&format!("i32.const {}", else_block.0));
+                    self.truth(&mut body, function, *condition)?;
+                    body.push("select");
+                    body.push("local.set $<block>");
+                    body.push("br $dispatch");
+                }
+                Terminator::Return(value) => {
+                    self.get(&mut body, function, *value, function.ret)?;
+                    body.push("return");
+                }
+                Terminator::Try { .. } | Terminator::Throw { .. } | Terminator::Rethrow(_) => {
+                    unreachable!("exceptions are rejected")
+                }
+            }
+        }
+        body.push("end");
+        body.push("unreachable)");
+        out.push_str(&body.lines.join("\n"));
+        out.push('\n');
+        Ok(())
+    }
+
+    fn instr(
+        &mut self,
+        body: &mut Body,
+        function: &Function,
+        instr: &Instr,
+    ) -> Result<(), BackendError> {
+        let local = |reg: Reg| function.reg_name(reg).replacen('%', "$", 1);
+
+        let ty = match instr {
+            Instr::Const { value, .. } => {
+                match value {
+                    Constant::Int(value) => body.push(&format!("i64.const {}", value)),
+                    Constant::Float(value) => body.push(&format!("f64.const {}", float(*value))),
+                    Constant::Bool(value) => body.push(&format!("i32.const {}", *value as i32)),
+                    Constant::Nil => body.push("i32.const 0"),
+                    Constant::Str(value) => {
+                        let offset = self.string(value);
+                        body.push(&format!("i32.const {}", offset));
+                    }
+                }
+                value.ty()
+            }
+            Instr::Copy { src, .. } => {
+                body.push(&format!("local.get {}", local(*src)));
+                function.ty(*src)
+            }
+            Instr::Unary { op, src, .. } => {
+                let ty = function.ty(*src);
+                match (op, ty) {
+                    (UnaryOp::Neg, Ty::Int) => {
+                        body.push(&format!("local.get {}", local(*src)));
+                        body.push(&self.call_helper("neg_i64"));
+                        Ty::Int
+                    }
+                    (UnaryOp::Neg, Ty::Float) => {
+                        body.push(&format!("local.get {}", local(*src)));
+                        body.push("f64.neg");
+                        Ty::Float
+                    }
+                    (UnaryOp::BitNot, Ty::Int) => {
+                        body.push(&format!("local.get {}", local(*src)));
+                        body.push("i64.const -1");
+                        body.push("i64.xor");
+                        Ty::Int
+                    }
+                    (UnaryOp::Not, _) => {
+                        self.truth(body, function, *src)?;
+                        body.push("i32.eqz");
+                        Ty::Bool
+                    }
+                    (UnaryOp::ToFloat, Ty::Int | Ty::Float) => {
+                        body.push(&format!("local.get {}", local(*src)));
+                        self.convert(body, function, ty, Ty::Float)?;
+                        Ty::Float
+                    }
+                    _ => {
+                        return Err(unsupported(&format!("'{}' on {}", op, ty), function));
+                    }
+                }
+            }
+            Instr::Binary {
+                op, left, right, ..
+            } => self.binary(body, function, *op, *left, *right)?,
+            Instr::LoadGlobal { name, .. } => {
+                body.push(&format!("global.get {}", defined(name)));
+                body.push("i32.eqz");
+                body.push("if");
+                self.error(body, &format!("Undefined variable '{}'", name));
+                body.push("end");
+                body.push(&format!("global.get ${}", identifier(name)));
+                self.global(name)
+            }
+            Instr::StoreGlobal { name, src } => {
+                self.get(body, function, *src, self.global(name))?;
+                body.push(&format!("global.set ${}", identifier(name)));
+                body.push("i32.const 1");
+                body.push(&format!("global.set {}", defined(name)));
+                return Ok(());
+            }
+            Instr::Call {
+                function: callee,
+                args,
+                ..
+            } => {
+                let callee = self.module.function(callee).unwrap();
+                for (arg, param) in args.iter().zip(&callee.params) {
+                    self.get(body, function, *arg, callee.ty(*param))?;
+                }
+                if args.len() != callee.params.len() {
+                    return Err(BackendError::new(format!(
+                        "Expected {} arguments for '{}', found {}",
+                        callee.params.len(),
+                        callee.name,
+                        args.len()
+                    )));
+                }
+                body.push(&format!("call ${}", identifier(&callee.name)));
+                callee.ret
+            }
+            Instr::Print { src } => {
+                let ty = function.ty(*src);
+                let import = match ty {
+                    Ty::Int => "print_i64",
+                    Ty::Float => "print_f64",
+                    Ty::Bool => "print_bool",
+                    Ty::Str => "print_str",
+                    Ty::Nil => "print_nil",
+                    Ty::Any => unreachable!(),
+                };
+                if ty != Ty::Nil {
+                    body.push(&format!("local.get {}", local(*src)));
+                }
+                self.imports.insert(import);
+                body.push(&format!("call $env.{}", import));
+                return Ok(());
+            }
+            Instr::FunctionRef { .. } | Instr::CallIndirect { .. } => {
+                return Err(unsupported("Functions as values", function))
+            }
+            Instr::New { .. }
+            | Instr::CallMethod { .. }
+            | Instr::CallSuper { .. }
+            | Instr::GetProperty { .. }
+            | Instr::SetProperty { .. } => return Err(unsupported("Classes", function)),
+            Instr::EndTry | Instr::Caught { .. } => unreachable!("exceptions are rejected"),
+            Instr::Phi { .. } => unreachable!("phi outside of SSA form"),
+        };
+
+        let dest = instr.dest().unwrap();
+        self.convert(body, function, ty, function.ty(dest))?;
+        body.push(&format!("local.set {}", local(dest)));
+        Ok(())
+    }
+
+    /// Pushes `left op right` and returns its type.
+    fn binary(
+        &mut self,
+        body: &mut Body,
+        function: &Function,
+        op: BinaryOp,
+        left: Reg,
+        right: Reg,
+    ) -> Result<Ty, BackendError> {
+        use BinaryOp::*;
+
+        let (left_ty, right_ty) = (function.ty(left), function.ty(right));
+        let numeric = |ty: Ty| matches!(ty, Ty::Int | Ty::Float);
+        let operands = match (left_ty, right_ty) {
+            (Ty::Int, Ty::Int) => Ty::Int,
+            (left, right) if numeric(left) && numeric(right) => Ty::Float,
+            (Ty::Bool | Ty::Nil, Ty::Bool | Ty::Nil) if matches!(op, Eq | Ne) => Ty::Bool,
+            _ => {
+                return Err(unsupported(
+                    &format!("'{}' on {} and {}", op, left_ty, right_ty),
+                    function,
+                ))
+            }
+        };
+
+        if operands == Ty::Bool {
+            // `nil` is only equal to `nil`, and never to a `bool`.
+            if left_ty == Ty::Nil || right_ty == Ty::Nil {
+                let equal = left_ty == right_ty;
+                body.push(&format!("i32.const {}", (equal == (op == Eq)) as i32));
+                return Ok(Ty::Bool);
+            }
+            self.get(body, function, left, Ty::Bool)?;
+            self.get(body, function, right, Ty::Bool)?;
+            body.push(if op == Eq { "i32.eq" } else { "i32.ne" });
+            return Ok(Ty::Bool);
+        }
+
+        if operands == Ty::Float && op == Rem {
+            // a - trunc(a / b) * b, which keeps the sign of `a` like `fmod`.
+            self.get(body, function, left, Ty::Float)?;
+            self.get(body, function, left, Ty::Float)?;
+            self.get(body, function, right, Ty::Float)?;
+            body.push("f64.div");
+            body.push("f64.trunc");
+            self.get(body, function, right, Ty::Float)?;
+            body.push("f64.mul");
+            body.push("f64.sub");
+            return Ok(Ty::Float);
+        }
+
+        self.get(body, function, left, operands)?;
+        self.get(body, function, right, operands)?;
+        let helper = match (operands, op) {
+            (Ty::Int, Add) => Some("add_i64"),
+            (Ty::Int, Sub) => Some("sub_i64"),
+            (Ty::Int, Mul) => Some("mul_i64"),
+            (Ty::Int, Div) => Some("div_i64"),
+            (Ty::Int, Rem) => Some("rem_i64"),
+            (Ty::Int, Pow) => Some("pow_i64"),
+            (Ty::Int, Shl) => Some("shl_i64"),
+            (Ty::Int, Shr) => Some("shr_i64"),
+            _ => None,
+        };
+        if let Some(helper) = helper {
+            body.push(&self.call_helper(helper));
+            return Ok(Ty::Int);
+        }
+
+        let instr = match (operands, op) {
+            (Ty::Float, Pow) => {
+                self.imports.insert("pow");
+                "call $env.pow"
+            }
+            (Ty::Int, BitAnd) => "i64.and",
+            (Ty::Int, BitOr) => "i64.or",
+            (Ty::Int, BitXor) => "i64.xor",
+            (Ty::Int, Eq) => "i64.eq",
+            (Ty::Int, Ne) => "i64.ne",
+            (Ty::Int, Lt) => "i64.lt_s",
+            (Ty::Int, Le) => "i64.le_s",
+            (Ty::Int, Gt) => "i64.gt_s",
+            (Ty::Int, Ge) => "i64.ge_s",
+            (_, Add) => "f64.add",
+            (_, Sub) => "f64.sub",
+            (_, Mul) => "f64.mul",
+            (_, Div) => "f64.div",
+            (_, Eq) => "f64.eq",
+            (_, Ne) => "f64.ne",
+            (_, Lt) => "f64.lt",
+            (_, Le) => "f64.le",
+            (_, Gt) => "f64.gt",
+            (_, Ge) => "f64.ge",
+            _ => {
+                return Err(unsupported(
+                    &format!("'{}' on {} and {}", op, left_ty, right_ty),
+                    function,
+                ))
+            }
+        };
+        body.push(instr);
+        Ok(binary(op, operands, operands))
+    }
+
+    /// Pushes `reg` as a value of type `ty`.
+    fn get(
+        &mut self,
+        body: &mut Body,
+        function: &Function,
+        reg: Reg,
+        ty: Ty,
+    ) -> Result<(), BackendError> {
+        body.push(&format!(
+            "local.get {}",
+            function.reg_name(reg).replacen('%', "$", 1)
+        ));
+        self.convert(body, function, function.ty(reg), ty)
+    }
+
+    /// Pushes whether `reg` is neither `nil` nor `false`.
+    fn truth(
+        &mut self,
+        body: &mut Body,
+        function: &Function,
+        reg: Reg,
+    ) -> Result<(), BackendError> {
+        match function.ty(reg) {
+            Ty::Bool => self.get(body, function, reg, Ty::Bool),
+            Ty::Nil => {
+                body.push("i32.const 0");
+                Ok(())
+            }
+            _ => {
+                body.push("i32.const 1");
+                Ok(())
+            }
+        }
+    }
+
+    /// Turns the value of type `from` on top of the stack into a `to`.
+    fn convert(
+        &mut self,
+        body: &mut Body,
+        function: &Function,
+        from: Ty,
+        to: Ty,
+    ) -> Result<(), BackendError> {
+        match (from, to) {
+            (from, to) if from == to => Ok(()),
+            (Ty::Int, Ty::Float) => {
+                body.push("f64.convert_i64_s");
+                Ok(())
+            }
+            (from, to) => Err(unsupported(
+                &format!("Turning {} into {}", from, to),
+                function,
+            )),
+        }
+    }
+
+    /// Pushes the instructions that report `message` and trap.
+    fn error(&mut self, body: &mut Body, message: &str) {
+        let offset = self.string(message);
+        self.imports.insert("error");
+        body.push(&format!("i32.const {}", offset));
+        body.push("call $env.error");
+        body.push("unreachable");
+    }
+
+    /// The call of an entry of `HELPERS`, which is written out with the
+    /// helpers it calls.
+    fn call_helper(&mut self, name: &'static str) -> String {
+        let (name, calls) = HELPERS.iter().find(|(helper, _)| *helper == name).unwrap();
+        self.helpers.insert(name);
+        self.helpers.extend(calls.iter());
+        format!("call $<{}>", name)
+    }
+
+    /// The function of the entry `name` of `HELPERS`.
+    fn helper(&mut self, name: &str) -> String {
+        let (signature, code) = match name {
+            "add_i64" => ("(param $a i64) (param $b i64) (result i64)", ADD_I64),
+            "sub_i64" => ("(param $a i64) (param $b i64) (result i64)", SUB_I64),
+            "mul_i64" => ("(param $a i64) (param $b i64) (result i64)", MUL_I64),
+            "div_i64" => ("(param $a i64) (param $b i64) (result i64)", DIV_I64),
+            "rem_i64" => ("(param $a i64) (param $b i64) (result i64)", REM_I64),
+            "pow_i64" => ("(param $base i64) (param $exp i64) (result i64)", POW_I64),
+            "shl_i64" => ("(param $a i64) (param $b i64) (result i64)", SHL_I64),
+            "shr_i64" => ("(param $a i64) (param $b i64) (result i64)", SHR_I64),
+            "neg_i64" => ("(param $a i64) (result i64)", NEG_I64),
+            _ => ("(param $a i64) (param $b i64) (result i32)", MUL_OVERFLOWS),
+        };
+
+        let mut body = Body {
+            lines: vec![],
+            depth: 2,
+        };
+        for line in code.lines() {
+            match line.strip_prefix("error ") {
+                Some(message) => self.error(&mut body, message),
+                None => body.push(line),
+            }
+        }
+        format!(
+            "  (func $<{}> {}\n{})\n",
+            name,
+            signature,
+            body.lines.join("\n")
+        )
+    }
+
+    fn global(&self, name: &str) -> Ty {
+        self.module
+            .globals
+            .iter()
+            .find(|global| global.name == name)
+            .unwrap()
+            .ty
+    }
+
+    /// The offset of `value` in the linear memory.
+    fn string(&mut self, value: &str) -> usize {
+        if let Some(offset) = self.strings.get(value) {
+            return *offset;
+        }
+        let offset = self.data.len();
+        self.data
+            .extend_from_slice(&(value.len() as u32).to_le_bytes());
+        self.data.extend_from_slice(value.as_bytes());
+        // Keep every length aligned.
+        while !self.data.len().is_multiple_of(4) {
+            self.data.push(0);
+        }
+        self.strings.insert(value.to_string(), offset);
+        offset
+    }
+}
+
+/// Instructions in the text format, indented by how deep in blocks they
+/// are.
+struct Body {
+    lines: Vec<String>,
+    depth: usize,
+}
+
+impl Body {
+    fn push(&mut self, line: &str) {
+        if line == "end" {
+            self.depth -= 1;
+        }
+        self.lines
+            .push(format!("{}{}", "  ".repeat(self.depth), line));
+        if line.starts_with("loop") || line.starts_with("block") || line == "if" {
+            self.depth += 1;
+        }
+    }
+
+    fn jump(&mut self, target: BlockId) {
+        self.push(&format!("i32.const {}", target.0));
+        self.push("local.set $<block>");
+        self.push("br $dispatch");
+    }
+}
+
+fn float(value: f64) -> String {
+    match value {
+        value if value.is_nan() => String::from("nan"),
+        value if value.is_infinite() && value > 0.0 => String::from("inf"),
+        value if value.is_infinite() => String::from("-inf"),
+        value => format!("{:?}", value),
+    }
+}
@@ -0,0 +1,476 @@
+//! x86-64 assembly generation from the IR, for the GNU assembler and the
+//! System V calling convention. Only integers, booleans and `nil` are
+//! supported, each kept in a stack slot of its own, and functions take at
+//! most six arguments, all in registers. `print` and `**` go through a
+//! small runtime written against libc, so the output links with `cc`.
+
+use std::fmt::Write;
+
+use super::error::BackendError;
+use crate::ir::ir::*;
+use crate::ir::types::binary;
+
+const RUNTIME: &str = include_str!("runtime_x86_64.s");
+
+/// Registers the first six arguments are passed in.
+const ARGUMENTS: [&str; 6] = ["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+
+/// The module as GNU assembler source.
+pub fn generate(module: &Module) -> Result<String, BackendError> {
+    if let Some(class) = module.classes.first() {
+        return Err(BackendError::new(format!(
+            "Class '{}' is not supported by the x86-64 target",
+            class.name
+        )));
+    }
+
+    for global in &module.globals {
+        if !supported(global.ty) {
+            return Err(unsupported_type(
+                &format!("the global '{}'", global.name),
+                global.ty,
+            ));
+        }
+    }
+
+    let mut generator = Generator {
+        module,
+        messages: vec![],
+    };
+    let mut text = String::new();
+    for (index, function) in module.functions.iter().enumerate() {
+        generator.function(&mut text, index, function)?;
+    }
+
+    let mut out = String::from(RUNTIME);
+    out.push_str("\n    .section .rodata\n");
+    for (index, message) in generator.messages.iter().enumerate() {
+        writeln!(out, ".Lmessage{}:\n    .string \"{}\"", index, message).unwrap();
+    }
+    if !module.globals.is_empty() {
+        out.push_str("\n    .bss\n    .align 8\n");
+        for (index, global) in module.globals.iter().enumerate() {
+            writeln!(out, "mc_global{}:  # {}\n    .zero 8", index, global.name).unwrap();
+            // Set to 1 by the first assignment.
+            writeln!(out, "mc_defined{}:\n    .zero 8", index).unwrap();
+        }
+    }
+    out.push_str("\n    .text\n");
+    out.push_str(&text);
+    out.push_str(
+        "
+    .globl main
+main:
+    subq $8, %rsp
+    call mc_function0
+    xorl %eax, %eax
+    addq $8, %rsp
+    ret
+
+    .section .note.GNU-stack,\"\",@progbits
+",
+    );
+    Ok(out)
+}
+
+fn supported(ty: Ty) -> bool {
+    matches!(ty, Ty::Int | Ty::Bool | Ty::Nil)
+}
+
+fn unsupported_type(what: &str, ty: Ty) -> BackendError {
+    BackendError::new(format!(
+        "The x86-64 target cannot represent {} of type {}",
+        what, ty
+    ))
+}
+
+fn unsupported(what: &str, function: &Function) -> BackendError {
+    BackendError::new(format!(
+        "{} in '{}' is not supported by the x86-64 target",
+        what, function.name
+    ))
+}
+
+/// `try` and `throw`, which need a way to unwind the stack that the target
+/// does not have.
+fn exceptions(function: &Function) -> Result<(), BackendError> {
+    for block in &function.blocks {
+        match block.terminator {
+            Terminator::Try { .. } => return Err(unsupported("'try'", function)),
+            Terminator::Throw { span, .. } => {
+                let mut err = unsupported("'throw'", function);
+                err.span = Some(span);
+                return Err(err);
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// The stack slot of `reg`, below the saved frame pointer.
+fn slot(reg: Reg) -> String {
+    format!("-{}(%rbp)", 8 * (reg.0 + 1))
+}
+
+struct Generator<'a> {
+    module: &'a Module,
+    /// Runtime error messages, referred to as `.LmessageN`.
+    messages: Vec<String>,
+}
+
+impl Generator<'_> {
+    fn function(
+        &mut self,
+        out: &mut String,
+        index: usize,
+        function: &Function,
+    ) -> Result<(), BackendError> {
+        exceptions(function)?;
+        for (index, register) in function.registers.iter().enumerate() {
+            if !supported(register.ty) {
+                let name = function.reg_name(Reg(index));
+                return Err(unsupported_type(
+                    &format!("{} in '{}'", name, function.name),
+                    register.ty,
+                ));
+            }
+        }
+        if !supported(function.ret) {
+            return Err(unsupported_type(
+                &format!("the result of '{}'", function.name),
+                function.ret,
+            ));
+        }
+        if function.params.len() > ARGUMENTS.len() {
+            return Err(unsupported("More than 6 parameters", function));
+        }
+
+        // Keep the stack 16 byte aligned for calls.
+        let frame = (8 * function.registers.len()).div_ceil(16) * 16;
+        writeln!(out, "\n# {}", function.name).unwrap();
+        writeln!(out, "mc_function{}:", index).unwrap();
+        out.push_str("    pushq %rbp\n    movq %rsp, %rbp\n");
+        if frame > 0 {
+            writeln!(out, "    subq ${}, %rsp", frame).unwrap();
+        }
+        for (param, register) in function.params.iter().zip(ARGUMENTS) {
+            writeln!(out, "    movq {}, {}", register, slot(*param)).unwrap();
+        }
+
+        let label = |block: BlockId| format!(".Lfunction{}_bb{}", index, block.0);
+        for (position, block) in function.blocks.iter().enumerate() {
+            writeln!(out, "{}:", label(BlockId(position))).unwrap();
+            for instr in &block.instrs {
+                self.instr(out, function, instr)?;
+            }
+            let next = BlockId(position + 1);
+            match &block.terminator {
+                Terminator::Jump(target) => {
+                    if *target != next {
+                        writeln!(out, "    jmp {}", label(*target)).unwrap();
+                    }
+                }
+                Terminator::Branch {
+                    condition,
+                    then_block,
+                    else_block,
+                } => {
+                    truth(out, function, *condition);
+                    writeln!(out, "    testq %rax, %rax").unwrap();
+                    writeln!(out, "    jne {}", label(*then_block)).unwrap();
+                    if *else_block != next {
+                        writeln!(out, "    jmp {}", label(*else_block)).unwrap();
+                    }
+                }
+                Terminator::Return(value) => {
+                    writeln!(out, "    movq {}, %rax", slot(*value)).unwrap();
+                    out.push_str("    leave\n    ret\n");
+                }
+                Terminator::Try { .. } | Terminator::Throw { .. } | Terminator::Rethrow(_) => {
+                    unreachable!("exceptions are rejected")
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn instr(
+        &mut self,
+        out: &mut String,
+        function: &Function,
+        instr: &Instr,
+    ) -> Result<(), BackendError> {
+        // Every instruction that produces a value leaves it in %rax.
+        let ty = match instr {
+            Instr::Const { value, .. } => {
+                let bits = match value {
+                    Constant::Int(value) => *value,
+                    Constant::Bool(value) => *value as i64,
+                    Constant::Nil => 0,
+                    Constant::Float(_) | Constant::Str(_) => {
+                        return Err(unsupported_type(
+                            &format!("a constant in '{}'", function.name),
+                            value.ty(),
+                        ))
+                    }
+                };
+                writeln!(out, "    movabsq ${}, %rax", bits).unwrap();
+                value.ty()
+            }
+            Instr::Copy { src, .. } => {
+                writeln!(out, "    movq {}, %rax", slot(*src)).unwrap();
+                function.ty(*src)
+            }
+            Instr::Unary { op, src, .. } => {
+                let ty = function.ty(*src);
+                match (op, ty) {
+                    (UnaryOp::Neg, Ty::Int) => {
+                        writeln!(out, "    movq {}, %rax", slot(*src)).unwrap();
+                        out.push_str("    negq %rax\n");
+                        self.on_overflow(out, "-");
+                        Ty::Int
+                    }
+                    (UnaryOp::BitNot, Ty::Int) => {
+                        writeln!(out, "    movq {}, %rax", slot(*src)).unwrap();
+                        out.push_str("    notq %rax\n");
+                        Ty::Int
+                    }
+                    (UnaryOp::Not, _) => {
+                        truth(out, function, *src);
+                        out.push_str("    xorq $1, %rax\n");
+                        Ty::Bool
+                    }
+                    _ => return Err(unsupported(&format!("'{}' on {}", op, ty), function)),
+                }
+            }
+            Instr::Binary {
+                op, left, right, ..
+            } => self.binary(out, function, *op, *left, *right)?,
+            Instr::LoadGlobal { name, .. } => {
+                let (index, ty) = self.global(name);
+                let undefined = self.message(&format!("Undefined variable '{}'", name));
+                writeln!(out, "    cmpq $0, mc_defined{}(%rip)", index).unwrap();
+                out.push_str("    jne 1f\n");
+                writeln!(out, "    leaq .Lmessage{}(%rip), %rdi", undefined).unwrap();
+                out.push_str("    call mc_error\n1:\n");
+                writeln!(out, "    movq mc_global{}(%rip), %rax", index).unwrap();
+                ty
+            }
+            Instr::StoreGlobal { name, src } => {
+                let (index, ty) = self.global(name);
+                expect(function, function.ty(*src), ty)?;
+                writeln!(out, "    movq {}, %rax", slot(*src)).unwrap();
+                writeln!(out, "    movq %rax, mc_global{}(%rip)", index).unwrap();
+                writeln!(out, "    movq $1, mc_defined{}(%rip)", index).unwrap();
+                return Ok(());
+            }
+            Instr::Call {
+                function: callee,
+                args,
+                ..
+            } => {
+                let index = self
+                    .module
+                    .functions
+                    .iter()
+                    .position(|function| &function.name == callee)
+                    .unwrap();
+                let callee = &self.module.functions[index];
+                if args.len() != callee.params.len() {
+                    return Err(BackendError::new(format!(
+                        "Expected {} arguments for '{}', found {}",
+                        callee.params.len(),
+                        callee.name,
+                        args.len()
+                    )));
+                }
+                for ((arg, param), register) in args.iter().zip(&callee.params).zip(ARGUMENTS) {
+                    expect(function, function.ty(*arg), callee.ty(*param))?;
+                    writeln!(out, "    movq {}, {}", slot(*arg), register).unwrap();
+                }
+                writeln!(out, "    call mc_function{}", index).unwrap();
+                callee.ret
+            }
+            Instr::Print { src } => {
+                let helper = match function.ty(*src) {
+                    Ty::Int => "mc_print_int",
+                    Ty::Bool => "mc_print_bool",
+                    _ => "mc_print_nil",
+                };
+                writeln!(out, "    movq {}, %rdi", slot(*src)).unwrap();
+                writeln!(out, "    call {}", helper).unwrap();
+                return Ok(());
+            }
+            Instr::FunctionRef { .. } | Instr::CallIndirect { .. } => {
+                return Err(unsupported("Functions as values", function))
+            }
+            Instr::New { .. }
+            | Instr::CallMethod { .. }
+            | Instr::CallSuper { .. }
+            | Instr::GetProperty { .. }
+            | Instr::SetProperty { .. } => return Err(unsupported("Classes", function)),
+            Instr::EndTry | Instr::Caught { .. } => unreachable!("exceptions are rejected"),
+            Instr::Phi { .. } => unreachable!("phi outside of SSA form"),
+        };
+
+        let dest = instr.dest().unwrap();
+        expect(function, ty, function.ty(dest))?;
+        writeln!(out, "    movq %rax, {}", slot(dest)).unwrap();
+        Ok(())
+    }
+
+    /// Computes `left op right` into %rax and returns its type.
+    fn binary(
+        &mut self,
+        out: &mut String,
+        function: &Function,
+        op: BinaryOp,
+        left: Reg,
+        right: Reg,
+    ) -> Result<Ty, BackendError> {
+        use BinaryOp::*;
+
+        let (left_ty, right_ty) = (function.ty(left), function.ty(right));
+        if left_ty != Ty::Int || right_ty != Ty::Int {
+            if !matches!(op, Eq | Ne) {
+                return Err(unsupported(
+                    &format!("'{}' on {} and {}", op, left_ty, right_ty),
+                    function,
+                ));
+            }
+            // Values of different types are never equal, and `nil` always
+            // equals `nil`.
+            if left_ty != right_ty || left_ty == Ty::Nil {
+                let equal = left_ty == right_ty;
+                writeln!(out, "    movq ${}, %rax", (equal == (op == Eq)) as i64).unwrap();
+                return Ok(Ty::Bool);
+            }
+        }
+
+        writeln!(out, "    movq {}, %rax", slot(left)).unwrap();
+        writeln!(out, "    movq {}, %rcx", slot(right)).unwrap();
+        match op {
+            Add | Sub | Mul => {
+                let instr = match op {
+                    Add => "addq",
+                    Sub => "subq",
+                    _ => "imulq",
+                };
+                writeln!(out, "    {} %rcx, %rax", instr).unwrap();
+                self.on_overflow(out, operator(op));
+            }
+            Div | Rem => {
+                let zero = self.message("Division by zero");
+                out.push_str("    testq %rcx, %rcx\n");
+                out.push_str("    jne 1f\n");
+                writeln!(out, "    leaq .Lmessage{}(%rip), %rdi", zero).unwrap();
+                out.push_str("    call mc_error\n1:\n");
+                // INT64_MIN / -1 is the one quotient that does not fit.
+                let overflow = self.message(&format!("Integer overflow in '{}'", operator(op)));
+                out.push_str("    cmpq $-1, %rcx\n");
+                out.push_str("    jne 1f\n");
+                out.push_str("    movabsq $-9223372036854775808, %rdx\n");
+                out.push_str("    cmpq %rdx, %rax\n");
+                out.push_str("    jne 1f\n");
+                writeln!(out, "    leaq .Lmessage{}(%rip), %rdi", overflow).unwrap();
+                out.push_str("    call mc_error\n1:\n");
+                out.push_str("    cqto\n    idivq %rcx\n");
+                if op == Rem {
+                    out.push_str("    movq %rdx, %rax\n");
+                }
+            }
+            Pow => {
+                out.push_str("    movq %rax, %rdi\n    movq %rcx, %rsi\n    call mc_pow\n");
+            }
+            BitAnd => out.push_str("    andq %rcx, %rax\n"),
+            BitOr => out.push_str("    orq %rcx, %rax\n"),
+            BitXor => out.push_str("    xorq %rcx, %rax\n"),
+            Shl | Shr => {
+                let overflow = self.message(&format!("Integer overflow in '{}'", operator(op)));
+                out.push_str("    cmpq $63, %rcx\n");
+                out.push_str("    jbe 1f\n");
+                writeln!(out, "    leaq .Lmessage{}(%rip), %rdi", overflow).unwrap();
+                out.push_str("    call mc_error\n1:\n");
+                let instr = if op == Shl { "shlq" } else { "sarq" };
+                writeln!(out, "    {} %cl, %rax", instr).unwrap();
+            }
+            Eq | Ne | Lt | Le | Gt | Ge => {
+                let condition = match op {
+                    Eq => "e",
+                    Ne => "ne",
+                    Lt => "l",
+                    Le => "le",
+                    Gt => "g",
+                    _ => "ge",
+                };
+                out.push_str("    cmpq %rcx, %rax\n");
+                writeln!(out, "    set{} %al", condition).unwrap();
+                out.push_str("    movzbq %al, %rax\n");
+            }
+        }
+        Ok(binary(op, left_ty, right_ty))
+    }
+
+    /// Stops the program when the last arithmetic instruction overflowed.
+    fn on_overflow(&mut self, out: &mut String, operator: &str) {
+        let message = self.message(&format!("Integer overflow in '{}'", operator));
+        out.push_str("    jno 1f\n");
+        writeln!(out, "    leaq .Lmessage{}(%rip), %rdi", message).unwrap();
+        out.push_str("    call mc_error\n1:\n");
+    }
+
+    /// The index of `message` among the runtime error messages.
+    fn message(&mut self, message: &str) -> usize {
+        match self.messages.iter().position(|known| known == message) {
+            Some(index) => index,
+            None => {
+                self.messages.push(message.to_string());
+                self.messages.len() - 1
+            }
+        }
+    }
+
+    fn global(&self, name: &str) -> (usize, Ty) {
+        let index = self
+            .module
+            .globals
+            .iter()
+            .position(|global| global.name == name)
+            .unwrap();
+        (index, self.module.globals[index].ty)
+    }
+}
+
+/// Puts 1 in %rax when `reg` is neither `nil` nor `false`, 0 otherwise.
+fn truth(out: &mut String, function: &Function, reg: Reg) {
+    match function.ty(reg) {
+        Ty::Bool => writeln!(out, "    movq {}, %rax", slot(reg)).unwrap(),
+        Ty::Nil => out.push_str("    movq $0, %rax\n"),
+        _ => out.push_str("    movq $1, %rax\n"),
+    }
+}
+
+/// Values are never converted: a `bool` stored where an `int` goes would
+/// print as a number.
+fn expect(function: &Function, from: Ty, to: Ty) -> Result<(), BackendError> {
+    if from != to {
+        return Err(unsupported(
+            &format!("Turning {} into {}", from, to),
+            function,
+        ));
+    }
+    Ok(())
+}
+
+fn operator(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Sub => "-",
+        BinaryOp::Mul => "*",
+        BinaryOp::Div => "/",
+        BinaryOp::Rem => "%",
+        BinaryOp::Shl => "<<",
+        _ => ">>",
+    }
+}
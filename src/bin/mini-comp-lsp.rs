@@ -0,0 +1,364 @@
+//! Language server for `.comp` files, speaking LSP over stdin and stdout.
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as RequestTrait,
+};
+use lsp_types::*;
+
+use mini_comp::checker::checker::check;
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::lexer::Span;
+use mini_comp::parser::parser::Parser;
+use mini_comp::resolver::resolver::{DeclarationKind, Resolution, Resolver};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Sync + Send>>;
+
+fn main() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = serde_json::to_value(ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        ..ServerCapabilities::default()
+    })?;
+    connection.initialize(capabilities)?;
+
+    run(connection)?;
+    io_threads.join()?;
+    Ok(())
+}
+
+/// Handles messages until the client shuts the server down. The connection
+/// is dropped on return, which lets the IO threads finish.
+fn run(connection: Connection) -> Result<()> {
+    let mut server = Server {
+        connection: &connection,
+        documents: HashMap::new(),
+    };
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                server.request(request)?;
+            }
+            Message::Notification(notification) => server.notification(notification)?,
+            Message::Response(_) => (),
+        }
+    }
+
+    Ok(())
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+    /// Names in every open file, from the last version of it that parsed, so
+    /// navigation keeps working while an edit is half done.
+    documents: HashMap<Url, Resolution>,
+}
+
+impl Server<'_> {
+    fn notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.analyze(params.text_document.uri, &params.text_document.text)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                match params.content_changes.last() {
+                    Some(change) => self.analyze(params.text_document.uri, &change.text),
+                    None => Ok(()),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                self.publish(params.text_document.uri, vec![])
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn request(&mut self, request: Request) -> Result<()> {
+        let result = match request.method.as_str() {
+            GotoDefinition::METHOD => {
+                let params: GotoDefinitionParams = serde_json::from_value(request.params)?;
+                serde_json::to_value(self.definition(params))?
+            }
+            HoverRequest::METHOD => {
+                let params: HoverParams = serde_json::from_value(request.params)?;
+                serde_json::to_value(self.hover(params))?
+            }
+            DocumentSymbolRequest::METHOD => {
+                let params: DocumentSymbolParams = serde_json::from_value(request.params)?;
+                serde_json::to_value(self.symbols(params))?
+            }
+            Completion::METHOD => {
+                let params: CompletionParams = serde_json::from_value(request.params)?;
+                serde_json::to_value(self.completion(params))?
+            }
+            _ => {
+                let response = Response::new_err(
+                    request.id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("Unsupported request {}", request.method),
+                );
+                self.connection.sender.send(Message::Response(response))?;
+                return Ok(());
+            }
+        };
+
+        let response = Response::new_ok(request.id, result);
+        self.connection.sender.send(Message::Response(response))?;
+        Ok(())
+    }
+
+    /// Lexes, parses, resolves and type checks `text`, then publishes the
+    /// errors found on the way. Names that are used but never declared are
+    /// warnings.
+    fn analyze(&mut self, uri: Url, text: &str) -> Result<()> {
+        let mut lexer = Lexer::new(text);
+        let tokens = match lexer.get_tokens() {
+            Ok(tokens) => tokens,
+            Err(err) => {
+                let diagnostic = diagnostic(lexer.position(), 1, err.to_string());
+                return self.publish(uri, vec![diagnostic]);
+            }
+        };
+
+        let statements = match Parser::new(tokens).try_parse_program() {
+            Ok(statements) => statements,
+            Err(err) => {
+                let diagnostic = diagnostic(err.span, 1, err.message);
+                return self.publish(uri, vec![diagnostic]);
+            }
+        };
+
+        let resolution = Resolver::new().resolve(&statements);
+        let mut diagnostics = resolution
+            .references
+            .iter()
+            .filter(|reference| reference.declaration.is_none())
+            .map(|reference| Diagnostic {
+                severity: Some(DiagnosticSeverity::WARNING),
+                ..diagnostic(
+                    reference.span,
+                    reference.name.chars().count(),
+                    format!("'{}' is not declared", reference.name),
+                )
+            })
+            .collect::<Vec<Diagnostic>>();
+        diagnostics.extend(
+            check(&statements)
+                .into_iter()
+                .map(|error| diagnostic(error.span, 1, error.message)),
+        );
+
+        self.documents.insert(uri.clone(), resolution);
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(&self, uri: Url, diagnostics: Vec<Diagnostic>) -> Result<()> {
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        };
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection
+            .sender
+            .send(Message::Notification(notification))?;
+        Ok(())
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let position = params.text_document_position_params;
+        let resolution = self.documents.get(&position.text_document.uri)?;
+        let declaration = resolution.declaration_at(span(position.position))?;
+
+        Some(GotoDefinitionResponse::Scalar(Location {
+            uri: position.text_document.uri,
+            range: range(declaration.span, &declaration.name),
+        }))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let position = params.text_document_position_params;
+        let resolution = self.documents.get(&position.text_document.uri)?;
+        let declaration = resolution.declaration_at(span(position.position))?;
+
+        let kind = match declaration.class {
+            Some(class) => format!(
+                "{} of class `{}`",
+                declaration.kind, resolution.declarations[class].name
+            ),
+            None => declaration.kind.to_string(),
+        };
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```\n{}\n```\n{}", declaration.detail, kind),
+            }),
+            range: None,
+        })
+    }
+
+    /// Functions anywhere in the file and classes with their methods.
+    fn symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let resolution = self.documents.get(&params.text_document.uri)?;
+
+        let symbols = resolution
+            .declarations
+            .iter()
+            .enumerate()
+            .filter_map(|(index, declaration)| {
+                let kind = match declaration.kind {
+                    DeclarationKind::Function => SymbolKind::FUNCTION,
+                    DeclarationKind::Class => SymbolKind::CLASS,
+                    _ => return None,
+                };
+
+                let methods = resolution
+                    .declarations
+                    .iter()
+                    .filter(|method| method.class == Some(index))
+                    .map(|method| {
+                        symbol(
+                            &method.name,
+                            &method.detail,
+                            SymbolKind::METHOD,
+                            method.span,
+                            None,
+                        )
+                    })
+                    .collect::<Vec<DocumentSymbol>>();
+                let children = match declaration.kind {
+                    DeclarationKind::Class => Some(methods),
+                    _ => None,
+                };
+
+                Some(symbol(
+                    &declaration.name,
+                    &declaration.detail,
+                    kind,
+                    declaration.span,
+                    children,
+                ))
+            })
+            .collect();
+
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+
+    /// Keywords and the names in scope at the cursor.
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let position = params.text_document_position;
+
+        let mut items = Lexer::new("")
+            .keywords()
+            .into_iter()
+            .map(|keyword| CompletionItem {
+                label: keyword.to_string(),
+                kind: Some(CompletionItemKind::KEYWORD),
+                ..CompletionItem::default()
+            })
+            .collect::<Vec<CompletionItem>>();
+
+        if let Some(resolution) = self.documents.get(&position.text_document.uri) {
+            for declaration in resolution.visible_at(span(position.position)) {
+                let kind = match declaration.kind {
+                    DeclarationKind::Function => CompletionItemKind::FUNCTION,
+                    DeclarationKind::Class => CompletionItemKind::CLASS,
+                    DeclarationKind::Method => CompletionItemKind::METHOD,
+                    DeclarationKind::Variable | DeclarationKind::Parameter => {
+                        CompletionItemKind::VARIABLE
+                    }
+                };
+
+                items.push(CompletionItem {
+                    label: declaration.name.clone(),
+                    kind: Some(kind),
+                    detail: Some(declaration.detail.clone()),
+                    ..CompletionItem::default()
+                });
+            }
+        }
+
+        Some(CompletionResponse::Array(items))
+    }
+}
+
+/// LSP positions count from 0, spans from 1. Columns are counted in
+/// characters on both sides, which only differs from LSP's UTF-16 units
+/// outside the basic multilingual plane.
+fn span(position: Position) -> Span {
+    Span {
+        line: position.line as usize + 1,
+        column: position.character as usize + 1,
+        offset: 0,
+    }
+}
+
+fn position(span: Span) -> Position {
+    Position::new(span.line as u32 - 1, span.column as u32 - 1)
+}
+
+fn range(span: Span, name: &str) -> Range {
+    let start = position(span);
+    Range::new(
+        start,
+        Position::new(start.line, start.character + name.chars().count() as u32),
+    )
+}
+
+fn diagnostic(span: Span, length: usize, message: String) -> Diagnostic {
+    let start = position(span);
+    Diagnostic {
+        range: Range::new(
+            start,
+            Position::new(start.line, start.character + length as u32),
+        ),
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some(String::from("mini-comp")),
+        message,
+        ..Diagnostic::default()
+    }
+}
+
+#[allow(deprecated)]
+fn symbol(
+    name: &str,
+    detail: &str,
+    kind: SymbolKind,
+    span: Span,
+    children: Option<Vec<DocumentSymbol>>,
+) -> DocumentSymbol {
+    DocumentSymbol {
+        name: name.to_string(),
+        detail: Some(detail.to_string()),
+        kind,
+        tags: None,
+        deprecated: None,
+        range: range(span, name),
+        selection_range: range(span, name),
+        children,
+    }
+}
@@ -0,0 +1,740 @@
+//! Static type checking. Variables, parameters and return values can be
+//! annotated with `int`, `float`, `string`, `bool`, `nil` or the name of a
+//! class. Anything that is not annotated gets the type of what it is first
+//! given, and where that cannot be worked out it is unknown, which is
+//! compatible with everything.
+
+use std::collections::HashMap;
+
+use crate::lexer::{Span, Token, TokenType};
+use crate::parser::expr::*;
+use crate::parser::stmt::Stmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Type {
+    Int,
+    Float,
+    String,
+    Bool,
+    Nil,
+    Function {
+        params: Vec<Type>,
+        ret: Box<Type>,
+    },
+    /// The class itself, which is called to make an instance of it.
+    Class(String),
+    Instance(String),
+    Unknown,
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::String => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+            Type::Nil => write!(f, "nil"),
+            Type::Function { params, ret } => {
+                let params = params.iter().map(Type::to_string).collect::<Vec<String>>();
+                write!(f, "def({}) -> {}", params.join(", "), ret)
+            }
+            Type::Class(name) => write!(f, "class {}", name),
+            Type::Instance(name) => write!(f, "{}", name),
+            Type::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeError {
+    pub span: Span,
+    pub message: String,
+}
+
+pub fn check(statements: &[Stmt]) -> Vec<TypeError> {
+    run(statements).errors
+}
+
+/// The types of both operands of every binary operator, keyed by the offset
+/// of the operator. Only a program without type errors has them.
+pub fn operand_types(
+    statements: &[Stmt],
+) -> Result<HashMap<usize, (Type, Type)>, Vec<TypeError>> {
+    let checker = run(statements);
+    match checker.errors.is_empty() {
+        true => Ok(checker.operands),
+        false => Err(checker.errors),
+    }
+}
+
+fn run(statements: &[Stmt]) -> Checker {
+    let mut checker = Checker {
+        scopes: vec![HashMap::new()],
+        classes: HashMap::new(),
+        functions: vec![],
+        class: None,
+        errors: vec![],
+        operands: HashMap::new(),
+    };
+    checker.statements(statements);
+
+    checker
+        .errors
+        .sort_by(|left, right| left.span.partial_cmp(&right.span).unwrap());
+    checker
+}
+
+struct Class {
+    super_class: Option<String>,
+    methods: HashMap<String, Type>,
+}
+
+/// The function whose body is being checked.
+struct Returns {
+    /// How messages refer to the function.
+    name: String,
+    /// The annotated return type.
+    declared: Option<Type>,
+    found: Vec<Type>,
+}
+
+struct Checker {
+    scopes: Vec<HashMap<String, Type>>,
+    classes: HashMap<String, Class>,
+    functions: Vec<Returns>,
+    /// The class whose methods are being checked, which `this` is an
+    /// instance of.
+    class: Option<String>,
+    errors: Vec<TypeError>,
+    operands: HashMap<usize, (Type, Type)>,
+}
+
+impl Checker {
+    fn report(&mut self, span: Span, message: String) {
+        self.errors.push(TypeError { span, message });
+    }
+
+    fn declare(&mut self, name: &str, ty: Type) {
+        self.scopes.last_mut().unwrap().insert(name.to_string(), ty);
+    }
+
+    /// Names that are never declared are globals the checker knows nothing
+    /// about.
+    fn lookup(&self, name: &str) -> Type {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+            .unwrap_or(Type::Unknown)
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) {
+        self.hoist(statements);
+        for stmt in statements {
+            self.statement(stmt);
+        }
+    }
+
+    fn block(&mut self, statements: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        self.statements(statements);
+        self.scopes.pop();
+    }
+
+    /// Classes and functions can be used before the statement declaring
+    /// them, by functions calling each other for example, so their
+    /// signatures are known up front. Classes go first since they can be
+    /// used as types in the signatures.
+    fn hoist(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            if let Stmt::Class {
+                name, super_class, ..
+            } = stmt
+            {
+                let name = name.kind.to_string();
+                let super_class = match super_class {
+                    Some(Expr::Variable(token)) => Some(token.kind.to_string()),
+                    _ => None,
+                };
+                self.classes.insert(
+                    name.clone(),
+                    Class {
+                        super_class,
+                        methods: HashMap::new(),
+                    },
+                );
+                self.declare(&name, Type::Class(name.clone()));
+            }
+        }
+
+        for stmt in statements {
+            match stmt {
+                Stmt::Function {
+                    name,
+                    param_types,
+                    return_type,
+                    ..
+                } => {
+                    let signature = self.signature(param_types, return_type.as_ref());
+                    self.declare(&name.kind.to_string(), signature);
+                }
+                Stmt::Class { name, methods, .. } => {
+                    for method in methods {
+                        if let Stmt::Function {
+                            name: method,
+                            param_types,
+                            return_type,
+                            ..
+                        } = method
+                        {
+                            let signature = self.signature(param_types, return_type.as_ref());
+                            if let Some(class) = self.classes.get_mut(&name.kind.to_string()) {
+                                class.methods.insert(method.kind.to_string(), signature);
+                            }
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block { statements } => self.block(statements),
+            Stmt::Expression(expr) | Stmt::Print(expr) => {
+                self.expr(expr);
+            }
+            Stmt::Let {
+                token,
+                annotation,
+                initilizer,
+            } => {
+                let value = self.expr(initilizer);
+                let ty = match annotation {
+                    Some(annotation) => {
+                        let declared = self.annotation(annotation);
+                        self.expect(&declared, &value, token.span, &quoted(token));
+                        declared
+                    }
+                    // `let x = nil;` is filled in later, with a value of
+                    // whatever type.
+                    None if value == Type::Nil => Type::Unknown,
+                    None => value,
+                };
+                self.declare(&token.kind.to_string(), ty);
+            }
+            Stmt::If { condition, branch } => {
+                self.expr(condition);
+                self.statement(branch);
+            }
+            Stmt::While { condition, body } => {
+                self.expr(condition);
+                self.statement(body);
+            }
+            Stmt::For {
+                initilizer,
+                condition,
+                increment,
+                body,
+            } => {
+                self.scopes.push(HashMap::new());
+                self.statement(initilizer);
+                self.expr(condition);
+                self.expr(increment);
+                self.statement(body);
+                self.scopes.pop();
+            }
+            Stmt::Function {
+                name,
+                params,
+                param_types,
+                return_type,
+                body,
+            } => {
+                let signature = match self.lookup(&name.kind.to_string()) {
+                    signature @ Type::Function { .. } => signature,
+                    _ => self.signature(param_types, return_type.as_ref()),
+                };
+                let signature =
+                    self.function(quoted(name), params, signature, return_type.is_some(), body);
+                self.declare(&name.kind.to_string(), signature);
+            }
+            Stmt::Return { keyword, value } => {
+                let found = match value {
+                    Some(value) => self.expr(value),
+                    None => Type::Nil,
+                };
+
+                if let Some(function) = self.functions.last() {
+                    if let Some(declared) = function.declared.clone() {
+                        if !self.assignable(&declared, &found) {
+                            let message = format!(
+                                "Expected {} to return {}, found {}",
+                                function.name, declared, found
+                            );
+                            self.report(keyword.span, message);
+                        }
+                    }
+                    self.functions.last_mut().unwrap().found.push(found);
+                }
+            }
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+            } => {
+                if let Some(super_class) = super_class {
+                    self.expr(super_class);
+                }
+
+                let class = name.kind.to_string();
+                let enclosing = self.class.replace(class.clone());
+
+                for method in methods {
+                    if let Stmt::Function {
+                        name,
+                        params,
+                        param_types,
+                        return_type,
+                        body,
+                    } = method
+                    {
+                        let method = name.kind.to_string();
+                        let signature = match self.method(&class, &method) {
+                            Some(signature) => signature,
+                            None => self.signature(param_types, return_type.as_ref()),
+                        };
+                        let signature = self.function(
+                            quoted(name),
+                            params,
+                            signature,
+                            return_type.is_some(),
+                            body,
+                        );
+                        if let Some(class) = self.classes.get_mut(&class) {
+                            class.methods.insert(method, signature);
+                        }
+                    }
+                }
+
+                self.class = enclosing;
+            }
+            Stmt::Throw { value, .. } => {
+                self.expr(value);
+            }
+            Stmt::Try {
+                body,
+                catch_name,
+                catch_body,
+                finally_body,
+            } => {
+                self.block(body);
+                if let Some(catch_body) = catch_body {
+                    self.scopes.push(HashMap::new());
+                    if let Some(catch_name) = catch_name {
+                        self.declare(&catch_name.kind.to_string(), Type::Unknown);
+                    }
+                    self.statements(catch_body);
+                    self.scopes.pop();
+                }
+                if let Some(finally_body) = finally_body {
+                    self.block(finally_body);
+                }
+            }
+            Stmt::Comment { .. } => (),
+        }
+    }
+
+    /// Checks the body of a function against its signature. When the return
+    /// type is not annotated it is worked out from the `return` statements.
+    fn function(
+        &mut self,
+        name: String,
+        params: &[Token],
+        signature: Type,
+        annotated: bool,
+        body: &[Stmt],
+    ) -> Type {
+        let (param_types, ret) = match signature {
+            Type::Function { params, ret } => (params, *ret),
+            _ => (vec![Type::Unknown; params.len()], Type::Unknown),
+        };
+
+        self.scopes.push(HashMap::new());
+        for (param, ty) in params.iter().zip(&param_types) {
+            self.declare(&param.kind.to_string(), ty.clone());
+        }
+        self.functions.push(Returns {
+            name,
+            declared: annotated.then(|| ret.clone()),
+            found: vec![],
+        });
+
+        self.statements(body);
+
+        let mut found = self.functions.pop().unwrap().found;
+        self.scopes.pop();
+
+        let ret = match annotated {
+            true => ret,
+            false => {
+                // Running off the end returns nil.
+                let last = body
+                    .iter()
+                    .rev()
+                    .find(|stmt| !matches!(stmt, Stmt::Comment { .. }));
+                if !matches!(last, Some(Stmt::Return { .. } | Stmt::Throw { .. })) {
+                    found.push(Type::Nil);
+                }
+                self.join(&found)
+            }
+        };
+
+        Type::Function {
+            params: param_types,
+            ret: Box::new(ret),
+        }
+    }
+
+    fn signature(&mut self, param_types: &[Option<Token>], return_type: Option<&Token>) -> Type {
+        let params = param_types
+            .iter()
+            .map(|annotation| match annotation {
+                Some(annotation) => self.annotation(annotation),
+                None => Type::Unknown,
+            })
+            .collect();
+        let ret = match return_type {
+            Some(annotation) => self.annotation(annotation),
+            None => Type::Unknown,
+        };
+
+        Type::Function {
+            params,
+            ret: Box::new(ret),
+        }
+    }
+
+    fn annotation(&mut self, annotation: &Token) -> Type {
+        match &annotation.kind {
+            TokenType::Identifier(name) => match name.as_str() {
+                "int" => Type::Int,
+                "float" => Type::Float,
+                "string" => Type::String,
+                "bool" => Type::Bool,
+                name if self.classes.contains_key(name) => Type::Instance(name.to_string()),
+                name => {
+                    self.report(annotation.span, format!("Unknown type '{}'", name));
+                    Type::Unknown
+                }
+            },
+            TokenType::Terminal(_) => Type::Nil,
+            _ => Type::Unknown,
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Type {
+        match expr {
+            Expr::Literal(literal) => match literal {
+                Literal::Integer(_) => Type::Int,
+                Literal::FloatingPoint(_) => Type::Float,
+                Literal::String(_) => Type::String,
+                Literal::Boolean(_) => Type::Bool,
+                Literal::Terminal(terminal) => match terminal.value.downcast_ref::<&str>() {
+                    Some(&"nil") => Type::Nil,
+                    Some(_) => Type::Bool,
+                    None => Type::Unknown,
+                },
+            },
+            Expr::Grouping(grouping) => self.expr(&grouping.expr),
+            Expr::Variable(token) => self.lookup(&token.kind.to_string()),
+            Expr::UnaryExpr(unary) => {
+                let right = self.expr(&unary.right);
+                let op = unary.op.kind.to_string();
+                match (op.as_str(), &right) {
+                    ("!", _) => Type::Bool,
+                    (_, Type::Unknown) => Type::Unknown,
+                    ("-", Type::Int | Type::Float) => right,
+                    ("~", Type::Int) => Type::Int,
+                    _ => {
+                        let message = format!("Cannot apply '{}' to {}", op, right);
+                        self.report(unary.op.span, message);
+                        Type::Unknown
+                    }
+                }
+            }
+            Expr::BinaryExpr(binary) => {
+                let left = self.expr(&binary.left);
+                let right = self.expr(&binary.right);
+                let ty = self.binary(&binary.op, &left, &right);
+                self.operands.insert(binary.op.span.offset, (left, right));
+                ty
+            }
+            Expr::Conditional(conditional) => {
+                self.expr(&conditional.condition);
+                let then_branch = self.expr(&conditional.then_branch);
+                let else_branch = self.expr(&conditional.else_branch);
+                self.union(&then_branch, &else_branch)
+            }
+            Expr::Assign(assign) => {
+                let value = self.expr(&assign.value);
+                let declared = self.lookup(&assign.name.kind.to_string());
+                self.expect(&declared, &value, assign.name.span, &quoted(&assign.name));
+                value
+            }
+            Expr::Update(update) => {
+                let target = self.expr(&update.target);
+                let change = self.expr(&update.change);
+                let value = self.binary(&update.op, &target, &change);
+
+                let name = match &*update.target {
+                    Expr::Get(get) => format!("property {}", quoted(&get.name)),
+                    Expr::Variable(token) => quoted(token),
+                    _ => String::from("the target"),
+                };
+                self.expect(&target, &value, update.op.span, &name);
+                target
+            }
+            Expr::Call(call) => self.call(call),
+            Expr::Lambda(lambda) => {
+                let signature = self.signature(&lambda.param_types, lambda.return_type.as_ref());
+                self.function(
+                    String::from("the function"),
+                    &lambda.params,
+                    signature,
+                    lambda.return_type.is_some(),
+                    &lambda.body,
+                )
+            }
+            Expr::Get(get) => {
+                let object = self.expr(&get.object);
+                match &object {
+                    // Fields are added by assigning to them, so anything
+                    // that is not a method is unknown.
+                    Type::Instance(class) => self
+                        .method(class, &get.name.kind.to_string())
+                        .unwrap_or(Type::Unknown),
+                    Type::Class(_) | Type::Unknown => Type::Unknown,
+                    _ => {
+                        let message = format!("{} has no property {}", object, quoted(&get.name));
+                        self.report(get.name.span, message);
+                        Type::Unknown
+                    }
+                }
+            }
+            Expr::Set(set) => {
+                let object = self.expr(&set.object);
+                let value = self.expr(&set.value);
+                if !matches!(object, Type::Instance(_) | Type::Class(_) | Type::Unknown) {
+                    let message =
+                        format!("Cannot set property {} on {}", quoted(&set.name), object);
+                    self.report(set.name.span, message);
+                }
+                value
+            }
+            Expr::This(_) => match &self.class {
+                Some(class) => Type::Instance(class.clone()),
+                None => Type::Unknown,
+            },
+            Expr::Super(expr) => {
+                let super_class = self
+                    .class
+                    .as_ref()
+                    .and_then(|class| self.classes.get(class))
+                    .and_then(|class| class.super_class.clone());
+                match super_class {
+                    Some(super_class) => self
+                        .method(&super_class, &expr.method.kind.to_string())
+                        .unwrap_or(Type::Unknown),
+                    None => Type::Unknown,
+                }
+            }
+        }
+    }
+
+    fn binary(&mut self, op: &Token, left: &Type, right: &Type) -> Type {
+        let text = op.kind.to_string();
+        let result = match (text.as_str(), left, right) {
+            ("==" | "!=", _, _) => Some(Type::Bool),
+            (_, Type::Unknown, _) | (_, _, Type::Unknown) => Some(Type::Unknown),
+            ("+", Type::String, Type::String) => Some(Type::String),
+            ("+" | "-" | "*" | "/" | "%" | "**", Type::Int, Type::Int) => Some(Type::Int),
+            (
+                "+" | "-" | "*" | "/" | "%" | "**",
+                Type::Int | Type::Float,
+                Type::Int | Type::Float,
+            ) => Some(Type::Float),
+            ("<" | "<=" | ">" | ">=", Type::Int | Type::Float, Type::Int | Type::Float)
+            | ("<" | "<=" | ">" | ">=", Type::String, Type::String) => Some(Type::Bool),
+            ("&" | "|" | "^" | "<<" | ">>", Type::Int, Type::Int) => Some(Type::Int),
+            _ => None,
+        };
+
+        match result {
+            Some(result) => result,
+            None => {
+                let message = format!("Cannot apply '{}' to {} and {}", text, left, right);
+                self.report(op.span, message);
+                Type::Unknown
+            }
+        }
+    }
+
+    fn call(&mut self, call: &CallExpr) -> Type {
+        let callee = self.expr(&call.callee);
+        let args = call
+            .args
+            .iter()
+            .map(|arg| (self.expr(arg), arg.start().unwrap_or(call.paren.span)))
+            .collect::<Vec<(Type, Span)>>();
+
+        let name = match &*call.callee {
+            Expr::Variable(token) => quoted(token),
+            Expr::Get(get) => quoted(&get.name),
+            _ => String::from("the function"),
+        };
+
+        match callee {
+            Type::Function { params, ret } => {
+                self.arguments(&name, &params, &args, call.paren.span);
+                *ret
+            }
+            Type::Class(class) => {
+                let params = match self.method(&class, "init") {
+                    Some(Type::Function { params, .. }) => params,
+                    _ => vec![],
+                };
+                self.arguments(&name, &params, &args, call.paren.span);
+                Type::Instance(class)
+            }
+            Type::Unknown => Type::Unknown,
+            callee => {
+                let message = format!("Cannot call a value of type {}", callee);
+                self.report(call.paren.span, message);
+                Type::Unknown
+            }
+        }
+    }
+
+    fn arguments(&mut self, name: &str, params: &[Type], args: &[(Type, Span)], span: Span) {
+        if params.len() != args.len() {
+            let message = format!(
+                "Expected {} argument{} for {}, found {}",
+                params.len(),
+                if params.len() == 1 { "" } else { "s" },
+                name,
+                args.len()
+            );
+            self.report(span, message);
+            return;
+        }
+
+        for (index, (param, (arg, span))) in params.iter().zip(args).enumerate() {
+            self.expect(
+                param,
+                arg,
+                *span,
+                &format!("argument {} of {}", index + 1, name),
+            );
+        }
+    }
+
+    /// Reports `found` where a value of type `expected` is needed.
+    fn expect(&mut self, expected: &Type, found: &Type, span: Span, what: &str) {
+        if !self.assignable(expected, found) {
+            let message = format!("Expected {} for {}, found {}", expected, what, found);
+            self.report(span, message);
+        }
+    }
+
+    /// Whether a value of type `found` can be stored where `expected` is
+    /// needed. Ints widen to floats and any instance may be nil.
+    fn assignable(&self, expected: &Type, found: &Type) -> bool {
+        match (expected, found) {
+            (Type::Unknown, _) | (_, Type::Unknown) => true,
+            (Type::Float, Type::Int) => true,
+            (Type::Instance(_), Type::Nil) => true,
+            (Type::Instance(expected), Type::Instance(found)) => self.extends(found, expected),
+            (
+                Type::Function {
+                    params: expected_params,
+                    ret: expected_ret,
+                },
+                Type::Function {
+                    params: found_params,
+                    ret: found_ret,
+                },
+            ) => {
+                expected_params.len() == found_params.len()
+                    && self.assignable(expected_ret, found_ret)
+                    && expected_params
+                        .iter()
+                        .zip(found_params)
+                        .all(|(expected, found)| self.assignable(found, expected))
+            }
+            _ => expected == found,
+        }
+    }
+
+    /// The type of a value that is either `left` or `right`.
+    fn union(&self, left: &Type, right: &Type) -> Type {
+        if self.assignable(left, right) {
+            left.clone()
+        } else if self.assignable(right, left) {
+            right.clone()
+        } else {
+            Type::Unknown
+        }
+    }
+
+    fn join(&self, types: &[Type]) -> Type {
+        match types.split_first() {
+            Some((first, rest)) => rest
+                .iter()
+                .fold(first.clone(), |joined, ty| self.union(&joined, ty)),
+            None => Type::Nil,
+        }
+    }
+
+    /// Whether `class` is `ancestor` or inherits from it.
+    fn extends(&self, class: &str, ancestor: &str) -> bool {
+        let mut class = Some(class);
+        // Bounded, in case of a class that extends itself.
+        for _ in 0..=self.classes.len() {
+            match class {
+                Some(name) if name == ancestor => return true,
+                Some(name) => {
+                    class = self
+                        .classes
+                        .get(name)
+                        .and_then(|class| class.super_class.as_deref())
+                }
+                None => return false,
+            }
+        }
+        false
+    }
+
+    /// Looks `name` up in `class` and the classes it inherits from.
+    fn method(&self, class: &str, name: &str) -> Option<Type> {
+        let mut class = self.classes.get(class);
+        for _ in 0..=self.classes.len() {
+            let current = class?;
+            if let Some(method) = current.methods.get(name) {
+                return Some(method.clone());
+            }
+            class = current
+                .super_class
+                .as_ref()
+                .and_then(|super_class| self.classes.get(super_class));
+        }
+        None
+    }
+}
+
+fn quoted(token: &Token) -> String {
+    format!("'{}'", token.kind)
+}
@@ -0,0 +1,425 @@
+use crate::lexer::Token;
+use crate::parser::expr::*;
+use crate::parser::stmt::Stmt;
+
+const INDENT: &str = "    ";
+
+/// Turns a parsed program back into canonically formatted source: four space
+/// indentation, one space around binary operators, opening braces on the same
+/// line and a blank line around every statement that spans several lines.
+#[derive(Default)]
+pub struct Formatter {
+    indent: usize,
+    out: String,
+    /// Comments between the head of the statement being written and its
+    /// body, when the body is not a block.
+    body_comments: Vec<(Token, bool)>,
+}
+
+impl Formatter {
+    pub fn new() -> Formatter {
+        Formatter::default()
+    }
+
+    pub fn format_program(&mut self, statements: &[Stmt]) -> String {
+        self.statements(statements, false);
+        std::mem::take(&mut self.out)
+    }
+
+    pub fn format_expr(&self, expr: &Expr) -> String {
+        self.expr(expr)
+    }
+
+    fn statements(&mut self, statements: &[Stmt], methods: bool) {
+        let mut previous: Option<&Stmt> = None;
+        let mut skip = 0;
+
+        for (index, stmt) in statements.iter().enumerate() {
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+
+            if let Stmt::Comment {
+                token,
+                trailing: true,
+                before_body: false,
+            } = stmt
+            {
+                if self.out.ends_with('\n') {
+                    self.out.pop();
+                    self.out += &format!(" {}\n", token.kind);
+                    continue;
+                }
+            }
+
+            if let Some(previous) = previous {
+                // Comments stick to the statement that follows them.
+                let next = statements[index..]
+                    .iter()
+                    .find(|stmt| !matches!(stmt, Stmt::Comment { .. }));
+
+                let blank_line = match (previous, stmt) {
+                    // Keep the blank lines that separate consecutive comments.
+                    (Stmt::Comment { token: above, .. }, Stmt::Comment { token: below, .. }) => {
+                        let text = above.kind.to_string();
+                        above.span.line + text.matches('\n').count() + 1 < below.span.line
+                    }
+                    (Stmt::Comment { .. }, _) => false,
+                    _ => {
+                        self.is_multiline(previous, methods)
+                            || next.is_some_and(|next| self.is_multiline(next, methods))
+                    }
+                };
+
+                if blank_line {
+                    self.out.push('\n');
+                }
+            }
+
+            // Comments in front of a body that is not a block come after the
+            // whole statement.
+            self.body_comments = statements[index + 1..]
+                .iter()
+                .map_while(|stmt| match stmt {
+                    Stmt::Comment {
+                        token,
+                        trailing,
+                        before_body: true,
+                    } => Some((token.clone(), *trailing)),
+                    _ => None,
+                })
+                .collect();
+            skip = self.body_comments.len();
+
+            self.statement(stmt, methods);
+            previous = Some(stmt);
+        }
+    }
+
+    fn is_multiline(&self, stmt: &Stmt, method: bool) -> bool {
+        let mut formatter = Formatter {
+            indent: self.indent,
+            ..Formatter::default()
+        };
+        formatter.statement(stmt, method);
+        formatter.out.trim_end().contains('\n')
+    }
+
+    fn statement(&mut self, stmt: &Stmt, method: bool) {
+        match stmt {
+            Stmt::Block { statements } => self.block("", statements, false),
+            Stmt::Expression(expr) => {
+                let text = format!("{};", self.expr(expr));
+                self.line(&text);
+            }
+            Stmt::Print(expr) => {
+                let text = format!("print {};", self.expr(expr));
+                self.line(&text);
+            }
+            Stmt::Let {
+                token,
+                annotation,
+                initilizer,
+            } => {
+                let text = format!(
+                    "let {}{} = {};",
+                    token.kind,
+                    annotation_text(annotation.as_ref()),
+                    self.expr(initilizer)
+                );
+                self.line(&text);
+            }
+            Stmt::If { condition, branch } => {
+                let head = format!("if ({})", self.expr(condition));
+                self.body(&head, branch);
+            }
+            Stmt::While { condition, body } => {
+                let head = format!("while ({})", self.expr(condition));
+                self.body(&head, body);
+            }
+            Stmt::For {
+                initilizer,
+                condition,
+                increment,
+                body,
+            } => {
+                let mut formatter = Formatter::new();
+                formatter.statement(initilizer, false);
+
+                let head = format!(
+                    "for ({} {}; {})",
+                    formatter.out.trim_end(),
+                    self.expr(condition),
+                    self.expr(increment)
+                );
+                self.body(&head, body);
+            }
+            Stmt::Function {
+                name,
+                params,
+                param_types,
+                return_type,
+                body,
+            } => {
+                let head = format!(
+                    "{}{}({}){}",
+                    if method { "" } else { "def " },
+                    name.kind,
+                    parameters(params, param_types),
+                    return_text(return_type.as_ref())
+                );
+                self.block(&head, body, false);
+            }
+            Stmt::Return { value, .. } => match value {
+                Some(value) => {
+                    let text = format!("return {};", self.expr(value));
+                    self.line(&text);
+                }
+                None => self.line("return;"),
+            },
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+            } => {
+                let head = match super_class {
+                    Some(super_class) => {
+                        format!("class {} extends {}", name.kind, self.expr(super_class))
+                    }
+                    None => format!("class {}", name.kind),
+                };
+                self.block(&head, methods, true);
+            }
+            Stmt::Throw { value, .. } => {
+                let text = format!("throw {};", self.expr(value));
+                self.line(&text);
+            }
+            Stmt::Try {
+                body,
+                catch_name,
+                catch_body,
+                finally_body,
+            } => {
+                self.block("try", body, false);
+
+                if let (Some(name), Some(catch_body)) = (catch_name, catch_body) {
+                    self.out.pop();
+                    self.block_tail(&format!(" catch ({})", name.kind), catch_body);
+                }
+
+                if let Some(finally_body) = finally_body {
+                    self.out.pop();
+                    self.block_tail(" finally", finally_body);
+                }
+            }
+            Stmt::Comment { token, .. } => {
+                let text = token.kind.to_string();
+                self.line(&text);
+            }
+        }
+    }
+
+    /// Bodies of `if`, `while` and `for`: blocks open on the same line, any
+    /// other statement goes on the next line, indented.
+    fn body(&mut self, head: &str, body: &Stmt) {
+        match body {
+            Stmt::Block { statements } => self.block(head, statements, false),
+            _ => {
+                // Comments after the head of a nested statement are left for
+                // its own body.
+                let count = match body.start() {
+                    Some(start) => self
+                        .body_comments
+                        .iter()
+                        .take_while(|(comment, _)| comment.span < start)
+                        .count(),
+                    None => self.body_comments.len(),
+                };
+                let comments: Vec<_> = self.body_comments.drain(..count).collect();
+                let mut comments = comments.into_iter().peekable();
+                match comments.next_if(|(_, trailing)| *trailing) {
+                    Some((comment, _)) => self.line(&format!("{} {}", head, comment.kind)),
+                    None => self.line(head),
+                }
+                self.indent += 1;
+                for (comment, _) in comments {
+                    self.line(&comment.kind.to_string());
+                }
+                self.statement(body, false);
+                self.indent -= 1;
+            }
+        }
+    }
+
+    fn block(&mut self, head: &str, statements: &[Stmt], methods: bool) {
+        let open = if head.is_empty() {
+            String::from("{")
+        } else {
+            format!("{} {{", head)
+        };
+
+        if statements.is_empty() {
+            self.line(&format!("{}}}", open));
+            return;
+        }
+
+        self.line(&open);
+        self.indent += 1;
+        self.statements(statements, methods);
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    /// Continues the line of a closing brace, as in `} catch (e) {`.
+    fn block_tail(&mut self, head: &str, statements: &[Stmt]) {
+        if statements.is_empty() {
+            self.out += &format!("{} {{}}\n", head);
+            return;
+        }
+
+        self.out += &format!("{} {{\n", head);
+        self.indent += 1;
+        self.statements(statements, false);
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn line(&mut self, text: &str) {
+        self.out += &INDENT.repeat(self.indent);
+        self.out += text;
+        self.out.push('\n');
+    }
+
+    fn expr(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::BinaryExpr(expr) => format!(
+                "{} {} {}",
+                self.expr(&expr.left),
+                expr.op.kind,
+                self.expr(&expr.right)
+            ),
+            Expr::UnaryExpr(expr) => {
+                let op = expr.op.kind.to_string();
+                let right = self.expr(&expr.right);
+
+                // `- -x` must not turn into the `--` operator.
+                if op == "-" && right.starts_with('-') {
+                    format!("{} {}", op, right)
+                } else {
+                    format!("{}{}", op, right)
+                }
+            }
+            Expr::Grouping(expr) => format!("({})", self.expr(&expr.expr)),
+            Expr::Conditional(expr) => format!(
+                "{} ? {} : {}",
+                self.expr(&expr.condition),
+                self.expr(&expr.then_branch),
+                self.expr(&expr.else_branch)
+            ),
+            Expr::Literal(literal) => match literal {
+                Literal::Integer(value) => value.to_string(),
+                Literal::FloatingPoint(value) => float_literal(*value),
+                Literal::String(value) => format!("\"{}\"", value),
+                Literal::Boolean(value) => value.to_string(),
+                Literal::Terminal(terminal) => match terminal.value.downcast_ref::<&str>() {
+                    Some(value) => value.to_string(),
+                    None => String::from("nil"),
+                },
+            },
+            Expr::Variable(token) => token.kind.to_string(),
+            Expr::Assign(expr) => format!("{} = {}", expr.name.kind, self.expr(&expr.value)),
+            Expr::Update(expr) => {
+                let target = self.expr(&expr.target);
+                let op = expr.op.kind.to_string();
+
+                match expr.kind {
+                    UpdateKind::Prefix => format!("{}{}{}", op, op, target),
+                    UpdateKind::Postfix => format!("{}{}{}", target, op, op),
+                    UpdateKind::Compound => {
+                        format!("{} {}= {}", target, op, self.expr(&expr.change))
+                    }
+                }
+            }
+            Expr::Call(expr) => {
+                let args = expr
+                    .args
+                    .iter()
+                    .map(|arg| self.expr(arg))
+                    .collect::<Vec<String>>();
+                format!("{}({})", self.expr(&expr.callee), args.join(", "))
+            }
+            Expr::Lambda(expr) => {
+                let head = format!(
+                    "def ({}){}",
+                    parameters(&expr.params, &expr.param_types),
+                    return_text(expr.return_type.as_ref())
+                );
+                if expr.body.is_empty() {
+                    return format!("{} {{}}", head);
+                }
+
+                let mut formatter = Formatter {
+                    indent: self.indent + 1,
+                    ..Formatter::default()
+                };
+                formatter.statements(&expr.body, false);
+
+                format!(
+                    "{} {{\n{}{}}}",
+                    head,
+                    formatter.out,
+                    INDENT.repeat(self.indent)
+                )
+            }
+            Expr::Get(expr) => format!("{}.{}", self.expr(&expr.object), expr.name.kind),
+            Expr::Set(expr) => format!(
+                "{}.{} = {}",
+                self.expr(&expr.object),
+                expr.name.kind,
+                self.expr(&expr.value)
+            ),
+            Expr::This(_) => String::from("this"),
+            Expr::Super(expr) => format!("super.{}", expr.method.kind),
+        }
+    }
+}
+
+/// Writes a float the way the lexer reads it back: plain digits without an
+/// exponent, and at least one digit after the point.
+pub fn float_literal(value: f64) -> String {
+    let text = value.to_string();
+    if text.contains('.') || !value.is_finite() {
+        text
+    } else {
+        format!("{}.0", text)
+    }
+}
+
+/// Parameters the way they are written in a signature. `types` holds the
+/// annotations of the first parameters, if any.
+pub fn parameters(params: &[Token], types: &[Option<Token>]) -> String {
+    params
+        .iter()
+        .enumerate()
+        .map(|(index, param)| {
+            let annotation = types.get(index).and_then(Option::as_ref);
+            format!("{}{}", param.kind, annotation_text(annotation))
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn annotation_text(annotation: Option<&Token>) -> String {
+    match annotation {
+        Some(annotation) => format!(": {}", annotation.kind),
+        None => String::new(),
+    }
+}
+
+fn return_text(return_type: Option<&Token>) -> String {
+    match return_type {
+        Some(return_type) => format!(" -> {}", return_type.kind),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,99 @@
+use super::escape;
+use crate::formatter::formatter::Formatter;
+use crate::parser::cst::NodeKind;
+use crate::parser::expr::{Expr, UpdateKind};
+use crate::parser::stmt::Stmt;
+use crate::parser::visitor::{walk_expr, walk_stmt, Visitor};
+
+/// Draws the statements as a DOT tree with one box per `Stmt` and `Expr`,
+/// labelled with the node kind and the name, operator or value it holds.
+pub fn ast_to_dot(statements: &[Stmt]) -> String {
+    let mut graph = AstGraph {
+        out: String::from("digraph ast {\n    node [shape=box];\n"),
+        parents: vec![],
+        count: 0,
+    };
+
+    graph.node(String::from("Program"));
+    for stmt in statements {
+        graph.visit_stmt(stmt);
+    }
+    graph.parents.pop();
+
+    graph.out += "}\n";
+    graph.out
+}
+
+struct AstGraph {
+    out: String,
+    parents: Vec<usize>,
+    count: usize,
+}
+
+impl AstGraph {
+    /// Adds a node under the current parent and makes it the parent of the
+    /// nodes that follow, until it is popped off `parents`.
+    fn node(&mut self, label: String) {
+        let id = self.count;
+        self.count += 1;
+
+        self.out += &format!("    n{} [label=\"{}\"];\n", id, escape(&label));
+        if let Some(parent) = self.parents.last() {
+            self.out += &format!("    n{} -> n{};\n", parent, id);
+        }
+        self.parents.push(id);
+    }
+}
+
+impl Visitor for AstGraph {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        let kind = format!("{:?}", NodeKind::from(stmt));
+        let label = match stmt {
+            Stmt::Let { token, .. } => format!("{} {}", kind, token.kind),
+            Stmt::Function { name, params, .. } => {
+                let params = params
+                    .iter()
+                    .map(|param| param.kind.to_string())
+                    .collect::<Vec<String>>();
+                format!("{} {}({})", kind, name.kind, params.join(", "))
+            }
+            Stmt::Class { name, .. } => format!("{} {}", kind, name.kind),
+            Stmt::Try {
+                catch_name: Some(name),
+                ..
+            } => format!("{} catch ({})", kind, name.kind),
+            Stmt::Comment { token, .. } => format!("{} {}", kind, token.kind),
+            _ => kind,
+        };
+
+        self.node(label);
+        walk_stmt(self, stmt);
+        self.parents.pop();
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        let kind = format!("{:?}", NodeKind::from(expr));
+        let label = match expr {
+            Expr::BinaryExpr(expr) => format!("{} {}", kind, expr.op.kind),
+            Expr::UnaryExpr(expr) => format!("{} {}", kind, expr.op.kind),
+            Expr::Literal(_) | Expr::Variable(_) => {
+                format!("{} {}", kind, Formatter::new().format_expr(expr))
+            }
+            Expr::Assign(expr) => format!("{} {}", kind, expr.name.kind),
+            Expr::Update(expr) => match expr.kind {
+                UpdateKind::Prefix | UpdateKind::Postfix => {
+                    format!("{} {}{}", kind, expr.op.kind, expr.op.kind)
+                }
+                UpdateKind::Compound => format!("{} {}=", kind, expr.op.kind),
+            },
+            Expr::Get(expr) => format!("{} .{}", kind, expr.name.kind),
+            Expr::Set(expr) => format!("{} .{}", kind, expr.name.kind),
+            Expr::Super(expr) => format!("{} .{}", kind, expr.method.kind),
+            _ => kind,
+        };
+
+        self.node(label);
+        walk_expr(self, expr);
+        self.parents.pop();
+    }
+}
@@ -0,0 +1,313 @@
+use super::escape;
+use crate::formatter::formatter::{parameters, Formatter};
+use crate::lexer::Token;
+use crate::parser::expr::{Expr, LambdaExpr};
+use crate::parser::stmt::Stmt;
+use crate::parser::visitor::{walk_block, Visitor};
+
+const ENTRY: usize = 0;
+const EXIT: usize = 1;
+
+/// A run of statements that is only entered at the top and only left at the
+/// bottom, through one of `edges`.
+#[derive(Debug, Default)]
+pub struct BasicBlock {
+    pub lines: Vec<String>,
+    pub edges: Vec<(usize, &'static str)>,
+}
+
+/// Control flow graph of one function body. Block 0 is the entry and block 1
+/// the exit that `return`, uncaught `throw` and falling off the end lead to.
+#[derive(Debug)]
+pub struct Cfg {
+    pub name: String,
+    pub blocks: Vec<BasicBlock>,
+    /// Where a `throw` inside the `try` bodies being lowered goes to.
+    handlers: Vec<usize>,
+}
+
+impl Cfg {
+    pub fn build(name: String, body: &[Stmt]) -> Cfg {
+        let mut cfg = Cfg {
+            name,
+            blocks: vec![BasicBlock::default(), BasicBlock::default()],
+            handlers: vec![],
+        };
+
+        let end = cfg.lower(body, ENTRY);
+        cfg.edge(end, EXIT, "");
+        cfg
+    }
+
+    fn block(&mut self) -> usize {
+        self.blocks.push(BasicBlock::default());
+        self.blocks.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize, label: &'static str) {
+        self.blocks[from].edges.push((to, label));
+    }
+
+    fn line(&mut self, block: usize, line: String) {
+        self.blocks[block].lines.push(line);
+    }
+
+    /// Adds `statements` to the graph, starting in `current`, and returns the
+    /// block that control leaves them from.
+    fn lower(&mut self, statements: &[Stmt], mut current: usize) -> usize {
+        for stmt in statements {
+            current = self.lower_stmt(stmt, current);
+        }
+        current
+    }
+
+    fn lower_stmt(&mut self, stmt: &Stmt, current: usize) -> usize {
+        match stmt {
+            Stmt::Block { statements } => self.lower(statements, current),
+            Stmt::If { condition, branch } => {
+                self.line(current, format!("if ({})", expr(condition)));
+
+                let then_block = self.block();
+                let after = self.block();
+                self.edge(current, then_block, "true");
+                self.edge(current, after, "false");
+
+                let end = self.lower_stmt(branch, then_block);
+                self.edge(end, after, "");
+                after
+            }
+            Stmt::While { condition, body } => {
+                let header = self.block();
+                self.edge(current, header, "");
+                self.line(header, format!("while ({})", expr(condition)));
+
+                self.lower_loop(header, body, None)
+            }
+            Stmt::For {
+                initilizer,
+                condition,
+                increment,
+                body,
+            } => {
+                let current = self.lower_stmt(initilizer, current);
+
+                let header = self.block();
+                self.edge(current, header, "");
+                self.line(header, format!("for (; {};)", expr(condition)));
+
+                self.lower_loop(header, body, Some(increment))
+            }
+            Stmt::Return { .. } | Stmt::Throw { .. } => {
+                self.line(current, statement(stmt));
+                match stmt {
+                    Stmt::Return { .. } => self.edge(current, EXIT, "return"),
+                    _ => {
+                        let handler = self.handlers.last().copied().unwrap_or(EXIT);
+                        self.edge(current, handler, "throw");
+                    }
+                }
+
+                // Anything after this point is unreachable.
+                self.block()
+            }
+            Stmt::Try {
+                body,
+                catch_name,
+                catch_body,
+                finally_body,
+            } => {
+                let try_block = self.block();
+                self.edge(current, try_block, "");
+                self.line(try_block, String::from("try"));
+
+                let after = self.block();
+                let join = match finally_body {
+                    Some(_) => self.block(),
+                    None => after,
+                };
+                let catch_block = match (catch_name, catch_body) {
+                    (Some(name), Some(_)) => {
+                        let catch_block = self.block();
+                        self.edge(try_block, catch_block, "catch");
+                        self.line(catch_block, format!("catch ({})", name.kind));
+                        Some(catch_block)
+                    }
+                    _ => None,
+                };
+
+                self.handlers.push(catch_block.unwrap_or(join));
+                let end = self.lower(body, try_block);
+                self.handlers.pop();
+                self.edge(end, join, "");
+
+                if let (Some(catch_block), Some(catch_body)) = (catch_block, catch_body) {
+                    let end = self.lower(catch_body, catch_block);
+                    self.edge(end, join, "");
+                }
+
+                if let Some(finally_body) = finally_body {
+                    self.line(join, String::from("finally"));
+                    let end = self.lower(finally_body, join);
+                    self.edge(end, after, "");
+                }
+
+                after
+            }
+            // Function and class bodies get graphs of their own.
+            Stmt::Function {
+                name,
+                params,
+                param_types,
+                ..
+            } => {
+                let params = parameters(params, param_types);
+                self.line(current, format!("def {}({})", name.kind, params));
+                current
+            }
+            Stmt::Class { name, .. } => {
+                self.line(current, format!("class {}", name.kind));
+                current
+            }
+            Stmt::Comment { .. } => current,
+            _ => {
+                self.line(current, statement(stmt));
+                current
+            }
+        }
+    }
+
+    /// `header` holds the loop condition. `increment` runs after every pass
+    /// through the body of a `for` loop.
+    fn lower_loop(&mut self, header: usize, body: &Stmt, increment: Option<&Expr>) -> usize {
+        let body_block = self.block();
+        let after = self.block();
+        self.edge(header, body_block, "true");
+        self.edge(header, after, "false");
+
+        let mut end = self.lower_stmt(body, body_block);
+        if let Some(increment) = increment {
+            let increment_block = self.block();
+            self.edge(end, increment_block, "");
+            self.line(increment_block, format!("{};", expr(increment)));
+            end = increment_block;
+        }
+        self.edge(end, header, "loop");
+
+        after
+    }
+
+    /// Blocks that can be reached from the entry, or that hold statements
+    /// even though they cannot.
+    fn visible(&self) -> Vec<bool> {
+        let mut reached = vec![false; self.blocks.len()];
+        let mut pending = vec![ENTRY];
+        while let Some(block) = pending.pop() {
+            if reached[block] {
+                continue;
+            }
+            reached[block] = true;
+            pending.extend(self.blocks[block].edges.iter().map(|(to, _)| *to));
+        }
+
+        reached
+            .iter()
+            .zip(&self.blocks)
+            .map(|(reached, block)| *reached || !block.lines.is_empty())
+            .collect()
+    }
+}
+
+/// Draws the top level code and every function, method and lambda in the
+/// program as its own cluster of basic blocks.
+pub fn cfg_to_dot(statements: &[Stmt]) -> String {
+    let mut functions = Functions {
+        class: None,
+        cfgs: vec![Cfg::build(String::from("<main>"), statements)],
+    };
+    walk_block(&mut functions, statements);
+
+    let mut out = String::from("digraph cfg {\n    node [shape=box, fontname=\"monospace\"];\n");
+
+    for (index, cfg) in functions.cfgs.iter().enumerate() {
+        out += &format!("    subgraph cluster_{} {{\n", index);
+        out += &format!("        label=\"{}\";\n", escape(&cfg.name));
+
+        let visible = cfg.visible();
+        for (id, block) in cfg.blocks.iter().enumerate() {
+            if !visible[id] {
+                continue;
+            }
+
+            let title = match id {
+                ENTRY => String::from("entry"),
+                EXIT => String::from("exit"),
+                _ => format!("B{}", id),
+            };
+            let mut label = escape(&title) + "\\l";
+            for line in &block.lines {
+                label += &escape(line);
+                label += "\\l";
+            }
+            out += &format!("        f{}_b{} [label=\"{}\"];\n", index, id, label);
+
+            for (to, edge) in &block.edges {
+                out += &format!("        f{}_b{} -> f{}_b{}", index, id, index, to);
+                if !edge.is_empty() {
+                    out += &format!(" [label=\"{}\"]", edge);
+                }
+                out += ";\n";
+            }
+        }
+
+        out += "    }\n";
+    }
+
+    out += "}\n";
+    out
+}
+
+/// Builds a graph for every function body it comes across, naming methods
+/// after their class.
+struct Functions {
+    class: Option<String>,
+    cfgs: Vec<Cfg>,
+}
+
+impl Visitor for Functions {
+    fn visit_function(&mut self, name: &Token, _params: &[Token], body: &[Stmt]) {
+        let name = match &self.class {
+            Some(class) => format!("{}.{}", class, name.kind),
+            None => name.kind.to_string(),
+        };
+        self.cfgs.push(Cfg::build(name, body));
+
+        let class = self.class.take();
+        walk_block(self, body);
+        self.class = class;
+    }
+
+    fn visit_class(&mut self, name: &Token, _super_class: Option<&Expr>, methods: &[Stmt]) {
+        let class = self.class.replace(name.kind.to_string());
+        walk_block(self, methods);
+        self.class = class;
+    }
+
+    fn visit_lambda(&mut self, expr: &LambdaExpr) {
+        let name = format!("def ({}) at {}", parameters(&expr.params, &expr.param_types), expr.keyword.span);
+        self.cfgs.push(Cfg::build(name, &expr.body));
+
+        let class = self.class.take();
+        walk_block(self, &expr.body);
+        self.class = class;
+    }
+}
+
+fn expr(expr: &Expr) -> String {
+    Formatter::new().format_expr(expr)
+}
+
+fn statement(stmt: &Stmt) -> String {
+    let text = Formatter::new().format_program(std::slice::from_ref(stmt));
+    text.trim_end().to_string()
+}
+
@@ -0,0 +1,10 @@
+pub mod ast;
+pub mod cfg;
+
+/// Quotes `text` for use inside a DOT string. Line breaks become
+/// left-justified DOT line breaks.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\l")
+}
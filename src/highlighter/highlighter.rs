@@ -0,0 +1,152 @@
+use crate::lexer::{PunctuationKind, Token, TokenType};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Category {
+    Keyword,
+    Identifier,
+    String,
+    Numeric,
+    Operator,
+    Comment,
+}
+
+impl Category {
+    /// CSS class of the category in HTML output.
+    pub fn class(&self) -> &'static str {
+        match self {
+            Category::Keyword => "keyword",
+            Category::Identifier => "identifier",
+            Category::String => "string",
+            Category::Numeric => "numeric",
+            Category::Operator => "operator",
+            Category::Comment => "comment",
+        }
+    }
+
+    fn ansi(&self) -> &'static str {
+        match self {
+            Category::Keyword => "\x1b[35m",
+            Category::Identifier => "\x1b[34m",
+            Category::String => "\x1b[32m",
+            Category::Numeric => "\x1b[33m",
+            Category::Operator => "\x1b[36m",
+            Category::Comment => "\x1b[90m",
+        }
+    }
+
+    /// Everything in the `keywords` table of the lexer comes out as a
+    /// `Terminal`. Braces, parentheses and separators have no category.
+    fn of(kind: &TokenType) -> Option<Category> {
+        match kind {
+            TokenType::Terminal(_) => Some(Category::Keyword),
+            TokenType::Identifier(_) => Some(Category::Identifier),
+            TokenType::String(_) | TokenType::Char(_) => Some(Category::String),
+            TokenType::Numeric { .. } => Some(Category::Numeric),
+            TokenType::Operator(_) | TokenType::Operations { .. } => Some(Category::Operator),
+            TokenType::Punctuation { kind, .. } => match kind {
+                PunctuationKind::Equal
+                | PunctuationKind::Bang
+                | PunctuationKind::Question
+                | PunctuationKind::Colon => Some(Category::Operator),
+                _ => None,
+            },
+            TokenType::Comment(_) => Some(Category::Comment),
+            TokenType::EOF => None,
+        }
+    }
+}
+
+/// Splits `source` into pieces with the category they are highlighted as.
+/// `tokens` must come from a lexer that keeps comments, so that everything
+/// between two tokens is whitespace. Joining the pieces gives back `source`.
+pub fn segments<'a>(source: &'a str, tokens: &[Token]) -> Vec<(Option<Category>, &'a str)> {
+    let mut segments = vec![];
+    let mut previous_end = 0;
+
+    for (index, token) in tokens.iter().enumerate() {
+        let start = token.span.offset;
+        let next = tokens
+            .get(index + 1)
+            .map_or(source.len(), |next| next.span.offset);
+        let end = start + source[start..next].trim_end().len();
+
+        if previous_end < start {
+            segments.push((None, &source[previous_end..start]));
+        }
+        if start < end {
+            segments.push((Category::of(&token.kind), &source[start..end]));
+        }
+        previous_end = end;
+    }
+
+    if previous_end < source.len() {
+        segments.push((None, &source[previous_end..]));
+    }
+
+    segments
+}
+
+pub fn to_ansi(segments: &[(Option<Category>, &str)]) -> String {
+    let mut out = String::new();
+
+    for (category, text) in segments {
+        match category {
+            Some(category) => {
+                out += category.ansi();
+                out += text;
+                out += "\x1b[0m";
+            }
+            None => out += text,
+        }
+    }
+
+    out
+}
+
+/// A standalone page with the code in a `<pre>` and a `<span>` with the
+/// class of its category around every highlighted piece.
+pub fn to_html(segments: &[(Option<Category>, &str)], title: &str) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{}</title>
+<style>
+pre.mini-comp {{ background: #fafafa; color: #24292e; padding: 1em; }}
+pre.mini-comp .keyword {{ color: #a626a4; font-weight: bold; }}
+pre.mini-comp .identifier {{ color: #4078f2; }}
+pre.mini-comp .string {{ color: #50a14f; }}
+pre.mini-comp .numeric {{ color: #986801; }}
+pre.mini-comp .operator {{ color: #0184bc; }}
+pre.mini-comp .comment {{ color: #a0a1a7; font-style: italic; }}
+</style>
+</head>
+<body>
+<pre class=\"mini-comp\">",
+        escape(title)
+    );
+
+    for (category, text) in segments {
+        match category {
+            Some(category) => {
+                out += &format!(
+                    "<span class=\"{}\">{}</span>",
+                    category.class(),
+                    escape(text)
+                )
+            }
+            None => out += &escape(text),
+        }
+    }
+
+    out += "</pre>\n</body>\n</html>\n";
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
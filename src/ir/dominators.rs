@@ -0,0 +1,173 @@
+//! Dominator tree and dominance frontiers of a function, computed with the
+//! algorithm of Cooper, Harvey and Kennedy.
+
+use super::ir::*;
+
+pub struct Dominators {
+    /// The blocks reachable from the entry block, in reverse postorder.
+    order: Vec<BlockId>,
+    /// `None` for the entry block and for unreachable blocks.
+    idom: Vec<Option<BlockId>>,
+    children: Vec<Vec<BlockId>>,
+    frontiers: Vec<Vec<BlockId>>,
+}
+
+impl Dominators {
+    pub fn new(function: &Function) -> Dominators {
+        let count = function.blocks.len();
+        let order = reverse_postorder(function);
+        let mut position = vec![usize::MAX; count];
+        for (index, block) in order.iter().enumerate() {
+            position[block.0] = index;
+        }
+        let predecessors = function.predecessors();
+        let predecessors = |block: BlockId| {
+            predecessors
+                .get(&block)
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|pred| position[pred.0] != usize::MAX)
+                .collect::<Vec<BlockId>>()
+        };
+
+        // The entry block is its own immediate dominator while iterating.
+        let mut idom = vec![None; count];
+        idom[0] = Some(BlockId(0));
+        let intersect = |idom: &[Option<BlockId>], mut a: BlockId, mut b: BlockId| {
+            while a != b {
+                while position[a.0] > position[b.0] {
+                    a = idom[a.0].unwrap();
+                }
+                while position[b.0] > position[a.0] {
+                    b = idom[b.0].unwrap();
+                }
+            }
+            a
+        };
+        loop {
+            let mut changed = false;
+            for block in order.iter().skip(1) {
+                let mut new_idom = None;
+                for pred in predecessors(*block) {
+                    if idom[pred.0].is_some() {
+                        new_idom = Some(match new_idom {
+                            Some(current) => intersect(&idom, pred, current),
+                            None => pred,
+                        });
+                    }
+                }
+                if new_idom != idom[block.0] {
+                    idom[block.0] = new_idom;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        idom[0] = None;
+
+        let mut children = vec![vec![]; count];
+        for block in &order {
+            if let Some(parent) = idom[block.0] {
+                children[parent.0].push(*block);
+            }
+        }
+
+        let mut frontiers: Vec<Vec<BlockId>> = vec![vec![]; count];
+        for block in &order {
+            let preds = predecessors(*block);
+            if preds.len() < 2 {
+                continue;
+            }
+            for pred in preds {
+                let mut runner = Some(pred);
+                while let Some(current) = runner {
+                    if Some(current) == idom[block.0] {
+                        break;
+                    }
+                    if !frontiers[current.0].contains(block) {
+                        frontiers[current.0].push(*block);
+                    }
+                    runner = idom[current.0];
+                }
+            }
+        }
+
+        Dominators {
+            order,
+            idom,
+            children,
+            frontiers,
+        }
+    }
+
+    pub fn reverse_postorder(&self) -> &[BlockId] {
+        &self.order
+    }
+
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        block.0 == 0 || self.idom[block.0].is_some()
+    }
+
+    pub fn idom(&self, block: BlockId) -> Option<BlockId> {
+        self.idom[block.0]
+    }
+
+    /// The blocks `block` immediately dominates.
+    pub fn children(&self, block: BlockId) -> &[BlockId] {
+        &self.children[block.0]
+    }
+
+    pub fn frontier(&self, block: BlockId) -> &[BlockId] {
+        &self.frontiers[block.0]
+    }
+
+    pub fn dominates(&self, a: BlockId, mut b: BlockId) -> bool {
+        loop {
+            if a == b {
+                return true;
+            }
+            match self.idom[b.0] {
+                Some(parent) => b = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// The reachable blocks in an order where every block comes after its
+    /// immediate dominator.
+    pub fn preorder(&self) -> Vec<BlockId> {
+        let mut blocks = vec![];
+        let mut stack = vec![BlockId(0)];
+        while let Some(block) = stack.pop() {
+            blocks.push(block);
+            stack.extend(self.children(block).iter().rev());
+        }
+        blocks
+    }
+}
+
+fn reverse_postorder(function: &Function) -> Vec<BlockId> {
+    let mut visited = vec![false; function.blocks.len()];
+    let mut postorder = vec![];
+    // Each entry is a block and how many of its successors have been seen.
+    let mut stack = vec![(BlockId(0), 0)];
+    visited[0] = true;
+    while let Some((block, next)) = stack.pop() {
+        let successors = function.blocks[block.0].terminator.successors();
+        match successors.get(next) {
+            Some(successor) => {
+                stack.push((block, next + 1));
+                if !visited[successor.0] {
+                    visited[successor.0] = true;
+                    stack.push((*successor, 0));
+                }
+            }
+            None => postorder.push(block),
+        }
+    }
+    postorder.reverse();
+    postorder
+}
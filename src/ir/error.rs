@@ -0,0 +1,12 @@
+use thiserror::Error;
+
+use crate::lexer::Span;
+
+/// Something in the program the IR cannot express, located where the tree
+/// keeps a token for it.
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct LowerError {
+    pub message: String,
+    pub span: Option<Span>,
+}
@@ -0,0 +1,818 @@
+//! Three-address intermediate representation. A module is made of functions,
+//! a function of basic blocks, and a block of instructions that each do one
+//! thing with registers followed by a terminator that says where control goes
+//! next. Top-level code is the function `<main>`, always the first one.
+//! Instructions that can fail when the program runs keep the span of the
+//! code they come from.
+
+use std::collections::HashMap;
+
+use crate::lexer::Span;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Ty {
+    Int,
+    Float,
+    Bool,
+    Str,
+    Nil,
+    /// Anything, only known when the program runs.
+    Any,
+}
+
+impl std::fmt::Display for Ty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Ty::Int => "int",
+            Ty::Float => "float",
+            Ty::Bool => "bool",
+            Ty::Str => "string",
+            Ty::Nil => "nil",
+            Ty::Any => "any",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+pub struct Reg(pub usize);
+
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+pub struct BlockId(pub usize);
+
+impl std::fmt::Display for BlockId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Constant {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+    Nil,
+}
+
+impl Constant {
+    pub fn ty(&self) -> Ty {
+        match self {
+            Constant::Int(_) => Ty::Int,
+            Constant::Float(_) => Ty::Float,
+            Constant::Str(_) => Ty::Str,
+            Constant::Bool(_) => Ty::Bool,
+            Constant::Nil => Ty::Nil,
+        }
+    }
+}
+
+impl std::fmt::Display for Constant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Constant::Int(value) => write!(f, "{}", value),
+            Constant::Float(value) => write!(f, "{:?}", value),
+            Constant::Str(value) => write!(f, "{:?}", value),
+            Constant::Bool(value) => write!(f, "{}", value),
+            Constant::Nil => write!(f, "nil"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    BitNot,
+    /// Makes an int a float and leaves anything else as it is. Written where
+    /// a value goes into a slot declared `float`.
+    ToFloat,
+}
+
+impl std::fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            UnaryOp::Neg => "neg",
+            UnaryOp::Not => "not",
+            UnaryOp::BitNot => "bitnot",
+            UnaryOp::ToFloat => "to_float",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    /// An integer to a negative integer power is a runtime error, so that
+    /// the result of two integers is always an integer.
+    Pow,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinaryOp {
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge
+        )
+    }
+}
+
+impl std::fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            BinaryOp::Div => "div",
+            BinaryOp::Rem => "rem",
+            BinaryOp::Pow => "pow",
+            BinaryOp::BitAnd => "and",
+            BinaryOp::BitOr => "or",
+            BinaryOp::BitXor => "xor",
+            BinaryOp::Shl => "shl",
+            BinaryOp::Shr => "shr",
+            BinaryOp::Eq => "eq",
+            BinaryOp::Ne => "ne",
+            BinaryOp::Lt => "lt",
+            BinaryOp::Le => "le",
+            BinaryOp::Gt => "gt",
+            BinaryOp::Ge => "ge",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Instr {
+    Const {
+        dest: Reg,
+        value: Constant,
+    },
+    Copy {
+        dest: Reg,
+        src: Reg,
+    },
+    Unary {
+        dest: Reg,
+        op: UnaryOp,
+        src: Reg,
+        span: Span,
+    },
+    Binary {
+        dest: Reg,
+        op: BinaryOp,
+        left: Reg,
+        right: Reg,
+        span: Span,
+    },
+    LoadGlobal {
+        dest: Reg,
+        name: String,
+        span: Span,
+    },
+    StoreGlobal {
+        name: String,
+        src: Reg,
+    },
+    /// A function of the module as a value.
+    FunctionRef {
+        dest: Reg,
+        name: String,
+    },
+    /// Calls a function of the module.
+    Call {
+        dest: Reg,
+        function: String,
+        args: Vec<Reg>,
+        span: Span,
+    },
+    /// Calls whatever function value `callee` holds.
+    CallIndirect {
+        dest: Reg,
+        callee: Reg,
+        args: Vec<Reg>,
+        span: Span,
+    },
+    /// Makes an instance of `class` and runs its `init` method, if it has
+    /// one, with `args`.
+    New {
+        dest: Reg,
+        class: String,
+        args: Vec<Reg>,
+        span: Span,
+    },
+    CallMethod {
+        dest: Reg,
+        object: Reg,
+        method: String,
+        args: Vec<Reg>,
+        span: Span,
+    },
+    /// Calls `method` as `class` defines it, with `object` as `this`.
+    CallSuper {
+        dest: Reg,
+        class: String,
+        method: String,
+        object: Reg,
+        args: Vec<Reg>,
+        span: Span,
+    },
+    GetProperty {
+        dest: Reg,
+        object: Reg,
+        name: String,
+        span: Span,
+    },
+    SetProperty {
+        object: Reg,
+        name: String,
+        src: Reg,
+        span: Span,
+    },
+    Print {
+        src: Reg,
+    },
+    /// Stops the innermost `try` from catching what is thrown.
+    EndTry,
+    /// What was thrown, first in the handler of a `try`.
+    Caught {
+        dest: Reg,
+    },
+    /// Only in SSA form, at the start of a block: the value `dest` gets
+    /// depends on the block control came from.
+    Phi {
+        dest: Reg,
+        args: Vec<(BlockId, Reg)>,
+    },
+}
+
+impl Instr {
+    /// The register this instruction writes, if any.
+    pub fn dest(&self) -> Option<Reg> {
+        match self {
+            Instr::Const { dest, .. }
+            | Instr::Copy { dest, .. }
+            | Instr::Unary { dest, .. }
+            | Instr::Binary { dest, .. }
+            | Instr::LoadGlobal { dest, .. }
+            | Instr::FunctionRef { dest, .. }
+            | Instr::Call { dest, .. }
+            | Instr::CallIndirect { dest, .. }
+            | Instr::New { dest, .. }
+            | Instr::CallMethod { dest, .. }
+            | Instr::CallSuper { dest, .. }
+            | Instr::GetProperty { dest, .. }
+            | Instr::Caught { dest }
+            | Instr::Phi { dest, .. } => Some(*dest),
+            Instr::StoreGlobal { .. }
+            | Instr::SetProperty { .. }
+            | Instr::Print { .. }
+            | Instr::EndTry => None,
+        }
+    }
+
+    /// Where the instruction comes from, if it can fail at runtime.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Instr::Unary { span, .. }
+            | Instr::Binary { span, .. }
+            | Instr::LoadGlobal { span, .. }
+            | Instr::Call { span, .. }
+            | Instr::CallIndirect { span, .. }
+            | Instr::New { span, .. }
+            | Instr::CallMethod { span, .. }
+            | Instr::CallSuper { span, .. }
+            | Instr::GetProperty { span, .. }
+            | Instr::SetProperty { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    pub fn span_mut(&mut self) -> Option<&mut Span> {
+        match self {
+            Instr::Unary { span, .. }
+            | Instr::Binary { span, .. }
+            | Instr::LoadGlobal { span, .. }
+            | Instr::Call { span, .. }
+            | Instr::CallIndirect { span, .. }
+            | Instr::New { span, .. }
+            | Instr::CallMethod { span, .. }
+            | Instr::CallSuper { span, .. }
+            | Instr::GetProperty { span, .. }
+            | Instr::SetProperty { span, .. } => Some(span),
+            _ => None,
+        }
+    }
+
+    /// The registers this instruction reads, in order.
+    pub fn uses(&self) -> Vec<Reg> {
+        match self {
+            Instr::Const { .. }
+            | Instr::LoadGlobal { .. }
+            | Instr::FunctionRef { .. }
+            | Instr::EndTry
+            | Instr::Caught { .. } => vec![],
+            Instr::Copy { src, .. }
+            | Instr::Unary { src, .. }
+            | Instr::StoreGlobal { src, .. }
+            | Instr::Print { src } => vec![*src],
+            Instr::Binary { left, right, .. } => vec![*left, *right],
+            Instr::Call { args, .. } | Instr::New { args, .. } => args.clone(),
+            Instr::CallIndirect {
+                callee: object,
+                args,
+                ..
+            }
+            | Instr::CallMethod { object, args, .. }
+            | Instr::CallSuper { object, args, .. } => std::iter::once(*object)
+                .chain(args.iter().copied())
+                .collect(),
+            Instr::GetProperty { object, .. } => vec![*object],
+            Instr::SetProperty { object, src, .. } => vec![*object, *src],
+            Instr::Phi { args, .. } => args.iter().map(|(_, reg)| *reg).collect(),
+        }
+    }
+
+    pub fn dest_mut(&mut self) -> Option<&mut Reg> {
+        match self {
+            Instr::Const { dest, .. }
+            | Instr::Copy { dest, .. }
+            | Instr::Unary { dest, .. }
+            | Instr::Binary { dest, .. }
+            | Instr::LoadGlobal { dest, .. }
+            | Instr::FunctionRef { dest, .. }
+            | Instr::Call { dest, .. }
+            | Instr::CallIndirect { dest, .. }
+            | Instr::New { dest, .. }
+            | Instr::CallMethod { dest, .. }
+            | Instr::CallSuper { dest, .. }
+            | Instr::GetProperty { dest, .. }
+            | Instr::Caught { dest }
+            | Instr::Phi { dest, .. } => Some(dest),
+            Instr::StoreGlobal { .. }
+            | Instr::SetProperty { .. }
+            | Instr::Print { .. }
+            | Instr::EndTry => None,
+        }
+    }
+
+    /// The registers this instruction reads, for rewriting them.
+    pub fn uses_mut(&mut self) -> Vec<&mut Reg> {
+        match self {
+            Instr::Const { .. }
+            | Instr::LoadGlobal { .. }
+            | Instr::FunctionRef { .. }
+            | Instr::EndTry
+            | Instr::Caught { .. } => vec![],
+            Instr::Copy { src, .. }
+            | Instr::Unary { src, .. }
+            | Instr::StoreGlobal { src, .. }
+            | Instr::Print { src } => vec![src],
+            Instr::Binary { left, right, .. } => vec![left, right],
+            Instr::Call { args, .. } | Instr::New { args, .. } => args.iter_mut().collect(),
+            Instr::CallIndirect {
+                callee: object,
+                args,
+                ..
+            }
+            | Instr::CallMethod { object, args, .. }
+            | Instr::CallSuper { object, args, .. } => {
+                std::iter::once(object).chain(args.iter_mut()).collect()
+            }
+            Instr::GetProperty { object, .. } => vec![object],
+            Instr::SetProperty { object, src, .. } => vec![object, src],
+            Instr::Phi { args, .. } => args.iter_mut().map(|(_, reg)| reg).collect(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch {
+        condition: Reg,
+        then_block: BlockId,
+        else_block: BlockId,
+    },
+    Return(Reg),
+    /// Sets `handler` to catch what is thrown until an `EndTry` and goes on
+    /// to `body`. A throw also ends the `try`, before jumping to `handler`
+    /// from wherever it happens, in this function or one it calls.
+    Try {
+        body: BlockId,
+        handler: BlockId,
+    },
+    Throw {
+        value: Reg,
+        span: Span,
+    },
+    /// Throws again what a handler caught, once the `finally` block of the
+    /// `try` has run.
+    Rethrow(Reg),
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                then_block,
+                else_block,
+                ..
+            } => vec![*then_block, *else_block],
+            Terminator::Try { body, handler } => vec![*body, *handler],
+            Terminator::Return(_) | Terminator::Throw { .. } | Terminator::Rethrow(_) => vec![],
+        }
+    }
+
+    pub fn successors_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![target],
+            Terminator::Branch {
+                then_block,
+                else_block,
+                ..
+            } => vec![then_block, else_block],
+            Terminator::Try { body, handler } => vec![body, handler],
+            Terminator::Return(_) | Terminator::Throw { .. } | Terminator::Rethrow(_) => vec![],
+        }
+    }
+
+    pub fn uses(&self) -> Vec<Reg> {
+        match self {
+            Terminator::Jump(_) | Terminator::Try { .. } => vec![],
+            Terminator::Branch { condition, .. } => vec![*condition],
+            Terminator::Return(value)
+            | Terminator::Throw { value, .. }
+            | Terminator::Rethrow(value) => vec![*value],
+        }
+    }
+
+    pub fn uses_mut(&mut self) -> Vec<&mut Reg> {
+        match self {
+            Terminator::Jump(_) | Terminator::Try { .. } => vec![],
+            Terminator::Branch { condition, .. } => vec![condition],
+            Terminator::Return(value)
+            | Terminator::Throw { value, .. }
+            | Terminator::Rethrow(value) => vec![value],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Block {
+    pub instrs: Vec<Instr>,
+    pub terminator: Terminator,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Register {
+    /// The variable or parameter this register holds, `None` for
+    /// temporaries.
+    pub name: Option<String>,
+    pub ty: Ty,
+    /// The type comes from an annotation rather than being inferred.
+    pub declared: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<Reg>,
+    pub ret: Ty,
+    /// The return type comes from an annotation rather than being inferred.
+    pub ret_declared: bool,
+    pub registers: Vec<Register>,
+    /// The entry block is the first one.
+    pub blocks: Vec<Block>,
+}
+
+impl Function {
+    /// How `reg` is written in the textual form: `%name` for variables and
+    /// parameters, with the register number added when the name is used by
+    /// more than one register, and `%number` for temporaries.
+    pub fn reg_name(&self, reg: Reg) -> String {
+        match &self.registers[reg.0].name {
+            Some(name) => {
+                let shared = self
+                    .registers
+                    .iter()
+                    .filter(|register| register.name.as_ref() == Some(name))
+                    .count()
+                    > 1;
+                match shared {
+                    true => format!("%{}.{}", name, reg.0),
+                    false => format!("%{}", name),
+                }
+            }
+            None => format!("%{}", reg.0),
+        }
+    }
+
+    pub fn ty(&self, reg: Reg) -> Ty {
+        self.registers[reg.0].ty
+    }
+
+    /// Whether the function has a `try`. The jumps a throw makes to its
+    /// handler are not edges of the graph, so optimizations leave such
+    /// functions alone.
+    pub fn catches(&self) -> bool {
+        self.blocks
+            .iter()
+            .any(|block| matches!(block.terminator, Terminator::Try { .. }))
+    }
+
+    /// Drops the blocks that cannot be reached from the entry block and
+    /// renumbers the rest in their original order.
+    pub fn remove_unreachable(&mut self) {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut stack = vec![BlockId(0)];
+        while let Some(block) = stack.pop() {
+            if !reachable[block.0] {
+                reachable[block.0] = true;
+                stack.extend(self.blocks[block.0].terminator.successors());
+            }
+        }
+
+        let mut numbers = HashMap::new();
+        for (index, _) in reachable.iter().enumerate().filter(|(_, reachable)| **reachable) {
+            numbers.insert(BlockId(index), BlockId(numbers.len()));
+        }
+
+        let blocks = std::mem::take(&mut self.blocks);
+        self.blocks = blocks
+            .into_iter()
+            .zip(reachable)
+            .filter(|(_, reachable)| *reachable)
+            .map(|(mut block, _)| {
+                for target in block.terminator.successors_mut() {
+                    *target = numbers[target];
+                }
+                for instr in &mut block.instrs {
+                    if let Instr::Phi { args, .. } = instr {
+                        args.retain(|(from, _)| numbers.contains_key(from));
+                        for (from, _) in args {
+                            *from = numbers[from];
+                        }
+                    }
+                }
+                block
+            })
+            .collect();
+    }
+
+    /// Drops the registers nothing refers to and numbers the rest from 0.
+    pub fn compact_registers(&mut self) {
+        let mut used = vec![false; self.registers.len()];
+        for param in &self.params {
+            used[param.0] = true;
+        }
+        for block in &self.blocks {
+            for instr in &block.instrs {
+                for reg in instr.uses().into_iter().chain(instr.dest()) {
+                    used[reg.0] = true;
+                }
+            }
+            for reg in block.terminator.uses() {
+                used[reg.0] = true;
+            }
+        }
+
+        let mut numbers = vec![Reg(0); self.registers.len()];
+        let mut registers = vec![];
+        for (index, register) in self.registers.drain(..).enumerate() {
+            if used[index] {
+                numbers[index] = Reg(registers.len());
+                registers.push(register);
+            }
+        }
+        self.registers = registers;
+
+        for param in &mut self.params {
+            *param = numbers[param.0];
+        }
+        for block in &mut self.blocks {
+            for instr in &mut block.instrs {
+                for reg in instr.uses_mut() {
+                    *reg = numbers[reg.0];
+                }
+                if let Some(dest) = instr.dest_mut() {
+                    *dest = numbers[dest.0];
+                }
+            }
+            for reg in block.terminator.uses_mut() {
+                *reg = numbers[reg.0];
+            }
+        }
+    }
+
+    /// The blocks that jump or branch to each block.
+    pub fn predecessors(&self) -> HashMap<BlockId, Vec<BlockId>> {
+        let mut predecessors: HashMap<BlockId, Vec<BlockId>> = HashMap::new();
+        for (index, block) in self.blocks.iter().enumerate() {
+            for successor in block.terminator.successors() {
+                predecessors
+                    .entry(successor)
+                    .or_default()
+                    .push(BlockId(index));
+            }
+        }
+        predecessors
+    }
+
+    fn args(&self, args: &[Reg]) -> String {
+        args.iter()
+            .map(|arg| self.reg_name(*arg))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    fn instr(&self, instr: &Instr) -> String {
+        let r = |reg: &Reg| self.reg_name(*reg);
+
+        let value = match instr {
+            Instr::Const { value, .. } => format!("const {}", value),
+            Instr::Copy { src, .. } => format!("copy {}", r(src)),
+            Instr::Unary { op, src, .. } => format!("{} {}", op, r(src)),
+            Instr::Binary {
+                op, left, right, ..
+            } => format!("{} {}, {}", op, r(left), r(right)),
+            Instr::LoadGlobal { name, .. } => format!("load_global {}", name),
+            Instr::FunctionRef { name, .. } => format!("function {}", name),
+            Instr::Call { function, args, .. } => {
+                format!("call {}({})", function, self.args(args))
+            }
+            Instr::CallIndirect { callee, args, .. } => {
+                format!("call_indirect {}({})", r(callee), self.args(args))
+            }
+            Instr::New { class, args, .. } => format!("new {}({})", class, self.args(args)),
+            Instr::CallMethod {
+                object,
+                method,
+                args,
+                ..
+            } => format!("call_method {}.{}({})", r(object), method, self.args(args)),
+            Instr::CallSuper {
+                class,
+                method,
+                object,
+                args,
+                ..
+            } => format!(
+                "call_super {}.{}({}; {})",
+                class,
+                method,
+                r(object),
+                self.args(args)
+            ),
+            Instr::GetProperty { object, name, .. } => format!("get {}.{}", r(object), name),
+            Instr::StoreGlobal { name, src } => {
+                return format!("store_global {}, {}", name, r(src))
+            }
+            Instr::SetProperty {
+                object, name, src, ..
+            } => {
+                return format!("set {}.{}, {}", r(object), name, r(src))
+            }
+            Instr::Print { src } => return format!("print {}", r(src)),
+            Instr::EndTry => return String::from("end_try"),
+            Instr::Caught { .. } => String::from("caught"),
+            Instr::Phi { args, .. } => {
+                let args = args
+                    .iter()
+                    .map(|(block, reg)| format!("[{}: {}]", block, r(reg)))
+                    .collect::<Vec<String>>();
+                format!("phi {}", args.join(", "))
+            }
+        };
+
+        let dest = instr.dest().unwrap();
+        format!("{}: {} = {}", r(&dest), self.ty(dest), value)
+    }
+}
+
+impl std::fmt::Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let params = self
+            .params
+            .iter()
+            .map(|param| format!("{}: {}", self.reg_name(*param), self.ty(*param)))
+            .collect::<Vec<String>>();
+        writeln!(
+            f,
+            "def {}({}) -> {} {{",
+            self.name,
+            params.join(", "),
+            self.ret
+        )?;
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "{}:", BlockId(index))?;
+            for instr in &block.instrs {
+                writeln!(f, "    {}", self.instr(instr))?;
+            }
+            let terminator = match &block.terminator {
+                Terminator::Jump(target) => format!("jump {}", target),
+                Terminator::Branch {
+                    condition,
+                    then_block,
+                    else_block,
+                } => format!(
+                    "branch {}, {}, {}",
+                    self.reg_name(*condition),
+                    then_block,
+                    else_block
+                ),
+                Terminator::Return(value) => format!("return {}", self.reg_name(*value)),
+                Terminator::Try { body, handler } => format!("try {}, {}", body, handler),
+                Terminator::Throw { value, .. } => format!("throw {}", self.reg_name(*value)),
+                Terminator::Rethrow(value) => format!("rethrow {}", self.reg_name(*value)),
+            };
+            writeln!(f, "    {}", terminator)?;
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Class {
+    pub name: String,
+    pub super_class: Option<String>,
+    /// Method names and the functions implementing them.
+    pub methods: Vec<(String, String)>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Global {
+    pub name: String,
+    pub ty: Ty,
+    /// The type comes from an annotation rather than being inferred.
+    pub declared: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Module {
+    pub globals: Vec<Global>,
+    pub classes: Vec<Class>,
+    pub functions: Vec<Function>,
+}
+
+impl Module {
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    pub fn class(&self, name: &str) -> Option<&Class> {
+        self.classes.iter().find(|class| class.name == name)
+    }
+}
+
+impl std::fmt::Display for Module {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for global in &self.globals {
+            writeln!(f, "global {}: {}", global.name, global.ty)?;
+        }
+        if !self.globals.is_empty() {
+            writeln!(f)?;
+        }
+
+        for class in &self.classes {
+            match &class.super_class {
+                Some(super_class) => {
+                    writeln!(f, "class {} extends {} {{", class.name, super_class)?
+                }
+                None => writeln!(f, "class {} {{", class.name)?,
+            }
+            for (method, function) in &class.methods {
+                writeln!(f, "    {} = {}", method, function)?;
+            }
+            writeln!(f, "}}")?;
+            writeln!(f)?;
+        }
+
+        for (index, function) in self.functions.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", function)?;
+        }
+        Ok(())
+    }
+}
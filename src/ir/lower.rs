@@ -0,0 +1,1033 @@
+//! Lowering of the AST to the IR. Top-level variables become globals, and
+//! variables and parameters inside functions become registers. Functions
+//! and methods are lifted to the module level. A `finally` block is lowered
+//! once for every way out of its `try`. Functions that use variables of the
+//! function around them have no IR form yet.
+
+use std::collections::HashMap;
+
+use super::error::LowerError;
+use super::ir::*;
+use super::types::infer_types;
+use crate::lexer::{OperationKind, OperatorKind, Span, Token, TokenType};
+use crate::parser::expr::*;
+use crate::parser::stmt::Stmt;
+
+pub fn lower(statements: &[Stmt]) -> Result<Module, LowerError> {
+    let mut lowerer = Lowerer {
+        module: Module::default(),
+        scopes: vec![],
+        builders: vec![],
+        classes: vec![],
+        lambdas: 0,
+    };
+
+    lowerer.builders.push(Builder::new("<main>"));
+    lowerer.scopes.push(Scope {
+        depth: 0,
+        bindings: HashMap::new(),
+    });
+    lowerer.statements(statements)?;
+    let main = lowerer.finish();
+
+    let mut module = lowerer.module;
+    module.functions.insert(0, main);
+    infer_types(&mut module);
+    Ok(module)
+}
+
+#[derive(Debug, Clone)]
+enum Binding {
+    Register(Reg),
+    Global(String),
+    Function(String),
+    Class(String),
+}
+
+struct Scope {
+    /// How many functions deep the scope is, 0 for top-level code.
+    depth: usize,
+    bindings: HashMap<String, Binding>,
+}
+
+/// A function whose blocks are still being filled in.
+struct Builder<'a> {
+    name: String,
+    params: Vec<Reg>,
+    ret: Option<Ty>,
+    registers: Vec<Register>,
+    blocks: Vec<(Vec<Instr>, Option<Terminator>)>,
+    current: usize,
+    /// The `finally` block, if any, of every `try` whose handler is set,
+    /// innermost last.
+    tries: Vec<Option<&'a [Stmt]>>,
+}
+
+impl Builder<'_> {
+    fn new(name: &str) -> Self {
+        Builder {
+            name: name.to_string(),
+            params: vec![],
+            ret: None,
+            registers: vec![],
+            blocks: vec![(vec![], None)],
+            current: 0,
+            tries: vec![],
+        }
+    }
+}
+
+struct Lowerer<'a> {
+    module: Module,
+    scopes: Vec<Scope>,
+    builders: Vec<Builder<'a>>,
+    /// The classes whose methods are being lowered, with their superclass.
+    classes: Vec<(String, Option<String>)>,
+    lambdas: usize,
+}
+
+impl<'a> Lowerer<'a> {
+    fn builder(&mut self) -> &mut Builder<'a> {
+        self.builders.last_mut().unwrap()
+    }
+
+    fn register(&mut self, name: Option<String>, ty: Option<Ty>) -> Reg {
+        let builder = self.builder();
+        builder.registers.push(Register {
+            name,
+            ty: ty.unwrap_or(Ty::Any),
+            declared: ty.is_some(),
+        });
+        Reg(builder.registers.len() - 1)
+    }
+
+    fn temp(&mut self) -> Reg {
+        self.register(None, None)
+    }
+
+    fn emit(&mut self, instr: Instr) {
+        let builder = self.builder();
+        builder.blocks[builder.current].0.push(instr);
+    }
+
+    fn constant(&mut self, value: Constant) -> Reg {
+        let dest = self.temp();
+        self.emit(Instr::Const { dest, value });
+        dest
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        let builder = self.builder();
+        builder.blocks.push((vec![], None));
+        BlockId(builder.blocks.len() - 1)
+    }
+
+    fn switch_to(&mut self, block: BlockId) {
+        self.builder().current = block.0;
+    }
+
+    /// Ends the current block, unless a `return` already did.
+    fn terminate(&mut self, terminator: Terminator) {
+        let builder = self.builder();
+        let block = &mut builder.blocks[builder.current];
+        if block.1.is_none() {
+            block.1 = Some(terminator);
+        }
+    }
+
+    /// Ends the function being built. Blocks that run off the end return
+    /// nil, and blocks nothing jumps to are dropped along with the registers
+    /// only they used.
+    fn finish(&mut self) -> Function {
+        for index in 0..self.builder().blocks.len() {
+            if self.builder().blocks[index].1.is_none() {
+                self.switch_to(BlockId(index));
+                let nil = self.constant(Constant::Nil);
+                self.terminate(Terminator::Return(nil));
+            }
+        }
+
+        let builder = self.builders.pop().unwrap();
+        let blocks = builder
+            .blocks
+            .into_iter()
+            .map(|(instrs, terminator)| Block {
+                instrs,
+                terminator: terminator.unwrap(),
+            })
+            .collect::<Vec<Block>>();
+
+        let mut function = Function {
+            name: builder.name,
+            params: builder.params,
+            ret: builder.ret.unwrap_or(Ty::Any),
+            ret_declared: builder.ret.is_some(),
+            registers: builder.registers,
+            blocks,
+        };
+        function.remove_unreachable();
+        function.compact_registers();
+        function
+    }
+
+    fn depth(&self) -> usize {
+        self.builders.len() - 1
+    }
+
+    fn at_top_level(&self) -> bool {
+        self.scopes.len() == 1
+    }
+
+    fn bind(&mut self, name: &str, binding: Binding) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .bindings
+            .insert(name.to_string(), binding);
+    }
+
+    fn lookup(&self, name: &Token) -> Result<Option<Binding>, LowerError> {
+        let depth = self.depth();
+        for scope in self.scopes.iter().rev() {
+            match scope.bindings.get(&name.kind.to_string()) {
+                Some(Binding::Register(_)) if scope.depth != depth => {
+                    return Err(LowerError {
+                        message: format!(
+                            "'{}' belongs to an enclosing function, closures are not supported by the IR yet",
+                            name.kind
+                        ),
+                        span: Some(name.span),
+                    })
+                }
+                Some(binding) => return Ok(Some(binding.clone())),
+                None => (),
+            }
+        }
+        Ok(None)
+    }
+
+    /// A name for a lifted function that no other function has.
+    fn function_name(&self, name: &str) -> String {
+        let taken = |name: &str| {
+            self.module
+                .functions
+                .iter()
+                .any(|function| function.name == name)
+                || self.builders.iter().any(|builder| builder.name == name)
+                || self.scopes.iter().any(|scope| {
+                    scope
+                        .bindings
+                        .values()
+                        .any(|binding| matches!(binding, Binding::Function(taken) if taken == name))
+                })
+        };
+
+        let mut unique = name.to_string();
+        let mut count = 1;
+        while taken(&unique) {
+            count += 1;
+            unique = format!("{}.{}", name, count);
+        }
+        unique
+    }
+
+    fn statements(&mut self, statements: &'a [Stmt]) -> Result<(), LowerError> {
+        self.hoist(statements);
+        for stmt in statements {
+            self.statement(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, statements: &'a [Stmt]) -> Result<(), LowerError> {
+        self.scopes.push(Scope {
+            depth: self.depth(),
+            bindings: HashMap::new(),
+        });
+        self.statements(statements)?;
+        self.scopes.pop();
+        Ok(())
+    }
+
+    /// Functions and classes can be called before the statement declaring
+    /// them.
+    fn hoist(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            match stmt {
+                Stmt::Function { name, .. } => {
+                    let lifted = match self.at_top_level() {
+                        true => self.function_name(&name.kind.to_string()),
+                        false => {
+                            let outer = self.builders.last().unwrap().name.clone();
+                            self.function_name(&format!("{}.{}", outer, name.kind))
+                        }
+                    };
+                    self.bind(&name.kind.to_string(), Binding::Function(lifted));
+                }
+                Stmt::Class { name, .. } => {
+                    let name = name.kind.to_string();
+                    self.bind(&name, Binding::Class(name.clone()));
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn statement(&mut self, stmt: &'a Stmt) -> Result<(), LowerError> {
+        match stmt {
+            Stmt::Block { statements } => self.block(statements)?,
+            Stmt::Expression(expr) => {
+                self.expr(expr)?;
+            }
+            Stmt::Print(expr) => {
+                let src = self.expr(expr)?;
+                self.emit(Instr::Print { src });
+            }
+            Stmt::Let {
+                token,
+                annotation,
+                initilizer,
+            } => {
+                let src = self.expr(initilizer)?;
+                let name = token.kind.to_string();
+                let ty = annotation.as_ref().map(annotation_type);
+                let src = self.convert(src, ty, token.span);
+
+                if self.at_top_level() {
+                    if let Some(ty) = ty {
+                        self.module.globals.push(Global {
+                            name: name.clone(),
+                            ty,
+                            declared: true,
+                        });
+                    }
+                    self.emit(Instr::StoreGlobal {
+                        name: name.clone(),
+                        src,
+                    });
+                    self.bind(&name, Binding::Global(name.clone()));
+                } else {
+                    let dest = self.register(Some(name.clone()), ty);
+                    self.emit(Instr::Copy { dest, src });
+                    self.bind(&name, Binding::Register(dest));
+                }
+            }
+            Stmt::If { condition, branch } => {
+                let condition = self.expr(condition)?;
+                let then_block = self.new_block();
+                let merge = self.new_block();
+                self.terminate(Terminator::Branch {
+                    condition,
+                    then_block,
+                    else_block: merge,
+                });
+
+                self.switch_to(then_block);
+                self.statement(branch)?;
+                self.terminate(Terminator::Jump(merge));
+                self.switch_to(merge);
+            }
+            Stmt::While { condition, body } => self.while_loop(condition, None, body)?,
+            Stmt::For {
+                initilizer,
+                condition,
+                increment,
+                body,
+            } => {
+                self.scopes.push(Scope {
+                    depth: self.depth(),
+                    bindings: HashMap::new(),
+                });
+                self.statement(initilizer)?;
+                self.while_loop(condition, Some(increment), body)?;
+                self.scopes.pop();
+            }
+            Stmt::Function {
+                name,
+                params,
+                param_types,
+                return_type,
+                body,
+            } => {
+                let lifted = match self.lookup(name)? {
+                    Some(Binding::Function(lifted)) => lifted,
+                    _ => self.function_name(&name.kind.to_string()),
+                };
+                self.function(&lifted, false, params, param_types, return_type, body)?;
+            }
+            Stmt::Return { keyword, value } => {
+                let value = match value {
+                    Some(value) => self.expr(value)?,
+                    None => self.constant(Constant::Nil),
+                };
+                let ret = self.builder().ret;
+                let value = self.convert(value, ret, keyword.span);
+                self.leave()?;
+                self.terminate(Terminator::Return(value));
+
+                // Anything after the return is unreachable.
+                let next = self.new_block();
+                self.switch_to(next);
+            }
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+            } => {
+                let super_class = match super_class {
+                    Some(Expr::Variable(token)) => match self.lookup(token)? {
+                        Some(Binding::Class(class)) => Some(class),
+                        _ => {
+                            return Err(LowerError {
+                                message: format!("'{}' is not a class", token.kind),
+                                span: Some(token.span),
+                            })
+                        }
+                    },
+                    Some(expr) => {
+                        return Err(LowerError {
+                            message: String::from("A class can only extend another class by name"),
+                            span: expr.start(),
+                        })
+                    }
+                    None => None,
+                };
+
+                let class = name.kind.to_string();
+                self.classes.push((class.clone(), super_class.clone()));
+
+                let mut lifted = vec![];
+                for method in methods {
+                    if let Stmt::Function {
+                        name,
+                        params,
+                        param_types,
+                        return_type,
+                        body,
+                    } = method
+                    {
+                        let function = self.function_name(&format!("{}.{}", class, name.kind));
+                        self.function(&function, true, params, param_types, return_type, body)?;
+                        lifted.push((name.kind.to_string(), function));
+                    }
+                }
+
+                self.classes.pop();
+                self.module.classes.push(Class {
+                    name: class,
+                    super_class,
+                    methods: lifted,
+                });
+            }
+            Stmt::Throw { keyword, value } => {
+                let value = self.expr(value)?;
+                self.terminate(Terminator::Throw {
+                    value,
+                    span: keyword.span,
+                });
+
+                let next = self.new_block();
+                self.switch_to(next);
+            }
+            Stmt::Try {
+                body,
+                catch_name,
+                catch_body,
+                finally_body,
+            } => {
+                let after = self.new_block();
+                // Where the `try` ends up when nothing is thrown, or when the
+                // `catch` block finishes.
+                let done = match finally_body {
+                    Some(_) => self.new_block(),
+                    None => after,
+                };
+                let finally = finally_body.as_deref();
+
+                let caught = self.protect(finally, done, |lowerer| lowerer.block(body))?;
+                let uncaught = match (catch_name, catch_body) {
+                    (Some(name), Some(catch_body)) if finally.is_some() => Some(
+                        self.protect(finally, done, |lowerer| {
+                            lowerer.catch(name, caught, catch_body)
+                        })?,
+                    ),
+                    (Some(name), Some(catch_body)) => {
+                        self.catch(name, caught, catch_body)?;
+                        self.terminate(Terminator::Jump(done));
+                        None
+                    }
+                    _ => Some(caught),
+                };
+
+                if let (Some(value), Some(finally)) = (uncaught, finally) {
+                    self.block(finally)?;
+                    self.terminate(Terminator::Rethrow(value));
+                    self.switch_to(done);
+                    self.block(finally)?;
+                    self.terminate(Terminator::Jump(after));
+                }
+                self.switch_to(after);
+            }
+            Stmt::Comment { .. } => (),
+        }
+        Ok(())
+    }
+
+    /// Lowers what `run` lowers with a handler set, and unsets it before
+    /// going on to `done`. Leaves the handler block current and returns the
+    /// register with what was thrown.
+    fn protect(
+        &mut self,
+        finally: Option<&'a [Stmt]>,
+        done: BlockId,
+        run: impl FnOnce(&mut Self) -> Result<(), LowerError>,
+    ) -> Result<Reg, LowerError> {
+        let body = self.new_block();
+        let handler = self.new_block();
+        self.terminate(Terminator::Try { body, handler });
+
+        self.switch_to(body);
+        self.builder().tries.push(finally);
+        run(self)?;
+        self.builder().tries.pop();
+        self.emit(Instr::EndTry);
+        self.terminate(Terminator::Jump(done));
+
+        self.switch_to(handler);
+        let caught = self.temp();
+        self.emit(Instr::Caught { dest: caught });
+        Ok(caught)
+    }
+
+    /// A `catch` block, with `name` holding what was thrown.
+    fn catch(&mut self, name: &Token, caught: Reg, body: &'a [Stmt]) -> Result<(), LowerError> {
+        self.scopes.push(Scope {
+            depth: self.depth(),
+            bindings: HashMap::new(),
+        });
+        let name = name.kind.to_string();
+        let dest = self.register(Some(name.clone()), None);
+        self.emit(Instr::Copy { dest, src: caught });
+        self.bind(&name, Binding::Register(dest));
+        self.statements(body)?;
+        self.scopes.pop();
+        Ok(())
+    }
+
+    /// Unsets the handlers and runs the `finally` blocks of every `try` the
+    /// function is in, before a `return`.
+    fn leave(&mut self) -> Result<(), LowerError> {
+        let tries = self.builder().tries.clone();
+        for (index, finally) in tries.iter().enumerate().rev() {
+            self.emit(Instr::EndTry);
+            if let Some(finally) = finally {
+                // A `return` in the `finally` block only leaves the `try`
+                // statements around this one.
+                self.builder().tries.truncate(index);
+                self.block(finally)?;
+            }
+        }
+        self.builder().tries = tries;
+        Ok(())
+    }
+
+    /// `while` loops, and `for` loops once their initializer has run.
+    fn while_loop(
+        &mut self,
+        condition: &'a Expr,
+        increment: Option<&'a Expr>,
+        body: &'a Stmt,
+    ) -> Result<(), LowerError> {
+        let header = self.new_block();
+        let body_block = self.new_block();
+        let exit = self.new_block();
+
+        self.terminate(Terminator::Jump(header));
+        self.switch_to(header);
+        let condition = self.expr(condition)?;
+        self.terminate(Terminator::Branch {
+            condition,
+            then_block: body_block,
+            else_block: exit,
+        });
+
+        self.switch_to(body_block);
+        self.statement(body)?;
+        if let Some(increment) = increment {
+            self.expr(increment)?;
+        }
+        self.terminate(Terminator::Jump(header));
+
+        self.switch_to(exit);
+        Ok(())
+    }
+
+    /// Lowers a function or method to a function of the module. Methods take
+    /// `this` as their first parameter.
+    fn function(
+        &mut self,
+        name: &str,
+        method: bool,
+        params: &[Token],
+        param_types: &[Option<Token>],
+        return_type: &Option<Token>,
+        body: &'a [Stmt],
+    ) -> Result<(), LowerError> {
+        let mut builder = Builder::new(name);
+        builder.ret = return_type.as_ref().map(annotation_type);
+        self.builders.push(builder);
+        self.scopes.push(Scope {
+            depth: self.depth(),
+            bindings: HashMap::new(),
+        });
+
+        if method {
+            let this = self.register(Some(String::from("this")), None);
+            self.builder().params.push(this);
+            self.bind("this", Binding::Register(this));
+        }
+        let mut converted = vec![];
+        for (param, annotation) in params.iter().zip(param_types) {
+            let name = param.kind.to_string();
+            let ty = annotation.as_ref().map(annotation_type);
+            let reg = self.register(Some(name.clone()), ty);
+            self.builder().params.push(reg);
+            self.bind(&name, Binding::Register(reg));
+            if ty == Some(Ty::Float) {
+                converted.push((reg, param.span));
+            }
+        }
+        // Callers can pass an int for a `float` parameter.
+        for (reg, span) in converted {
+            self.emit(Instr::Unary {
+                dest: reg,
+                op: UnaryOp::ToFloat,
+                src: reg,
+                span,
+            });
+        }
+
+        self.statements(body)?;
+
+        self.scopes.pop();
+        let function = self.finish();
+        self.module.functions.push(function);
+        Ok(())
+    }
+
+    /// Reads a variable into a new temporary, so that later assignments to
+    /// the variable do not change the value already read.
+    fn read(&mut self, name: &Token) -> Result<Reg, LowerError> {
+        let dest = self.temp();
+        let instr = match self.lookup(name)? {
+            Some(Binding::Register(src)) => Instr::Copy { dest, src },
+            Some(Binding::Global(global)) => Instr::LoadGlobal {
+                dest,
+                name: global,
+                span: name.span,
+            },
+            Some(Binding::Function(function)) => Instr::FunctionRef {
+                dest,
+                name: function,
+            },
+            Some(Binding::Class(_)) => {
+                return Err(LowerError {
+                    message: format!(
+                        "Class '{}' can only be called, the IR has no class values yet",
+                        name.kind
+                    ),
+                    span: Some(name.span),
+                })
+            }
+            // Declared later at the top level, or never.
+            None => Instr::LoadGlobal {
+                dest,
+                name: name.kind.to_string(),
+                span: name.span,
+            },
+        };
+        self.emit(instr);
+        Ok(dest)
+    }
+
+    /// `this` of the method being lowered, for `this` and `super` keywords.
+    fn this(&mut self, keyword: &Token) -> Result<Reg, LowerError> {
+        let this = Token {
+            kind: TokenType::Terminal(String::from("this")),
+            span: keyword.span,
+        };
+        match self.lookup(&this) {
+            Ok(Some(Binding::Register(src))) => {
+                let dest = self.temp();
+                self.emit(Instr::Copy { dest, src });
+                Ok(dest)
+            }
+            _ => Err(LowerError {
+                message: format!(
+                    "'{}' is only supported directly inside methods",
+                    keyword.kind
+                ),
+                span: Some(keyword.span),
+            }),
+        }
+    }
+
+    /// Stores `src` in the variable `name` and returns what was stored.
+    fn write(&mut self, name: &Token, src: Reg) -> Result<Reg, LowerError> {
+        let binding = self.lookup(name)?;
+        let declared = match &binding {
+            Some(Binding::Register(dest)) => {
+                let register = &self.builder().registers[dest.0];
+                register.declared.then_some(register.ty)
+            }
+            Some(Binding::Global(global)) => self
+                .module
+                .globals
+                .iter()
+                .find(|other| other.name == *global && other.declared)
+                .map(|other| other.ty),
+            _ => None,
+        };
+        let src = self.convert(src, declared, name.span);
+
+        let instr = match binding {
+            Some(Binding::Register(dest)) => Instr::Copy { dest, src },
+            Some(Binding::Global(global)) => Instr::StoreGlobal { name: global, src },
+            Some(Binding::Function(_) | Binding::Class(_)) => {
+                return Err(LowerError {
+                    message: format!("Cannot assign to '{}'", name.kind),
+                    span: Some(name.span),
+                })
+            }
+            None => Instr::StoreGlobal {
+                name: name.kind.to_string(),
+                src,
+            },
+        };
+        self.emit(instr);
+        Ok(src)
+    }
+
+    /// `src` as it goes into a slot of type `ty`: ints going into a `float`
+    /// slot are converted, so that it only ever holds floats.
+    fn convert(&mut self, src: Reg, ty: Option<Ty>, span: Span) -> Reg {
+        if ty != Some(Ty::Float) {
+            return src;
+        }
+        let dest = self.temp();
+        self.emit(Instr::Unary {
+            dest,
+            op: UnaryOp::ToFloat,
+            src,
+            span,
+        });
+        dest
+    }
+
+    fn expr(&mut self, expr: &'a Expr) -> Result<Reg, LowerError> {
+        let reg = match expr {
+            Expr::Literal(literal) => {
+                let value = match literal {
+                    Literal::Integer(value) => Constant::Int(*value),
+                    Literal::FloatingPoint(value) => Constant::Float(*value),
+                    Literal::String(value) => Constant::Str(value.clone()),
+                    Literal::Boolean(value) => Constant::Bool(*value),
+                    Literal::Terminal(terminal) => match terminal.value.downcast_ref::<&str>() {
+                        Some(&"true") => Constant::Bool(true),
+                        Some(&"false") => Constant::Bool(false),
+                        _ => Constant::Nil,
+                    },
+                };
+                self.constant(value)
+            }
+            Expr::Grouping(grouping) => self.expr(&grouping.expr)?,
+            Expr::Variable(token) => self.read(token)?,
+            Expr::Assign(assign) => {
+                let src = self.expr(&assign.value)?;
+                self.write(&assign.name, src)?
+            }
+            Expr::UnaryExpr(unary) => {
+                let src = self.expr(&unary.right)?;
+                let op = match &unary.op.kind {
+                    TokenType::Operations {
+                        kind: OperationKind::Minus,
+                        ..
+                    } => UnaryOp::Neg,
+                    TokenType::Operations {
+                        kind: OperationKind::Tilde,
+                        ..
+                    } => UnaryOp::BitNot,
+                    _ => UnaryOp::Not,
+                };
+                let dest = self.temp();
+                self.emit(Instr::Unary {
+                    dest,
+                    op,
+                    src,
+                    span: unary.op.span,
+                });
+                dest
+            }
+            Expr::BinaryExpr(binary) => {
+                let left = self.expr(&binary.left)?;
+                let right = self.expr(&binary.right)?;
+                self.binary(&binary.op, left, right)?
+            }
+            Expr::Conditional(conditional) => {
+                let condition = self.expr(&conditional.condition)?;
+                let result = self.temp();
+                let then_block = self.new_block();
+                let else_block = self.new_block();
+                let merge = self.new_block();
+                self.terminate(Terminator::Branch {
+                    condition,
+                    then_block,
+                    else_block,
+                });
+
+                for (block, expr) in [
+                    (then_block, &conditional.then_branch),
+                    (else_block, &conditional.else_branch),
+                ] {
+                    self.switch_to(block);
+                    let src = self.expr(expr)?;
+                    self.emit(Instr::Copy { dest: result, src });
+                    self.terminate(Terminator::Jump(merge));
+                }
+
+                self.switch_to(merge);
+                result
+            }
+            Expr::Update(update) => {
+                let (old, object) = match &*update.target {
+                    Expr::Variable(token) => (self.read(token)?, None),
+                    Expr::Get(get) => {
+                        let object = self.expr(&get.object)?;
+                        let dest = self.temp();
+                        self.emit(Instr::GetProperty {
+                            dest,
+                            object,
+                            name: get.name.kind.to_string(),
+                            span: get.name.span,
+                        });
+                        (dest, Some(object))
+                    }
+                    _ => unreachable!("the parser only makes updates of variables and properties"),
+                };
+                let change = self.expr(&update.change)?;
+                let new = self.binary(&update.op, old, change)?;
+
+                match (&*update.target, object) {
+                    (Expr::Get(get), Some(object)) => self.emit(Instr::SetProperty {
+                        object,
+                        name: get.name.kind.to_string(),
+                        src: new,
+                        span: get.name.span,
+                    }),
+                    (Expr::Variable(token), _) => {
+                        self.write(token, new)?;
+                    }
+                    _ => unreachable!(),
+                }
+
+                match update.kind {
+                    UpdateKind::Postfix => old,
+                    UpdateKind::Prefix | UpdateKind::Compound => new,
+                }
+            }
+            Expr::Call(call) => self.call(call)?,
+            Expr::Lambda(lambda) => {
+                self.lambdas += 1;
+                let name = format!("<lambda {}>", self.lambdas);
+                self.function(
+                    &name,
+                    false,
+                    &lambda.params,
+                    &lambda.param_types,
+                    &lambda.return_type,
+                    &lambda.body,
+                )?;
+                let dest = self.temp();
+                self.emit(Instr::FunctionRef { dest, name });
+                dest
+            }
+            Expr::Get(get) => {
+                let object = self.expr(&get.object)?;
+                let dest = self.temp();
+                self.emit(Instr::GetProperty {
+                    dest,
+                    object,
+                    name: get.name.kind.to_string(),
+                    span: get.name.span,
+                });
+                dest
+            }
+            Expr::Set(set) => {
+                let object = self.expr(&set.object)?;
+                let src = self.expr(&set.value)?;
+                self.emit(Instr::SetProperty {
+                    object,
+                    name: set.name.kind.to_string(),
+                    src,
+                    span: set.name.span,
+                });
+                src
+            }
+            Expr::This(this) => self.this(&this.keyword)?,
+            Expr::Super(sup) => {
+                return Err(LowerError {
+                    message: String::from("super methods can only be called, not used as values"),
+                    span: Some(sup.keyword.span),
+                })
+            }
+        };
+        Ok(reg)
+    }
+
+    fn binary(&mut self, op: &Token, left: Reg, right: Reg) -> Result<Reg, LowerError> {
+        let span = op.span;
+        let op = match &op.kind {
+            TokenType::Operations { kind, .. } => match kind {
+                OperationKind::Plus => BinaryOp::Add,
+                OperationKind::Minus => BinaryOp::Sub,
+                OperationKind::Star => BinaryOp::Mul,
+                OperationKind::Slash => BinaryOp::Div,
+                OperationKind::Percent => BinaryOp::Rem,
+                OperationKind::Ampersand => BinaryOp::BitAnd,
+                OperationKind::Pipe => BinaryOp::BitOr,
+                OperationKind::Caret => BinaryOp::BitXor,
+                OperationKind::Tilde => return Err(unknown_operator(op)),
+            },
+            TokenType::Operator(kind) => match kind {
+                OperatorKind::StarStar => BinaryOp::Pow,
+                OperatorKind::LessLess => BinaryOp::Shl,
+                OperatorKind::GreaterGreater => BinaryOp::Shr,
+                OperatorKind::EqualEqual => BinaryOp::Eq,
+                OperatorKind::BangEqual => BinaryOp::Ne,
+                OperatorKind::Less => BinaryOp::Lt,
+                OperatorKind::LessEqual => BinaryOp::Le,
+                OperatorKind::Greater => BinaryOp::Gt,
+                OperatorKind::GreaterEqual => BinaryOp::Ge,
+                _ => return Err(unknown_operator(op)),
+            },
+            _ => return Err(unknown_operator(op)),
+        };
+
+        let dest = self.temp();
+        self.emit(Instr::Binary {
+            dest,
+            op,
+            left,
+            right,
+            span,
+        });
+        Ok(dest)
+    }
+
+    fn call(&mut self, call: &'a CallExpr) -> Result<Reg, LowerError> {
+        enum Callee {
+            Function(String),
+            Class(String),
+            Method(Reg, String),
+            Super(String, Reg, String),
+            Value(Reg),
+        }
+
+        let callee = match &*call.callee {
+            Expr::Variable(token) => match self.lookup(token)? {
+                Some(Binding::Function(function)) => Callee::Function(function),
+                Some(Binding::Class(class)) => Callee::Class(class),
+                _ => Callee::Value(self.read(token)?),
+            },
+            Expr::Get(get) => {
+                let object = self.expr(&get.object)?;
+                Callee::Method(object, get.name.kind.to_string())
+            }
+            Expr::Super(sup) => {
+                let class = self
+                    .classes
+                    .last()
+                    .and_then(|(_, super_class)| super_class.clone());
+                let class = match class {
+                    Some(class) => class,
+                    None => {
+                        return Err(LowerError {
+                            message: String::from("'super' used in a class without a superclass"),
+                            span: Some(sup.keyword.span),
+                        })
+                    }
+                };
+                let object = self.this(&sup.keyword)?;
+                Callee::Super(class, object, sup.method.kind.to_string())
+            }
+            callee => Callee::Value(self.expr(callee)?),
+        };
+
+        let args = call
+            .args
+            .iter()
+            .map(|arg| self.expr(arg))
+            .collect::<Result<Vec<Reg>, LowerError>>()?;
+
+        let dest = self.temp();
+        let span = call.paren.span;
+        let instr = match callee {
+            Callee::Function(function) => Instr::Call {
+                dest,
+                function,
+                args,
+                span,
+            },
+            Callee::Class(class) => Instr::New {
+                dest,
+                class,
+                args,
+                span,
+            },
+            Callee::Method(object, method) => Instr::CallMethod {
+                dest,
+                object,
+                method,
+                args,
+                span,
+            },
+            Callee::Super(class, object, method) => Instr::CallSuper {
+                dest,
+                class,
+                method,
+                object,
+                args,
+                span,
+            },
+            Callee::Value(callee) => Instr::CallIndirect {
+                dest,
+                callee,
+                args,
+                span,
+            },
+        };
+        self.emit(instr);
+        Ok(dest)
+    }
+}
+
+fn unknown_operator(op: &Token) -> LowerError {
+    LowerError {
+        message: format!("'{}' is not a binary operator", op.kind),
+        span: Some(op.span),
+    }
+}
+
+/// Builtin types map to their IR type and classes to `any`.
+fn annotation_type(annotation: &Token) -> Ty {
+    match &annotation.kind {
+        TokenType::Identifier(name) => match name.as_str() {
+            "int" => Ty::Int,
+            "float" => Ty::Float,
+            "string" => Ty::Str,
+            "bool" => Ty::Bool,
+            _ => Ty::Any,
+        },
+        _ => Ty::Nil,
+    }
+}
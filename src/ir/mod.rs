@@ -0,0 +1,8 @@
+pub mod dominators;
+pub mod error;
+#[allow(clippy::module_inception)]
+pub mod ir;
+pub mod lower;
+pub mod passes;
+pub mod ssa;
+pub mod types;
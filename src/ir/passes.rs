@@ -0,0 +1,531 @@
+//! Optimizations on the IR. The passes work on SSA form; `optimize` takes
+//! care of converting the module into it and back out of it.
+
+use std::collections::{HashMap, HashSet};
+
+use super::dominators::Dominators;
+use super::ir::*;
+use super::ssa::{from_ssa, to_ssa};
+use super::types::infer_types;
+use crate::lexer::Span;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Pass {
+    /// Sparse conditional constant propagation.
+    ConstantPropagation,
+    CommonSubexpressions,
+    CopyPropagation,
+    DeadCode,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 4] = [
+        Pass::ConstantPropagation,
+        Pass::CommonSubexpressions,
+        Pass::CopyPropagation,
+        Pass::DeadCode,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::ConstantPropagation => "sccp",
+            Pass::CommonSubexpressions => "cse",
+            Pass::CopyPropagation => "copy-prop",
+            Pass::DeadCode => "dce",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Pass> {
+        Pass::ALL.into_iter().find(|pass| pass.name() == name)
+    }
+
+    /// The passes `--opt-level=level` runs, in order.
+    pub fn for_level(level: u32) -> Vec<Pass> {
+        match level {
+            0 => vec![],
+            1 => vec![Pass::CopyPropagation, Pass::DeadCode],
+            // Copies are propagated first so that equal computations on
+            // copies of the same value look the same.
+            _ => vec![
+                Pass::ConstantPropagation,
+                Pass::CopyPropagation,
+                Pass::CommonSubexpressions,
+                Pass::CopyPropagation,
+                Pass::DeadCode,
+            ],
+        }
+    }
+
+    pub fn run(&self, function: &mut Function) {
+        match self {
+            Pass::ConstantPropagation => constant_propagation(function),
+            Pass::CommonSubexpressions => common_subexpressions(function),
+            Pass::CopyPropagation => copy_propagation(function),
+            Pass::DeadCode => dead_code(function),
+        }
+    }
+}
+
+/// Runs `passes` in order over every function without a `try`. `dump` is
+/// called with the module in SSA form, under the name "ssa", and after each
+/// pass, under the name of the pass. Does nothing without passes.
+pub fn optimize(module: &mut Module, passes: &[Pass], mut dump: impl FnMut(&str, &Module)) {
+    if passes.is_empty() {
+        return;
+    }
+
+    for function in module.functions.iter_mut().filter(|function| !function.catches()) {
+        to_ssa(function);
+    }
+    infer_types(module);
+    dump("ssa", module);
+
+    for pass in passes {
+        for function in module.functions.iter_mut().filter(|function| !function.catches()) {
+            pass.run(function);
+        }
+        infer_types(module);
+        dump(pass.name(), module);
+    }
+
+    for function in module.functions.iter_mut().filter(|function| !function.catches()) {
+        from_ssa(function);
+        function.compact_registers();
+    }
+    infer_types(module);
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Value {
+    /// Nothing is known yet.
+    Top,
+    Constant(Constant),
+    /// Can hold more than one value.
+    Bottom,
+}
+
+impl Value {
+    fn meet(&self, other: &Value) -> Value {
+        match (self, other) {
+            (Value::Top, value) | (value, Value::Top) => value.clone(),
+            (Value::Constant(a), Value::Constant(b)) if a == b => self.clone(),
+            _ => Value::Bottom,
+        }
+    }
+}
+
+fn constant_propagation(function: &mut Function) {
+    let mut values = vec![Value::Top; function.registers.len()];
+    for param in &function.params {
+        values[param.0] = Value::Bottom;
+    }
+    let mut executable = vec![false; function.blocks.len()];
+    executable[0] = true;
+    let mut edges = HashSet::new();
+
+    loop {
+        let mut changed = false;
+        for (index, block) in function.blocks.iter().enumerate() {
+            if !executable[index] {
+                continue;
+            }
+
+            for instr in &block.instrs {
+                let dest = match instr.dest() {
+                    Some(dest) => dest,
+                    None => continue,
+                };
+                let value = evaluate(instr, &values, &edges, BlockId(index));
+                let value = values[dest.0].meet(&value);
+                if value != values[dest.0] {
+                    values[dest.0] = value;
+                    changed = true;
+                }
+            }
+
+            let targets = match &block.terminator {
+                Terminator::Branch {
+                    condition,
+                    then_block,
+                    else_block,
+                } => match &values[condition.0] {
+                    Value::Top => vec![],
+                    Value::Constant(Constant::Bool(true)) => vec![*then_block],
+                    Value::Constant(Constant::Bool(false)) => vec![*else_block],
+                    _ => vec![*then_block, *else_block],
+                },
+                terminator => terminator.successors(),
+            };
+            for target in targets {
+                if edges.insert((BlockId(index), target)) {
+                    executable[target.0] = true;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    for (index, block) in function.blocks.iter_mut().enumerate() {
+        if !executable[index] {
+            continue;
+        }
+
+        for instr in &mut block.instrs {
+            if let Instr::Phi { dest, args } = instr {
+                args.retain(|(from, _)| edges.contains(&(*from, BlockId(index))));
+                if let [(_, src)] = args[..] {
+                    *instr = Instr::Copy { dest: *dest, src };
+                }
+            }
+            if let Some(dest) = instr.dest() {
+                if let Value::Constant(value) = &values[dest.0] {
+                    *instr = Instr::Const {
+                        dest,
+                        value: value.clone(),
+                    };
+                }
+            }
+        }
+        // Phis that became constants or copies are no longer at the start.
+        block
+            .instrs
+            .sort_by_key(|instr| !matches!(instr, Instr::Phi { .. }));
+
+        if let Terminator::Branch {
+            condition,
+            then_block,
+            else_block,
+        } = block.terminator
+        {
+            match values[condition.0] {
+                Value::Constant(Constant::Bool(true)) => {
+                    block.terminator = Terminator::Jump(then_block)
+                }
+                Value::Constant(Constant::Bool(false)) => {
+                    block.terminator = Terminator::Jump(else_block)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    function.remove_unreachable();
+}
+
+fn evaluate(
+    instr: &Instr,
+    values: &[Value],
+    edges: &HashSet<(BlockId, BlockId)>,
+    block: BlockId,
+) -> Value {
+    let operand = |reg: &Reg| &values[reg.0];
+
+    match instr {
+        Instr::Const { value, .. } => Value::Constant(value.clone()),
+        Instr::Copy { src, .. } => operand(src).clone(),
+        Instr::Phi { args, .. } => args
+            .iter()
+            .filter(|(from, _)| edges.contains(&(*from, block)))
+            .fold(Value::Top, |value, (_, src)| value.meet(operand(src))),
+        Instr::Unary { op, src, .. } => match operand(src) {
+            Value::Top => Value::Top,
+            Value::Constant(value) => match unary(*op, value) {
+                Some(value) => Value::Constant(value),
+                None => Value::Bottom,
+            },
+            Value::Bottom => Value::Bottom,
+        },
+        Instr::Binary {
+            op, left, right, ..
+        } => match (operand(left), operand(right)) {
+            (Value::Bottom, _) | (_, Value::Bottom) => Value::Bottom,
+            (Value::Constant(left), Value::Constant(right)) => match binary(*op, left, right) {
+                Some(value) => Value::Constant(value),
+                None => Value::Bottom,
+            },
+            _ => Value::Top,
+        },
+        _ => Value::Bottom,
+    }
+}
+
+/// The value of `op value`, if it can be computed without an error.
+fn unary(op: UnaryOp, value: &Constant) -> Option<Constant> {
+    match (op, value) {
+        (UnaryOp::Neg, Constant::Int(value)) => value.checked_neg().map(Constant::Int),
+        (UnaryOp::Neg, Constant::Float(value)) => Some(Constant::Float(-value)),
+        (UnaryOp::BitNot, Constant::Int(value)) => Some(Constant::Int(!value)),
+        (UnaryOp::Not, Constant::Bool(value)) => Some(Constant::Bool(!value)),
+        (UnaryOp::ToFloat, Constant::Int(value)) => Some(Constant::Float(*value as f64)),
+        (UnaryOp::ToFloat, value) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+/// The value of `left op right`, if it can be computed without an error.
+/// Works like constant folding on the syntax tree.
+fn binary(op: BinaryOp, left: &Constant, right: &Constant) -> Option<Constant> {
+    use BinaryOp::*;
+
+    match (left, right) {
+        (Constant::Int(left), Constant::Int(right)) => {
+            let (left, right) = (*left, *right);
+            let value = match op {
+                Add => left.checked_add(right),
+                Sub => left.checked_sub(right),
+                Mul => left.checked_mul(right),
+                Div => left.checked_div(right),
+                Rem => left.checked_rem(right),
+                Pow => left.checked_pow(u32::try_from(right).ok()?),
+                BitAnd => Some(left & right),
+                BitOr => Some(left | right),
+                BitXor => Some(left ^ right),
+                Shl => left.checked_shl(u32::try_from(right).ok()?),
+                Shr => left.checked_shr(u32::try_from(right).ok()?),
+                _ => return compare(op, left.cmp(&right)),
+            };
+            value.map(Constant::Int)
+        }
+        (Constant::Int(_) | Constant::Float(_), Constant::Int(_) | Constant::Float(_)) => {
+            let (left, right) = (number(left)?, number(right)?);
+            let value = match op {
+                Add => left + right,
+                Sub => left - right,
+                Mul => left * right,
+                Div if right != 0.0 => left / right,
+                Rem if right != 0.0 => left % right,
+                Pow => left.powf(right),
+                _ if op.is_comparison() => return compare(op, left.partial_cmp(&right)?),
+                _ => return None,
+            };
+            value.is_finite().then_some(Constant::Float(value))
+        }
+        (Constant::Str(left), Constant::Str(right)) => match op {
+            Add => Some(Constant::Str(format!("{}{}", left, right))),
+            Eq => Some(Constant::Bool(left == right)),
+            Ne => Some(Constant::Bool(left != right)),
+            _ => None,
+        },
+        (Constant::Bool(_) | Constant::Nil, Constant::Bool(_) | Constant::Nil) => match op {
+            Eq => Some(Constant::Bool(left == right)),
+            Ne => Some(Constant::Bool(left != right)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn compare(op: BinaryOp, ordering: std::cmp::Ordering) -> Option<Constant> {
+    use std::cmp::Ordering::*;
+
+    let value = match op {
+        BinaryOp::Eq => ordering == Equal,
+        BinaryOp::Ne => ordering != Equal,
+        BinaryOp::Lt => ordering == Less,
+        BinaryOp::Le => ordering != Greater,
+        BinaryOp::Gt => ordering == Greater,
+        BinaryOp::Ge => ordering != Less,
+        _ => return None,
+    };
+    Some(Constant::Bool(value))
+}
+
+fn number(value: &Constant) -> Option<f64> {
+    match value {
+        Constant::Int(value) => Some(*value as f64),
+        Constant::Float(value) => Some(*value),
+        _ => None,
+    }
+}
+
+enum Visit {
+    Enter(BlockId),
+    /// Forgets the expressions the block made available.
+    Leave(Vec<String>),
+}
+
+/// Replaces a pure computation with a copy of the same computation in a
+/// dominating block.
+fn common_subexpressions(function: &mut Function) {
+    let dominators = Dominators::new(function);
+    let mut available: HashMap<String, Reg> = HashMap::new();
+
+    let mut work = vec![Visit::Enter(BlockId(0))];
+    while let Some(visit) = work.pop() {
+        let block = match visit {
+            Visit::Enter(block) => block,
+            Visit::Leave(keys) => {
+                for key in keys {
+                    available.remove(&key);
+                }
+                continue;
+            }
+        };
+
+        let mut added = vec![];
+        for index in 0..function.blocks[block.0].instrs.len() {
+            let instr = &function.blocks[block.0].instrs[index];
+            let dest = match instr {
+                Instr::Const { dest, .. }
+                | Instr::Unary { dest, .. }
+                | Instr::Binary { dest, .. }
+                | Instr::FunctionRef { dest, .. } => *dest,
+                _ => continue,
+            };
+
+            // The instruction without its destination and span identifies
+            // the value.
+            let mut key = instr.clone();
+            *key.dest_mut().unwrap() = Reg(0);
+            if let Some(span) = key.span_mut() {
+                *span = Span::default();
+            }
+            let key = format!("{:?}", key);
+            match available.get(&key) {
+                Some(src) if function.ty(*src) == function.ty(dest) => {
+                    function.blocks[block.0].instrs[index] = Instr::Copy { dest, src: *src };
+                }
+                Some(_) => {}
+                None => {
+                    available.insert(key.clone(), dest);
+                    added.push(key);
+                }
+            }
+        }
+
+        work.push(Visit::Leave(added));
+        for child in dominators.children(block).iter().rev() {
+            work.push(Visit::Enter(*child));
+        }
+    }
+}
+
+/// Replaces reads of a copy with reads of what it copies, where both have
+/// the same type.
+fn copy_propagation(function: &mut Function) {
+    let mut replacements = vec![None; function.registers.len()];
+    for block in &function.blocks {
+        for instr in &block.instrs {
+            if let Instr::Copy { dest, src } = instr {
+                if function.ty(*dest) == function.ty(*src) {
+                    replacements[dest.0] = Some(*src);
+                }
+            }
+        }
+    }
+
+    let resolve = |mut reg: Reg| {
+        while let Some(src) = replacements[reg.0] {
+            reg = src;
+        }
+        reg
+    };
+    for block in &mut function.blocks {
+        block.instrs.retain(|instr| match instr.dest() {
+            Some(dest) => replacements[dest.0].is_none(),
+            None => true,
+        });
+        for instr in &mut block.instrs {
+            for reg in instr.uses_mut() {
+                *reg = resolve(*reg);
+            }
+        }
+        for reg in block.terminator.uses_mut() {
+            *reg = resolve(*reg);
+        }
+    }
+}
+
+/// Removes the instructions whose results are never used and that cannot
+/// have an effect, such as an error, when they run.
+fn dead_code(function: &mut Function) {
+    let mut defs = HashMap::new();
+    for block in &function.blocks {
+        for instr in &block.instrs {
+            if let Some(dest) = instr.dest() {
+                defs.insert(dest, instr);
+            }
+        }
+    }
+
+    let mut live = vec![false; function.registers.len()];
+    let mut work = vec![];
+    for block in &function.blocks {
+        for instr in &block.instrs {
+            if !is_pure(function, instr) {
+                work.extend(instr.uses());
+            }
+        }
+        work.extend(block.terminator.uses());
+    }
+    while let Some(reg) = work.pop() {
+        if !live[reg.0] {
+            live[reg.0] = true;
+            if let Some(instr) = defs.get(&reg) {
+                work.extend(instr.uses());
+            }
+        }
+    }
+
+    let pure = function
+        .blocks
+        .iter()
+        .map(|block| {
+            block
+                .instrs
+                .iter()
+                .map(|instr| is_pure(function, instr))
+                .collect::<Vec<bool>>()
+        })
+        .collect::<Vec<Vec<bool>>>();
+    for (block, pure) in function.blocks.iter_mut().zip(pure) {
+        let mut pure = pure.into_iter();
+        block.instrs.retain(|instr| {
+            let pure = pure.next().unwrap();
+            match instr.dest() {
+                Some(dest) => !pure || live[dest.0],
+                None => true,
+            }
+        });
+    }
+}
+
+fn is_pure(function: &Function, instr: &Instr) -> bool {
+    use BinaryOp::*;
+
+    let numeric = |ty: Ty| matches!(ty, Ty::Int | Ty::Float);
+    match instr {
+        Instr::Const { .. }
+        | Instr::Copy { .. }
+        | Instr::FunctionRef { .. }
+        | Instr::Phi { .. } => true,
+        Instr::Unary { op, src, .. } => matches!(
+            (op, function.ty(*src)),
+            (UnaryOp::Not | UnaryOp::ToFloat, _)
+                | (UnaryOp::Neg, Ty::Float)
+                | (UnaryOp::BitNot, Ty::Int)
+        ),
+        Instr::Binary {
+            op, left, right, ..
+        } => {
+            let (left, right) = (function.ty(*left), function.ty(*right));
+            // Integer arithmetic can overflow or divide by zero.
+            let float =
+                numeric(left) && numeric(right) && (left == Ty::Float || right == Ty::Float);
+            match op {
+                Eq | Ne => true,
+                Lt | Le | Gt | Ge => {
+                    (numeric(left) && numeric(right)) || (left == Ty::Str && right == Ty::Str)
+                }
+                Add => float || (left == Ty::Str && right == Ty::Str),
+                Sub | Mul | Div | Rem | Pow => float,
+                BitAnd | BitOr | BitXor => left == Ty::Int && right == Ty::Int,
+                Shl | Shr => false,
+            }
+        }
+        _ => false,
+    }
+}
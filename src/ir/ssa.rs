@@ -0,0 +1,339 @@
+//! Conversion of functions to and from SSA form, where every register is
+//! written by exactly one instruction and `phi` instructions merge the
+//! values that reach a block along different edges.
+
+use std::collections::HashSet;
+
+use super::dominators::Dominators;
+use super::ir::*;
+
+/// Builds pruned SSA form: a register gets a `phi` only at the blocks in
+/// the iterated dominance frontier of its writes where it is still live.
+pub fn to_ssa(function: &mut Function) {
+    function.remove_unreachable();
+    let dominators = Dominators::new(function);
+    let phis = insert_phis(function, &dominators);
+    rename(function, &dominators, &phis);
+}
+
+/// Replaces every `phi` with copies at the end of the blocks it takes its
+/// values from.
+pub fn from_ssa(function: &mut Function) {
+    split_critical_edges(function);
+
+    for index in 0..function.blocks.len() {
+        let mut phis = vec![];
+        function.blocks[index].instrs.retain(|instr| match instr {
+            Instr::Phi { dest, args } => {
+                phis.push((*dest, args.clone()));
+                false
+            }
+            _ => true,
+        });
+        if phis.is_empty() {
+            continue;
+        }
+
+        let dests = phis.iter().map(|(dest, _)| *dest).collect::<HashSet<Reg>>();
+        let mut preds = vec![];
+        for (from, _) in phis.iter().flat_map(|(_, args)| args) {
+            if !preds.contains(from) {
+                preds.push(*from);
+            }
+        }
+
+        for pred in preds {
+            let moves = phis
+                .iter()
+                .filter_map(|(dest, args)| {
+                    let (_, src) = args.iter().find(|(from, _)| *from == pred)?;
+                    (src != dest).then_some((*dest, *src))
+                })
+                .collect::<Vec<(Reg, Reg)>>();
+
+            // The copies all happen at once, so a value another copy
+            // overwrites has to be saved first.
+            let parallel = moves.iter().any(|(_, src)| dests.contains(src));
+            let mut copies = vec![];
+            if parallel {
+                let mut temps = vec![];
+                for (dest, src) in &moves {
+                    let temp = Reg(function.registers.len());
+                    function.registers.push(Register {
+                        name: None,
+                        ..function.registers[dest.0].clone()
+                    });
+                    copies.push(Instr::Copy {
+                        dest: temp,
+                        src: *src,
+                    });
+                    temps.push(temp);
+                }
+                for ((dest, _), temp) in moves.iter().zip(temps) {
+                    copies.push(Instr::Copy {
+                        dest: *dest,
+                        src: temp,
+                    });
+                }
+            } else {
+                for (dest, src) in moves {
+                    copies.push(Instr::Copy { dest, src });
+                }
+            }
+            function.blocks[pred.0].instrs.extend(copies);
+        }
+    }
+}
+
+/// The registers that are read in each block before being written there,
+/// or that are live at the end of the block.
+fn live_in(function: &Function) -> Vec<HashSet<Reg>> {
+    let count = function.blocks.len();
+    let mut uses = vec![HashSet::new(); count];
+    let mut defs = vec![HashSet::new(); count];
+    for (index, block) in function.blocks.iter().enumerate() {
+        for instr in &block.instrs {
+            for reg in instr.uses() {
+                if !defs[index].contains(&reg) {
+                    uses[index].insert(reg);
+                }
+            }
+            if let Some(dest) = instr.dest() {
+                defs[index].insert(dest);
+            }
+        }
+        for reg in block.terminator.uses() {
+            if !defs[index].contains(&reg) {
+                uses[index].insert(reg);
+            }
+        }
+    }
+
+    let mut live_in = uses.clone();
+    loop {
+        let mut changed = false;
+        for index in (0..count).rev() {
+            let mut live = uses[index].clone();
+            for successor in function.blocks[index].terminator.successors() {
+                for reg in &live_in[successor.0] {
+                    if !defs[index].contains(reg) {
+                        live.insert(*reg);
+                    }
+                }
+            }
+            // The sets only grow, so comparing sizes is enough.
+            if live.len() != live_in[index].len() {
+                live_in[index] = live;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    live_in
+}
+
+/// Returns the register each inserted `phi` stands for, per block.
+fn insert_phis(function: &mut Function, dominators: &Dominators) -> Vec<Vec<Reg>> {
+    let live_in = live_in(function);
+
+    let mut def_blocks = vec![vec![]; function.registers.len()];
+    for param in &function.params {
+        def_blocks[param.0].push(BlockId(0));
+    }
+    for (index, block) in function.blocks.iter().enumerate() {
+        for dest in block.instrs.iter().filter_map(Instr::dest) {
+            if def_blocks[dest.0].last() != Some(&BlockId(index)) {
+                def_blocks[dest.0].push(BlockId(index));
+            }
+        }
+    }
+
+    let mut phis = vec![vec![]; function.blocks.len()];
+    for (reg, blocks) in def_blocks.into_iter().enumerate() {
+        let mut queued = blocks.iter().copied().collect::<HashSet<BlockId>>();
+        let mut has_phi = HashSet::new();
+        let mut work = blocks;
+        while let Some(block) = work.pop() {
+            for frontier in dominators.frontier(block) {
+                if live_in[frontier.0].contains(&Reg(reg)) && has_phi.insert(*frontier) {
+                    phis[frontier.0].push(Reg(reg));
+                    if queued.insert(*frontier) {
+                        work.push(*frontier);
+                    }
+                }
+            }
+        }
+    }
+
+    let predecessors = function.predecessors();
+    for (index, regs) in phis.iter().enumerate() {
+        let preds = predecessors
+            .get(&BlockId(index))
+            .cloned()
+            .unwrap_or_default();
+        let instrs = regs.iter().map(|reg| Instr::Phi {
+            dest: *reg,
+            args: preds.iter().map(|pred| (*pred, *reg)).collect(),
+        });
+        function.blocks[index].instrs.splice(0..0, instrs);
+    }
+    phis
+}
+
+enum Visit {
+    Enter(BlockId),
+    /// Forgets the versions the block pushed for these registers.
+    Leave(Vec<Reg>),
+}
+
+struct Renamer {
+    registers: Vec<Register>,
+    /// The current version of each original register.
+    stacks: Vec<Vec<Reg>>,
+    /// Whether the original register has been given to a write already.
+    taken: Vec<bool>,
+    /// A `nil` for reads that no write reaches.
+    undefined: Option<Reg>,
+}
+
+impl Renamer {
+    fn current(&mut self, reg: Reg) -> Reg {
+        if let Some(current) = self.stacks[reg.0].last() {
+            return *current;
+        }
+        *self.undefined.get_or_insert_with(|| {
+            self.registers.push(Register {
+                name: None,
+                ty: Ty::Nil,
+                declared: false,
+            });
+            Reg(self.registers.len() - 1)
+        })
+    }
+
+    /// The first write keeps the original register so that the names in
+    /// the textual form stay the same where possible.
+    fn version(&mut self, reg: Reg) -> Reg {
+        let version = match self.taken[reg.0] {
+            true => {
+                self.registers.push(self.registers[reg.0].clone());
+                Reg(self.registers.len() - 1)
+            }
+            false => {
+                self.taken[reg.0] = true;
+                reg
+            }
+        };
+        self.stacks[reg.0].push(version);
+        version
+    }
+}
+
+fn rename(function: &mut Function, dominators: &Dominators, phis: &[Vec<Reg>]) {
+    let count = function.registers.len();
+    let mut renamer = Renamer {
+        registers: std::mem::take(&mut function.registers),
+        stacks: vec![vec![]; count],
+        taken: vec![false; count],
+        undefined: None,
+    };
+    for param in &function.params {
+        renamer.taken[param.0] = true;
+        renamer.stacks[param.0].push(*param);
+    }
+
+    let mut work = vec![Visit::Enter(BlockId(0))];
+    while let Some(visit) = work.pop() {
+        let block = match visit {
+            Visit::Enter(block) => block,
+            Visit::Leave(pushed) => {
+                for reg in pushed {
+                    renamer.stacks[reg.0].pop();
+                }
+                continue;
+            }
+        };
+
+        let mut pushed = vec![];
+        let current = &mut function.blocks[block.0];
+        for instr in &mut current.instrs {
+            if !matches!(instr, Instr::Phi { .. }) {
+                for reg in instr.uses_mut() {
+                    *reg = renamer.current(*reg);
+                }
+            }
+            if let Some(dest) = instr.dest_mut() {
+                pushed.push(*dest);
+                *dest = renamer.version(*dest);
+            }
+        }
+        for reg in current.terminator.uses_mut() {
+            *reg = renamer.current(*reg);
+        }
+
+        for successor in current.terminator.successors() {
+            let instrs = &mut function.blocks[successor.0].instrs;
+            for (instr, original) in instrs.iter_mut().zip(&phis[successor.0]) {
+                if let Instr::Phi { args, .. } = instr {
+                    for (from, reg) in args {
+                        if *from == block {
+                            *reg = renamer.current(*original);
+                        }
+                    }
+                }
+            }
+        }
+
+        work.push(Visit::Leave(pushed));
+        for child in dominators.children(block).iter().rev() {
+            work.push(Visit::Enter(*child));
+        }
+    }
+
+    function.registers = renamer.registers;
+    if let Some(undefined) = renamer.undefined {
+        function.blocks[0].instrs.insert(
+            0,
+            Instr::Const {
+                dest: undefined,
+                value: Constant::Nil,
+            },
+        );
+    }
+}
+
+/// Gives every edge from a block with several successors to a block with
+/// `phi`s a block of its own, so the copies for the edge have a place.
+fn split_critical_edges(function: &mut Function) {
+    for index in 0..function.blocks.len() {
+        let successors = function.blocks[index].terminator.successors();
+        if successors.len() < 2 {
+            continue;
+        }
+        for (slot, target) in successors.into_iter().enumerate() {
+            let has_phi = function.blocks[target.0]
+                .instrs
+                .iter()
+                .any(|instr| matches!(instr, Instr::Phi { .. }));
+            if !has_phi {
+                continue;
+            }
+
+            let split = BlockId(function.blocks.len());
+            function.blocks.push(Block {
+                instrs: vec![],
+                terminator: Terminator::Jump(target),
+            });
+            *function.blocks[index].terminator.successors_mut()[slot] = split;
+            for instr in &mut function.blocks[target.0].instrs {
+                if let Instr::Phi { args, .. } = instr {
+                    if let Some(arg) = args.iter_mut().find(|(from, _)| from.0 == index) {
+                        arg.0 = split;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Type inference on the IR. Every register, global and return value that
+//! is not annotated gets the type of all the values written to it, or `any`
+//! when those disagree.
+
+use std::collections::HashMap;
+
+use super::ir::*;
+
+pub fn infer_types(module: &mut Module) {
+    // `None` until something is known about the register.
+    let mut registers = module
+        .functions
+        .iter()
+        .map(|function| {
+            let mut types = function
+                .registers
+                .iter()
+                .map(|register| register.declared.then_some(register.ty))
+                .collect::<Vec<Option<Ty>>>();
+            // Callers can pass anything for a parameter without annotation.
+            for param in &function.params {
+                types[param.0].get_or_insert(Ty::Any);
+            }
+            types
+        })
+        .collect::<Vec<Vec<Option<Ty>>>>();
+    let mut returns = module
+        .functions
+        .iter()
+        .map(|function| {
+            let ret = function.ret_declared.then_some(function.ret);
+            (function.name.clone(), ret)
+        })
+        .collect::<HashMap<String, Option<Ty>>>();
+    let mut globals = module
+        .globals
+        .iter()
+        .map(|global| (global.name.clone(), global.declared.then_some(global.ty)))
+        .collect::<HashMap<String, Option<Ty>>>();
+
+    loop {
+        let mut changed = false;
+
+        for (index, function) in module.functions.iter().enumerate() {
+            for block in &function.blocks {
+                for instr in &block.instrs {
+                    if let Instr::StoreGlobal { name, src } = instr {
+                        let declared = module
+                            .globals
+                            .iter()
+                            .any(|global| &global.name == name && global.declared);
+                        if !declared {
+                            let ty = registers[index][src.0];
+                            changed |= join(globals.entry(name.clone()).or_default(), ty);
+                        }
+                    }
+
+                    if let Some(dest) = instr.dest() {
+                        if !function.registers[dest.0].declared {
+                            let ty = result(instr, &registers[index], &returns, &globals);
+                            changed |= join(&mut registers[index][dest.0], ty);
+                        }
+                    }
+                }
+
+                if let Terminator::Return(value) = block.terminator {
+                    if !function.ret_declared {
+                        let ty = registers[index][value.0];
+                        changed |= join(returns.get_mut(&function.name).unwrap(), ty);
+                    }
+                }
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    for (function, types) in module.functions.iter_mut().zip(registers) {
+        for (register, ty) in function.registers.iter_mut().zip(types) {
+            register.ty = ty.unwrap_or(Ty::Any);
+        }
+        function.ret = returns[&function.name].unwrap_or(Ty::Any);
+    }
+
+    for function in &module.functions {
+        for block in &function.blocks {
+            for instr in &block.instrs {
+                if let Instr::LoadGlobal { name, .. } | Instr::StoreGlobal { name, .. } = instr {
+                    globals.entry(name.clone()).or_default();
+                }
+            }
+        }
+    }
+    let mut names = globals.keys().cloned().collect::<Vec<String>>();
+    names.sort();
+    module.globals = names
+        .into_iter()
+        .map(|name| Global {
+            ty: globals[&name].unwrap_or(Ty::Any),
+            declared: module
+                .globals
+                .iter()
+                .any(|global| global.name == name && global.declared),
+            name,
+        })
+        .collect();
+}
+
+/// Widens `slot` to also hold `ty`. Returns whether it changed.
+fn join(slot: &mut Option<Ty>, ty: Option<Ty>) -> bool {
+    match (*slot, ty) {
+        (_, None) => false,
+        (None, Some(ty)) => {
+            *slot = Some(ty);
+            true
+        }
+        (Some(current), Some(ty)) if current == ty || current == Ty::Any => false,
+        (Some(_), Some(_)) => {
+            *slot = Some(Ty::Any);
+            true
+        }
+    }
+}
+
+fn result(
+    instr: &Instr,
+    registers: &[Option<Ty>],
+    returns: &HashMap<String, Option<Ty>>,
+    globals: &HashMap<String, Option<Ty>>,
+) -> Option<Ty> {
+    match instr {
+        Instr::Const { value, .. } => Some(value.ty()),
+        Instr::Copy { src, .. } => registers[src.0],
+        Instr::Unary { op, src, .. } => match (op, registers[src.0]?) {
+            (UnaryOp::Not, _) => Some(Ty::Bool),
+            (UnaryOp::Neg, ty @ (Ty::Int | Ty::Float)) => Some(ty),
+            (UnaryOp::BitNot, Ty::Int) => Some(Ty::Int),
+            (UnaryOp::ToFloat, Ty::Int | Ty::Float) => Some(Ty::Float),
+            _ => Some(Ty::Any),
+        },
+        Instr::Binary {
+            op, left, right, ..
+        } => Some(binary(*op, registers[left.0]?, registers[right.0]?)),
+        Instr::LoadGlobal { name, .. } => globals.get(name).copied().flatten(),
+        Instr::Phi { args, .. } => {
+            let mut ty = None;
+            for (_, src) in args {
+                join(&mut ty, registers[src.0]);
+            }
+            ty
+        }
+        Instr::Call { function, .. } => match returns.get(function) {
+            Some(ret) => *ret,
+            None => Some(Ty::Any),
+        },
+        _ => Some(Ty::Any),
+    }
+}
+
+/// The type of `left op right`.
+pub fn binary(op: BinaryOp, left: Ty, right: Ty) -> Ty {
+    use BinaryOp::*;
+
+    match (op, left, right) {
+        (op, _, _) if op.is_comparison() => Ty::Bool,
+        (Add, Ty::Str, Ty::Str) => Ty::Str,
+        (Add | Sub | Mul | Div | Rem | Pow, Ty::Int, Ty::Int) => Ty::Int,
+        (Add | Sub | Mul | Div | Rem | Pow, Ty::Int | Ty::Float, Ty::Int | Ty::Float) => Ty::Float,
+        (BitAnd | BitOr | BitXor | Shl | Shr, Ty::Int, Ty::Int) => Ty::Int,
+        _ => Ty::Any,
+    }
+}
@@ -20,6 +20,9 @@ pub enum LexerError {
     #[error("Can't create numeric literal due to invalid character {raw:?}")]
     NumericLiteralInvalidChar { raw: String, invalid: char },
 
+    #[error("Integer literal {raw:?} is out of range for a 64-bit integer")]
+    NumericLiteralOutOfRange { raw: String },
+
     #[error("Unrecognized symbol")]
     UnknownSymbol { symbol: String },
 }
\ No newline at end of file
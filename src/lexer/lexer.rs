@@ -4,8 +4,12 @@ use crate::lexer::*;
 
 #[derive(Debug, Clone)]
 pub struct Lexer<'a> {
+    source: &'a str,
     chars: std::iter::Peekable<std::str::Chars<'a>>,
     keywords: HashMap<String, TokenType>,
+    position: Span,
+    token_start: Span,
+    comments: bool,
 }
 
 macro_rules! try_consume {
@@ -13,7 +17,7 @@ macro_rules! try_consume {
         if let Some(c) = $self.chars.peek() {
             if try_consume!(impl c, $($inner), *) {
                 let tmp = *c;
-                $self.chars.next();
+                $self.bump();
                 Some(tmp)
             } else {
                 None
@@ -29,8 +33,18 @@ macro_rules! try_consume {
 
 impl<'a> Lexer<'a> {
     pub fn new(chars: &'a str) -> Lexer<'a> {
+        let start = Span {
+            line: 1,
+            column: 1,
+            offset: 0,
+        };
+
         Lexer {
+            source: chars,
             chars: chars.chars().peekable(),
+            position: start,
+            token_start: start,
+            comments: false,
 
             keywords: HashMap::from([
                 (
@@ -80,23 +94,55 @@ impl<'a> Lexer<'a> {
                 (String::from("if"), TokenType::Terminal(String::from("if"))),
                 (
                     String::from("while"),
-                    TokenType::Terminal(String::from("wjile")),
+                    TokenType::Terminal(String::from("while")),
                 ),
                 (
                     String::from("for"),
                     TokenType::Terminal(String::from("for")),
                 ),
+                (
+                    String::from("throw"),
+                    TokenType::Terminal(String::from("throw")),
+                ),
+                (
+                    String::from("try"),
+                    TokenType::Terminal(String::from("try")),
+                ),
+                (
+                    String::from("catch"),
+                    TokenType::Terminal(String::from("catch")),
+                ),
+                (
+                    String::from("finally"),
+                    TokenType::Terminal(String::from("finally")),
+                ),
             ]),
         }
     }
 
-    pub fn get_tokens(&mut self) -> Result<Vec<TokenType>, LexerError> {
+    /// A lexer that keeps comments as `TokenType::Comment` tokens instead of
+    /// discarding them, for tools such as the formatter that must not lose them.
+    pub fn with_comments(chars: &'a str) -> Lexer<'a> {
+        let mut lexer = Lexer::new(chars);
+        lexer.comments = true;
+        lexer
+    }
+
+    pub fn get_tokens(&mut self) -> Result<Vec<Token>, LexerError> {
         let mut tokens = Vec::new();
 
         loop {
             match self.next_token() {
-                Ok(TokenType::EOF) => break tokens.push(TokenType::EOF),
-                Ok(token) => tokens.push(token),
+                Ok(TokenType::EOF) => {
+                    break tokens.push(Token {
+                        kind: TokenType::EOF,
+                        span: self.token_start,
+                    })
+                }
+                Ok(kind) => tokens.push(Token {
+                    kind,
+                    span: self.token_start,
+                }),
                 Err(err) => return Err(err),
             }
         }
@@ -104,6 +150,88 @@ impl<'a> Lexer<'a> {
         Ok(tokens)
     }
 
+    /// Tokens with their surrounding whitespace and comments attached as
+    /// trivia, for tools that need to reproduce the source byte for byte.
+    /// Comments only appear as trivia, never as tokens of their own.
+    pub fn get_cst_tokens(&mut self) -> Result<Vec<CstToken>, LexerError> {
+        self.comments = true;
+
+        let mut tokens = vec![];
+        let mut comments = vec![];
+
+        loop {
+            let kind = self.next_token()?;
+            let range = self.token_start.offset..self.position.offset;
+
+            match kind {
+                TokenType::Comment(_) => comments.push(range),
+                TokenType::EOF => {
+                    tokens.push((kind, self.token_start, range));
+                    break;
+                }
+                _ => tokens.push((kind, self.token_start, range)),
+            }
+        }
+
+        let mut cst_tokens: Vec<CstToken> = vec![];
+        let mut previous_end = 0;
+
+        for (kind, span, range) in tokens {
+            let gap_start = previous_end;
+            let mut leading_start = gap_start;
+
+            // The previous token keeps the trivia up to the first line break
+            // that is not part of a block comment.
+            if let Some(previous) = cst_tokens.last_mut() {
+                leading_start = self.source[gap_start..range.start]
+                    .match_indices('\n')
+                    .map(|(index, _)| gap_start + index)
+                    .find(|index| !comments.iter().any(|comment| comment.contains(index)))
+                    .unwrap_or(range.start);
+                previous.trailing = self.source[gap_start..leading_start].to_string();
+            }
+
+            previous_end = range.end;
+            cst_tokens.push(CstToken {
+                token: Token { kind, span },
+                leading: self.source[leading_start..range.start].to_string(),
+                text: self.source[range].to_string(),
+                trailing: String::new(),
+            });
+        }
+
+        Ok(cst_tokens)
+    }
+
+    /// The reserved words of the language, sorted.
+    pub fn keywords(&self) -> Vec<&str> {
+        let mut keywords = self
+            .keywords
+            .keys()
+            .map(|keyword| keyword.as_str())
+            .collect::<Vec<&str>>();
+        keywords.sort_unstable();
+        keywords
+    }
+
+    /// Where the lexer currently is, e.g. the location of the character that
+    /// caused a `LexerError`.
+    pub fn position(&self) -> Span {
+        self.position
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.position.offset += c.len_utf8();
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(c)
+    }
+
     fn parse_numbers(&mut self, start: char) -> Result<TokenType, LexerError> {
         let mut raw = start.to_string();
         let radix = 10;
@@ -128,6 +256,10 @@ impl<'a> Lexer<'a> {
             });
         }
 
+        if hint == NumericHint::Integer && strip_separators(&raw).parse::<i64>().is_err() {
+            return Err(LexerError::NumericLiteralOutOfRange { raw });
+        }
+
         Ok(TokenType::Numeric { raw, hint })
     }
 
@@ -136,7 +268,7 @@ impl<'a> Lexer<'a> {
         loop {
             match self.chars.peek() {
                 None => {
-                    break if allow_empty || raw.len() > 0 {
+                    break if allow_empty || !raw.is_empty() {
                         Ok(raw)
                     } else {
                         Err(LexerError::MissingExpectedSymbol {
@@ -145,18 +277,18 @@ impl<'a> Lexer<'a> {
                         })
                     }
                 }
-                Some(c) if c.is_digit(radix) || (*c == '_' && raw.len() > 0) => raw.push(*c),
+                Some(c) if c.is_digit(radix) || (*c == '_' && (allow_empty || !raw.is_empty())) => raw.push(*c),
                 Some(c) if !c.is_ascii_alphabetic() && *c != '_' => break Ok(raw),
                 Some(c) => break Err(LexerError::NumericLiteralInvalidChar { raw, invalid: *c }),
             }
-            self.chars.next();
+            self.bump();
         }
     }
 
     fn parse_string(&mut self) -> Result<TokenType, LexerError> {
         let mut buf = String::new();
         loop {
-            match self.chars.next() {
+            match self.bump() {
                 Some('"') => break Ok(TokenType::String(buf)),
                 Some(c) => buf.push(c),
                 None => {
@@ -173,8 +305,8 @@ impl<'a> Lexer<'a> {
         let mut buf = start.to_string();
         loop {
             match self.chars.peek() {
-                Some(c) if c.is_alphanumeric() || c.is_digit(10) || *c == '_' => {
-                    buf.push(self.chars.next().unwrap())
+                Some(c) if c.is_alphanumeric() || c.is_ascii_digit() || *c == '_' => {
+                    buf.push(self.bump().unwrap())
                 }
                 _ => break self.tag_identifier(buf),
             }
@@ -189,29 +321,40 @@ impl<'a> Lexer<'a> {
     }
 
     fn parse_single_comment(&mut self) -> Result<TokenType, LexerError> {
+        let mut buf = String::from("//");
         while let Some(c) = self.chars.peek() {
             if c == &'\n' {
                 break;
             }
-            self.chars.next();
+            buf.push(*c);
+            self.bump();
         }
 
+        if self.comments {
+            return Ok(TokenType::Comment(buf.trim_end().to_string()));
+        }
         self.next_token()
     }
 
     fn parse_block_comment(&mut self) -> Result<TokenType, LexerError> {
+        let mut buf = String::from("/*");
         while let Some(c) = self.chars.peek() {
             if c == &'*' {
-                self.chars.next(); // consume the '*'
+                self.bump(); // consume the '*'
+                buf.push('*');
                 if self.check_next('/') {
-                    self.chars.next(); // consume the '/'
+                    buf.push('/');
                     break;
                 }
                 continue;
             }
-            self.chars.next();
+            buf.push(*c);
+            self.bump();
         }
 
+        if self.comments {
+            return Ok(TokenType::Comment(buf));
+        }
         self.next_token()
     }
 
@@ -220,7 +363,7 @@ impl<'a> Lexer<'a> {
             Some(c) => {
                 let result = c == &next;
                 if result {
-                    self.chars.next();
+                    self.bump();
                 }
                 result
             }
@@ -258,6 +401,14 @@ impl<'a> Lexer<'a> {
                 raw: c,
                 kind: PunctuationKind::Dot,
             }),
+            '?' => Ok(TokenType::Punctuation {
+                raw: c,
+                kind: PunctuationKind::Question,
+            }),
+            ':' => Ok(TokenType::Punctuation {
+                raw: c,
+                kind: PunctuationKind::Colon,
+            }),
             '!' => {
                 if self.check_next('=') {
                     return Ok(TokenType::Operator(OperatorKind::BangEqual));
@@ -294,6 +445,8 @@ impl<'a> Lexer<'a> {
                     return Ok(TokenType::Operator(OperatorKind::MinusEqual));
                 } else if self.check_next('-') {
                     return Ok(TokenType::Operator(OperatorKind::Decrement));
+                } else if self.check_next('>') {
+                    return Ok(TokenType::Operator(OperatorKind::Arrow));
                 }
                 Ok(TokenType::Operations {
                     raw: c,
@@ -301,31 +454,70 @@ impl<'a> Lexer<'a> {
                 })
             }
 
-            '*' => Ok(TokenType::Operations {
-                raw: c,
-                kind: OperationKind::Star,
-            }),
+            '*' => {
+                if self.check_next('=') {
+                    return Ok(TokenType::Operator(OperatorKind::StarEqual));
+                } else if self.check_next('*') {
+                    return Ok(TokenType::Operator(OperatorKind::StarStar));
+                }
+                Ok(TokenType::Operations {
+                    raw: c,
+                    kind: OperationKind::Star,
+                })
+            }
             '/' => {
                 if self.check_next('/') {
                     return self.parse_single_comment();
                 } else if self.check_next('*') {
                     return self.parse_block_comment();
+                } else if self.check_next('=') {
+                    return Ok(TokenType::Operator(OperatorKind::SlashEqual));
                 }
                 Ok(TokenType::Operations {
                     raw: c,
                     kind: OperationKind::Slash,
                 })
             }
+            '%' => {
+                if self.check_next('=') {
+                    return Ok(TokenType::Operator(OperatorKind::PercentEqual));
+                }
+                Ok(TokenType::Operations {
+                    raw: c,
+                    kind: OperationKind::Percent,
+                })
+            }
+
+            '&' => Ok(TokenType::Operations {
+                raw: c,
+                kind: OperationKind::Ampersand,
+            }),
+            '|' => Ok(TokenType::Operations {
+                raw: c,
+                kind: OperationKind::Pipe,
+            }),
+            '^' => Ok(TokenType::Operations {
+                raw: c,
+                kind: OperationKind::Caret,
+            }),
+            '~' => Ok(TokenType::Operations {
+                raw: c,
+                kind: OperationKind::Tilde,
+            }),
 
             '<' => {
                 if self.check_next('=') {
                     return Ok(TokenType::Operator(OperatorKind::LessEqual));
+                } else if self.check_next('<') {
+                    return Ok(TokenType::Operator(OperatorKind::LessLess));
                 }
                 Ok(TokenType::Operator(OperatorKind::Less))
             }
             '>' => {
                 if self.check_next('=') {
                     return Ok(TokenType::Operator(OperatorKind::GreaterEqual));
+                } else if self.check_next('>') {
+                    return Ok(TokenType::Operator(OperatorKind::GreaterGreater));
                 }
                 Ok(TokenType::Operator(OperatorKind::Greater))
             }
@@ -344,14 +536,15 @@ impl<'a> Lexer<'a> {
             if !c.is_whitespace() {
                 break;
             }
-            self.chars.next();
+            self.bump();
         }
     }
 
     fn next_token(&mut self) -> Result<TokenType, LexerError> {
         self.skip_whitespace();
+        self.token_start = self.position;
 
-        if let Some(c) = self.chars.next() {
+        if let Some(c) = self.bump() {
             self.transform_to_type(c)
         } else {
             Ok(TokenType::EOF)
@@ -1,6 +1,9 @@
+#[allow(clippy::module_inception)]
 pub mod lexer;
 pub mod token;
 pub mod error;
 
-pub use token::{TokenType, NumericHint, OperationKind, PunctuationKind, OperatorKind};
+pub use token::{
+    strip_separators, CstToken, NumericHint, OperationKind, OperatorKind, PunctuationKind, Span, Token, TokenType,
+};
 pub use error::LexerError;
\ No newline at end of file
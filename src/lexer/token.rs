@@ -1,4 +1,6 @@
-#[derive(Debug, PartialEq, Clone)]
+use serde::Serialize;
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
 pub enum PunctuationKind {
     OpenParen,
     CloseParen,
@@ -9,9 +11,11 @@ pub enum PunctuationKind {
     Bang,
     Comma,
     Dot,
+    Question,
+    Colon,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
 pub enum OperatorKind {
     Greater,
     GreaterEqual,
@@ -21,19 +25,32 @@ pub enum OperatorKind {
     BangEqual,
     PlusEqual,
     MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
     Increment,
     Decrement,
+    StarStar,
+    LessLess,
+    GreaterGreater,
+    /// `->` before the return type of a function.
+    Arrow,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
 pub enum OperationKind {
     Plus,
     Minus,
     Star,
     Slash,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
 pub enum NumericHint {
     Integer,
     FloatingPoint,
@@ -46,7 +63,23 @@ pub struct Punctuation {
 }
 
 
-#[derive(Debug, PartialEq, Clone)]
+/// Line and column (both starting at 1) of the first character of a token,
+/// along with its byte offset into the source.
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Default, Serialize)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(tag = "type", content = "value")]
 pub enum TokenType {
     EOF,
     Punctuation { raw: char, kind: PunctuationKind },
@@ -57,15 +90,80 @@ pub enum TokenType {
     Numeric { raw: String, hint: NumericHint },
     String(String),
     Terminal(String),
+    Comment(String),
 }
 
-impl TokenType {
-    pub fn is_string(&self) -> bool {
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Token {
+    pub kind: TokenType,
+    pub span: Span,
+}
+
+/// A token with the whitespace and comments around it. `trailing` trivia runs
+/// up to the end of the token's line and `leading` trivia is everything else
+/// before the token, so writing out `leading`, `text` and `trailing` of every
+/// token reproduces the source exactly.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct CstToken {
+    pub token: Token,
+    pub leading: String,
+    pub text: String,
+    pub trailing: String,
+}
+
+impl std::fmt::Display for CstToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}", self.leading, self.text, self.trailing)
+    }
+}
+
+impl std::fmt::Display for OperatorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            OperatorKind::Greater => ">",
+            OperatorKind::GreaterEqual => ">=",
+            OperatorKind::Less => "<",
+            OperatorKind::LessEqual => "<=",
+            OperatorKind::EqualEqual => "==",
+            OperatorKind::BangEqual => "!=",
+            OperatorKind::PlusEqual => "+=",
+            OperatorKind::MinusEqual => "-=",
+            OperatorKind::StarEqual => "*=",
+            OperatorKind::SlashEqual => "/=",
+            OperatorKind::PercentEqual => "%=",
+            OperatorKind::Increment => "++",
+            OperatorKind::Decrement => "--",
+            OperatorKind::StarStar => "**",
+            OperatorKind::LessLess => "<<",
+            OperatorKind::GreaterGreater => ">>",
+            OperatorKind::Arrow => "->",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Writes the token the way it appears in source code.
+impl std::fmt::Display for TokenType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            TokenType::String(_) => true,
-            _ => false
+            TokenType::EOF => Ok(()),
+            TokenType::Punctuation { raw, .. } => write!(f, "{}", raw),
+            TokenType::Operations { raw, .. } => write!(f, "{}", raw),
+            TokenType::Operator(kind) => write!(f, "{}", kind),
+            TokenType::Identifier(value) => write!(f, "{}", value),
+            TokenType::Char(value) => write!(f, "{}", value),
+            TokenType::Numeric { raw, .. } => write!(f, "{}", raw),
+            TokenType::String(value) => write!(f, "\"{}\"", value),
+            TokenType::Terminal(value) => write!(f, "{}", value),
+            TokenType::Comment(value) => write!(f, "{}", value),
         }
     }
+}
+
+impl TokenType {
+    pub fn is_string(&self) -> bool {
+        matches!(self, TokenType::String(_))
+    }
 
     pub fn unwrap_string(&self) -> String {
         match self {
@@ -73,4 +171,9 @@ impl TokenType {
             _ => panic!("Called unwrap_string on non-string type")
         }
     }
-}
\ No newline at end of file
+}
+
+/// Removes the `_` digit separators allowed inside numeric literals (`1_000`).
+pub fn strip_separators(raw: &str) -> String {
+    raw.chars().filter(|c| *c != '_').collect()
+}
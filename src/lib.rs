@@ -0,0 +1,11 @@
+pub mod backend;
+pub mod checker;
+pub mod formatter;
+pub mod graphviz;
+pub mod highlighter;
+pub mod ir;
+pub mod lexer;
+pub mod linter;
+pub mod optimizer;
+pub mod parser;
+pub mod resolver;
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::linter::Rule;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[cfg(feature = "toml")]
+    #[error("{0}")]
+    Toml(#[from] toml::de::Error),
+
+    #[cfg(not(feature = "toml"))]
+    #[error("Built without the 'toml' feature, lint.toml cannot be read")]
+    Unsupported,
+
+    #[error("Unknown lint rule {0:?}")]
+    UnknownRule(String),
+}
+
+/// Which rules run, read from a TOML file such as
+///
+/// ```toml
+/// [rules]
+/// shadowed-name = false
+/// ```
+///
+/// Rules that are not listed are enabled.
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    rules: HashMap<String, bool>,
+}
+
+impl Config {
+    #[cfg(feature = "toml")]
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        let config: Config = toml::from_str(text)?;
+
+        for name in config.rules.keys() {
+            if Rule::from_name(name).is_none() {
+                return Err(ConfigError::UnknownRule(name.clone()));
+            }
+        }
+
+        Ok(config)
+    }
+
+    #[cfg(not(feature = "toml"))]
+    pub fn parse(_text: &str) -> Result<Config, ConfigError> {
+        Err(ConfigError::Unsupported)
+    }
+
+    pub fn enabled(&self, rule: Rule) -> bool {
+        self.rules.get(rule.name()).copied().unwrap_or(true)
+    }
+}
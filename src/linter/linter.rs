@@ -0,0 +1,412 @@
+use std::collections::HashMap;
+
+use super::config::Config;
+use crate::lexer::{OperatorKind, PunctuationKind, Span, Token, TokenType};
+use crate::parser::cst::{CstNode, SyntaxTree};
+use crate::parser::expr::*;
+use crate::parser::stmt::Stmt;
+use crate::parser::visitor::{walk_assign, walk_binary, walk_set, walk_stmt, Visitor};
+use crate::resolver::resolver::{DeclarationKind, Resolver};
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Rule {
+    UnusedVariable,
+    UnreachableCode,
+    SelfAssignment,
+    SelfComparison,
+    EmptyBlock,
+    ShadowedName,
+    InfiniteLoop,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 7] = [
+        Rule::UnusedVariable,
+        Rule::UnreachableCode,
+        Rule::SelfAssignment,
+        Rule::SelfComparison,
+        Rule::EmptyBlock,
+        Rule::ShadowedName,
+        Rule::InfiniteLoop,
+    ];
+
+    /// The name used in config files and `lint:allow` comments.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::UnusedVariable => "unused-variable",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::SelfAssignment => "self-assignment",
+            Rule::SelfComparison => "self-comparison",
+            Rule::EmptyBlock => "empty-block",
+            Rule::ShadowedName => "shadowed-name",
+            Rule::InfiniteLoop => "infinite-loop",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lint {
+    pub rule: Rule,
+    pub span: Span,
+    pub message: String,
+}
+
+/// Runs the rules enabled in `config` over a program. `tree` locates the
+/// keywords and braces the AST does not keep. `tokens` are the tokens of the
+/// program including comments, which the tree only holds as trivia: a comment
+/// with `lint:allow(rule, ...)` silences those rules on its own line and the
+/// line after it.
+pub fn lint(tree: &SyntaxTree, tokens: &[Token], config: &Config) -> Vec<Lint> {
+    let mut linter = Linter {
+        nodes: nodes(tree),
+        lints: vec![],
+    };
+    linter.visit_block(&tree.statements);
+    linter.names(&tree.statements);
+
+    let allowed = allowed(tokens);
+    let mut lints = linter
+        .lints
+        .into_iter()
+        .filter(|lint| config.enabled(lint.rule))
+        .filter(|lint| {
+            !allowed
+                .get(&lint.span.line)
+                .is_some_and(|rules| rules.contains(&lint.rule))
+        })
+        .collect::<Vec<Lint>>();
+
+    lints.sort_by(|left, right| left.span.partial_cmp(&right.span).unwrap());
+    lints
+}
+
+struct Linter<'a> {
+    /// The node of every statement in the concrete syntax tree.
+    nodes: HashMap<*const Stmt, &'a CstNode>,
+    lints: Vec<Lint>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: Rule, span: Span, message: String) {
+        self.lints.push(Lint {
+            rule,
+            span,
+            message,
+        });
+    }
+
+    /// Where `stmt` starts: its keyword, its opening brace or else its first
+    /// token.
+    fn start(&self, stmt: &Stmt) -> Span {
+        let node = self.nodes[&(stmt as *const Stmt)];
+        node.first_token().unwrap().token.span
+    }
+
+    /// Rules that need to know which declaration a name refers to.
+    fn names(&mut self, statements: &[Stmt]) {
+        let resolution = Resolver::new().resolve(statements);
+
+        for (index, declaration) in resolution.declarations.iter().enumerate() {
+            // Assigning a variable does not use the value it had.
+            if declaration.scope != 0
+                && matches!(
+                    declaration.kind,
+                    DeclarationKind::Variable | DeclarationKind::Parameter
+                )
+                && !resolution
+                    .references
+                    .iter()
+                    .any(|reference| reference.declaration == Some(index) && !reference.assignment)
+            {
+                self.report(
+                    Rule::UnusedVariable,
+                    declaration.span,
+                    format!("{} '{}' is never used", declaration.kind, declaration.name),
+                );
+            }
+
+            if declaration.kind == DeclarationKind::Method {
+                continue;
+            }
+
+            let mut scope = resolution.scopes[declaration.scope].parent;
+            while let Some(index) = scope {
+                let shadowed = resolution.declarations.iter().find(|outer| {
+                    outer.scope == index
+                        && outer.name == declaration.name
+                        && outer.kind != DeclarationKind::Method
+                        && outer.span < declaration.span
+                });
+
+                if let Some(outer) = shadowed {
+                    self.report(
+                        Rule::ShadowedName,
+                        declaration.span,
+                        format!(
+                            "'{}' shadows the {} declared at {}",
+                            declaration.name, outer.kind, outer.span
+                        ),
+                    );
+                    break;
+                }
+                scope = resolution.scopes[index].parent;
+            }
+        }
+    }
+}
+
+impl Visitor for Linter<'_> {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::While { condition, body } if is_true(condition) && !exits(body) => {
+                self.report(
+                    Rule::InfiniteLoop,
+                    self.start(stmt),
+                    String::from("'while (true)' has no return or throw to leave it"),
+                );
+            }
+            Stmt::Block { statements } if is_empty(statements) => {
+                self.report(
+                    Rule::EmptyBlock,
+                    self.start(stmt),
+                    String::from("Empty block"),
+                );
+            }
+            // The bodies of `try`, `catch` and `finally` are not
+            // `Stmt::Block`s, their braces belong to the `try` itself.
+            Stmt::Try {
+                body,
+                catch_body,
+                finally_body,
+                ..
+            } => {
+                let node = self.nodes[&(stmt as *const Stmt)];
+                let braces = node
+                    .tokens()
+                    .filter(|token| token.token.kind == open_curly())
+                    .map(|token| token.token.span)
+                    .collect::<Vec<Span>>();
+                let bodies = [Some(body), catch_body.as_ref(), finally_body.as_ref()];
+
+                for (body, brace) in bodies.into_iter().flatten().zip(braces) {
+                    if is_empty(body) {
+                        self.report(Rule::EmptyBlock, brace, String::from("Empty block"));
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        walk_stmt(self, stmt)
+    }
+
+    /// Flags the first statement after a `return` or `throw` in the block.
+    fn visit_block(&mut self, statements: &[Stmt]) {
+        let mut exited = false;
+
+        for stmt in statements {
+            if exited && !matches!(stmt, Stmt::Comment { .. }) {
+                self.report(
+                    Rule::UnreachableCode,
+                    self.start(stmt),
+                    String::from("Unreachable code"),
+                );
+                exited = false;
+            }
+
+            if matches!(stmt, Stmt::Return { .. } | Stmt::Throw { .. }) {
+                exited = true;
+            }
+            self.visit_stmt(stmt);
+        }
+    }
+
+    fn visit_binary(&mut self, expr: &BinaryExpr) {
+        let comparison = matches!(
+            expr.op.kind,
+            TokenType::Operator(
+                OperatorKind::EqualEqual
+                    | OperatorKind::BangEqual
+                    | OperatorKind::Greater
+                    | OperatorKind::GreaterEqual
+                    | OperatorKind::Less
+                    | OperatorKind::LessEqual
+            )
+        );
+
+        if comparison && same(&expr.left, &expr.right) {
+            self.report(
+                Rule::SelfComparison,
+                expr.op.span,
+                format!("Both sides of '{}' are the same", expr.op.kind),
+            );
+        }
+
+        walk_binary(self, expr)
+    }
+
+    fn visit_assign(&mut self, expr: &AssignExpr) {
+        if let Expr::Variable(value) = &*expr.value {
+            if value.kind == expr.name.kind {
+                self.report(
+                    Rule::SelfAssignment,
+                    expr.name.span,
+                    format!("'{}' is assigned to itself", expr.name.kind),
+                );
+            }
+        }
+
+        walk_assign(self, expr)
+    }
+
+    fn visit_set(&mut self, expr: &SetExpr) {
+        if let Expr::Get(value) = &*expr.value {
+            if value.name.kind == expr.name.kind && same(&value.object, &expr.object) {
+                self.report(
+                    Rule::SelfAssignment,
+                    expr.name.span,
+                    format!("'{}' is assigned to itself", expr.name.kind),
+                );
+            }
+        }
+
+        walk_set(self, expr)
+    }
+}
+
+/// Expressions that are certain to evaluate to the same value twice in a
+/// row: variables, `this` and properties of those.
+fn same(left: &Expr, right: &Expr) -> bool {
+    match (left, right) {
+        (Expr::Grouping(left), _) => same(&left.expr, right),
+        (_, Expr::Grouping(right)) => same(left, &right.expr),
+        (Expr::Variable(left), Expr::Variable(right)) => left.kind == right.kind,
+        (Expr::This(_), Expr::This(_)) => true,
+        (Expr::Get(left), Expr::Get(right)) => {
+            left.name.kind == right.name.kind && same(&left.object, &right.object)
+        }
+        _ => false,
+    }
+}
+
+fn is_empty(statements: &[Stmt]) -> bool {
+    statements
+        .iter()
+        .all(|stmt| matches!(stmt, Stmt::Comment { .. }))
+}
+
+fn is_true(expr: &Expr) -> bool {
+    match expr {
+        Expr::Grouping(expr) => is_true(&expr.expr),
+        Expr::Literal(Literal::Boolean(value)) => *value,
+        Expr::Literal(Literal::Terminal(terminal)) => {
+            terminal.value.downcast_ref::<&str>() == Some(&"true")
+        }
+        _ => false,
+    }
+}
+
+/// Whether `body` can leave the loop through a `return` or `throw` of its
+/// own, not counting those in functions declared inside it.
+fn exits(body: &Stmt) -> bool {
+    let mut exits = Exits(false);
+    exits.visit_stmt(body);
+    exits.0
+}
+
+struct Exits(bool);
+
+impl Visitor for Exits {
+    fn visit_return(&mut self, _keyword: &Token, _value: Option<&Expr>) {
+        self.0 = true;
+    }
+
+    fn visit_throw(&mut self, _keyword: &Token, _value: &Expr) {
+        self.0 = true;
+    }
+
+    fn visit_function(&mut self, _name: &Token, _params: &[Token], _body: &[Stmt]) {}
+
+    fn visit_class(&mut self, _name: &Token, _super_class: Option<&Expr>, _methods: &[Stmt]) {}
+
+    fn visit_lambda(&mut self, _expr: &LambdaExpr) {}
+}
+
+/// The node of every statement in `tree`. Both trees hold the statements in
+/// the same order, which a pre-order walk of each visits them in.
+fn nodes(tree: &SyntaxTree) -> HashMap<*const Stmt, &CstNode> {
+    fn collect<'a>(node: &'a CstNode, nodes: &mut Vec<&'a CstNode>) {
+        for child in node.nodes() {
+            if child.kind.is_stmt() {
+                nodes.push(child);
+            }
+            collect(child, nodes);
+        }
+    }
+
+    let mut statements = Statements(vec![]);
+    statements.visit_block(&tree.statements);
+    let mut nodes = vec![];
+    collect(&tree.root, &mut nodes);
+
+    assert_eq!(statements.0.len(), nodes.len());
+    statements.0.into_iter().zip(nodes).collect()
+}
+
+struct Statements(Vec<*const Stmt>);
+
+impl Visitor for Statements {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        self.0.push(stmt);
+        walk_stmt(self, stmt)
+    }
+}
+
+/// Lines on which `lint:allow` comments silence rules.
+fn allowed(tokens: &[Token]) -> HashMap<usize, Vec<Rule>> {
+    let mut allowed: HashMap<usize, Vec<Rule>> = HashMap::new();
+
+    for token in tokens {
+        let text = match &token.kind {
+            TokenType::Comment(text) => text,
+            _ => continue,
+        };
+
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find("lint:allow(") {
+            rest = &rest[start + "lint:allow(".len()..];
+            let end = rest.find(')').unwrap_or(rest.len());
+
+            let rules = rest[..end]
+                .split(',')
+                .filter_map(|name| Rule::from_name(name.trim()));
+            let last_line = token.span.line + text.matches('\n').count() + 1;
+            for rule in rules {
+                for line in token.span.line..=last_line {
+                    allowed.entry(line).or_default().push(rule);
+                }
+            }
+
+            rest = &rest[end..];
+        }
+    }
+
+    allowed
+}
+
+fn open_curly() -> TokenType {
+    TokenType::Punctuation {
+        raw: '{',
+        kind: PunctuationKind::OpenCurly,
+    }
+}
@@ -1,23 +1,184 @@
 extern crate clap;
 
-pub mod lexer;
-pub mod parser;
+use mini_comp::backend::error::BackendError;
+use mini_comp::backend::{c, js, wasm, x86_64};
+use mini_comp::checker::checker::check;
+use mini_comp::formatter::formatter::Formatter;
+use mini_comp::graphviz::ast::ast_to_dot;
+use mini_comp::graphviz::cfg::cfg_to_dot;
+use mini_comp::highlighter::highlighter::{segments, to_ansi, to_html};
+use mini_comp::ir::lower::lower;
+use mini_comp::ir::passes::{optimize, Pass};
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::lexer::Token;
+use mini_comp::linter::config::Config;
+use mini_comp::linter::linter::lint;
+use mini_comp::optimizer::fold::fold_constants;
+use mini_comp::parser::cst::SyntaxTree;
+use mini_comp::parser::parser::Parser;
+use mini_comp::parser::sexpr::to_sexpr;
+use mini_comp::parser::stmt::Stmt;
 
-use lexer::lexer::Lexer;
-use parser::parser::Parser;
-
-use clap::{App, SubCommand};
+use clap::{App, ArgMatches, SubCommand};
 fn main() -> std::io::Result<()> {
     let matches = App::new("MiniComp")
+        .subcommand(SubCommand::with_name("build").args_from_usage(
+            "
+            --target=<TARGET>        'what to compile to, valid values are 'c', 'js', 'wat', 'wasm', 'x86_64-asm''
+            --output=[OUTPUT]        'file to write, the input file with the extension of the target by default'
+            --opt-level=[LEVEL]      'optimizations to run on the ir, valid values are '0' (default), '1', '2''
+            --passes=[PASSES]...     'ir passes to run instead of the ones of --opt-level, valid values are 'sccp', 'cse', 'copy-prop', 'dce''
+            <INPUT>         'File to compile'
+            "
+        ))
+        .subcommand(SubCommand::with_name("check").args_from_usage(
+            "
+            <FILES>...      'Files to type check'
+            "
+        ))
         .subcommand(SubCommand::with_name("debug").args_from_usage(
             "
-            --show=[TOKENS]...       'show certain steps in compiling process, valid values are 'tokens', 'ast''
+            --show=[TOKENS]...       'show certain steps in compiling process, valid values are 'tokens', 'ast', 'optimized-ast', 'cst', 'ast-dot', 'cfg-dot', 'ir', 'ir-passes''
+            --format=[FORMAT]        'output format for tokens and ast, valid values are 'debug' (default), 'json', 'sexpr'; the last two only show 'tokens', 'ast' and 'optimized-ast''
+            --opt-level=[LEVEL]      'optimizations to run on the ir, valid values are '0' (default), '1', '2''
+            --passes=[PASSES]...     'ir passes to run instead of the ones of --opt-level, valid values are 'sccp', 'cse', 'copy-prop', 'dce''
             <INPUT>         'File to load'
             "
         ))
+        .subcommand(SubCommand::with_name("fmt").args_from_usage(
+            "
+            --check         'report files that are not formatted instead of rewriting them'
+            <FILES>...      'Files to format'
+            "
+        ))
+        .subcommand(SubCommand::with_name("highlight").args_from_usage(
+            "
+            --format=[FORMAT]   'output format, valid values are 'ansi' (default), 'html''
+            <INPUT>             'File to highlight'
+            "
+        ))
+        .subcommand(SubCommand::with_name("lint").args_from_usage(
+            "
+            --config=[CONFIG]   'rules to run, read from lint.toml when it exists otherwise'
+            <FILES>...          'Files to lint'
+            "
+        ))
         .get_matches();
 
     match matches.subcommand() {
+        Some(("build", sub_matches)) => {
+            let filename = sub_matches.value_of("INPUT").unwrap();
+            let text = std::fs::read_to_string(filename)?;
+            let mut lexer = Lexer::new(&text);
+
+            let tokens = match lexer.get_tokens() {
+                Ok(tokens) => tokens,
+                Err(err) => {
+                    eprintln!("{}:{}: {}", filename, lexer.position(), err);
+                    std::process::exit(1);
+                }
+            };
+            let statements = match parse(filename, tokens) {
+                Some(statements) => statements,
+                None => std::process::exit(1),
+            };
+
+            // JavaScript is written from the AST, which keeps the names and
+            // positions the source map refers to.
+            let target = sub_matches.value_of("target").unwrap();
+            if target == "js" {
+                let path = match sub_matches.value_of("output") {
+                    Some(path) => std::path::PathBuf::from(path),
+                    None => std::path::Path::new(filename).with_extension("js"),
+                };
+                let input = std::path::Path::new(filename);
+                let source = if input.parent() == path.parent() {
+                    input.file_name().unwrap().to_string_lossy().to_string()
+                } else {
+                    std::fs::canonicalize(input)?.display().to_string()
+                };
+                let file = path.file_name().unwrap().to_string_lossy().to_string();
+                let output = match js::generate(&statements, &source, &file) {
+                    Ok(output) => output,
+                    Err(err) => backend_error(filename, err),
+                };
+                std::fs::write(&path, output.code)?;
+                std::fs::write(format!("{}.map", path.display()), output.source_map)?;
+                return Ok(());
+            }
+
+            let mut module = match lower(&statements) {
+                Ok(module) => module,
+                Err(err) => {
+                    match err.span {
+                        Some(span) => eprintln!("{}:{}: {}", filename, span, err),
+                        None => eprintln!("{}: {}", filename, err),
+                    }
+                    std::process::exit(1);
+                }
+            };
+            optimize(&mut module, &passes(sub_matches), |_, _| {});
+
+            let (extension, output) = match target {
+                "c" => ("c", c::generate(&module).into_bytes()),
+                "wat" | "wasm" => {
+                    let output = wasm::generate(&module).and_then(|text| match target {
+                        "wat" => Ok(text.into_bytes()),
+                        _ => wasm::assemble(&text),
+                    });
+                    match output {
+                        Ok(output) => (target, output),
+                        Err(err) => backend_error(filename, err),
+                    }
+                }
+                "x86_64-asm" => match x86_64::generate(&module) {
+                    Ok(output) => ("s", output.into_bytes()),
+                    Err(err) => backend_error(filename, err),
+                },
+                _ => {
+                    eprintln!("Unknown target '{}'", target);
+                    std::process::exit(1);
+                }
+            };
+            let path = match sub_matches.value_of("output") {
+                Some(path) => std::path::PathBuf::from(path),
+                None => std::path::Path::new(filename).with_extension(extension),
+            };
+            std::fs::write(path, output)?;
+        }
+        Some(("check", sub_matches)) => {
+            let mut failed = false;
+
+            for filename in sub_matches.values_of("FILES").unwrap() {
+                let text = std::fs::read_to_string(filename)?;
+                let mut lexer = Lexer::new(&text);
+
+                let tokens = match lexer.get_tokens() {
+                    Ok(tokens) => tokens,
+                    Err(err) => {
+                        eprintln!("{}:{}: {}", filename, lexer.position(), err);
+                        failed = true;
+                        continue;
+                    }
+                };
+
+                let statements = match parse(filename, tokens) {
+                    Some(statements) => statements,
+                    None => {
+                        failed = true;
+                        continue;
+                    }
+                };
+                for error in check(&statements) {
+                    println!("{}:{}: error: {}", filename, error.span, error.message);
+                    failed = true;
+                }
+            }
+
+            if failed {
+                std::process::exit(1);
+            }
+        }
         Some(("debug", sub_matches)) => {
             let filename = sub_matches.value_of("INPUT").unwrap();
             let text = std::fs::read_to_string(filename)?;
@@ -27,6 +188,61 @@ fn main() -> std::io::Result<()> {
                 .values_of("show")
                 .unwrap_or_default()
                 .collect::<Vec<&str>>();
+            let format = sub_matches.value_of("format").unwrap_or("debug");
+
+            if format != "debug" {
+                if format != "json" && format != "sexpr" {
+                    eprintln!("Unknown format '{}'", format);
+                    std::process::exit(1);
+                }
+                // The other steps print text or graphs that have no
+                // structured form.
+                if let Some(show) = shows
+                    .iter()
+                    .find(|show| !["tokens", "ast", "optimized-ast"].contains(show))
+                {
+                    eprintln!(
+                        "'--show={}' cannot be written as {}, only 'tokens', 'ast' and 'optimized-ast' can",
+                        show, format
+                    );
+                    std::process::exit(1);
+                }
+
+                let mut document = serde_json::Map::new();
+                document.insert(String::from("version"), serde_json::json!(1));
+
+                let mut lexer = lexer.clone();
+                let tokens = match lexer.get_tokens() {
+                    Ok(tokens) => tokens,
+                    Err(err) => {
+                        eprintln!("Error at {}: {:#?}", lexer.position(), err);
+                        std::process::exit(1);
+                    }
+                };
+
+                if shows.contains(&"tokens") {
+                    document.insert(String::from("tokens"), serde_json::json!(tokens));
+                }
+
+                let mut statements = Parser::new(tokens).parse_program();
+                if shows.contains(&"ast") {
+                    document.insert(String::from("ast"), serde_json::json!(statements));
+                }
+
+                if shows.contains(&"optimized-ast") {
+                    fold_constants(&mut statements);
+                    document.insert(String::from("optimized-ast"), serde_json::json!(statements));
+                }
+
+                let document = serde_json::Value::Object(document);
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&document).unwrap());
+                } else {
+                    println!("{}", to_sexpr(&document));
+                }
+                return Ok(());
+            }
+
             if shows.contains(&"tokens") {
                 let mut lexer = lexer.clone();
                 let tokens = lexer.get_tokens();
@@ -43,12 +259,255 @@ fn main() -> std::io::Result<()> {
                         println!("{:#?}", statements);
                     }
                     Err(err) => {
-                        eprintln!("{:#?}", err)
+                        eprintln!("Error at {}: {:#?}", lexer.position(), err)
                     }
                 }                
             }
+
+            if shows.contains(&"optimized-ast") {
+                let mut lexer = lexer.clone();
+                match lexer.get_tokens() {
+                    Ok(tokens) => {
+                        let mut statements = Parser::new(tokens).parse_program();
+                        fold_constants(&mut statements);
+                        println!("{:#?}", statements);
+                    }
+                    Err(err) => {
+                        eprintln!("Error at {}: {:#?}", lexer.position(), err)
+                    }
+                }
+            }
+
+            if shows.contains(&"cst") {
+                let mut lexer = lexer.clone();
+                match lexer.get_cst_tokens() {
+                    Ok(tokens) => {
+                        let tree = SyntaxTree::parse(tokens);
+                        print!("{}", tree.root.outline());
+                    }
+                    Err(err) => {
+                        eprintln!("Error at {}: {:#?}", lexer.position(), err)
+                    }
+                }
+            }
+
+            if shows.contains(&"ast-dot") || shows.contains(&"cfg-dot") {
+                let mut lexer = lexer.clone();
+                match lexer.get_tokens() {
+                    Ok(tokens) => {
+                        let statements = Parser::new(tokens).parse_program();
+                        if shows.contains(&"ast-dot") {
+                            print!("{}", ast_to_dot(&statements));
+                        }
+                        if shows.contains(&"cfg-dot") {
+                            print!("{}", cfg_to_dot(&statements));
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("Error at {}: {:#?}", lexer.position(), err)
+                    }
+                }
+            }
+
+            if shows.contains(&"ir") || shows.contains(&"ir-passes") {
+                let passes = passes(sub_matches);
+                let dump = shows.contains(&"ir-passes");
+
+                let mut lexer = lexer.clone();
+                match lexer.get_tokens() {
+                    Ok(tokens) => {
+                        let statements = Parser::new(tokens).parse_program();
+                        match lower(&statements) {
+                            Ok(mut module) => {
+                                if dump {
+                                    print!("; before optimization\n{}", module);
+                                }
+                                optimize(&mut module, &passes, |name, module| {
+                                    if dump {
+                                        print!("\n; after {}\n{}", name, module);
+                                    }
+                                });
+                                if shows.contains(&"ir") {
+                                    if dump {
+                                        print!("\n; after leaving ssa\n");
+                                    }
+                                    print!("{}", module);
+                                }
+                            }
+                            Err(err) => match err.span {
+                                Some(span) => eprintln!("Error at {}: {}", span, err),
+                                None => eprintln!("Error: {}", err),
+                            },
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("Error at {}: {:#?}", lexer.position(), err)
+                    }
+                }
+            }
         }
-        _ => ()
+        Some(("fmt", sub_matches)) => {
+            let check = sub_matches.is_present("check");
+            let mut unformatted = false;
+
+            for filename in sub_matches.values_of("FILES").unwrap() {
+                let text = std::fs::read_to_string(filename)?;
+                let mut lexer = Lexer::with_comments(&text);
+
+                let tokens = match lexer.get_tokens() {
+                    Ok(tokens) => tokens,
+                    Err(err) => {
+                        eprintln!("{}:{}: {}", filename, lexer.position(), err);
+                        unformatted = true;
+                        continue;
+                    }
+                };
+
+                let statements = match parse(filename, tokens) {
+                    Some(statements) => statements,
+                    None => {
+                        unformatted = true;
+                        continue;
+                    }
+                };
+                let formatted = Formatter::new().format_program(&statements);
+
+                if formatted == text {
+                    continue;
+                }
+
+                if check {
+                    println!("{}", filename);
+                    unformatted = true;
+                } else {
+                    std::fs::write(filename, formatted)?;
+                }
+            }
+
+            if unformatted {
+                std::process::exit(1);
+            }
+        }
+        Some(("highlight", sub_matches)) => {
+            let filename = sub_matches.value_of("INPUT").unwrap();
+            let text = std::fs::read_to_string(filename)?;
+            let mut lexer = Lexer::with_comments(&text);
+
+            let tokens = match lexer.get_tokens() {
+                Ok(tokens) => tokens,
+                Err(err) => {
+                    eprintln!("{}:{}: {}", filename, lexer.position(), err);
+                    std::process::exit(1);
+                }
+            };
+            let segments = segments(&text, &tokens);
+
+            match sub_matches.value_of("format").unwrap_or("ansi") {
+                "ansi" => print!("{}", to_ansi(&segments)),
+                "html" => print!("{}", to_html(&segments, filename)),
+                format => {
+                    eprintln!("Unknown format '{}'", format);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(("lint", sub_matches)) => {
+            let config_file = sub_matches.value_of("config");
+            let config = match config_file {
+                Some(filename) => Some(std::fs::read_to_string(filename)?),
+                None => std::fs::read_to_string("lint.toml").ok(),
+            };
+            let config = match config.as_deref().map(Config::parse) {
+                Some(Ok(config)) => config,
+                Some(Err(err)) => {
+                    eprintln!("{}: {}", config_file.unwrap_or("lint.toml"), err);
+                    std::process::exit(1);
+                }
+                None => Config::default(),
+            };
+
+            let mut failed = false;
+
+            for filename in sub_matches.values_of("FILES").unwrap() {
+                let text = std::fs::read_to_string(filename)?;
+                let mut lexer = Lexer::with_comments(&text);
+
+                let tokens = match lexer.get_tokens() {
+                    Ok(tokens) => tokens,
+                    Err(err) => {
+                        eprintln!("{}:{}: {}", filename, lexer.position(), err);
+                        failed = true;
+                        continue;
+                    }
+                };
+
+                // The text lexed fine just now.
+                let cst_tokens = Lexer::new(&text).get_cst_tokens().unwrap();
+                let tree = match SyntaxTree::try_parse(cst_tokens) {
+                    Ok(tree) => tree,
+                    Err(err) => {
+                        eprintln!("{}:{}: {}", filename, err.span, err);
+                        failed = true;
+                        continue;
+                    }
+                };
+                for lint in lint(&tree, &tokens, &config) {
+                    println!(
+                        "{}:{}: {}: {}",
+                        filename, lint.span, lint.rule, lint.message
+                    );
+                    failed = true;
+                }
+            }
+
+            if failed {
+                std::process::exit(1);
+            }
+        }
+        _ => (),
     }
     Ok(())
 }
+
+/// The ir passes `--passes` or `--opt-level` ask for.
+fn passes(sub_matches: &ArgMatches) -> Vec<Pass> {
+    match sub_matches.values_of("passes") {
+        Some(names) => names
+            .map(|name| match Pass::from_name(name) {
+                Some(pass) => pass,
+                None => {
+                    eprintln!("Unknown pass '{}'", name);
+                    std::process::exit(1);
+                }
+            })
+            .collect(),
+        None => match sub_matches.value_of("opt-level").unwrap_or("0").parse() {
+            Ok(level) if level <= 2 => Pass::for_level(level),
+            _ => {
+                eprintln!("Unknown optimization level");
+                std::process::exit(1);
+            }
+        },
+    }
+}
+
+/// Parses `tokens`, or reports the syntax error the way lexer errors are
+/// reported.
+fn parse(filename: &str, tokens: Vec<Token>) -> Option<Vec<Stmt>> {
+    match Parser::new(tokens).try_parse_program() {
+        Ok(statements) => Some(statements),
+        Err(err) => {
+            eprintln!("{}:{}: {}", filename, err.span, err);
+            None
+        }
+    }
+}
+
+/// Reports what a backend could not translate and exits.
+fn backend_error(filename: &str, err: BackendError) -> ! {
+    match err.span {
+        Some(span) => eprintln!("{}:{}: {}", filename, span, err),
+        None => eprintln!("{}: {}", filename, err),
+    }
+    std::process::exit(1);
+}
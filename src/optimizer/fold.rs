@@ -0,0 +1,310 @@
+//! Constant folding. Arithmetic, comparisons and string concatenation on
+//! literals are evaluated at compile time, additions of zero and
+//! multiplications by one are dropped where the checker knows the other
+//! operand has the literal's type, and `if`/`while`/`for` statements
+//! whose condition is a literal `true` or `false` lose the branch that can
+//! never run.
+//!
+//! Integer arithmetic follows 64-bit two's complement with truncating
+//! division. Operations that would overflow, divide by zero or mix types in
+//! a way that fails at runtime are left alone, so that the error still
+//! happens when the program runs.
+
+use std::collections::HashMap;
+
+use crate::checker::checker::{operand_types, Type};
+use crate::lexer::{OperationKind, OperatorKind, TokenType};
+use crate::parser::expr::*;
+use crate::parser::stmt::Stmt;
+use crate::parser::visitor::{walk_block_mut, walk_expr_mut, walk_stmt_mut, MutVisitor};
+
+pub fn fold_constants(statements: &mut Vec<Stmt>) {
+    let operands = operand_types(statements).unwrap_or_default();
+    ConstantFolder { operands }.visit_block_mut(statements)
+}
+
+pub struct ConstantFolder {
+    /// The checker's types for the operands of each binary operator, keyed
+    /// by the offset of the operator. Empty when the program has type errors.
+    operands: HashMap<usize, (Type, Type)>,
+}
+
+impl MutVisitor for ConstantFolder {
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt);
+
+        let replacement = match stmt {
+            Stmt::If { condition, branch } => match truth(condition) {
+                Some(true) => Some(take(branch)),
+                Some(false) => Some(empty()),
+                None => None,
+            },
+            Stmt::While { condition, .. } if truth(condition) == Some(false) => Some(empty()),
+            // The initializer still runs once.
+            Stmt::For {
+                initilizer,
+                condition,
+                ..
+            } if truth(condition) == Some(false) => Some(Stmt::Block {
+                statements: vec![take(initilizer)],
+            }),
+            _ => None,
+        };
+
+        if let Some(replacement) = replacement {
+            *stmt = replacement;
+        }
+    }
+
+    /// Drops the empty blocks left behind by pruned branches.
+    fn visit_block_mut(&mut self, statements: &mut Vec<Stmt>) {
+        walk_block_mut(self, statements);
+        statements.retain(|stmt| {
+            !matches!(stmt, Stmt::Block { statements } if statements.is_empty())
+        });
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+
+        let folded = match expr {
+            Expr::BinaryExpr(binary) => match (&*binary.left, &*binary.right) {
+                (Expr::Literal(left), Expr::Literal(right)) => {
+                    binary_op(left, &binary.op.kind, right).map(Expr::Literal)
+                }
+                _ => self.simplify(binary),
+            },
+            Expr::UnaryExpr(unary) => match &*unary.right {
+                Expr::Literal(right) => unary_op(&unary.op.kind, right).map(Expr::Literal),
+                _ => None,
+            },
+            // Parentheses are only needed around something with operators,
+            // which includes the sign of a negative number: `(-2) ** x`.
+            Expr::Grouping(grouping) => match &mut *grouping.expr {
+                Expr::Literal(literal) if negative(literal) => None,
+                inner @ (Expr::Literal(_) | Expr::Variable(_)) => Some(take_expr(inner)),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if let Some(folded) = folded {
+            *expr = folded;
+        }
+    }
+}
+
+impl ConstantFolder {
+    /// `x + 0`, `0 + x`, `x - 0`, `x * 1`, `1 * x` and `x / 1` become `x`
+    /// when `x` is an int. Floats stay: a `float` can hold an int at runtime,
+    /// which `x * 1.0` turns into a float, and `-0.0 + 0.0` is `0.0`.
+    fn simplify(&self, binary: &mut BinaryExpr) -> Option<Expr> {
+        let (left, right) = self.operands.get(&binary.op.span.offset)?;
+        let (zero_left, one_left) = identity(&binary.left, right);
+        let (zero_right, one_right) = identity(&binary.right, left);
+
+        let keep_left = match &binary.op.kind {
+            TokenType::Operations { kind, .. } => match kind {
+                OperationKind::Plus if zero_left => Some(false),
+                OperationKind::Plus if zero_right => Some(true),
+                OperationKind::Minus if zero_right => Some(true),
+                OperationKind::Star if one_left => Some(false),
+                OperationKind::Star | OperationKind::Slash if one_right => Some(true),
+                _ => None,
+            },
+            _ => None,
+        }?;
+
+        Some(match keep_left {
+            true => take_expr(&mut binary.left),
+            false => take_expr(&mut binary.right),
+        })
+    }
+}
+
+/// Whether `expr` is an integer zero or one and the other operand an int.
+fn identity(expr: &Expr, other: &Type) -> (bool, bool) {
+    match (expr, other) {
+        (Expr::Literal(Literal::Integer(value)), Type::Int) => (*value == 0, *value == 1),
+        _ => (false, false),
+    }
+}
+
+fn binary_op(left: &Literal, op: &TokenType, right: &Literal) -> Option<Literal> {
+    use Literal::*;
+
+    match (left, right) {
+        (Integer(left), Integer(right)) => integer_op(*left, op, *right),
+        (Integer(_) | FloatingPoint(_), Integer(_) | FloatingPoint(_)) => {
+            float_op(number(left)?, op, number(right)?)
+        }
+        (String(left), String(right)) => match op {
+            TokenType::Operations {
+                kind: OperationKind::Plus,
+                ..
+            } => Some(String(format!("{}{}", left, right))),
+            TokenType::Operator(OperatorKind::EqualEqual) => Some(boolean(left == right)),
+            TokenType::Operator(OperatorKind::BangEqual) => Some(boolean(left != right)),
+            _ => None,
+        },
+        _ => {
+            let left = terminal(left)?;
+            let right = terminal(right)?;
+            match op {
+                TokenType::Operator(OperatorKind::EqualEqual) => Some(boolean(left == right)),
+                TokenType::Operator(OperatorKind::BangEqual) => Some(boolean(left != right)),
+                _ => None,
+            }
+        }
+    }
+}
+
+fn integer_op(left: i64, op: &TokenType, right: i64) -> Option<Literal> {
+    let value = match op {
+        TokenType::Operations { kind, .. } => match kind {
+            OperationKind::Plus => left.checked_add(right),
+            OperationKind::Minus => left.checked_sub(right),
+            OperationKind::Star => left.checked_mul(right),
+            OperationKind::Slash => left.checked_div(right),
+            OperationKind::Percent => left.checked_rem(right),
+            OperationKind::Ampersand => Some(left & right),
+            OperationKind::Pipe => Some(left | right),
+            OperationKind::Caret => Some(left ^ right),
+            OperationKind::Tilde => None,
+        },
+        TokenType::Operator(kind) => match kind {
+            OperatorKind::StarStar => left.checked_pow(u32::try_from(right).ok()?),
+            OperatorKind::LessLess => left.checked_shl(u32::try_from(right).ok()?),
+            OperatorKind::GreaterGreater => left.checked_shr(u32::try_from(right).ok()?),
+            _ => return compare(left.partial_cmp(&right)?, kind),
+        },
+        _ => None,
+    };
+
+    value.map(Literal::Integer)
+}
+
+fn float_op(left: f64, op: &TokenType, right: f64) -> Option<Literal> {
+    let value = match op {
+        TokenType::Operations { kind, .. } => match kind {
+            OperationKind::Plus => left + right,
+            OperationKind::Minus => left - right,
+            OperationKind::Star => left * right,
+            OperationKind::Slash if right != 0.0 => left / right,
+            OperationKind::Percent if right != 0.0 => left % right,
+            _ => return None,
+        },
+        TokenType::Operator(OperatorKind::StarStar) => left.powf(right),
+        TokenType::Operator(kind) => return compare(left.partial_cmp(&right)?, kind),
+        _ => return None,
+    };
+
+    // Results such as `inf` and `NaN` have no literal to write them as.
+    match value.is_finite() {
+        true => Some(Literal::FloatingPoint(value)),
+        false => None,
+    }
+}
+
+fn compare(ordering: std::cmp::Ordering, op: &OperatorKind) -> Option<Literal> {
+    use std::cmp::Ordering::*;
+
+    let value = match op {
+        OperatorKind::EqualEqual => ordering == Equal,
+        OperatorKind::BangEqual => ordering != Equal,
+        OperatorKind::Less => ordering == Less,
+        OperatorKind::LessEqual => ordering != Greater,
+        OperatorKind::Greater => ordering == Greater,
+        OperatorKind::GreaterEqual => ordering != Less,
+        _ => return None,
+    };
+    Some(boolean(value))
+}
+
+fn unary_op(op: &TokenType, right: &Literal) -> Option<Literal> {
+    match (op, right) {
+        (
+            TokenType::Operations {
+                kind: OperationKind::Minus,
+                ..
+            },
+            Literal::Integer(value),
+        ) => value.checked_neg().map(Literal::Integer),
+        (
+            TokenType::Operations {
+                kind: OperationKind::Minus,
+                ..
+            },
+            Literal::FloatingPoint(value),
+        ) => Some(Literal::FloatingPoint(-value)),
+        (
+            TokenType::Operations {
+                kind: OperationKind::Tilde,
+                ..
+            },
+            Literal::Integer(value),
+        ) => Some(Literal::Integer(!value)),
+        (TokenType::Punctuation { raw: '!', .. }, _) => match terminal(right)? {
+            "true" => Some(boolean(false)),
+            "false" => Some(boolean(true)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn negative(literal: &Literal) -> bool {
+    match literal {
+        Literal::Integer(value) => *value < 0,
+        Literal::FloatingPoint(value) => value.is_sign_negative(),
+        _ => false,
+    }
+}
+
+fn number(literal: &Literal) -> Option<f64> {
+    match literal {
+        Literal::Integer(value) => Some(*value as f64),
+        Literal::FloatingPoint(value) => Some(*value),
+        _ => None,
+    }
+}
+
+/// `true`, `false` or `nil`.
+fn terminal(literal: &Literal) -> Option<&'static str> {
+    match literal {
+        Literal::Terminal(terminal) => terminal.value.downcast_ref::<&str>().copied(),
+        Literal::Boolean(true) => Some("true"),
+        Literal::Boolean(false) => Some("false"),
+        _ => None,
+    }
+}
+
+/// Booleans are written the way the parser creates them.
+fn boolean(value: bool) -> Literal {
+    Literal::Terminal(Terminal {
+        value: Box::new(if value { "true" } else { "false" }),
+    })
+}
+
+fn truth(expr: &Expr) -> Option<bool> {
+    match expr {
+        Expr::Literal(literal) => match terminal(literal)? {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn empty() -> Stmt {
+    Stmt::Block { statements: vec![] }
+}
+
+fn take(stmt: &mut Stmt) -> Stmt {
+    std::mem::replace(stmt, empty())
+}
+
+fn take_expr(expr: &mut Expr) -> Expr {
+    std::mem::replace(expr, Expr::Literal(boolean(false)))
+}
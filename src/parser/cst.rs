@@ -0,0 +1,260 @@
+use super::error::ParseError;
+use super::expr::Expr;
+use super::parser::Parser;
+use super::stmt::Stmt;
+use crate::lexer::CstToken;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NodeKind {
+    Program,
+    BlockStmt,
+    ExpressionStmt,
+    PrintStmt,
+    LetStmt,
+    IfStmt,
+    WhileStmt,
+    ForStmt,
+    FunctionStmt,
+    ReturnStmt,
+    ClassStmt,
+    ThrowStmt,
+    TryStmt,
+    CommentStmt,
+    BinaryExpr,
+    UnaryExpr,
+    Grouping,
+    Conditional,
+    Literal,
+    Variable,
+    Assign,
+    Update,
+    Call,
+    Lambda,
+    Get,
+    Set,
+    This,
+    Super,
+}
+
+impl NodeKind {
+    pub fn is_stmt(&self) -> bool {
+        matches!(
+            self,
+            NodeKind::BlockStmt
+                | NodeKind::ExpressionStmt
+                | NodeKind::PrintStmt
+                | NodeKind::LetStmt
+                | NodeKind::IfStmt
+                | NodeKind::WhileStmt
+                | NodeKind::ForStmt
+                | NodeKind::FunctionStmt
+                | NodeKind::ReturnStmt
+                | NodeKind::ClassStmt
+                | NodeKind::ThrowStmt
+                | NodeKind::TryStmt
+                | NodeKind::CommentStmt
+        )
+    }
+}
+
+impl From<&Stmt> for NodeKind {
+    fn from(stmt: &Stmt) -> NodeKind {
+        match stmt {
+            Stmt::Block { .. } => NodeKind::BlockStmt,
+            Stmt::Expression(_) => NodeKind::ExpressionStmt,
+            Stmt::Print(_) => NodeKind::PrintStmt,
+            Stmt::Let { .. } => NodeKind::LetStmt,
+            Stmt::If { .. } => NodeKind::IfStmt,
+            Stmt::While { .. } => NodeKind::WhileStmt,
+            Stmt::For { .. } => NodeKind::ForStmt,
+            Stmt::Function { .. } => NodeKind::FunctionStmt,
+            Stmt::Return { .. } => NodeKind::ReturnStmt,
+            Stmt::Class { .. } => NodeKind::ClassStmt,
+            Stmt::Throw { .. } => NodeKind::ThrowStmt,
+            Stmt::Try { .. } => NodeKind::TryStmt,
+            Stmt::Comment { .. } => NodeKind::CommentStmt,
+        }
+    }
+}
+
+impl From<&Expr> for NodeKind {
+    fn from(expr: &Expr) -> NodeKind {
+        match expr {
+            Expr::BinaryExpr(_) => NodeKind::BinaryExpr,
+            Expr::UnaryExpr(_) => NodeKind::UnaryExpr,
+            Expr::Grouping(_) => NodeKind::Grouping,
+            Expr::Conditional(_) => NodeKind::Conditional,
+            Expr::Literal(_) => NodeKind::Literal,
+            Expr::Variable(_) => NodeKind::Variable,
+            Expr::Assign(_) => NodeKind::Assign,
+            Expr::Update(_) => NodeKind::Update,
+            Expr::Call(_) => NodeKind::Call,
+            Expr::Lambda(_) => NodeKind::Lambda,
+            Expr::Get(_) => NodeKind::Get,
+            Expr::Set(_) => NodeKind::Set,
+            Expr::This(_) => NodeKind::This,
+            Expr::Super(_) => NodeKind::Super,
+        }
+    }
+}
+
+/// Recorded by the parser as it consumes tokens. `Token` holds the index of
+/// the consumed token.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Event {
+    Start(NodeKind),
+    Token(usize),
+    Finish,
+}
+
+#[derive(Debug, Clone)]
+pub enum CstElement {
+    Node(CstNode),
+    Token(CstToken),
+}
+
+#[derive(Debug, Clone)]
+pub struct CstNode {
+    pub kind: NodeKind,
+    pub children: Vec<CstElement>,
+}
+
+impl CstNode {
+    /// Child nodes, skipping the tokens in between.
+    pub fn nodes(&self) -> impl Iterator<Item = &CstNode> {
+        self.children.iter().filter_map(|child| match child {
+            CstElement::Node(node) => Some(node),
+            CstElement::Token(_) => None,
+        })
+    }
+
+    /// Tokens directly under this node, skipping the child nodes.
+    pub fn tokens(&self) -> impl Iterator<Item = &CstToken> {
+        self.children.iter().filter_map(|child| match child {
+            CstElement::Node(_) => None,
+            CstElement::Token(token) => Some(token),
+        })
+    }
+
+    /// The first token anywhere under this node.
+    pub fn first_token(&self) -> Option<&CstToken> {
+        self.children.iter().find_map(|child| match child {
+            CstElement::Node(node) => node.first_token(),
+            CstElement::Token(token) => Some(token),
+        })
+    }
+
+    /// One line per node and token, indented by depth.
+    pub fn outline(&self) -> String {
+        let mut out = String::new();
+        self.write_outline(0, &mut out);
+        out
+    }
+
+    fn write_outline(&self, depth: usize, out: &mut String) {
+        *out += &format!("{}{:?}\n", "  ".repeat(depth), self.kind);
+
+        for child in &self.children {
+            match child {
+                CstElement::Node(node) => node.write_outline(depth + 1, out),
+                CstElement::Token(token) => {
+                    *out += &format!(
+                        "{}{:?} {:?} {:?} {:?}\n",
+                        "  ".repeat(depth + 1),
+                        token.leading,
+                        token.text,
+                        token.trailing,
+                        token.token.span
+                    )
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for CstNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for child in &self.children {
+            match child {
+                CstElement::Node(node) => write!(f, "{}", node)?,
+                CstElement::Token(token) => write!(f, "{}", token)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A lossless tree over the whole source, together with the AST parsed from
+/// it. Every `Stmt` and `Expr` the parser builds from tokens gets a node of
+/// the matching kind, so the statement nodes under `root` line up one to one,
+/// in order, with `statements`.
+#[derive(Debug)]
+pub struct SyntaxTree {
+    pub root: CstNode,
+    pub statements: Vec<Stmt>,
+}
+
+impl SyntaxTree {
+    pub fn parse(tokens: Vec<CstToken>) -> SyntaxTree {
+        let mut parser = Parser::new(tokens.iter().map(|token| token.token.clone()).collect());
+        let statements = parser.parse_program();
+        SyntaxTree::build(tokens, statements, parser)
+    }
+
+    /// Like `parse`, but a syntax error comes back as a `ParseError`.
+    pub fn try_parse(tokens: Vec<CstToken>) -> Result<SyntaxTree, ParseError> {
+        let mut parser = Parser::new(tokens.iter().map(|token| token.token.clone()).collect());
+        let statements = parser.try_parse_program()?;
+        Ok(SyntaxTree::build(tokens, statements, parser))
+    }
+
+    /// Puts the nodes the parser recorded around `tokens`.
+    fn build(tokens: Vec<CstToken>, statements: Vec<Stmt>, mut parser: Parser) -> SyntaxTree {
+        let mut stack = vec![CstNode {
+            kind: NodeKind::Program,
+            children: vec![],
+        }];
+        let mut consumed = 0;
+
+        for event in parser.take_events() {
+            match event {
+                Event::Start(kind) => stack.push(CstNode {
+                    kind,
+                    children: vec![],
+                }),
+                Event::Token(index) => {
+                    let token = tokens[index].clone();
+                    stack
+                        .last_mut()
+                        .unwrap()
+                        .children
+                        .push(CstElement::Token(token));
+                    consumed = index + 1;
+                }
+                Event::Finish => {
+                    let node = stack.pop().unwrap();
+                    stack
+                        .last_mut()
+                        .unwrap()
+                        .children
+                        .push(CstElement::Node(node));
+                }
+            }
+        }
+
+        // Whatever the parser did not consume, at least the EOF token and the
+        // trivia before it, still belongs to the file.
+        let mut root = stack.pop().unwrap();
+        for token in tokens.into_iter().skip(consumed) {
+            root.children.push(CstElement::Token(token));
+        }
+
+        SyntaxTree { root, statements }
+    }
+}
+
+impl std::fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.root)
+    }
+}
@@ -0,0 +1,11 @@
+use thiserror::Error;
+
+use crate::lexer::Span;
+
+/// A syntax error, located at the token the parser gave up on.
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
@@ -1,15 +1,19 @@
 use std::any::Any;
 
-use crate::lexer::TokenType;
+use serde::{Serialize, Serializer};
 
-#[derive(Debug)]
+use super::stmt::Stmt;
+use crate::lexer::{Span, Token};
+
+#[derive(Debug, Serialize)]
 pub struct Program {
     pub expr: Expr,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "value")]
 pub enum Literal {
-    Integer(i32),
+    Integer(i64),
     FloatingPoint(f64),
     String(String),
     Boolean(bool),
@@ -21,80 +25,164 @@ pub struct Terminal {
     pub value: Box<dyn Any>,
 }
 
-#[derive(Debug)]
+/// Terminals hold `"true"`, `"false"` or `"nil"` and are written out as that
+/// string.
+impl Serialize for Terminal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.value.downcast_ref::<&str>() {
+            Some(value) => serializer.serialize_str(value),
+            None => serializer.serialize_unit(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub struct Grouping {
     pub expr: Box<Expr>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct UnaryExpr {
-    pub op: TokenType,
+    pub op: Token,
     pub right: Box<Expr>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct BinaryExpr {
     pub left: Box<Expr>,
-    pub op: TokenType,
+    pub op: Token,
     pub right: Box<Expr>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+pub struct ConditionalExpr {
+    pub condition: Box<Expr>,
+    pub then_branch: Box<Expr>,
+    pub else_branch: Box<Expr>,
+}
+
+#[derive(Debug, Serialize)]
 pub struct AssignExpr {
-    pub name: TokenType,
+    pub name: Token,
     pub value: Box<Expr>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+pub enum UpdateKind {
+    Prefix,
+    Postfix,
+    Compound,
+}
+
+/// `x++`, `--this.count` and `x += 5` style updates. `target` is either an
+/// `Expr::Variable` or an `Expr::Get`. Only `Postfix` updates evaluate to the
+/// value before the update; the other kinds evaluate to the updated value.
+#[derive(Debug, Serialize)]
 pub struct UpdateExpr {
-    pub name: TokenType,
-    pub op: TokenType,
+    pub target: Box<Expr>,
+    pub op: Token,
     pub change: Box<Expr>,
+    pub kind: UpdateKind,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct CallExpr {
     pub callee: Box<Expr>,
-    pub paren: TokenType,
+    pub paren: Token,
     pub args: Vec<Expr>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+pub struct LambdaExpr {
+    pub keyword: Token,
+    pub params: Vec<Token>,
+    /// Annotation of every parameter, in the same order as `params`.
+    pub param_types: Vec<Option<Token>>,
+    pub return_type: Option<Token>,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Serialize)]
 pub struct GetExpr {
     pub object: Box<Expr>,
-    pub name: TokenType,
+    pub name: Token,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct SetExpr {
     pub object: Box<Expr>,
-    pub name: TokenType,
+    pub name: Token,
     pub value: Box<Expr>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ThisExpr {
-    pub keyword: TokenType,
+    pub keyword: Token,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct SuperExpr {
-    pub keyword: TokenType,
-    pub method: TokenType,
+    pub keyword: Token,
+    pub method: Token,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(tag = "node")]
 pub enum Expr {
     BinaryExpr(BinaryExpr),
     UnaryExpr(UnaryExpr),
     Grouping(Grouping),
+    Conditional(ConditionalExpr),
     Literal(Literal),
-    Variable(TokenType),
+    Variable(Token),
     Assign(AssignExpr),
     Update(UpdateExpr),
     Call(CallExpr),
+    Lambda(LambdaExpr),
     Get(GetExpr),
     Set(SetExpr),
     This(ThisExpr),
     Super(SuperExpr),
 }
+
+impl Expr {
+    /// Location of the token that best identifies this expression: the
+    /// operator of a binary expression, the name of a variable or property,
+    /// the closing paren of a call, and so on. Literals carry no token.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Expr::BinaryExpr(expr) => Some(expr.op.span),
+            Expr::UnaryExpr(expr) => Some(expr.op.span),
+            Expr::Grouping(expr) => expr.expr.span(),
+            Expr::Conditional(expr) => expr.condition.span(),
+            Expr::Literal(_) => None,
+            Expr::Variable(token) => Some(token.span),
+            Expr::Assign(expr) => Some(expr.name.span),
+            Expr::Update(expr) => Some(expr.op.span),
+            Expr::Call(expr) => Some(expr.paren.span),
+            Expr::Lambda(expr) => Some(expr.keyword.span),
+            Expr::Get(expr) => Some(expr.name.span),
+            Expr::Set(expr) => Some(expr.name.span),
+            Expr::This(expr) => Some(expr.keyword.span),
+            Expr::Super(expr) => Some(expr.keyword.span),
+        }
+    }
+
+    /// Location of the first token of this expression, where it is kept in
+    /// the tree.
+    pub fn start(&self) -> Option<Span> {
+        match self {
+            Expr::BinaryExpr(expr) => expr.left.start(),
+            Expr::Conditional(expr) => expr.condition.start(),
+            Expr::Update(UpdateExpr {
+                target,
+                kind: UpdateKind::Postfix | UpdateKind::Compound,
+                ..
+            }) => target.start(),
+            Expr::Call(expr) => expr.callee.start(),
+            Expr::Get(expr) => expr.object.start(),
+            Expr::Set(expr) => expr.object.start(),
+            _ => self.span(),
+        }
+    }
+}
@@ -1,3 +1,8 @@
+pub mod cst;
+pub mod error;
 pub mod expr;
+#[allow(clippy::module_inception)]
 pub mod parser;
+pub mod sexpr;
 pub mod stmt;
+pub mod visitor;
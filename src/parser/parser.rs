@@ -1,42 +1,178 @@
 use super::*;
-use crate::lexer::{NumericHint, OperationKind, OperatorKind, PunctuationKind, TokenType};
+use crate::lexer::{
+    strip_separators, NumericHint, OperationKind, OperatorKind, PunctuationKind, Span,
+    Token, TokenType,
+};
+use parser::cst::{Event, NodeKind};
+use parser::error::ParseError;
 use parser::expr::*;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
 use stmt::Stmt;
 
+thread_local! {
+    /// Set while `try_parse_program` runs on this thread.
+    static RECOVERING: Cell<bool> = const { Cell::new(false) };
+}
+
 pub struct Parser {
     current: usize,
-    tokens: Vec<TokenType>,
+    tokens: Vec<Token>,
+    comments: VecDeque<Token>,
+    /// Comments in front of the body of the statement being parsed, kept for
+    /// after it.
+    body_comments: Vec<Stmt>,
+    events: Vec<Event>,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<TokenType>) -> Parser {
-        Parser { current: 0, tokens }
+    pub fn new(tokens: Vec<Token>) -> Parser {
+        let (comments, tokens): (Vec<Token>, Vec<Token>) = tokens
+            .into_iter()
+            .partition(|token| matches!(token.kind, TokenType::Comment(_)));
+        Parser {
+            current: 0,
+            tokens,
+            comments: comments.into(),
+            body_comments: vec![],
+            events: vec![],
+        }
     }
 
     pub fn parse_program(&mut self) -> Vec<Stmt> {
         let mut statements = vec![];
 
         while !self.end_of_stream() {
+            self.take_comments(&mut statements);
             statements.push(self.parse_declaration());
         }
+        self.take_comments(&mut statements);
+
+        statements
+    }
+
+    /// Like `parse_program`, but the panic raised for a syntax error is turned
+    /// into a `ParseError` with its message and the span of the token the
+    /// parser stopped at, for callers such as editors that must keep running.
+    /// The panic message is not printed.
+    pub fn try_parse_program(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        silence_syntax_errors();
+        RECOVERING.with(|recovering| recovering.set(true));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| self.parse_program()));
+        RECOVERING.with(|recovering| recovering.set(false));
+
+        result.map_err(|payload| {
+            let message = match payload.downcast::<String>() {
+                Ok(message) => *message,
+                Err(payload) => match payload.downcast::<&str>() {
+                    Ok(message) => message.to_string(),
+                    Err(_) => String::from("Invalid syntax"),
+                },
+            };
+            let current = self.current.min(self.tokens.len() - 1);
+
+            ParseError {
+                message,
+                span: self.tokens[current].span,
+            }
+        })
+    }
 
-        return statements;
+    /// The nodes and tokens seen so far, from which `SyntaxTree` builds the
+    /// concrete syntax tree.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn checkpoint(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Records that everything consumed since `checkpoint` makes up `node`.
+    fn node<T>(&mut self, checkpoint: usize, node: T) -> T
+    where
+        for<'a> NodeKind: From<&'a T>,
+    {
+        self.events
+            .insert(checkpoint, Event::Start(NodeKind::from(&node)));
+        self.events.push(Event::Finish);
+        node
+    }
+
+    /// Moves the comments that come before the current token into
+    /// `statements`. Comments are only placed between statements, so one
+    /// written inside an expression ends up before the next statement.
+    fn take_comments(&mut self, statements: &mut Vec<Stmt>) {
+        statements.append(&mut self.body_comments);
+        while let Some(comment) = self.comments.front() {
+            if !self.end_of_stream() && comment.span > self.current_span() {
+                break;
+            }
+
+            let trailing =
+                self.current > 0 && self.tokens[self.current - 1].span.line == comment.span.line;
+            let token = self.comments.pop_front().unwrap();
+            statements.push(Stmt::Comment {
+                token,
+                trailing,
+                before_body: false,
+            });
+        }
+    }
+
+    /// Parses the body of an `if`, `while` or `for`. Comments between the
+    /// head and a body that is not a block go after the whole statement.
+    fn body(&mut self) -> Stmt {
+        let mut comments = vec![];
+        if !self.check_type(&TokenType::Punctuation {
+            raw: '{',
+            kind: PunctuationKind::OpenCurly,
+        }) {
+            let head = self.tokens[self.current - 1].span.line;
+            while let Some(comment) = self.comments.front() {
+                if comment.span > self.current_span() {
+                    break;
+                }
+                let token = self.comments.pop_front().unwrap();
+                comments.push(Stmt::Comment {
+                    trailing: token.span.line == head,
+                    token,
+                    before_body: true,
+                });
+            }
+        }
+
+        let body = self.parse_statement();
+        self.body_comments.splice(0..0, comments);
+        body
     }
 
     fn parse_declaration(&mut self) -> Stmt {
+        let checkpoint = self.checkpoint();
+
         if self.match_type(&[&TokenType::Terminal(String::from("let"))]) {
-            return self.let_declaration();
+            let stmt = self.let_declaration();
+            return self.node(checkpoint, stmt);
         }
 
-        if self.match_type(&[&TokenType::Terminal(String::from("def"))]) {
-            return self.function_statement("function");
+        // `def (` starts an anonymous function, which is an expression.
+        if !self.match_look_ahead(&[&TokenType::Punctuation {
+            raw: '(',
+            kind: PunctuationKind::OpenParen,
+        }]) && self.match_type(&[&TokenType::Terminal(String::from("def"))])
+        {
+            let stmt = self.function_statement("function");
+            return self.node(checkpoint, stmt);
         }
 
         if self.match_type(&[&TokenType::Terminal(String::from("class"))]) {
-            return self.class_statement();
+            let stmt = self.class_statement();
+            return self.node(checkpoint, stmt);
         }
 
-        return self.parse_statement();
+        self.parse_statement()
     }
 
     fn class_statement(&mut self) -> Stmt {
@@ -55,8 +191,10 @@ impl Parser {
                 _ => String::from("Invalid"),
             };
 
+            let checkpoint = self.checkpoint();
             self.consume_unit(&TokenType::Identifier(ident), "Expected parent classname.");
-            super_class = Some(Expr::Variable(self.previous()));
+            let variable = Expr::Variable(self.previous());
+            super_class = Some(self.node(checkpoint, variable));
         }
 
         self.consume_unit(
@@ -74,8 +212,18 @@ impl Parser {
             kind: PunctuationKind::CloseCurly,
         }) && !self.end_of_stream()
         {
-            methods.push(self.function_statement("method"));
+            self.take_comments(&mut methods);
+            if self.check_type(&TokenType::Punctuation {
+                raw: '}',
+                kind: PunctuationKind::CloseCurly,
+            }) {
+                break;
+            }
+            let checkpoint = self.checkpoint();
+            let method = self.function_statement("method");
+            methods.push(self.node(checkpoint, method));
         }
+        self.take_comments(&mut methods);
 
         self.consume_unit(
             &TokenType::Punctuation {
@@ -101,6 +249,7 @@ impl Parser {
             &TokenType::Identifier(ident),
             "Expected variable name after let",
         );
+        let annotation = self.type_annotation("variable name");
 
         if self.match_type(&[&TokenType::Punctuation {
             raw: '=',
@@ -116,43 +265,66 @@ impl Parser {
             );
             return Stmt::Let {
                 token: name,
+                annotation,
                 initilizer,
             };
         }
-        panic!("Invalid syntax, Expected '=' after variable name")
+        panic!(
+            "Invalid syntax at {}, Expected '=' after variable name",
+            self.current_span()
+        )
     }
 
     fn parse_statement(&mut self) -> Stmt {
+        let checkpoint = self.checkpoint();
+
         if self.match_type(&[&TokenType::Terminal(String::from("print"))]) {
-            return self.print_statement();
+            let stmt = self.print_statement();
+            return self.node(checkpoint, stmt);
         }
 
         if self.match_type(&[&TokenType::Terminal(String::from("if"))]) {
-            return self.if_statement();
+            let stmt = self.if_statement();
+            return self.node(checkpoint, stmt);
         }
 
         if self.match_type(&[&TokenType::Terminal(String::from("while"))]) {
-            return self.while_statement();
+            let stmt = self.while_statement();
+            return self.node(checkpoint, stmt);
         }
 
         if self.match_type(&[&TokenType::Terminal(String::from("for"))]) {
-            return self.for_statement();
+            let stmt = self.for_statement();
+            return self.node(checkpoint, stmt);
         }
 
         if self.match_type(&[&TokenType::Terminal(String::from("return"))]) {
-            return self.return_statement();
+            let stmt = self.return_statement();
+            return self.node(checkpoint, stmt);
+        }
+
+        if self.match_type(&[&TokenType::Terminal(String::from("throw"))]) {
+            let stmt = self.throw_statement();
+            return self.node(checkpoint, stmt);
+        }
+
+        if self.match_type(&[&TokenType::Terminal(String::from("try"))]) {
+            let stmt = self.try_statement();
+            return self.node(checkpoint, stmt);
         }
 
         if self.match_type(&[&TokenType::Punctuation {
             raw: '{',
             kind: PunctuationKind::OpenCurly,
         }]) {
-            return Stmt::Block {
+            let stmt = Stmt::Block {
                 statements: self.parse_block(),
             };
+            return self.node(checkpoint, stmt);
         }
 
-        return self.expression_statement();
+        let stmt = self.expression_statement();
+        self.node(checkpoint, stmt)
     }
 
     fn return_statement(&mut self) -> Stmt {
@@ -180,6 +352,98 @@ impl Parser {
         Stmt::Return { keyword, value }
     }
 
+    fn throw_statement(&mut self) -> Stmt {
+        let keyword = self.previous();
+
+        let value = self.parse_expr();
+
+        self.consume_unit(
+            &TokenType::Punctuation {
+                raw: ';',
+                kind: PunctuationKind::Separator,
+            },
+            "Expected ';' after thrown value",
+        );
+
+        Stmt::Throw { keyword, value }
+    }
+
+    fn try_statement(&mut self) -> Stmt {
+        self.consume_unit(
+            &TokenType::Punctuation {
+                raw: '{',
+                kind: PunctuationKind::OpenCurly,
+            },
+            "Expected '{' after try",
+        );
+        let body = self.parse_block();
+
+        let mut catch_name = None;
+        let mut catch_body = None;
+
+        if self.match_type(&[&TokenType::Terminal(String::from("catch"))]) {
+            self.consume_unit(
+                &TokenType::Punctuation {
+                    raw: '(',
+                    kind: PunctuationKind::OpenParen,
+                },
+                "Expected '(' after catch",
+            );
+
+            let ident: String = match self.peek() {
+                TokenType::Identifier(value) => value,
+                _ => String::from("Invalid"),
+            };
+            catch_name = Some(self.consume_unit(
+                &TokenType::Identifier(ident),
+                "Expected error name after '('",
+            ));
+
+            self.consume_unit(
+                &TokenType::Punctuation {
+                    raw: ')',
+                    kind: PunctuationKind::CloseParen,
+                },
+                "Expected ')' after error name",
+            );
+            self.consume_unit(
+                &TokenType::Punctuation {
+                    raw: '{',
+                    kind: PunctuationKind::OpenCurly,
+                },
+                "Expected '{' before catch body",
+            );
+            catch_body = Some(self.parse_block());
+        }
+
+        let mut finally_body = None;
+
+        if self.match_type(&[&TokenType::Terminal(String::from("finally"))]) {
+            self.consume_unit(
+                &TokenType::Punctuation {
+                    raw: '{',
+                    kind: PunctuationKind::OpenCurly,
+                },
+                "Expected '{' after finally",
+            );
+            finally_body = Some(self.parse_block());
+        }
+
+        if catch_body.is_none() && finally_body.is_none() {
+            panic!(
+                "Invalid syntax at {}, Expected catch or finally after try block",
+                self.current_span()
+            );
+        }
+
+        Stmt::Try {
+            body,
+            catch_name,
+            catch_body,
+            finally_body,
+        }
+    }
+
     fn function_statement(&mut self, kind: &str) -> Stmt {
         let ident = match self.peek() {
             TokenType::Identifier(value) => value,
@@ -191,15 +455,40 @@ impl Parser {
             "Expected function name after def",
         );
 
+        let (params, param_types) = self.parse_parameters("function name");
+        let return_type = self.return_type();
+
+        self.consume_unit(
+            &TokenType::Punctuation {
+                raw: '{',
+                kind: PunctuationKind::OpenCurly,
+            },
+            &format!("Expected '{{' before {} body.", kind),
+        );
+
+        let body = self.parse_block();
+
+        Stmt::Function {
+            name,
+            params,
+            param_types,
+            return_type,
+            body,
+        }
+    }
+
+    /// Names of the parameters and the types they are annotated with.
+    fn parse_parameters(&mut self, after: &str) -> (Vec<Token>, Vec<Option<Token>>) {
         self.consume_unit(
             &TokenType::Punctuation {
                 raw: '(',
                 kind: PunctuationKind::OpenParen,
             },
-            "Expected '(' after function name",
+            &format!("Expected '(' after {}", after),
         );
 
         let mut parameters = vec![];
+        let mut types = vec![];
 
         if !self.check_type(&TokenType::Punctuation {
             raw: ')',
@@ -214,6 +503,7 @@ impl Parser {
                 &TokenType::Identifier(ident),
                 "Expected parameter after '('",
             ));
+            types.push(self.type_annotation("parameter name"));
 
             while self.match_type(&[&TokenType::Punctuation {
                 raw: ',',
@@ -232,6 +522,7 @@ impl Parser {
                     &TokenType::Identifier(ident),
                     "Expected parameter after ','",
                 ));
+                types.push(self.type_annotation("parameter name"));
             }
         }
 
@@ -240,23 +531,38 @@ impl Parser {
                 raw: ')',
                 kind: PunctuationKind::CloseParen,
             },
-            "Expected ')' after for",
+            "Expected ')' after parameters",
         );
 
-        self.consume_unit(
-            &TokenType::Punctuation {
-                raw: '{',
-                kind: PunctuationKind::OpenCurly,
-            },
-            &format!("Expected '{{' before {} body.", kind),
-        );
+        (parameters, types)
+    }
 
-        let body = self.parse_block();
+    /// An optional `: type` after the name of a variable or parameter.
+    fn type_annotation(&mut self, after: &str) -> Option<Token> {
+        if self.match_type(&[&TokenType::Punctuation {
+            raw: ':',
+            kind: PunctuationKind::Colon,
+        }]) {
+            return Some(self.type_name(&format!("Expected type after ':' in {}", after)));
+        }
+        None
+    }
 
-        Stmt::Function {
-            name,
-            params: parameters,
-            body,
+    /// An optional `-> type` after the parameters of a function.
+    fn return_type(&mut self) -> Option<Token> {
+        if self.match_type(&[&TokenType::Operator(OperatorKind::Arrow)]) {
+            return Some(self.type_name("Expected type after '->'"));
+        }
+        None
+    }
+
+    /// Types are written as a name, either of a builtin type such as `int`
+    /// or of a class, or as `nil`.
+    fn type_name(&mut self, message: &str) -> Token {
+        match self.peek() {
+            TokenType::Identifier(_) => self.advance(),
+            TokenType::Terminal(keyword) if keyword == "nil" => self.advance(),
+            _ => panic!("Invalid syntax at {}, {}", self.current_span(), message),
         }
     }
 
@@ -269,14 +575,15 @@ impl Parser {
             "Expected '(' after for",
         );
 
-        let initilizer;
-        if self.match_type(&[&TokenType::Terminal(String::from("let"))]) {
-            initilizer = self.let_declaration();
+        let checkpoint = self.checkpoint();
+        let initilizer = if self.match_type(&[&TokenType::Terminal(String::from("let"))]) {
+            self.let_declaration()
         } else {
-            initilizer = self.expression_statement();
-        }
+            self.expression_statement()
+        };
+        let initilizer = self.node(checkpoint, initilizer);
 
-        let _condition = self.parse_expr();
+        let condition = self.parse_expr();
         self.consume_unit(
             &TokenType::Punctuation {
                 raw: ';',
@@ -285,7 +592,7 @@ impl Parser {
             "Expected ';' after for loop condition",
         );
 
-        let change = self.parse_expr();
+        let increment = self.parse_expr();
 
         self.consume_unit(
             &TokenType::Punctuation {
@@ -295,22 +602,14 @@ impl Parser {
             "Expected ')' after for",
         );
 
-        let mut body = self.parse_statement();
+        let body = self.body();
 
-        body = Stmt::Block {
-            statements: vec![body, Stmt::Expression(change)],
-        };
-
-        body = Stmt::While {
-            condition: Expr::Literal(Literal::Boolean(true)),
+        Stmt::For {
+            initilizer: Box::new(initilizer),
+            condition,
+            increment,
             body: Box::new(body),
-        };
-
-        body = Stmt::Block {
-            statements: vec![initilizer, body],
-        };
-
-        body
+        }
     }
 
     fn while_statement(&mut self) -> Stmt {
@@ -330,7 +629,7 @@ impl Parser {
             "Expected ')' after expression",
         );
 
-        let statment = self.parse_statement();
+        let statment = self.body();
         Stmt::While {
             condition,
             body: Box::new(statment),
@@ -354,7 +653,7 @@ impl Parser {
             "Expected ')' after expression",
         );
 
-        let branch = self.parse_statement();
+        let branch = self.body();
 
         Stmt::If {
             condition,
@@ -370,8 +669,16 @@ impl Parser {
             kind: PunctuationKind::CloseCurly,
         }) && !self.end_of_stream()
         {
+            self.take_comments(&mut statements);
+            if self.check_type(&TokenType::Punctuation {
+                raw: '}',
+                kind: PunctuationKind::CloseCurly,
+            }) {
+                break;
+            }
             statements.push(self.parse_declaration());
         }
+        self.take_comments(&mut statements);
 
         self.consume_unit(
             &TokenType::Punctuation {
@@ -394,7 +701,7 @@ impl Parser {
             "Expected ; after expresion",
         );
 
-        return Stmt::Print(expr);
+        Stmt::Print(expr)
     }
 
     fn expression_statement(&mut self) -> Stmt {
@@ -407,56 +714,16 @@ impl Parser {
             "Expected ; after expression",
         );
 
-        return Stmt::Expression(expr);
+        Stmt::Expression(expr)
     }
 
     fn parse_expr(&mut self) -> Expr {
-        if self.match_look_ahead(&[
-            &TokenType::Operator(OperatorKind::Increment),
-            &TokenType::Operator(OperatorKind::Decrement),
-        ]) {
-            let ident = match self.peek() {
-                TokenType::Identifier(value) => value,
-                _ => String::from("Invalid"),
-            };
-
-            let identifier =
-                self.consume_unit(&TokenType::Identifier(ident), "Expected identifier.");
-            let operator = self.advance();
-
-            match operator {
-                TokenType::Operator(OperatorKind::Increment) => {
-                    let new_expr = UpdateExpr {
-                        name: identifier,
-                        op: TokenType::Operations {
-                            raw: '+',
-                            kind: OperationKind::Plus,
-                        },
-                        change: Box::new(Expr::Literal(Literal::Integer(1))),
-                    };
-                    return Expr::Update(new_expr);
-                }
-                TokenType::Operator(OperatorKind::Decrement) => {
-                    let new_expr = UpdateExpr {
-                        name: identifier,
-                        op: TokenType::Operations {
-                            raw: '-',
-                            kind: OperationKind::Minus,
-                        },
-                        change: Box::new(Expr::Literal(Literal::Integer(1))),
-                    };
-                    return Expr::Update(new_expr);
-                }
-                _ => {
-                    panic!("Invalid operator")
-                }
-            }
-        }
         self.parse_assignment()
     }
 
     fn parse_assignment(&mut self) -> Expr {
-        let expr = self.parse_equality();
+        let checkpoint = self.checkpoint();
+        let expr = self.parse_conditional();
 
         if self.match_type(&[&TokenType::Punctuation {
             raw: '=',
@@ -471,7 +738,7 @@ impl Parser {
                         name,
                         value: Box::new(value),
                     };
-                    return Expr::Assign(new_expr);
+                    return self.node(checkpoint, Expr::Assign(new_expr));
                 }
                 Expr::Get(get) => {
                     let new_expr = SetExpr {
@@ -479,51 +746,96 @@ impl Parser {
                         name: get.name,
                         value: Box::new(value),
                     };
-                    return Expr::Set(new_expr);
+                    return self.node(checkpoint, Expr::Set(new_expr));
                 }
                 _ => (),
             }
-        } else if self.match_type(&[&TokenType::Operator(OperatorKind::PlusEqual)]) {
+        } else if self.match_type(&[
+            &TokenType::Operator(OperatorKind::PlusEqual),
+            &TokenType::Operator(OperatorKind::MinusEqual),
+            &TokenType::Operator(OperatorKind::StarEqual),
+            &TokenType::Operator(OperatorKind::SlashEqual),
+            &TokenType::Operator(OperatorKind::PercentEqual),
+        ]) {
+            let operator = self.previous();
+            let kind = match operator.kind {
+                TokenType::Operator(OperatorKind::PlusEqual) => TokenType::Operations {
+                    raw: '+',
+                    kind: OperationKind::Plus,
+                },
+                TokenType::Operator(OperatorKind::MinusEqual) => TokenType::Operations {
+                    raw: '-',
+                    kind: OperationKind::Minus,
+                },
+                TokenType::Operator(OperatorKind::StarEqual) => TokenType::Operations {
+                    raw: '*',
+                    kind: OperationKind::Star,
+                },
+                TokenType::Operator(OperatorKind::SlashEqual) => TokenType::Operations {
+                    raw: '/',
+                    kind: OperationKind::Slash,
+                },
+                _ => TokenType::Operations {
+                    raw: '%',
+                    kind: OperationKind::Percent,
+                },
+            };
+            let op = Token {
+                kind,
+                span: operator.span,
+            };
             let value = self.parse_assignment();
 
-            match expr {
-                Expr::Variable(ident_name) => {
-                    let name = ident_name;
-                    let new_expr = UpdateExpr {
-                        name,
-                        op: TokenType::Operations {
-                            raw: '+',
-                            kind: OperationKind::Plus,
-                        },
-                        change: Box::new(value),
-                    };
-                    return Expr::Update(new_expr);
-                }
-                _ => (),
+            if let Expr::Variable(_) | Expr::Get(_) = expr {
+                let new_expr = UpdateExpr {
+                    target: Box::new(expr),
+                    op,
+                    change: Box::new(value),
+                    kind: UpdateKind::Compound,
+                };
+                return self.node(checkpoint, Expr::Update(new_expr));
             }
-        } else if self.match_type(&[&TokenType::Operator(OperatorKind::MinusEqual)]) {
-            let value = self.parse_assignment();
+            panic!(
+                "Invalid syntax at {}, Expected variable or property before '{}'",
+                operator.span, operator.kind
+            );
+        }
+        expr
+    }
 
-            match expr {
-                Expr::Variable(ident_name) => {
-                    let name = ident_name;
-                    let new_expr = UpdateExpr {
-                        name,
-                        op: TokenType::Operations {
-                            raw: '-',
-                            kind: OperationKind::Minus,
-                        },
-                        change: Box::new(value),
-                    };
-                    return Expr::Update(new_expr);
-                }
-                _ => (),
-            }
+    fn parse_conditional(&mut self) -> Expr {
+        let checkpoint = self.checkpoint();
+        let expr = self.parse_equality();
+
+        if self.match_type(&[&TokenType::Punctuation {
+            raw: '?',
+            kind: PunctuationKind::Question,
+        }]) {
+            let then_branch = self.parse_expr();
+
+            self.consume_unit(
+                &TokenType::Punctuation {
+                    raw: ':',
+                    kind: PunctuationKind::Colon,
+                },
+                "Expected ':' after then branch of conditional expression",
+            );
+
+            let else_branch = self.parse_conditional();
+
+            let new_expr = ConditionalExpr {
+                condition: Box::new(expr),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            };
+            return self.node(checkpoint, Expr::Conditional(new_expr));
         }
+
         expr
     }
 
     fn parse_equality(&mut self) -> Expr {
+        let checkpoint = self.checkpoint();
         let mut expr = self.parse_comparison();
 
         while self.match_type(&[
@@ -540,14 +852,15 @@ impl Parser {
                 right: Box::new(right),
             };
 
-            expr = Expr::BinaryExpr(new_expr)
+            expr = self.node(checkpoint, Expr::BinaryExpr(new_expr))
         }
 
         expr
     }
 
     fn parse_comparison(&mut self) -> Expr {
-        let mut expr = self.parse_term();
+        let checkpoint = self.checkpoint();
+        let mut expr = self.parse_bit_or();
 
         while self.match_type(&[
             &TokenType::Operator(OperatorKind::Greater),
@@ -557,6 +870,102 @@ impl Parser {
         ]) {
             let operator = self.previous();
 
+            let right = self.parse_bit_or();
+
+            let new_expr = BinaryExpr {
+                left: Box::new(expr),
+                op: operator,
+                right: Box::new(right),
+            };
+
+            expr = self.node(checkpoint, Expr::BinaryExpr(new_expr))
+        }
+
+        expr
+    }
+
+    fn parse_bit_or(&mut self) -> Expr {
+        let checkpoint = self.checkpoint();
+        let mut expr = self.parse_bit_xor();
+
+        while self.match_type(&[&TokenType::Operations {
+            raw: '|',
+            kind: OperationKind::Pipe,
+        }]) {
+            let operator = self.previous();
+
+            let right = self.parse_bit_xor();
+
+            let new_expr = BinaryExpr {
+                left: Box::new(expr),
+                op: operator,
+                right: Box::new(right),
+            };
+
+            expr = self.node(checkpoint, Expr::BinaryExpr(new_expr))
+        }
+
+        expr
+    }
+
+    fn parse_bit_xor(&mut self) -> Expr {
+        let checkpoint = self.checkpoint();
+        let mut expr = self.parse_bit_and();
+
+        while self.match_type(&[&TokenType::Operations {
+            raw: '^',
+            kind: OperationKind::Caret,
+        }]) {
+            let operator = self.previous();
+
+            let right = self.parse_bit_and();
+
+            let new_expr = BinaryExpr {
+                left: Box::new(expr),
+                op: operator,
+                right: Box::new(right),
+            };
+
+            expr = self.node(checkpoint, Expr::BinaryExpr(new_expr))
+        }
+
+        expr
+    }
+
+    fn parse_bit_and(&mut self) -> Expr {
+        let checkpoint = self.checkpoint();
+        let mut expr = self.parse_shift();
+
+        while self.match_type(&[&TokenType::Operations {
+            raw: '&',
+            kind: OperationKind::Ampersand,
+        }]) {
+            let operator = self.previous();
+
+            let right = self.parse_shift();
+
+            let new_expr = BinaryExpr {
+                left: Box::new(expr),
+                op: operator,
+                right: Box::new(right),
+            };
+
+            expr = self.node(checkpoint, Expr::BinaryExpr(new_expr))
+        }
+
+        expr
+    }
+
+    fn parse_shift(&mut self) -> Expr {
+        let checkpoint = self.checkpoint();
+        let mut expr = self.parse_term();
+
+        while self.match_type(&[
+            &TokenType::Operator(OperatorKind::LessLess),
+            &TokenType::Operator(OperatorKind::GreaterGreater),
+        ]) {
+            let operator = self.previous();
+
             let right = self.parse_term();
 
             let new_expr = BinaryExpr {
@@ -565,13 +974,14 @@ impl Parser {
                 right: Box::new(right),
             };
 
-            expr = Expr::BinaryExpr(new_expr)
+            expr = self.node(checkpoint, Expr::BinaryExpr(new_expr))
         }
 
         expr
     }
 
     fn parse_term(&mut self) -> Expr {
+        let checkpoint = self.checkpoint();
         let mut expr = self.parse_factor();
 
         while self.match_type(&[
@@ -594,13 +1004,14 @@ impl Parser {
                 right: Box::new(right),
             };
 
-            expr = Expr::BinaryExpr(new_expr)
+            expr = self.node(checkpoint, Expr::BinaryExpr(new_expr))
         }
 
         expr
     }
 
     fn parse_factor(&mut self) -> Expr {
+        let checkpoint = self.checkpoint();
         let mut expr = self.parse_unary();
 
         while self.match_type(&[
@@ -612,6 +1023,10 @@ impl Parser {
                 raw: '/',
                 kind: OperationKind::Slash,
             },
+            &TokenType::Operations {
+                raw: '%',
+                kind: OperationKind::Percent,
+            },
         ]) {
             let operator = self.previous();
 
@@ -623,13 +1038,14 @@ impl Parser {
                 right: Box::new(right),
             };
 
-            expr = Expr::BinaryExpr(new_expr)
+            expr = self.node(checkpoint, Expr::BinaryExpr(new_expr))
         }
 
         expr
     }
 
     fn parse_unary(&mut self) -> Expr {
+        let checkpoint = self.checkpoint();
         if self.match_type(&[
             &TokenType::Punctuation {
                 raw: '!',
@@ -639,6 +1055,10 @@ impl Parser {
                 raw: '-',
                 kind: OperationKind::Minus,
             },
+            &TokenType::Operations {
+                raw: '~',
+                kind: OperationKind::Tilde,
+            },
         ]) {
             let operator = self.previous();
 
@@ -649,13 +1069,100 @@ impl Parser {
                 right: Box::new(right),
             };
 
-            return Expr::UnaryExpr(new_expr);
+            return self.node(checkpoint, Expr::UnaryExpr(new_expr));
+        }
+
+        if self.match_type(&[
+            &TokenType::Operator(OperatorKind::Increment),
+            &TokenType::Operator(OperatorKind::Decrement),
+        ]) {
+            let operator = self.previous();
+
+            let target = self.parse_unary();
+
+            let update = self.update_expression(target, operator, UpdateKind::Prefix);
+            return self.node(checkpoint, update);
         }
 
-        self.parse_call()
+        self.parse_power()
+    }
+
+    fn parse_power(&mut self) -> Expr {
+        let checkpoint = self.checkpoint();
+        let expr = self.parse_postfix();
+
+        // `**` is right-associative and its right operand may itself be
+        // negated, so `-2 ** -1` parses as `-(2 ** (-1))`.
+        if self.match_type(&[&TokenType::Operator(OperatorKind::StarStar)]) {
+            let operator = self.previous();
+
+            let right = self.parse_unary();
+
+            let new_expr = BinaryExpr {
+                left: Box::new(expr),
+                op: operator,
+                right: Box::new(right),
+            };
+
+            return self.node(checkpoint, Expr::BinaryExpr(new_expr));
+        }
+
+        expr
+    }
+
+    fn parse_postfix(&mut self) -> Expr {
+        let checkpoint = self.checkpoint();
+        let expr = self.parse_call();
+
+        if self.match_type(&[
+            &TokenType::Operator(OperatorKind::Increment),
+            &TokenType::Operator(OperatorKind::Decrement),
+        ]) {
+            let operator = self.previous();
+
+            let update = self.update_expression(expr, operator, UpdateKind::Postfix);
+            return self.node(checkpoint, update);
+        }
+
+        expr
+    }
+
+    fn update_expression(&mut self, target: Expr, operator: Token, kind: UpdateKind) -> Expr {
+        let op_kind = match operator.kind {
+            TokenType::Operator(OperatorKind::Increment) => TokenType::Operations {
+                raw: '+',
+                kind: OperationKind::Plus,
+            },
+            TokenType::Operator(OperatorKind::Decrement) => TokenType::Operations {
+                raw: '-',
+                kind: OperationKind::Minus,
+            },
+            _ => panic!("Invalid operator"),
+        };
+        let op = Token {
+            kind: op_kind,
+            span: operator.span,
+        };
+
+        match target {
+            Expr::Variable(_) | Expr::Get(_) => {
+                let new_expr = UpdateExpr {
+                    target: Box::new(target),
+                    op,
+                    change: Box::new(Expr::Literal(Literal::Integer(1))),
+                    kind,
+                };
+                Expr::Update(new_expr)
+            }
+            _ => panic!(
+                "Invalid syntax at {}, Expected variable or property before '++' or '--'",
+                operator.span
+            ),
+        }
     }
 
     fn parse_call(&mut self) -> Expr {
+        let checkpoint = self.checkpoint();
         let mut expr = self.parse_literal();
 
         loop {
@@ -663,7 +1170,8 @@ impl Parser {
                 raw: '(',
                 kind: PunctuationKind::OpenParen,
             }]) {
-                expr = self.finish_call(expr);
+                let call = self.finish_call(expr);
+                expr = self.node(checkpoint, call);
             } else if self.match_type(&[&TokenType::Punctuation {
                 raw: '.',
                 kind: PunctuationKind::Dot,
@@ -677,10 +1185,11 @@ impl Parser {
                     &TokenType::Identifier(ident),
                     "Expected property name after '.'",
                 );
-                expr = Expr::Get(GetExpr {
+                let get = Expr::Get(GetExpr {
                     object: Box::new(expr),
                     name,
                 });
+                expr = self.node(checkpoint, get);
             } else {
                 break;
             }
@@ -690,6 +1199,7 @@ impl Parser {
     }
 
     fn parse_literal(&mut self) -> Expr {
+        let checkpoint = self.checkpoint();
         /*
             Parse Terminals
         */
@@ -698,28 +1208,33 @@ impl Parser {
             let expr = Literal::Terminal(Terminal {
                 value: Box::new("true"),
             });
-            return Expr::Literal(expr);
+            return self.node(checkpoint, Expr::Literal(expr));
         }
 
         if self.match_type(&[&TokenType::Terminal(String::from("false"))]) {
             let expr = Literal::Terminal(Terminal {
                 value: Box::new("false"),
             });
-            return Expr::Literal(expr);
+            return self.node(checkpoint, Expr::Literal(expr));
         }
 
         if self.match_type(&[&TokenType::Terminal(String::from("nil"))]) {
             let expr = Literal::Terminal(Terminal {
                 value: Box::new("nil"),
             });
-            return Expr::Literal(expr);
+            return self.node(checkpoint, Expr::Literal(expr));
         }
 
         if self.match_type(&[&TokenType::Terminal(String::from("this"))]) {
             let expr = ThisExpr {
                 keyword: self.previous(),
             };
-            return Expr::This(expr);
+            return self.node(checkpoint, Expr::This(expr));
+        }
+
+        if self.match_type(&[&TokenType::Terminal(String::from("def"))]) {
+            let lambda = self.lambda_expression();
+            return self.node(checkpoint, lambda);
         }
 
         if self.match_type(&[&TokenType::Terminal(String::from("super"))]) {
@@ -741,7 +1256,7 @@ impl Parser {
                 self.consume_unit(&TokenType::Identifier(ident), "Expected superclass name.");
 
             let new_expr = SuperExpr { keyword, method };
-            return Expr::Super(new_expr);
+            return self.node(checkpoint, Expr::Super(new_expr));
         }
 
         let value = match self.peek() {
@@ -750,31 +1265,33 @@ impl Parser {
         };
 
         if self.match_type(&[&TokenType::Identifier(value)]) {
-            return Expr::Variable(self.previous());
+            let variable = Expr::Variable(self.previous());
+            return self.node(checkpoint, variable);
         }
 
         /*
             Parse Numerics
         */
 
-        match self.peek() {
-            TokenType::Numeric { raw, hint } => {
-                self.advance();
+        if let TokenType::Numeric { raw, hint } = self.peek() {
+            self.advance();
 
-                match hint {
-                    NumericHint::Integer => {
-                        let value = raw.parse::<i32>().unwrap();
-                        let expr = Literal::Integer(value);
-                        return Expr::Literal(expr);
-                    },
-                    NumericHint::FloatingPoint => {
-                        let value = raw.parse::<f64>().unwrap();
-                        let expr = Literal::FloatingPoint(value);
-                        return Expr::Literal(expr);
-                    },
-                }
+            let digits = strip_separators(&raw);
+            match hint {
+                NumericHint::Integer => {
+                    let value = match digits.parse::<i64>() {
+                        Ok(value) => value,
+                        Err(_) => panic!("Invalid syntax, integer literal {} is out of range", raw),
+                    };
+                    let expr = Literal::Integer(value);
+                    return self.node(checkpoint, Expr::Literal(expr));
+                },
+                NumericHint::FloatingPoint => {
+                    let value = digits.parse::<f64>().unwrap();
+                    let expr = Literal::FloatingPoint(value);
+                    return self.node(checkpoint, Expr::Literal(expr));
+                },
             }
-            _ => ()
         }
 
         // let hint = match self.peek() {
@@ -813,9 +1330,9 @@ impl Parser {
         */
         
         if self.peek().is_string() {
-            let value = self.advance().unwrap_string();
-            let expr = Literal::String(String::from(value));
-            return Expr::Literal(expr);
+            let value = self.advance().kind.unwrap_string();
+            let expr = Literal::String(value);
+            return self.node(checkpoint, Expr::Literal(expr));
         }
 
         /*
@@ -839,10 +1356,36 @@ impl Parser {
             let expr = Grouping {
                 expr: Box::new(expr),
             };
-            return Expr::Grouping(expr);
+            return self.node(checkpoint, Expr::Grouping(expr));
         }
 
-        panic!("Invalid Syntax, No literal match");
+        panic!("Invalid Syntax at {}, No literal match", self.current_span());
+    }
+
+    fn lambda_expression(&mut self) -> Expr {
+        let keyword = self.previous();
+
+        let (params, param_types) = self.parse_parameters("def");
+        let return_type = self.return_type();
+
+        self.consume_unit(
+            &TokenType::Punctuation {
+                raw: '{',
+                kind: PunctuationKind::OpenCurly,
+            },
+            "Expected '{' before anonymous function body.",
+        );
+
+        let body = self.parse_block();
+
+        let new_expr = LambdaExpr {
+            keyword,
+            params,
+            param_types,
+            return_type,
+            body,
+        };
+        Expr::Lambda(new_expr)
     }
 
     fn finish_call(&mut self, callee: Expr) -> Expr {
@@ -880,11 +1423,11 @@ impl Parser {
         Expr::Call(expr)
     }
 
-    fn consume_unit(&mut self, token_type: &TokenType, message: &str) -> TokenType {
+    fn consume_unit(&mut self, token_type: &TokenType, message: &str) -> Token {
         if self.check_type(token_type) {
             return self.advance();
         }
-        panic!("Invalid syntax, {}", message);
+        panic!("Invalid syntax at {}, {}", self.current_span(), message);
     }
 
     fn match_type(&mut self, types: &[&TokenType]) -> bool {
@@ -894,7 +1437,7 @@ impl Parser {
                 return true;
             }
         }
-        return false;
+        false
     }
 
     fn match_look_ahead(&mut self, types: &[&TokenType]) -> bool {
@@ -902,7 +1445,7 @@ impl Parser {
             if self.current + 1 >= self.tokens.len() {
                 return false;
             } else {
-                if &&self.tokens[self.current + 1] == token_type {
+                if &&self.tokens[self.current + 1].kind == token_type {
                     return true;
                 }
             }
@@ -917,18 +1460,23 @@ impl Parser {
         &self.peek() == token_type
     }
 
-    fn advance(&mut self) -> TokenType {
+    fn advance(&mut self) -> Token {
         if !self.end_of_stream() {
+            self.events.push(Event::Token(self.current));
             self.current += 1;
         }
         self.previous()
     }
 
+    fn current_span(&self) -> Span {
+        self.tokens[self.current].span
+    }
+
     fn peek(&self) -> TokenType {
-        self.tokens[self.current].clone()
+        self.tokens[self.current].kind.clone()
     }
 
-    fn previous(&mut self) -> TokenType {
+    fn previous(&mut self) -> Token {
         self.tokens[self.current - 1].clone()
     }
 
@@ -936,3 +1484,19 @@ impl Parser {
         self.peek() == TokenType::EOF
     }
 }
+
+/// Wraps the panic hook so that the syntax errors `try_parse_program` turns
+/// into a `ParseError` are not also printed. Every other panic still reaches
+/// the hook that was installed before.
+fn silence_syntax_errors() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !RECOVERING.with(Cell::get) {
+                hook(info);
+            }
+        }));
+    });
+}
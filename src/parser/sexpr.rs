@@ -0,0 +1,38 @@
+use serde_json::Value;
+
+/// Writes a serialized token list or AST as an s-expression. Objects tagged
+/// with `node` or `type` become `(Tag :field value ...)`, other objects become
+/// `(:field value ...)`, arrays become plain lists and `null` becomes `nil`.
+pub fn to_sexpr(value: &Value) -> String {
+    match value {
+        Value::Null => String::from("nil"),
+        Value::Bool(value) => value.to_string(),
+        Value::Number(value) => value.to_string(),
+        Value::String(value) => format!("{:?}", value),
+        Value::Array(items) => {
+            let items = items.iter().map(to_sexpr).collect::<Vec<String>>();
+            format!("({})", items.join(" "))
+        }
+        Value::Object(fields) => {
+            let mut parts = vec![];
+
+            let tag = ["node", "type"]
+                .iter()
+                .find(|tag| matches!(fields.get(**tag), Some(Value::String(_))));
+            if let Some(tag) = tag {
+                if let Some(Value::String(name)) = fields.get(*tag) {
+                    parts.push(name.clone());
+                }
+            }
+
+            for (key, value) in fields {
+                if Some(&key.as_str()) == tag {
+                    continue;
+                }
+                parts.push(format!(":{} {}", key, to_sexpr(value)));
+            }
+
+            format!("({})", parts.join(" "))
+        }
+    }
+}
@@ -1,15 +1,23 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
 use super::expr::Expr;
-use crate::lexer::TokenType;
+use crate::lexer::{Span, Token};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(tag = "node")]
 pub enum Stmt {
     Block {
         statements: Vec<Stmt>,
     },
+    #[serde(serialize_with = "expression_field")]
     Expression(Expr),
+    #[serde(serialize_with = "expression_field")]
     Print(Expr),
     Let {
-        token: TokenType,
+        token: Token,
+        annotation: Option<Token>,
+        #[serde(rename = "initializer")]
         initilizer: Expr,
     },
     If {
@@ -20,18 +28,74 @@ pub enum Stmt {
         condition: Expr,
         body: Box<Stmt>,
     },
+    For {
+        #[serde(rename = "initializer")]
+        initilizer: Box<Stmt>,
+        condition: Expr,
+        increment: Expr,
+        body: Box<Stmt>,
+    },
     Function {
-        name: TokenType,
-        params: Vec<TokenType>,
+        name: Token,
+        params: Vec<Token>,
+        /// Annotation of every parameter, in the same order as `params`.
+        param_types: Vec<Option<Token>>,
+        return_type: Option<Token>,
         body: Vec<Stmt>,
     },
     Return {
-        keyword: TokenType,
+        keyword: Token,
         value: Option<Expr>,
     },
     Class {
-        name: TokenType,
+        name: Token,
         super_class: Option<Expr>,
         methods: Vec<Stmt>,
     },
+    Throw {
+        keyword: Token,
+        value: Expr,
+    },
+    Try {
+        body: Vec<Stmt>,
+        catch_name: Option<Token>,
+        catch_body: Option<Vec<Stmt>>,
+        finally_body: Option<Vec<Stmt>>,
+    },
+    /// Only produced when the tokens come from `Lexer::with_comments`.
+    /// `trailing` comments share a line with the code before them.
+    /// `before_body` comments sit between the head of the `if`, `while` or
+    /// `for` before them and its body, which is not a block.
+    Comment {
+        token: Token,
+        trailing: bool,
+        before_body: bool,
+    },
+}
+
+impl Stmt {
+    /// Location of the first token of this statement that is kept in the
+    /// tree. Keywords such as `print`, `if` and `while` are not, so those
+    /// statements start at their expression.
+    pub fn start(&self) -> Option<Span> {
+        match self {
+            Stmt::Block { statements } => statements.iter().find_map(Stmt::start),
+            Stmt::Expression(expr) | Stmt::Print(expr) => expr.start(),
+            Stmt::Let { token, .. } => Some(token.span),
+            Stmt::If { condition, .. } | Stmt::While { condition, .. } => condition.start(),
+            Stmt::For { initilizer, .. } => initilizer.start(),
+            Stmt::Function { name, .. } | Stmt::Class { name, .. } => Some(name.span),
+            Stmt::Return { keyword, .. } | Stmt::Throw { keyword, .. } => Some(keyword.span),
+            Stmt::Try { body, .. } => body.iter().find_map(Stmt::start),
+            Stmt::Comment { token, .. } => Some(token.span),
+        }
+    }
+}
+
+/// `Stmt::Expression` and `Stmt::Print` wrap their expression in an `expr`
+/// field, so its own `node` tag does not clash with the statement's.
+fn expression_field<S: Serializer>(expr: &Expr, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut state = serializer.serialize_struct("Stmt", 1)?;
+    state.serialize_field("expr", expr)?;
+    state.end()
 }
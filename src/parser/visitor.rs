@@ -0,0 +1,674 @@
+//! Traversal of the AST. Every `visit_*` method of a node with children
+//! defaults to the matching `walk_*` function, which visits those children,
+//! so a pass only overrides the nodes it is interested in and calls `walk_*`
+//! itself when it still wants to reach the nodes underneath. Leaves such as
+//! literals and comments have nothing to walk.
+
+use super::expr::*;
+use super::stmt::Stmt;
+use crate::lexer::Token;
+
+pub trait Visitor {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt]) {
+        walk_block(self, statements)
+    }
+
+    fn visit_expression_stmt(&mut self, expr: &Expr) {
+        walk_expression_stmt(self, expr)
+    }
+
+    fn visit_print(&mut self, expr: &Expr) {
+        walk_print(self, expr)
+    }
+
+    fn visit_let(&mut self, token: &Token, initilizer: &Expr) {
+        walk_let(self, token, initilizer)
+    }
+
+    fn visit_if(&mut self, condition: &Expr, branch: &Stmt) {
+        walk_if(self, condition, branch)
+    }
+
+    fn visit_while(&mut self, condition: &Expr, body: &Stmt) {
+        walk_while(self, condition, body)
+    }
+
+    fn visit_for(&mut self, initilizer: &Stmt, condition: &Expr, increment: &Expr, body: &Stmt) {
+        walk_for(self, initilizer, condition, increment, body)
+    }
+
+    fn visit_function(&mut self, name: &Token, params: &[Token], body: &[Stmt]) {
+        walk_function(self, name, params, body)
+    }
+
+    fn visit_return(&mut self, keyword: &Token, value: Option<&Expr>) {
+        walk_return(self, keyword, value)
+    }
+
+    fn visit_class(&mut self, name: &Token, super_class: Option<&Expr>, methods: &[Stmt]) {
+        walk_class(self, name, super_class, methods)
+    }
+
+    fn visit_throw(&mut self, keyword: &Token, value: &Expr) {
+        walk_throw(self, keyword, value)
+    }
+
+    fn visit_try(
+        &mut self,
+        body: &[Stmt],
+        catch_name: Option<&Token>,
+        catch_body: Option<&[Stmt]>,
+        finally_body: Option<&[Stmt]>,
+    ) {
+        walk_try(self, body, catch_name, catch_body, finally_body)
+    }
+
+    fn visit_comment(&mut self, _token: &Token, _trailing: bool) {}
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr)
+    }
+
+    fn visit_binary(&mut self, expr: &BinaryExpr) {
+        walk_binary(self, expr)
+    }
+
+    fn visit_unary(&mut self, expr: &UnaryExpr) {
+        walk_unary(self, expr)
+    }
+
+    fn visit_grouping(&mut self, expr: &Grouping) {
+        walk_grouping(self, expr)
+    }
+
+    fn visit_conditional(&mut self, expr: &ConditionalExpr) {
+        walk_conditional(self, expr)
+    }
+
+    fn visit_literal(&mut self, _literal: &Literal) {}
+
+    fn visit_variable(&mut self, _name: &Token) {}
+
+    fn visit_assign(&mut self, expr: &AssignExpr) {
+        walk_assign(self, expr)
+    }
+
+    fn visit_update(&mut self, expr: &UpdateExpr) {
+        walk_update(self, expr)
+    }
+
+    fn visit_call(&mut self, expr: &CallExpr) {
+        walk_call(self, expr)
+    }
+
+    fn visit_lambda(&mut self, expr: &LambdaExpr) {
+        walk_lambda(self, expr)
+    }
+
+    fn visit_get(&mut self, expr: &GetExpr) {
+        walk_get(self, expr)
+    }
+
+    fn visit_set(&mut self, expr: &SetExpr) {
+        walk_set(self, expr)
+    }
+
+    fn visit_this(&mut self, _expr: &ThisExpr) {}
+
+    fn visit_super(&mut self, _expr: &SuperExpr) {}
+}
+
+/// Calls the `visit_*` method for the kind of statement.
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match stmt {
+        Stmt::Block { statements } => visitor.visit_block(statements),
+        Stmt::Expression(expr) => visitor.visit_expression_stmt(expr),
+        Stmt::Print(expr) => visitor.visit_print(expr),
+        Stmt::Let {
+            token, initilizer, ..
+        } => visitor.visit_let(token, initilizer),
+        Stmt::If { condition, branch } => visitor.visit_if(condition, branch),
+        Stmt::While { condition, body } => visitor.visit_while(condition, body),
+        Stmt::For {
+            initilizer,
+            condition,
+            increment,
+            body,
+        } => visitor.visit_for(initilizer, condition, increment, body),
+        Stmt::Function {
+            name, params, body, ..
+        } => visitor.visit_function(name, params, body),
+        Stmt::Return { keyword, value } => visitor.visit_return(keyword, value.as_ref()),
+        Stmt::Class {
+            name,
+            super_class,
+            methods,
+        } => visitor.visit_class(name, super_class.as_ref(), methods),
+        Stmt::Throw { keyword, value } => visitor.visit_throw(keyword, value),
+        Stmt::Try {
+            body,
+            catch_name,
+            catch_body,
+            finally_body,
+        } => visitor.visit_try(
+            body,
+            catch_name.as_ref(),
+            catch_body.as_deref(),
+            finally_body.as_deref(),
+        ),
+        Stmt::Comment {
+            token, trailing, ..
+        } => visitor.visit_comment(token, *trailing),
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, statements: &[Stmt]) {
+    for stmt in statements {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_expression_stmt<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    visitor.visit_expr(expr)
+}
+
+pub fn walk_print<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    visitor.visit_expr(expr)
+}
+
+pub fn walk_let<V: Visitor + ?Sized>(visitor: &mut V, _token: &Token, initilizer: &Expr) {
+    visitor.visit_expr(initilizer)
+}
+
+pub fn walk_if<V: Visitor + ?Sized>(visitor: &mut V, condition: &Expr, branch: &Stmt) {
+    visitor.visit_expr(condition);
+    visitor.visit_stmt(branch);
+}
+
+pub fn walk_while<V: Visitor + ?Sized>(visitor: &mut V, condition: &Expr, body: &Stmt) {
+    visitor.visit_expr(condition);
+    visitor.visit_stmt(body);
+}
+
+pub fn walk_for<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    initilizer: &Stmt,
+    condition: &Expr,
+    increment: &Expr,
+    body: &Stmt,
+) {
+    visitor.visit_stmt(initilizer);
+    visitor.visit_expr(condition);
+    visitor.visit_expr(increment);
+    visitor.visit_stmt(body);
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    _name: &Token,
+    _params: &[Token],
+    body: &[Stmt],
+) {
+    visitor.visit_block(body)
+}
+
+pub fn walk_return<V: Visitor + ?Sized>(visitor: &mut V, _keyword: &Token, value: Option<&Expr>) {
+    if let Some(value) = value {
+        visitor.visit_expr(value);
+    }
+}
+
+pub fn walk_class<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    _name: &Token,
+    super_class: Option<&Expr>,
+    methods: &[Stmt],
+) {
+    if let Some(super_class) = super_class {
+        visitor.visit_expr(super_class);
+    }
+    visitor.visit_block(methods);
+}
+
+pub fn walk_throw<V: Visitor + ?Sized>(visitor: &mut V, _keyword: &Token, value: &Expr) {
+    visitor.visit_expr(value)
+}
+
+pub fn walk_try<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    body: &[Stmt],
+    _catch_name: Option<&Token>,
+    catch_body: Option<&[Stmt]>,
+    finally_body: Option<&[Stmt]>,
+) {
+    visitor.visit_block(body);
+    if let Some(catch_body) = catch_body {
+        visitor.visit_block(catch_body);
+    }
+    if let Some(finally_body) = finally_body {
+        visitor.visit_block(finally_body);
+    }
+}
+
+/// Calls the `visit_*` method for the kind of expression.
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::BinaryExpr(expr) => visitor.visit_binary(expr),
+        Expr::UnaryExpr(expr) => visitor.visit_unary(expr),
+        Expr::Grouping(expr) => visitor.visit_grouping(expr),
+        Expr::Conditional(expr) => visitor.visit_conditional(expr),
+        Expr::Literal(literal) => visitor.visit_literal(literal),
+        Expr::Variable(name) => visitor.visit_variable(name),
+        Expr::Assign(expr) => visitor.visit_assign(expr),
+        Expr::Update(expr) => visitor.visit_update(expr),
+        Expr::Call(expr) => visitor.visit_call(expr),
+        Expr::Lambda(expr) => visitor.visit_lambda(expr),
+        Expr::Get(expr) => visitor.visit_get(expr),
+        Expr::Set(expr) => visitor.visit_set(expr),
+        Expr::This(expr) => visitor.visit_this(expr),
+        Expr::Super(expr) => visitor.visit_super(expr),
+    }
+}
+
+pub fn walk_binary<V: Visitor + ?Sized>(visitor: &mut V, expr: &BinaryExpr) {
+    visitor.visit_expr(&expr.left);
+    visitor.visit_expr(&expr.right);
+}
+
+pub fn walk_unary<V: Visitor + ?Sized>(visitor: &mut V, expr: &UnaryExpr) {
+    visitor.visit_expr(&expr.right)
+}
+
+pub fn walk_grouping<V: Visitor + ?Sized>(visitor: &mut V, expr: &Grouping) {
+    visitor.visit_expr(&expr.expr)
+}
+
+pub fn walk_conditional<V: Visitor + ?Sized>(visitor: &mut V, expr: &ConditionalExpr) {
+    visitor.visit_expr(&expr.condition);
+    visitor.visit_expr(&expr.then_branch);
+    visitor.visit_expr(&expr.else_branch);
+}
+
+pub fn walk_assign<V: Visitor + ?Sized>(visitor: &mut V, expr: &AssignExpr) {
+    visitor.visit_expr(&expr.value)
+}
+
+pub fn walk_update<V: Visitor + ?Sized>(visitor: &mut V, expr: &UpdateExpr) {
+    visitor.visit_expr(&expr.target);
+    visitor.visit_expr(&expr.change);
+}
+
+pub fn walk_call<V: Visitor + ?Sized>(visitor: &mut V, expr: &CallExpr) {
+    visitor.visit_expr(&expr.callee);
+    for arg in &expr.args {
+        visitor.visit_expr(arg);
+    }
+}
+
+pub fn walk_lambda<V: Visitor + ?Sized>(visitor: &mut V, expr: &LambdaExpr) {
+    visitor.visit_block(&expr.body)
+}
+
+pub fn walk_get<V: Visitor + ?Sized>(visitor: &mut V, expr: &GetExpr) {
+    visitor.visit_expr(&expr.object)
+}
+
+pub fn walk_set<V: Visitor + ?Sized>(visitor: &mut V, expr: &SetExpr) {
+    visitor.visit_expr(&expr.object);
+    visitor.visit_expr(&expr.value);
+}
+
+/// Like `Visitor`, but with mutable access so a pass can rewrite the tree in
+/// place. Replacing a whole node is done from `visit_stmt_mut` or
+/// `visit_expr_mut`, which see the node itself rather than its contents.
+pub trait MutVisitor {
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt)
+    }
+
+    fn visit_block_mut(&mut self, statements: &mut Vec<Stmt>) {
+        walk_block_mut(self, statements)
+    }
+
+    fn visit_expression_stmt_mut(&mut self, expr: &mut Expr) {
+        walk_expression_stmt_mut(self, expr)
+    }
+
+    fn visit_print_mut(&mut self, expr: &mut Expr) {
+        walk_print_mut(self, expr)
+    }
+
+    fn visit_let_mut(&mut self, token: &mut Token, initilizer: &mut Expr) {
+        walk_let_mut(self, token, initilizer)
+    }
+
+    fn visit_if_mut(&mut self, condition: &mut Expr, branch: &mut Stmt) {
+        walk_if_mut(self, condition, branch)
+    }
+
+    fn visit_while_mut(&mut self, condition: &mut Expr, body: &mut Stmt) {
+        walk_while_mut(self, condition, body)
+    }
+
+    fn visit_for_mut(
+        &mut self,
+        initilizer: &mut Stmt,
+        condition: &mut Expr,
+        increment: &mut Expr,
+        body: &mut Stmt,
+    ) {
+        walk_for_mut(self, initilizer, condition, increment, body)
+    }
+
+    fn visit_function_mut(
+        &mut self,
+        name: &mut Token,
+        params: &mut Vec<Token>,
+        body: &mut Vec<Stmt>,
+    ) {
+        walk_function_mut(self, name, params, body)
+    }
+
+    fn visit_return_mut(&mut self, keyword: &mut Token, value: Option<&mut Expr>) {
+        walk_return_mut(self, keyword, value)
+    }
+
+    fn visit_class_mut(
+        &mut self,
+        name: &mut Token,
+        super_class: Option<&mut Expr>,
+        methods: &mut Vec<Stmt>,
+    ) {
+        walk_class_mut(self, name, super_class, methods)
+    }
+
+    fn visit_throw_mut(&mut self, keyword: &mut Token, value: &mut Expr) {
+        walk_throw_mut(self, keyword, value)
+    }
+
+    fn visit_try_mut(
+        &mut self,
+        body: &mut Vec<Stmt>,
+        catch_name: Option<&mut Token>,
+        catch_body: Option<&mut Vec<Stmt>>,
+        finally_body: Option<&mut Vec<Stmt>>,
+    ) {
+        walk_try_mut(self, body, catch_name, catch_body, finally_body)
+    }
+
+    fn visit_comment_mut(&mut self, _token: &mut Token, _trailing: &mut bool) {}
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+
+    fn visit_binary_mut(&mut self, expr: &mut BinaryExpr) {
+        walk_binary_mut(self, expr)
+    }
+
+    fn visit_unary_mut(&mut self, expr: &mut UnaryExpr) {
+        walk_unary_mut(self, expr)
+    }
+
+    fn visit_grouping_mut(&mut self, expr: &mut Grouping) {
+        walk_grouping_mut(self, expr)
+    }
+
+    fn visit_conditional_mut(&mut self, expr: &mut ConditionalExpr) {
+        walk_conditional_mut(self, expr)
+    }
+
+    fn visit_literal_mut(&mut self, _literal: &mut Literal) {}
+
+    fn visit_variable_mut(&mut self, _name: &mut Token) {}
+
+    fn visit_assign_mut(&mut self, expr: &mut AssignExpr) {
+        walk_assign_mut(self, expr)
+    }
+
+    fn visit_update_mut(&mut self, expr: &mut UpdateExpr) {
+        walk_update_mut(self, expr)
+    }
+
+    fn visit_call_mut(&mut self, expr: &mut CallExpr) {
+        walk_call_mut(self, expr)
+    }
+
+    fn visit_lambda_mut(&mut self, expr: &mut LambdaExpr) {
+        walk_lambda_mut(self, expr)
+    }
+
+    fn visit_get_mut(&mut self, expr: &mut GetExpr) {
+        walk_get_mut(self, expr)
+    }
+
+    fn visit_set_mut(&mut self, expr: &mut SetExpr) {
+        walk_set_mut(self, expr)
+    }
+
+    fn visit_this_mut(&mut self, _expr: &mut ThisExpr) {}
+
+    fn visit_super_mut(&mut self, _expr: &mut SuperExpr) {}
+}
+
+pub fn walk_stmt_mut<V: MutVisitor + ?Sized>(visitor: &mut V, stmt: &mut Stmt) {
+    match stmt {
+        Stmt::Block { statements } => visitor.visit_block_mut(statements),
+        Stmt::Expression(expr) => visitor.visit_expression_stmt_mut(expr),
+        Stmt::Print(expr) => visitor.visit_print_mut(expr),
+        Stmt::Let {
+            token, initilizer, ..
+        } => visitor.visit_let_mut(token, initilizer),
+        Stmt::If { condition, branch } => visitor.visit_if_mut(condition, branch),
+        Stmt::While { condition, body } => visitor.visit_while_mut(condition, body),
+        Stmt::For {
+            initilizer,
+            condition,
+            increment,
+            body,
+        } => visitor.visit_for_mut(initilizer, condition, increment, body),
+        Stmt::Function {
+            name, params, body, ..
+        } => visitor.visit_function_mut(name, params, body),
+        Stmt::Return { keyword, value } => visitor.visit_return_mut(keyword, value.as_mut()),
+        Stmt::Class {
+            name,
+            super_class,
+            methods,
+        } => visitor.visit_class_mut(name, super_class.as_mut(), methods),
+        Stmt::Throw { keyword, value } => visitor.visit_throw_mut(keyword, value),
+        Stmt::Try {
+            body,
+            catch_name,
+            catch_body,
+            finally_body,
+        } => visitor.visit_try_mut(
+            body,
+            catch_name.as_mut(),
+            catch_body.as_mut(),
+            finally_body.as_mut(),
+        ),
+        Stmt::Comment {
+            token, trailing, ..
+        } => visitor.visit_comment_mut(token, trailing),
+    }
+}
+
+pub fn walk_block_mut<V: MutVisitor + ?Sized>(visitor: &mut V, statements: &mut Vec<Stmt>) {
+    for stmt in statements {
+        visitor.visit_stmt_mut(stmt);
+    }
+}
+
+pub fn walk_expression_stmt_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    visitor.visit_expr_mut(expr)
+}
+
+pub fn walk_print_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    visitor.visit_expr_mut(expr)
+}
+
+pub fn walk_let_mut<V: MutVisitor + ?Sized>(
+    visitor: &mut V,
+    _token: &mut Token,
+    initilizer: &mut Expr,
+) {
+    visitor.visit_expr_mut(initilizer)
+}
+
+pub fn walk_if_mut<V: MutVisitor + ?Sized>(
+    visitor: &mut V,
+    condition: &mut Expr,
+    branch: &mut Stmt,
+) {
+    visitor.visit_expr_mut(condition);
+    visitor.visit_stmt_mut(branch);
+}
+
+pub fn walk_while_mut<V: MutVisitor + ?Sized>(
+    visitor: &mut V,
+    condition: &mut Expr,
+    body: &mut Stmt,
+) {
+    visitor.visit_expr_mut(condition);
+    visitor.visit_stmt_mut(body);
+}
+
+pub fn walk_for_mut<V: MutVisitor + ?Sized>(
+    visitor: &mut V,
+    initilizer: &mut Stmt,
+    condition: &mut Expr,
+    increment: &mut Expr,
+    body: &mut Stmt,
+) {
+    visitor.visit_stmt_mut(initilizer);
+    visitor.visit_expr_mut(condition);
+    visitor.visit_expr_mut(increment);
+    visitor.visit_stmt_mut(body);
+}
+
+pub fn walk_function_mut<V: MutVisitor + ?Sized>(
+    visitor: &mut V,
+    _name: &mut Token,
+    _params: &mut Vec<Token>,
+    body: &mut Vec<Stmt>,
+) {
+    visitor.visit_block_mut(body)
+}
+
+pub fn walk_return_mut<V: MutVisitor + ?Sized>(
+    visitor: &mut V,
+    _keyword: &mut Token,
+    value: Option<&mut Expr>,
+) {
+    if let Some(value) = value {
+        visitor.visit_expr_mut(value);
+    }
+}
+
+pub fn walk_class_mut<V: MutVisitor + ?Sized>(
+    visitor: &mut V,
+    _name: &mut Token,
+    super_class: Option<&mut Expr>,
+    methods: &mut Vec<Stmt>,
+) {
+    if let Some(super_class) = super_class {
+        visitor.visit_expr_mut(super_class);
+    }
+    visitor.visit_block_mut(methods);
+}
+
+pub fn walk_throw_mut<V: MutVisitor + ?Sized>(
+    visitor: &mut V,
+    _keyword: &mut Token,
+    value: &mut Expr,
+) {
+    visitor.visit_expr_mut(value)
+}
+
+pub fn walk_try_mut<V: MutVisitor + ?Sized>(
+    visitor: &mut V,
+    body: &mut Vec<Stmt>,
+    _catch_name: Option<&mut Token>,
+    catch_body: Option<&mut Vec<Stmt>>,
+    finally_body: Option<&mut Vec<Stmt>>,
+) {
+    visitor.visit_block_mut(body);
+    if let Some(catch_body) = catch_body {
+        visitor.visit_block_mut(catch_body);
+    }
+    if let Some(finally_body) = finally_body {
+        visitor.visit_block_mut(finally_body);
+    }
+}
+
+pub fn walk_expr_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    match expr {
+        Expr::BinaryExpr(expr) => visitor.visit_binary_mut(expr),
+        Expr::UnaryExpr(expr) => visitor.visit_unary_mut(expr),
+        Expr::Grouping(expr) => visitor.visit_grouping_mut(expr),
+        Expr::Conditional(expr) => visitor.visit_conditional_mut(expr),
+        Expr::Literal(literal) => visitor.visit_literal_mut(literal),
+        Expr::Variable(name) => visitor.visit_variable_mut(name),
+        Expr::Assign(expr) => visitor.visit_assign_mut(expr),
+        Expr::Update(expr) => visitor.visit_update_mut(expr),
+        Expr::Call(expr) => visitor.visit_call_mut(expr),
+        Expr::Lambda(expr) => visitor.visit_lambda_mut(expr),
+        Expr::Get(expr) => visitor.visit_get_mut(expr),
+        Expr::Set(expr) => visitor.visit_set_mut(expr),
+        Expr::This(expr) => visitor.visit_this_mut(expr),
+        Expr::Super(expr) => visitor.visit_super_mut(expr),
+    }
+}
+
+pub fn walk_binary_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut BinaryExpr) {
+    visitor.visit_expr_mut(&mut expr.left);
+    visitor.visit_expr_mut(&mut expr.right);
+}
+
+pub fn walk_unary_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut UnaryExpr) {
+    visitor.visit_expr_mut(&mut expr.right)
+}
+
+pub fn walk_grouping_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut Grouping) {
+    visitor.visit_expr_mut(&mut expr.expr)
+}
+
+pub fn walk_conditional_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut ConditionalExpr) {
+    visitor.visit_expr_mut(&mut expr.condition);
+    visitor.visit_expr_mut(&mut expr.then_branch);
+    visitor.visit_expr_mut(&mut expr.else_branch);
+}
+
+pub fn walk_assign_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut AssignExpr) {
+    visitor.visit_expr_mut(&mut expr.value)
+}
+
+pub fn walk_update_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut UpdateExpr) {
+    visitor.visit_expr_mut(&mut expr.target);
+    visitor.visit_expr_mut(&mut expr.change);
+}
+
+pub fn walk_call_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut CallExpr) {
+    visitor.visit_expr_mut(&mut expr.callee);
+    for arg in &mut expr.args {
+        visitor.visit_expr_mut(arg);
+    }
+}
+
+pub fn walk_lambda_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut LambdaExpr) {
+    visitor.visit_block_mut(&mut expr.body)
+}
+
+pub fn walk_get_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut GetExpr) {
+    visitor.visit_expr_mut(&mut expr.object)
+}
+
+pub fn walk_set_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut SetExpr) {
+    visitor.visit_expr_mut(&mut expr.object);
+    visitor.visit_expr_mut(&mut expr.value);
+}
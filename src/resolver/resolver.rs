@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+
+use crate::formatter::formatter::parameters;
+use crate::lexer::{Span, Token};
+use crate::parser::expr::*;
+use crate::parser::stmt::Stmt;
+use crate::parser::visitor::Visitor;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DeclarationKind {
+    Variable,
+    Parameter,
+    Function,
+    Class,
+    Method,
+}
+
+impl std::fmt::Display for DeclarationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            DeclarationKind::Variable => "variable",
+            DeclarationKind::Parameter => "parameter",
+            DeclarationKind::Function => "function",
+            DeclarationKind::Class => "class",
+            DeclarationKind::Method => "method",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub name: String,
+    pub kind: DeclarationKind,
+    /// Span of the name.
+    pub span: Span,
+    /// The declaration as written, e.g. `def add(a, b)` or `let x`.
+    pub detail: String,
+    /// Index of the class a method belongs to.
+    pub class: Option<usize>,
+    pub scope: usize,
+}
+
+/// A use of a name. `declaration` is `None` for names that are not declared
+/// anywhere in the file.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    pub span: Span,
+    pub declaration: Option<usize>,
+    /// The name is assigned to with `=`, not read.
+    pub assignment: bool,
+}
+
+/// A region of the source in which names can be declared. `end` is where the
+/// statement after the one that opened the scope starts, or `None` when the
+/// scope runs to the end of the file.
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub parent: Option<usize>,
+    pub start: Span,
+    pub end: Option<Span>,
+}
+
+impl Scope {
+    fn contains(&self, position: Span) -> bool {
+        before(self.start, position) && self.end.is_none_or(|end| before(position, end))
+    }
+}
+
+/// Every declaration, every use of a name and which declaration it refers
+/// to, for one program.
+#[derive(Debug, Default)]
+pub struct Resolution {
+    pub declarations: Vec<Declaration>,
+    pub references: Vec<Reference>,
+    pub scopes: Vec<Scope>,
+}
+
+impl Resolution {
+    /// The declaration of the name at `position`, whether that is a use of
+    /// the name or the declaration itself. Only `line` and `column` of
+    /// `position` are looked at.
+    pub fn declaration_at(&self, position: Span) -> Option<&Declaration> {
+        let declaration = self
+            .declarations
+            .iter()
+            .find(|declaration| covers(declaration.span, &declaration.name, position));
+        if declaration.is_some() {
+            return declaration;
+        }
+
+        self.references
+            .iter()
+            .find(|reference| covers(reference.span, &reference.name, position))
+            .and_then(|reference| reference.declaration)
+            .map(|index| &self.declarations[index])
+    }
+
+    /// Names that can be used at `position`, innermost first. Functions and
+    /// classes at the top level can be used anywhere, everything else only
+    /// after it is declared. Methods are left out as they are only reached
+    /// through an object.
+    pub fn visible_at(&self, position: Span) -> Vec<&Declaration> {
+        let mut visible: Vec<&Declaration> = vec![];
+
+        let mut scope = self
+            .scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, scope)| scope.contains(position))
+            .map(|(index, _)| index);
+
+        while let Some(index) = scope {
+            for declaration in self.declarations.iter().filter(|declaration| {
+                declaration.scope == index
+                    && declaration.kind != DeclarationKind::Method
+                    && (before(declaration.span, position)
+                        || (index == 0
+                            && matches!(
+                                declaration.kind,
+                                DeclarationKind::Function | DeclarationKind::Class
+                            )))
+            }) {
+                if !visible
+                    .iter()
+                    .any(|visible| visible.name == declaration.name)
+                {
+                    visible.push(declaration);
+                }
+            }
+            scope = self.scopes[index].parent;
+        }
+
+        visible
+    }
+}
+
+/// Builds a `Resolution` from the statements of a program, following the
+/// scoping rules of the language: blocks, function bodies, class bodies,
+/// `for` loops and `catch` clauses each open a scope, and a name refers to
+/// the innermost declaration of it that comes before. Names that are not
+/// found that way are looked up once more among the top level declarations,
+/// as those can be used before they are declared.
+#[derive(Default)]
+pub struct Resolver {
+    resolution: Resolution,
+    /// Scopes currently open, innermost last, with the names declared in them.
+    active: Vec<(usize, HashMap<String, usize>)>,
+    /// Start of the statement after the one being resolved.
+    limit: Option<Span>,
+    class: Option<usize>,
+}
+
+impl Resolver {
+    pub fn new() -> Resolver {
+        Resolver::default()
+    }
+
+    pub fn resolve(mut self, statements: &[Stmt]) -> Resolution {
+        self.begin_scope(Span {
+            line: 1,
+            column: 1,
+            offset: 0,
+        });
+        self.statements(statements);
+
+        let (_, globals) = self.active.pop().unwrap();
+        for reference in &mut self.resolution.references {
+            if reference.declaration.is_none() {
+                reference.declaration = globals.get(&reference.name).copied();
+            }
+        }
+
+        self.resolution
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) {
+        let limit = self.limit;
+
+        for (index, stmt) in statements.iter().enumerate() {
+            self.limit = statements[index + 1..].iter().find_map(Stmt::start).or(limit);
+            self.visit_stmt(stmt);
+        }
+
+        self.limit = limit;
+    }
+
+    fn begin_scope(&mut self, start: Span) {
+        self.resolution.scopes.push(Scope {
+            parent: self.active.last().map(|(index, _)| *index),
+            start,
+            end: self.limit,
+        });
+        self.active
+            .push((self.resolution.scopes.len() - 1, HashMap::new()));
+    }
+
+    fn end_scope(&mut self) {
+        self.active.pop();
+    }
+
+    fn declare(&mut self, name: &Token, kind: DeclarationKind, detail: String) -> usize {
+        let (scope, names) = self.active.last_mut().unwrap();
+        let name_text = name.kind.to_string();
+
+        self.resolution.declarations.push(Declaration {
+            name: name_text.clone(),
+            kind,
+            span: name.span,
+            detail,
+            class: match kind {
+                DeclarationKind::Method => self.class,
+                _ => None,
+            },
+            scope: *scope,
+        });
+
+        let index = self.resolution.declarations.len() - 1;
+        names.insert(name_text, index);
+        index
+    }
+
+    fn reference(&mut self, name: &Token, assignment: bool) {
+        let name_text = name.kind.to_string();
+        let declaration = self
+            .active
+            .iter()
+            .rev()
+            .find_map(|(_, names)| names.get(&name_text).copied());
+
+        self.resolution.references.push(Reference {
+            name: name_text,
+            span: name.span,
+            declaration,
+            assignment,
+        });
+    }
+
+    fn function(&mut self, start: Span, params: &[Token], body: &[Stmt]) {
+        let class = self.class.take();
+        self.begin_scope(start);
+
+        for param in params {
+            self.declare(param, DeclarationKind::Parameter, format!("{}", param.kind));
+        }
+        self.statements(body);
+
+        self.end_scope();
+        self.class = class;
+    }
+}
+
+impl Visitor for Resolver {
+    fn visit_block(&mut self, statements: &[Stmt]) {
+        if let Some(start) = statements.iter().find_map(Stmt::start) {
+            self.begin_scope(start);
+            self.statements(statements);
+            self.end_scope();
+        }
+    }
+
+    fn visit_let(&mut self, token: &Token, initilizer: &Expr) {
+        self.visit_expr(initilizer);
+        self.declare(
+            token,
+            DeclarationKind::Variable,
+            format!("let {}", token.kind),
+        );
+    }
+
+    fn visit_for(&mut self, initilizer: &Stmt, condition: &Expr, increment: &Expr, body: &Stmt) {
+        match initilizer.start() {
+            Some(start) => {
+                self.begin_scope(start);
+                self.visit_stmt(initilizer);
+                self.visit_expr(condition);
+                self.visit_expr(increment);
+                self.visit_stmt(body);
+                self.end_scope();
+            }
+            None => {
+                self.visit_expr(condition);
+                self.visit_expr(increment);
+                self.visit_stmt(body);
+            }
+        }
+    }
+
+    fn visit_function(&mut self, name: &Token, params: &[Token], body: &[Stmt]) {
+        let (kind, detail) = match self.class {
+            Some(_) => (DeclarationKind::Method, String::new()),
+            None => (DeclarationKind::Function, String::from("def ")),
+        };
+        let detail = format!("{}{}({})", detail, name.kind, parameters(params, &[]));
+        self.declare(name, kind, detail);
+
+        self.function(name.span, params, body);
+    }
+
+    fn visit_class(&mut self, name: &Token, super_class: Option<&Expr>, methods: &[Stmt]) {
+        let detail = match super_class {
+            Some(Expr::Variable(super_name)) => {
+                format!("class {} extends {}", name.kind, super_name.kind)
+            }
+            _ => format!("class {}", name.kind),
+        };
+        let class = self.declare(name, DeclarationKind::Class, detail);
+
+        if let Some(super_class) = super_class {
+            self.visit_expr(super_class);
+        }
+
+        self.begin_scope(name.span);
+        let outer = self.class.replace(class);
+        self.statements(methods);
+        self.class = outer;
+        self.end_scope();
+    }
+
+    fn visit_try(
+        &mut self,
+        body: &[Stmt],
+        catch_name: Option<&Token>,
+        catch_body: Option<&[Stmt]>,
+        finally_body: Option<&[Stmt]>,
+    ) {
+        self.visit_block(body);
+
+        if let (Some(name), Some(catch_body)) = (catch_name, catch_body) {
+            self.begin_scope(name.span);
+            self.declare(
+                name,
+                DeclarationKind::Variable,
+                format!("catch ({})", name.kind),
+            );
+            self.statements(catch_body);
+            self.end_scope();
+        }
+
+        if let Some(finally_body) = finally_body {
+            self.visit_block(finally_body);
+        }
+    }
+
+    fn visit_variable(&mut self, name: &Token) {
+        self.reference(name, false)
+    }
+
+    fn visit_assign(&mut self, expr: &AssignExpr) {
+        self.visit_expr(&expr.value);
+        self.reference(&expr.name, true);
+    }
+
+    fn visit_lambda(&mut self, expr: &LambdaExpr) {
+        self.function(expr.keyword.span, &expr.params, &expr.body);
+    }
+}
+
+/// Compares line and column only, as positions coming from editors do not
+/// know the byte offset.
+fn before(left: Span, right: Span) -> bool {
+    (left.line, left.column) <= (right.line, right.column)
+}
+
+fn covers(span: Span, name: &str, position: Span) -> bool {
+    span.line == position.line
+        && span.column <= position.column
+        && position.column <= span.column + name.chars().count()
+}
+
@@ -0,0 +1,82 @@
+mod common;
+
+use std::process::Command;
+
+use common::TempDir;
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::parser::parser::Parser;
+
+/// What the program printed when compiled to C, as lines.
+fn c(name: &str, source: &str) -> Vec<String> {
+    let out = common::stdout(|level| {
+        let dir = TempDir::new(&format!("backends-c-{}-{}", name, level));
+        let c_file = dir.path.join("program.c");
+        let binary = dir.path.join("program");
+        let code = mini_comp::backend::c::generate(&common::module(source, level));
+        std::fs::write(&c_file, code).unwrap();
+        common::compile(
+            Command::new("cc")
+                .args(["-std=c11", "-Wall", "-Wextra", "-Werror", "-o"])
+                .arg(&binary)
+                .arg(&c_file)
+                .arg("-lm"),
+        );
+        Command::new(&binary).output().unwrap()
+    });
+    out.lines().map(String::from).collect()
+}
+
+/// What the program printed when translated to JavaScript, as lines.
+fn js(name: &str, source: &str) -> Vec<String> {
+    let tokens = Lexer::new(source).get_tokens().unwrap();
+    let statements = Parser::new(tokens).parse_program();
+    let js = mini_comp::backend::js::generate(&statements, "test.comp", "test.js").unwrap();
+
+    let dir = TempDir::new(&format!("backends-js-{}", name));
+    let file = dir.path.join("program.js");
+    std::fs::write(&file, js.code).unwrap();
+    let output = Command::new("node").arg(&file).output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+/// Checks that every backend prints `expected` for `source`.
+fn assert_prints(name: &str, source: &str, expected: &[&str]) {
+    for level in [0, 2] {
+        assert_eq!(
+            common::wasm::run(source, level),
+            expected,
+            "wasm at {}",
+            level
+        );
+    }
+    assert_eq!(c(name, source), expected, "C");
+    assert_eq!(js(name, source), expected, "JavaScript");
+}
+
+#[test]
+#[cfg_attr(not(all(has_cc, has_node)), ignore = "needs a C compiler and node")]
+fn ints_in_float_slots_are_floats() {
+    assert_prints(
+        "floats",
+        "def half(x: float) -> float { return x / 2; }
+         def one() -> float { return 1; }
+         let g: float = 7;
+         def main() {
+             let y: float = 3;
+             y = 5;
+             print half(3);
+             print y / 2;
+             print one() / 2;
+             g = 1;
+             print g / 2;
+             print y;
+         }
+         main();",
+        &["1.5", "2.5", "0.5", "0.5", "5.0"],
+    );
+}
@@ -0,0 +1,229 @@
+mod common;
+
+use std::fs::File;
+use std::process::{Command, Output};
+
+use common::TempDir;
+use mini_comp::backend::c::generate;
+
+/// Compiles `source` with the system C compiler into the directory it
+/// returns, as `program`.
+fn build(name: &str, source: &str, level: u32) -> TempDir {
+    let dir = TempDir::new(&format!("c-{}-{}", name, level));
+    let c_file = dir.path.join("program.c");
+    let binary = dir.path.join("program");
+    std::fs::write(&c_file, generate(&common::module(source, level))).unwrap();
+
+    common::compile(
+        Command::new("cc")
+            .args(["-std=c11", "-Wall", "-Wextra", "-Werror", "-o"])
+            .arg(&binary)
+            .arg(&c_file)
+            .arg("-lm"),
+    );
+    dir
+}
+
+/// Compiles `source` with the system C compiler and runs it.
+fn run(name: &str, source: &str, level: u32) -> Output {
+    let dir = build(name, source, level);
+    Command::new(dir.path.join("program")).output().unwrap()
+}
+
+fn stdout(name: &str, source: &str) -> String {
+    common::stdout(|level| run(name, source, level))
+}
+
+#[test]
+#[cfg_attr(not(has_cc), ignore = "needs a C compiler")]
+fn values_print_like_literals() {
+    let out = stdout(
+        "values",
+        "print 1 + 2; print 7 / 2; print 1.5 * 2; print \"a\" + \"b\"; print nil; print !nil; print 2 ** 10; print 1 == 1.0;",
+    );
+    assert_eq!(out, "3\n3\n3.0\nab\nnil\ntrue\n1024\ntrue\n");
+}
+
+#[test]
+#[cfg_attr(not(has_cc), ignore = "needs a C compiler")]
+fn functions_and_loops() {
+    let out = stdout(
+        "functions",
+        "def fib(n) { if (n < 2) { return n; } return fib(n - 1) + fib(n - 2); }
+         let total = 0;
+         for (let i = 0; i < 10; i++) { total = total + fib(i); }
+         print total;
+         let twice = def (x) { return x * 2; };
+         print twice(21);
+         print fib;",
+    );
+    assert_eq!(out, "88\n42\n<fn fib>\n");
+}
+
+#[test]
+#[cfg_attr(not(has_cc), ignore = "needs a C compiler")]
+fn classes_with_inheritance() {
+    let out = stdout(
+        "classes",
+        "class Animal {
+             init(name) { this.name = name; }
+             speak() { return this.name + \" makes a sound\"; }
+         }
+         class Dog extends Animal {
+             speak() { return super.speak() + \" (woof)\"; }
+         }
+         let dog = Dog(\"Rex\");
+         print dog.speak();
+         let speak = dog.speak;
+         print speak();
+         dog.name = \"Max\";
+         print dog.name;
+         print dog;",
+    );
+    assert_eq!(
+        out,
+        "Rex makes a sound (woof)\nRex makes a sound (woof)\nMax\nDog instance\n"
+    );
+}
+
+#[test]
+#[cfg_attr(not(has_cc), ignore = "needs a C compiler")]
+fn runtime_errors_exit_with_70() {
+    let output = run("error", "print 1; print 1 / 0; print 2;", 0);
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Runtime error: Division by zero\n    at <main> (1:18)\n"
+    );
+}
+
+#[test]
+#[cfg_attr(not(has_cc), ignore = "needs a C compiler")]
+fn integer_powers_follow_the_ir() {
+    assert_eq!(
+        stdout("powers", "print 2 ** 62; print 2.0 ** -1; print 2 ** -1.0;"),
+        "4611686018427387904\n0.5\n0.5\n"
+    );
+
+    let output = run("exponent", "def f(x) { return 2 ** x; } print f(-1);", 2);
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Runtime error: Negative exponent in '**'\n    at f (1:21)\n    at <main> (1:39)\n"
+    );
+}
+
+const EXCEPTIONS: &str = "def check(x) { if (x < 0) throw \"negative\"; return x; }
+         def safe(x) {
+             try { return check(x); }
+             catch (e) { print \"caught \" + e; return 0; }
+             finally { print \"done\"; }
+         }
+         print safe(1);
+         print safe(-1);
+         try { print 1 / 0; } catch (e) { print e; }
+         try { try { throw 1; } finally { print \"inner\"; } } catch (e) { print e + 1; }";
+
+#[test]
+#[cfg_attr(not(has_cc), ignore = "needs a C compiler")]
+fn throws_unwind_to_the_nearest_catch() {
+    assert_eq!(
+        stdout("exceptions", EXCEPTIONS),
+        "done\n1\ncaught negative\ndone\n0\nDivision by zero\ninner\n2\n"
+    );
+}
+
+#[test]
+#[cfg_attr(not(has_cc), ignore = "needs a C compiler")]
+fn uncaught_throws_exit_with_70() {
+    let output = run(
+        "uncaught",
+        "def f() { throw 42; } print 1; try { f(); } finally { print 2; }",
+        0,
+    );
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n2\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Runtime error: 42\n    at f (1:11)\n    at <main> (1:40)\n"
+    );
+}
+
+#[test]
+#[cfg_attr(not(has_cc), ignore = "needs a C compiler")]
+fn output_comes_before_the_error() {
+    let dir = build("order", "print 1; print nil.x;", 0);
+    let log = dir.path.join("log");
+    let file = File::create(&log).unwrap();
+    let status = Command::new(dir.path.join("program"))
+        .stdout(file.try_clone().unwrap())
+        .stderr(file)
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(70));
+    assert_eq!(
+        std::fs::read_to_string(&log).unwrap(),
+        "1\nRuntime error: nil has no property 'x'\n    at <main> (1:20)\n"
+    );
+}
+
+#[test]
+fn build_reports_syntax_errors() {
+    let dir = TempDir::new("c-syntax");
+    let file = dir.path.join("invalid.comp");
+    std::fs::write(&file, "print 1;\nlet a = ;\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mini-comp"))
+        .args(["build", "--target", "c"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        format!(
+            "{}:2:9: Invalid Syntax at 2:9, No literal match\n",
+            file.display()
+        )
+    );
+    assert!(!file.with_extension("c").exists());
+}
+
+#[test]
+#[cfg_attr(not(has_cc), ignore = "needs a C compiler")]
+fn runtime_errors_trace_the_calls() {
+    let output = run(
+        "trace",
+        "class A {
+  m(x) { return 10 / x; }
+}
+def f(x) {
+  return A().m(x);
+}
+try { f(0); } finally { print 1; }",
+        2,
+    );
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Runtime error: Division by zero
+    at A.m (2:20)
+    at f (5:17)
+    at <main> (7:10)
+"
+    );
+
+    let output = run(
+        "deep",
+        "def down(n) { if (n == 0) return nil.x; return down(n - 1); } down(40);",
+        0,
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(lines.len(), 34, "{}", stderr);
+    assert_eq!(lines[1], "    at down (1:38)");
+    assert_eq!(lines[2], "    at down (1:58)");
+    assert_eq!(lines[33], "    ... 10 more");
+}
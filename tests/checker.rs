@@ -0,0 +1,138 @@
+mod common;
+
+use std::process::Command;
+
+use common::TempDir;
+use mini_comp::checker::checker::check;
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::parser::parser::Parser;
+
+/// The type errors in `source` as `line:column message` strings.
+fn errors(source: &str) -> Vec<String> {
+    let tokens = Lexer::new(source).get_tokens().unwrap();
+    check(&Parser::new(tokens).parse_program())
+        .into_iter()
+        .map(|error| format!("{} {}", error.span, error.message))
+        .collect()
+}
+
+#[test]
+fn annotations_have_to_match() {
+    assert_eq!(
+        errors("let a: int = \"x\";\nlet b: bool = nil;\nlet c: string = \"ok\";"),
+        [
+            "1:5 Expected int for 'a', found string",
+            "2:5 Expected bool for 'b', found nil"
+        ]
+    );
+    assert_eq!(
+        errors("def f(x: int) {}\nf(\"s\");\nf(2);"),
+        ["2:6 Expected int for argument 1 of 'f', found string"]
+    );
+}
+
+#[test]
+fn calls_need_as_many_arguments_as_parameters() {
+    assert_eq!(
+        errors("def f(x, y) {}\nf(1);\nf(1, 2);\nf(1, 2, 3);"),
+        [
+            "2:4 Expected 2 arguments for 'f', found 1",
+            "4:10 Expected 2 arguments for 'f', found 3"
+        ]
+    );
+}
+
+#[test]
+fn subclasses_inherit_init() {
+    assert_eq!(
+        errors(
+            "class P { init(x: int) { this.x = x; } }
+class Q extends P {}
+Q(1);
+Q(\"s\");
+Q(1, 2);"
+        ),
+        [
+            "4:6 Expected int for argument 1 of 'Q', found string",
+            "5:7 Expected 1 argument for 'Q', found 2"
+        ]
+    );
+}
+
+#[test]
+fn ints_widen_to_floats() {
+    assert_eq!(
+        errors(
+            "let w: float = 1;
+def half(x: float) -> float { return x / 2; }
+half(3);
+def whole() -> float { return 1; }"
+        ),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        errors("let i: int = 1.5;"),
+        ["1:5 Expected int for 'i', found float"]
+    );
+}
+
+#[test]
+fn return_types_are_inferred() {
+    assert_eq!(
+        errors(
+            "def g() { return 1; }
+let z: string = g();
+let n: int = g();
+def maybe(c) { if (c) return 1; return \"one\"; }
+let m: int = maybe(true);"
+        ),
+        ["2:5 Expected string for 'z', found int"]
+    );
+}
+
+#[test]
+fn type_names_have_to_exist() {
+    assert_eq!(
+        errors(
+            "class Point {}
+let p: Point = Point();
+let u: Foo = nil;
+def h(p: Bar) -> Baz { return p; }"
+        ),
+        [
+            "3:8 Unknown type 'Foo'",
+            "4:10 Unknown type 'Bar'",
+            "4:18 Unknown type 'Baz'"
+        ]
+    );
+}
+
+#[test]
+fn syntax_errors_are_reported_with_the_other_files() {
+    let dir = TempDir::new("check-syntax");
+    let invalid = dir.path.join("invalid.comp");
+    let mismatch = dir.path.join("mismatch.comp");
+    std::fs::write(&invalid, "let a = ;\n").unwrap();
+    std::fs::write(&mismatch, "let a: int = \"x\";\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mini-comp"))
+        .arg("check")
+        .args([&invalid, &mismatch])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        format!(
+            "{}:1:9: Invalid Syntax at 1:9, No literal match\n",
+            invalid.display()
+        )
+    );
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "{}:1:5: error: Expected int for 'a', found string\n",
+            mismatch.display()
+        )
+    );
+}
@@ -0,0 +1,65 @@
+//! Helpers shared by the tests of the backends.
+
+#![allow(dead_code)]
+
+pub mod wasm;
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use mini_comp::ir::ir::Module;
+use mini_comp::ir::lower::lower;
+use mini_comp::ir::passes::{optimize, Pass};
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::parser::parser::Parser;
+
+/// `source` lowered to the IR and optimized at `level`.
+pub fn module(source: &str, level: u32) -> Module {
+    let tokens = Lexer::new(source).get_tokens().unwrap();
+    let mut module = lower(&Parser::new(tokens).parse_program()).unwrap();
+    optimize(&mut module, &Pass::for_level(level), |_, _| {});
+    module
+}
+
+/// A directory of its own for one test, deleted when dropped.
+pub struct TempDir {
+    pub path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("mini-comp-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Runs `command`, which compiles a program, and fails with what it wrote to
+/// stderr if it did not succeed.
+pub fn compile(command: &mut Command) {
+    let output = command.output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// What the program printed when compiled with and without optimizations,
+/// which has to be the same.
+pub fn stdout(mut run: impl FnMut(u32) -> Output) -> String {
+    let mut outputs = vec![];
+    for level in [0, 2] {
+        let output = run(level);
+        assert!(output.status.success(), "{:?}", output);
+        outputs.push(String::from_utf8(output.stdout).unwrap());
+    }
+    assert_eq!(outputs[0], outputs[1], "optimized output differs");
+    outputs.pop().unwrap()
+}
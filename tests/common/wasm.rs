@@ -0,0 +1,101 @@
+//! Runs programs compiled to WebAssembly, for the tests of more than one
+//! backend.
+
+use mini_comp::backend::wasm::{assemble, generate};
+use wasmi::{Caller, Engine, Extern, Linker, Module, Store};
+
+/// The string at `offset` in the memory of the module.
+fn string(caller: &Caller<Vec<String>>, offset: i32) -> String {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .unwrap();
+    let data = memory.data(caller);
+    let offset = offset as usize;
+    let length = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    let bytes = &data[offset + 4..offset + 4 + length as usize];
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+/// Runs `main` of the compiled program and returns the lines it printed,
+/// ending with the runtime error it stopped on, if any.
+pub fn run(source: &str, level: u32) -> Vec<String> {
+    let wasm = assemble(&generate(&super::module(source, level)).unwrap()).unwrap();
+    let engine = Engine::default();
+    let module = Module::new(&engine, &wasm[..]).unwrap();
+    let mut store = Store::new(&engine, vec![]);
+
+    let mut linker = Linker::<Vec<String>>::new(&engine);
+    linker
+        .func_wrap(
+            "env",
+            "print_i64",
+            |mut caller: Caller<Vec<String>>, value: i64| caller.data_mut().push(value.to_string()),
+        )
+        .unwrap();
+    linker
+        .func_wrap(
+            "env",
+            "print_f64",
+            |mut caller: Caller<Vec<String>>, value: f64| {
+                caller.data_mut().push(format!("{:?}", value))
+            },
+        )
+        .unwrap();
+    linker
+        .func_wrap(
+            "env",
+            "print_bool",
+            |mut caller: Caller<Vec<String>>, value: i32| {
+                caller.data_mut().push((value != 0).to_string())
+            },
+        )
+        .unwrap();
+    linker
+        .func_wrap("env", "print_nil", |mut caller: Caller<Vec<String>>| {
+            caller.data_mut().push(String::from("nil"))
+        })
+        .unwrap();
+    linker
+        .func_wrap(
+            "env",
+            "print_str",
+            |mut caller: Caller<Vec<String>>, offset: i32| {
+                let text = string(&caller, offset);
+                caller.data_mut().push(text)
+            },
+        )
+        .unwrap();
+    linker
+        .func_wrap(
+            "env",
+            "error",
+            |mut caller: Caller<Vec<String>>, offset: i32| {
+                let text = format!("Runtime error: {}", string(&caller, offset));
+                caller.data_mut().push(text)
+            },
+        )
+        .unwrap();
+    linker
+        .func_wrap(
+            "env",
+            "pow",
+            |_: Caller<Vec<String>>, base: f64, exp: f64| base.powf(exp),
+        )
+        .unwrap();
+
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .unwrap()
+        .start(&mut store)
+        .unwrap();
+    let main = instance.get_typed_func::<(), i32>(&store, "main").unwrap();
+    let result = main.call(&mut store, ());
+    let lines = store.into_data();
+    // The module traps right after reporting an error.
+    let reported = lines
+        .last()
+        .is_some_and(|line| line.starts_with("Runtime error: "));
+    assert_eq!(result.is_err(), reported, "{:?} {:?}", result, lines);
+    lines
+}
@@ -0,0 +1,101 @@
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::parser::cst::SyntaxTree;
+
+#[test]
+fn cst_reproduces_every_example() {
+    for entry in std::fs::read_dir("examples").unwrap() {
+        let path = entry.unwrap().path();
+        assert_reproduces(&std::fs::read_to_string(&path).unwrap());
+    }
+}
+
+fn assert_reproduces(text: &str) {
+    let tokens = Lexer::new(text).get_cst_tokens().unwrap();
+    assert_eq!(SyntaxTree::parse(tokens).to_string(), text, "{:?}", text);
+}
+
+#[test]
+fn cst_keeps_comments_and_whitespace() {
+    for text in [
+        "",
+        "\n\n",
+        "// only a comment",
+        "/* only\n   a block comment */\n",
+        "print 1;",
+        "print 1;   \n\n\n",
+        "\tlet a\t=\t1 ;\r\nprint a;\r\n",
+        "let a = /* inside */ 1 + // end of line\n    2;\n",
+        "def f(a /* first */, // second\n      b) {\n  return a + b; }\n",
+        "class A { /* empty */ }\n// after the class",
+        "if (a) // head\n    print 1; // body\n",
+        "for (let i = 0; /* condition */ i < 3; i++) {\n\n}\n",
+        "print \"a // not a comment\"; /* a /* b */",
+    ] {
+        assert_reproduces(text);
+    }
+}
+
+/// Splices comments and runs of whitespace between the tokens of a few
+/// programs, with a fixed seed so failures can be reproduced.
+#[test]
+fn cst_keeps_generated_trivia() {
+    let programs = [
+        ["let", "a", "=", "1", "+", "2", "*", "3", ";"].as_slice(),
+        &[
+            "def", "f", "(", "x", ",", "y", ")", "{", "return", "x", "**", "y", ";", "}",
+        ],
+        &[
+            "class", "B", "extends", "A", "{", "m", "(", ")", "{", "super", ".", "m", "(", ")",
+            ";", "}", "}",
+        ],
+        &[
+            "for", "(", "let", "i", "=", "0", ";", "i", "<", "3", ";", "i", "+=", "1", ")",
+            "print", "i", ";",
+        ],
+    ];
+    let trivia = [
+        " ",
+        "\n",
+        "\t",
+        "  \n  ",
+        "/* c */",
+        "// c\n",
+        "\r\n",
+        "/* a\nb */ ",
+    ];
+
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = |bound: usize| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed as usize % bound
+    };
+
+    for _ in 0..200 {
+        let tokens = programs[next(programs.len())];
+        let mut text = String::new();
+        for token in tokens {
+            text += token;
+            text += " ";
+            for _ in 0..next(3) {
+                text += trivia[next(trivia.len())];
+            }
+        }
+        assert_reproduces(&text);
+    }
+}
+
+#[test]
+fn try_parse_returns_syntax_errors() {
+    let tokens = Lexer::new("print 1;\nprint (;").get_cst_tokens().unwrap();
+    let err = SyntaxTree::try_parse(tokens).unwrap_err();
+    assert_eq!(err.span.to_string(), "2:8");
+    assert_eq!(err.message, "Invalid Syntax at 2:8, No literal match");
+
+    let tokens = Lexer::new("print 1; // fine\n").get_cst_tokens().unwrap();
+    assert_eq!(
+        SyntaxTree::try_parse(tokens).unwrap().to_string(),
+        "print 1; // fine\n"
+    );
+}
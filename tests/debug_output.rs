@@ -0,0 +1,155 @@
+use std::process::{Command, Output};
+
+use mini_comp::graphviz::ast::ast_to_dot;
+use mini_comp::graphviz::cfg::cfg_to_dot;
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::parser::parser::Parser;
+use mini_comp::parser::sexpr::to_sexpr;
+use serde_json::{json, Value};
+
+/// Runs `mini-comp debug` with `args` on a file holding `source`.
+fn debug(name: &str, source: &str, args: &[&str]) -> Output {
+    let file = std::env::temp_dir().join(format!(
+        "mini-comp-debug-{}-{}.comp",
+        std::process::id(),
+        name
+    ));
+    std::fs::write(&file, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_mini-comp"))
+        .arg("debug")
+        .args(args)
+        .arg(&file)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&file).unwrap();
+    output
+}
+
+fn json(name: &str, source: &str, shows: &[&str]) -> Value {
+    let mut args = vec!["--format=json"];
+    args.extend(shows);
+    let output = debug(name, source, &args);
+    assert!(output.status.success(), "{:?}", output);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn json_has_tokens_and_trees() {
+    let document = json(
+        "json",
+        "print 1 + 2;",
+        &["--show=tokens", "--show=ast", "--show=optimized-ast"],
+    );
+    assert_eq!(document["version"], 1);
+
+    let tokens = document["tokens"].as_array().unwrap();
+    assert_eq!(tokens.len(), 6);
+    assert_eq!(
+        tokens[0]["kind"],
+        json!({"type": "Terminal", "value": "print"})
+    );
+    assert_eq!(
+        tokens[2]["span"],
+        json!({"line": 1, "column": 9, "offset": 8})
+    );
+    assert_eq!(tokens[5]["kind"], json!({"type": "EOF"}));
+
+    assert_eq!(document["ast"][0]["node"], "Print");
+    assert_eq!(document["ast"][0]["expr"]["node"], "BinaryExpr");
+    assert_eq!(
+        document["optimized-ast"],
+        json!([{
+            "node": "Print",
+            "expr": {"node": "Literal", "type": "Integer", "value": 3}
+        }])
+    );
+}
+
+#[test]
+fn json_leaves_out_steps_that_were_not_asked_for() {
+    let document = json("only_ast", "let a = 1;", &["--show=ast"]);
+    assert_eq!(
+        document.as_object().unwrap().keys().collect::<Vec<_>>(),
+        ["ast", "version"]
+    );
+}
+
+#[test]
+fn sexpr_matches_the_json_document() {
+    let source = "let a: int = 1; print -a;";
+    let output = debug("sexpr", source, &["--format=sexpr", "--show=ast"]);
+    assert!(output.status.success(), "{:?}", output);
+    let sexpr = String::from_utf8(output.stdout).unwrap();
+
+    assert_eq!(
+        sexpr.trim_end(),
+        to_sexpr(&json("sexpr_json", source, &["--show=ast"]))
+    );
+    assert!(sexpr.starts_with("(:ast ((Let "), "{}", sexpr);
+    assert!(sexpr.contains("(UnaryExpr :op "), "{}", sexpr);
+}
+
+#[test]
+fn sexpr_writes_tags_fields_and_nil() {
+    let value = json!({
+        "version": 1,
+        "items": [{"node": "Return", "value": null}, {"type": "Boolean", "value": true}],
+        "text": "a \"quoted\" string"
+    });
+    assert_eq!(
+        to_sexpr(&value),
+        "(:items ((Return :value nil) (Boolean :value true)) :text \"a \\\"quoted\\\" string\" :version 1)"
+    );
+}
+
+#[test]
+fn structured_formats_reject_other_steps() {
+    for format in ["--format=json", "--format=sexpr"] {
+        for show in [
+            "--show=cst",
+            "--show=ir",
+            "--show=ast-dot",
+            "--show=cfg-dot",
+        ] {
+            let output = debug("reject", "print 1;", &[format, "--show=ast", show]);
+            assert_eq!(output.status.code(), Some(1));
+            assert!(output.stdout.is_empty());
+            assert!(
+                String::from_utf8(output.stderr)
+                    .unwrap()
+                    .starts_with(&format!("'{}' cannot be written as", show)),
+                "{} {}",
+                format,
+                show
+            );
+        }
+    }
+
+    let output = debug("unknown", "print 1;", &["--format=yaml", "--show=ast"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Unknown format 'yaml'\n"
+    );
+}
+
+#[test]
+fn dot_graphs_are_printed_as_they_are() {
+    let source = "def f(x) { if (x) return 1; return 2; }";
+    let tokens = Lexer::new(source).get_tokens().unwrap();
+    let statements = Parser::new(tokens).parse_program();
+
+    let output = debug("ast_dot", source, &["--show=ast-dot"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        ast_to_dot(&statements)
+    );
+
+    let output = debug("cfg_dot", source, &["--show=cfg-dot"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        cfg_to_dot(&statements)
+    );
+}
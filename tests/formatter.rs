@@ -0,0 +1,145 @@
+mod common;
+
+use std::process::Command;
+
+use common::TempDir;
+use mini_comp::formatter::formatter::Formatter;
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::parser::parser::Parser;
+use serde_json::Value;
+
+fn format(source: &str) -> String {
+    let tokens = Lexer::with_comments(source).get_tokens().unwrap();
+    Formatter::new().format_program(&Parser::new(tokens).parse_program())
+}
+
+/// The tree of `source` as JSON, without locations.
+fn tree(source: &str) -> Value {
+    fn strip(value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                map.remove("span");
+                map.values_mut().for_each(strip);
+            }
+            Value::Array(values) => values.iter_mut().for_each(strip),
+            _ => (),
+        }
+    }
+
+    let tokens = Lexer::with_comments(source).get_tokens().unwrap();
+    let mut value = serde_json::to_value(Parser::new(tokens).parse_program()).unwrap();
+    strip(&mut value);
+    value
+}
+
+fn assert_round_trips(source: &str) -> String {
+    let formatted = format(source);
+    assert_eq!(tree(&formatted), tree(source), "{}", formatted);
+    assert_eq!(format(&formatted), formatted, "formatting is not stable");
+    formatted
+}
+
+#[test]
+fn examples_round_trip() {
+    for entry in std::fs::read_dir("examples").unwrap() {
+        let path = entry.unwrap().path();
+        assert_round_trips(&std::fs::read_to_string(&path).unwrap());
+    }
+}
+
+#[test]
+fn floats_keep_their_digits() {
+    let formatted = assert_round_trips(
+        "let big = 100000000000000000000.0; let small = 0.0000001;
+         let whole = 3.0; let half = 0.5; let large = 123456789012345678901234567890.5;",
+    );
+    assert_eq!(
+        formatted,
+        "let big = 100000000000000000000.0;
+let small = 0.0000001;
+let whole = 3.0;
+let half = 0.5;
+let large = 123456789012345680000000000000.0;
+"
+    );
+}
+
+#[test]
+fn comments_stay_in_place() {
+    let formatted = assert_round_trips(
+        "// top
+let a = 1; // after a
+
+/* block */
+if (a) // head
+    print 1;
+for (let i = 0; i < 2; i++) // loop
+    // above
+    if (i) // inner
+        print i; // after
+def f() {
+    // inside
+    return 1; // done
+}
+// end
+",
+    );
+    assert_eq!(
+        formatted,
+        "// top
+let a = 1; // after a
+
+/* block */
+if (a) // head
+    print 1;
+
+for (let i = 0; i < 2; i++) // loop
+    // above
+    if (i) // inner
+        print i; // after
+
+def f() {
+    // inside
+    return 1; // done
+}
+
+// end
+"
+    );
+}
+
+#[test]
+fn check_reports_every_file() {
+    let dir = TempDir::new("fmt-check");
+    let files = [
+        ("unformatted.comp", "print  1;\n"),
+        ("invalid.comp", "print 1 +;\n"),
+        ("formatted.comp", "print 2;\n"),
+        ("unformatted-too.comp", "let a=1;\n"),
+    ];
+    for (name, source) in files {
+        std::fs::write(dir.path.join(name), source).unwrap();
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mini-comp"))
+        .args(["fmt", "--check"])
+        .args(files.iter().map(|(name, _)| dir.path.join(name)))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "{}\n{}\n",
+            dir.path.join("unformatted.comp").display(),
+            dir.path.join("unformatted-too.comp").display()
+        )
+    );
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        format!(
+            "{}:1:10: Invalid Syntax at 1:10, No literal match\n",
+            dir.path.join("invalid.comp").display()
+        )
+    );
+}
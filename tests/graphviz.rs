@@ -0,0 +1,197 @@
+use mini_comp::graphviz::ast::ast_to_dot;
+use mini_comp::graphviz::cfg::cfg_to_dot;
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::parser::parser::Parser;
+use mini_comp::parser::stmt::Stmt;
+
+fn statements(source: &str) -> Vec<Stmt> {
+    let tokens = Lexer::new(source).get_tokens().unwrap();
+    Parser::new(tokens).parse_program()
+}
+
+/// The edges of the graph of function `index`, as `from -> to label`, with
+/// blocks named the way their labels start.
+fn edges(source: &str, index: usize) -> Vec<String> {
+    let prefix = format!("f{}_", index);
+    let block = |id: &str| match id.strip_prefix(&prefix).unwrap() {
+        "b0" => String::from("entry"),
+        "b1" => String::from("exit"),
+        id => id.replace('b', "B"),
+    };
+
+    cfg_to_dot(&statements(source))
+        .lines()
+        .filter_map(|line| {
+            let (from, rest) = line.trim().split_once(" -> ")?;
+            let (to, label) = match rest.split_once(" [label=\"") {
+                Some((to, label)) => (to, label.trim_end_matches("\"];")),
+                None => (rest.trim_end_matches(';'), ""),
+            };
+            if !from.starts_with(&prefix) {
+                return None;
+            }
+            Some(
+                format!("{} -> {} {}", block(from), block(to), label)
+                    .trim_end()
+                    .to_string(),
+            )
+        })
+        .collect()
+}
+
+/// The label of every node of the graph, in order.
+fn labels(dot: &str) -> Vec<String> {
+    dot.lines()
+        .filter(|line| !line.contains("->"))
+        .filter_map(|line| {
+            let (_, label) = line.split_once("[label=\"")?;
+            Some(label.trim_end_matches("\"];").to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn if_branches_and_joins() {
+    assert_eq!(
+        edges("if (a) print 1; print 2;", 0),
+        [
+            "entry -> B2 true",
+            "entry -> B3 false",
+            "B2 -> B3",
+            "B3 -> exit"
+        ]
+    );
+}
+
+#[test]
+fn while_loops_back_to_its_condition() {
+    assert_eq!(
+        edges("while (b) b = b - 1;", 0),
+        [
+            "entry -> B2",
+            "B2 -> B3 true",
+            "B2 -> B4 false",
+            "B3 -> B2 loop",
+            "B4 -> exit"
+        ]
+    );
+}
+
+#[test]
+fn for_runs_the_increment_before_looping() {
+    assert_eq!(
+        edges("for (let i = 0; i < 2; i++) print i;", 0),
+        [
+            "entry -> B2",
+            "B2 -> B3 true",
+            "B2 -> B4 false",
+            "B3 -> B5",
+            "B4 -> exit",
+            "B5 -> B2 loop"
+        ]
+    );
+}
+
+#[test]
+fn return_goes_to_the_exit() {
+    assert_eq!(
+        edges("def f(x) { if (x) return 1; return 2; }", 1),
+        [
+            "entry -> B2 true",
+            "entry -> B3 false",
+            "B2 -> exit return",
+            "B3 -> exit return"
+        ]
+    );
+}
+
+#[test]
+fn throw_goes_to_the_catch_or_the_exit() {
+    assert_eq!(
+        edges(
+            "try { if (a) throw 1; } catch (e) { print e; } finally { print 0; } throw 2;",
+            0
+        ),
+        [
+            "entry -> B2",
+            "B2 -> B5 catch",
+            "B2 -> B6 true",
+            "B2 -> B7 false",
+            "B3 -> exit throw",
+            "B4 -> B3",
+            "B5 -> B4",
+            "B6 -> B5 throw",
+            "B7 -> B4"
+        ]
+    );
+    // Without a catch, a throw goes through the finally block.
+    assert_eq!(
+        edges("try { throw 1; } finally { print 0; }", 0),
+        ["entry -> B2", "B2 -> B4 throw", "B3 -> exit", "B4 -> B3"]
+    );
+}
+
+#[test]
+fn functions_get_a_cluster_each() {
+    let dot = cfg_to_dot(&statements(
+        "def f() {} class A { m() {} } let g = def (x) { return x; };",
+    ));
+    let clusters: Vec<&str> = dot
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("label=\""))
+        .collect();
+    assert_eq!(
+        clusters,
+        ["<main>\";", "f\";", "A.m\";", "def (x) at 1:39\";"]
+    );
+}
+
+#[test]
+fn cfg_labels_are_escaped() {
+    // Strings hold backslashes and line breaks as they are written.
+    let dot = cfg_to_dot(&statements("print \"a\\b\nc\";"));
+    assert_eq!(
+        labels(&dot),
+        ["entry\\lprint \\\"a\\\\b\\lc\\\";\\l", "exit\\l"]
+    );
+}
+
+#[test]
+fn ast_nodes_hang_under_their_parent() {
+    let dot = ast_to_dot(&statements("if (a) print -1;"));
+    assert_eq!(
+        labels(&dot),
+        [
+            "Program",
+            "IfStmt",
+            "Variable a",
+            "PrintStmt",
+            "UnaryExpr -",
+            "Literal 1"
+        ]
+    );
+    let edges: Vec<&str> = dot
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.contains("->"))
+        .collect();
+    assert_eq!(
+        edges,
+        [
+            "n0 -> n1;",
+            "n1 -> n2;",
+            "n1 -> n3;",
+            "n3 -> n4;",
+            "n4 -> n5;"
+        ]
+    );
+}
+
+#[test]
+fn ast_labels_are_escaped() {
+    let dot = ast_to_dot(&statements("print \"a\\b\";"));
+    assert_eq!(
+        labels(&dot),
+        ["Program", "PrintStmt", "Literal \\\"a\\\\b\\\""]
+    );
+}
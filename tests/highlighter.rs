@@ -0,0 +1,89 @@
+use mini_comp::highlighter::highlighter::{segments, to_ansi, to_html, Category};
+use mini_comp::lexer::lexer::Lexer;
+
+fn highlight<T>(source: &str, write: impl Fn(&[(Option<Category>, &str)]) -> T) -> T {
+    let tokens = Lexer::with_comments(source).get_tokens().unwrap();
+    write(&segments(source, &tokens))
+}
+
+/// The pieces of `source` that are highlighted, with their category.
+fn categories(source: &str) -> Vec<(&'static str, String)> {
+    highlight(source, |segments| {
+        segments
+            .iter()
+            .filter_map(|(category, text)| Some((category.as_ref()?.class(), text.to_string())))
+            .collect()
+    })
+}
+
+#[test]
+fn tokens_fall_into_categories() {
+    let pieces = |pairs: &[(&'static str, &str)]| {
+        pairs
+            .iter()
+            .map(|(class, text)| (*class, text.to_string()))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        categories("let x = 1_000; // note\nprint x <= 2.5 ? \"a\" : !x;"),
+        pieces(&[
+            ("keyword", "let"),
+            ("identifier", "x"),
+            ("operator", "="),
+            ("numeric", "1_000"),
+            ("comment", "// note"),
+            ("keyword", "print"),
+            ("identifier", "x"),
+            ("operator", "<="),
+            ("numeric", "2.5"),
+            ("operator", "?"),
+            ("string", "\"a\""),
+            ("operator", ":"),
+            ("operator", "!"),
+            ("identifier", "x"),
+        ])
+    );
+}
+
+#[test]
+fn segments_join_back_into_the_source() {
+    let source = "def f(a) {\n    return a ** 2;   // square\n}\n\n";
+    let joined = highlight(source, |segments| {
+        segments.iter().map(|(_, text)| *text).collect::<String>()
+    });
+    assert_eq!(joined, source);
+}
+
+#[test]
+fn ansi_wraps_every_highlighted_piece() {
+    assert_eq!(
+        highlight("print a;", to_ansi),
+        "\x1b[35mprint\x1b[0m \x1b[34ma\x1b[0m;"
+    );
+}
+
+#[test]
+fn html_escapes_the_code_and_the_title() {
+    let html = highlight("print a < b & \"<i>\";", |segments| {
+        to_html(segments, "a<b>&c")
+    });
+    assert!(html.contains("<title>a&lt;b&gt;&amp;c</title>"), "{}", html);
+
+    let code = html
+        .split_once("<pre class=\"mini-comp\">")
+        .unwrap()
+        .1
+        .split_once("</pre>")
+        .unwrap()
+        .0;
+    assert_eq!(
+        code,
+        "<span class=\"keyword\">print</span> \
+         <span class=\"identifier\">a</span> \
+         <span class=\"operator\">&lt;</span> \
+         <span class=\"identifier\">b</span> \
+         <span class=\"operator\">&amp;</span> \
+         <span class=\"string\">&quot;&lt;i&gt;&quot;</span>;"
+    );
+}
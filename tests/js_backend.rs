@@ -0,0 +1,332 @@
+mod common;
+
+use std::process::{Command, Output};
+
+use common::TempDir;
+use mini_comp::backend::error::BackendError;
+use mini_comp::backend::js::{generate, JavaScript};
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::parser::parser::Parser;
+
+fn translate(source: &str) -> JavaScript {
+    let tokens = Lexer::new(source).get_tokens().unwrap();
+    generate(&Parser::new(tokens).parse_program(), "test.comp", "test.js").unwrap()
+}
+
+fn error(source: &str) -> BackendError {
+    let tokens = Lexer::new(source).get_tokens().unwrap();
+    match generate(&Parser::new(tokens).parse_program(), "test.comp", "test.js") {
+        Ok(js) => panic!("translated to {}", js.code),
+        Err(err) => err,
+    }
+}
+
+/// The translated program without the helpers written after it.
+fn program(js: &JavaScript) -> &str {
+    js.code.split("\nfunction $").next().unwrap()
+}
+
+/// Runs `script` with node.
+fn run(name: &str, script: &str) -> Output {
+    let dir = TempDir::new(&format!("js-{}", name));
+    let file = dir.path.join("program.js");
+    std::fs::write(&file, script).unwrap();
+    Command::new("node").arg(&file).output().unwrap()
+}
+
+/// Runs `script` with node and returns what it printed.
+fn node(name: &str, script: &str) -> String {
+    let output = run(name, script);
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+const CLASSES: &str = "class Animal {
+             init(name) { this.name = name; }
+             speak() { return this.name + \" makes a sound\"; }
+         }
+         class Dog extends Animal {
+             init(name) { super.init(name); this.tricks = 0; }
+             speak() { return super.speak() + \" (woof)\"; }
+         }
+         class Puppy extends Dog {
+             init() { this.name = \"pup\"; }
+         }
+         print Dog(\"Rex\").speak();
+         print Puppy().name;";
+
+#[test]
+fn classes_translate_to_es_classes() {
+    let js = translate(CLASSES);
+    assert_eq!(
+        program(&js),
+        "class Animal {
+  constructor(name) {
+    this.name = name;
+  }
+  speak() {
+    return $add(this.name, \" makes a sound\");
+  }
+}
+class Dog extends Animal {
+  constructor(name) {
+    super(name);
+    this.tricks = 0n;
+  }
+  speak() {
+    return $add(super.speak(), \" (woof)\");
+  }
+}
+class Puppy extends Dog {
+  constructor() {
+    super();
+    this.name = \"pup\";
+  }
+}
+console.log($show(new Dog(\"Rex\").speak()));
+console.log($show(new Puppy().name));
+"
+    );
+}
+
+#[test]
+#[cfg_attr(not(has_node), ignore = "needs node")]
+fn classes_run_in_node() {
+    assert_eq!(
+        node("classes", &translate(CLASSES).code),
+        "Rex makes a sound (woof)\npup\n"
+    );
+}
+
+#[test]
+#[cfg_attr(not(has_node), ignore = "needs node")]
+fn statements_and_expressions() {
+    let js = translate(
+        "def fib(n: int) -> int { if (n < 2) { return n; } return fib(n - 1) + fib(n - 2); }
+         for (let i = 0; i < 5; i++) print fib(i);
+         let new = 1 & 3 == 1;
+         print new;
+         print -2 ** 2;
+         let twice = def (x) { return x * 2; };
+         print twice(21);
+         let counter = 0;
+         counter += 5;
+         print counter > 3 ? \"big\" : \"small\";
+         try { throw \"oops\"; } catch (e) { print e; } finally { print nil; }",
+    );
+    assert_eq!(
+        node("statements", &js.code),
+        "0\n1\n1\n2\n3\ntrue\n-4\n42\nbig\noops\nnil\n"
+    );
+}
+
+#[test]
+fn reserved_words_and_operators() {
+    let js =
+        translate("let new = 1 & 3 == 1; print -2 ** 2; print -(1.5); print !(1 < 2) ? new : 0;");
+    assert_eq!(
+        program(&js),
+        "let new$ = $eq($bitand(1n, 3n), 1n);
+console.log($show($neg($pow(2n, 2n))));
+console.log($show(-1.5));
+console.log($show(!$lt(1n, 2n) ? new$ : 0n));
+"
+    );
+}
+
+#[test]
+#[cfg_attr(not(has_node), ignore = "needs node")]
+fn names_do_not_hide_the_globals_of_the_helpers() {
+    let source = "let Number = 1; let Math = 2; let String = \"s\"; let Object = nil;
+         def BigInt(x) { return x; }
+         let yield = 3;
+         print Number + 0.5; print 1.25 * Math; print 9223372036854775806 + 1 - yield;";
+    let js = translate(source);
+    assert!(program(&js).starts_with("let Number$ = 1n;\nlet Math$ = 2n;"));
+    assert_eq!(node("globals", &js.code), "1.5\n2.5\n9223372036854775804\n");
+
+    let js = translate("let process = 1; let console = 2; let Error = 3; throw process;");
+    let output = run("globals-throw", &js.code);
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Runtime error: 1\n"
+    );
+}
+
+const SOURCE_MAP: &str = "let a = 1;\n\ndef f(x) {\n    return x + a;\n}\nprint f(2);\n";
+
+#[test]
+fn source_map_describes_the_files() {
+    let js = translate(SOURCE_MAP);
+    let map: serde_json::Value = serde_json::from_str(&js.source_map).unwrap();
+    assert_eq!(map["version"], 3);
+    assert_eq!(map["sources"][0], "test.comp");
+    assert_eq!(map["file"], "test.js");
+
+    assert_eq!(
+        program(&js),
+        "let a = 1n;
+function f(x) {
+  return $add(x, a);
+}
+console.log($show(f(2n)));
+"
+    );
+    assert!(js.code.ends_with("}\n//# sourceMappingURL=test.js.map\n"));
+}
+
+#[test]
+#[cfg_attr(not(has_node), ignore = "needs node")]
+fn source_map_points_back_to_the_source() {
+    let js = translate(SOURCE_MAP);
+
+    // Where `$add` in `return $add(x, a);` and `f` in
+    // `console.log($show(f(2n)));` come from, as 0-based line and column
+    // pairs.
+    let script = format!(
+        "const {{ SourceMap }} = require('module');
+         const map = new SourceMap({});
+         for (const [line, column] of [[2, 9], [4, 18]]) {{
+             const entry = map.findEntry(line, column);
+             console.log(entry.originalLine + ':' + entry.originalColumn);
+         }}",
+        js.source_map
+    );
+    assert_eq!(node("source_map", &script), "3:13\n5:6\n");
+}
+
+#[test]
+#[cfg_attr(not(has_node), ignore = "needs node")]
+fn numbers_follow_the_ir() {
+    let js = translate(
+        "print -7 / 2; print -7 % 2; print 7.0 / 2; print 1.0; print 0.1 + 0.2;
+         print 1 << 62; print -8 >> 1; print ~5; print 2 ** 62 - 1 + 2 ** 62;
+         print 1 == 1.0; print 0 ? \"0 is true\" : \"0 is false\";",
+    );
+    assert_eq!(
+        node("numbers", &js.code),
+        "-3\n-1\n3.5\n1.0\n0.30000000000000004\n4611686018427387904\n-4\n-6\n\
+         9223372036854775807\ntrue\n0 is true\n"
+    );
+}
+
+#[test]
+#[cfg_attr(not(has_node), ignore = "needs node")]
+fn runtime_errors_exit_like_c() {
+    for (source, message) in [
+        ("print 1 / 0;", "Division by zero"),
+        ("print 2 ** -1;", "Negative exponent in '**'"),
+        ("print 9223372036854775807 + 1;", "Integer overflow in '+'"),
+        ("print 1 << 64;", "Integer overflow in '<<'"),
+    ] {
+        let output = run("errors", &translate(&format!("print 1; {}", source)).code);
+        assert_eq!(output.status.code(), Some(70), "{}", source);
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n");
+        assert_eq!(
+            String::from_utf8(output.stderr).unwrap(),
+            format!("Runtime error: {}\n", message)
+        );
+    }
+}
+
+#[test]
+#[cfg_attr(not(has_node), ignore = "needs node")]
+fn throws_unwind_to_the_nearest_catch() {
+    let out = node(
+        "exceptions",
+        &translate(
+            "def check(x) { if (x < 0) throw \"negative\"; return x; }
+             def safe(x) {
+                 try { return check(x); }
+                 catch (e) { print \"caught \" + e; return 0; }
+                 finally { print \"done\"; }
+             }
+             print safe(1);
+             print safe(-1);
+             try { print 1 / 0; } catch (e) { print e; }
+             try { try { throw 1; } finally { print \"inner\"; } } catch (e) { print e + 1; }",
+        )
+        .code,
+    );
+    assert_eq!(
+        out,
+        "done\n1\ncaught negative\ndone\n0\nDivision by zero\ninner\n2\n"
+    );
+
+    let output = run("uncaught", &translate("print 1; throw 42;").code);
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Runtime error: 42\n"
+    );
+}
+
+#[test]
+fn names_can_be_declared_again() {
+    let js = translate(
+        "let x = 1; let x = x + 1;
+         def f() { let y = x; { let y = y * 2; print y; } }
+         def f() { return x; }",
+    );
+    assert_eq!(
+        program(&js),
+        "let x = 1n;
+x = $add(x, 1n);
+function f() {
+  let y = x;
+  {
+    let y$1 = $mul(y, 2n);
+    console.log($show(y$1));
+  }
+}
+f = function () {
+  return x;
+};
+"
+    );
+}
+
+const UPDATES: &str = "class P {} let p = P(); p.n = 1;
+     def mk() { return p; }
+     print mk().n++; mk().n += 2; p.n++; print p.n;";
+
+#[test]
+fn updates_evaluate_their_object_once() {
+    let js = translate(UPDATES);
+    let code = program(&js);
+    assert!(code.contains(
+        "console.log($show((($object, $old = $object.n) => ($object.n = $add($old, 1n), $old))(mk())));\n"
+    ));
+    assert!(code.contains("(($object) => $object.n = $add($object.n, 2n))(mk());\n"));
+    assert!(code.contains("p.n = $add(p.n, 1n);\n"));
+}
+
+#[test]
+#[cfg_attr(not(has_node), ignore = "needs node")]
+fn updates_run_in_node() {
+    assert_eq!(node("updates", &translate(UPDATES).code), "1\n5\n");
+}
+
+#[test]
+fn super_init_has_to_come_first() {
+    let err = error(
+        "class A { init(x) { this.x = x; } }
+         class B extends A { init() { this.y = 1; super.init(2); } }",
+    );
+    assert_eq!(
+        err.message,
+        "'this' cannot come before 'super.init' for the js target"
+    );
+    assert_eq!(err.span.unwrap().to_string(), "2:39");
+
+    let err = error(
+        "class A { init(x) { this.x = x; } }
+         class B extends A { init() { if (true) super.init(2); } }",
+    );
+    assert_eq!(
+        err.message,
+        "'super.init' needs to be called once, as a statement of 'init', for the js target"
+    );
+}
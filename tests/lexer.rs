@@ -0,0 +1,76 @@
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::lexer::{LexerError, NumericHint, TokenType};
+use mini_comp::parser::expr::{Expr, Literal};
+use mini_comp::parser::parser::Parser;
+use mini_comp::parser::stmt::Stmt;
+
+fn numeric(source: &str) -> (String, NumericHint) {
+    let tokens = Lexer::new(source).get_tokens().unwrap();
+    match &tokens[0].kind {
+        TokenType::Numeric { raw, hint } => (raw.clone(), hint.clone()),
+        other => panic!("expected a number, found {:?}", other),
+    }
+}
+
+fn literal(source: &str) -> Literal {
+    let tokens = Lexer::new(&format!("print {};", source))
+        .get_tokens()
+        .unwrap();
+    let mut statements = Parser::new(tokens).parse_program();
+    match statements.remove(0) {
+        Stmt::Print(Expr::Literal(literal)) => literal,
+        other => panic!("expected a printed literal, found {:?}", other),
+    }
+}
+
+#[test]
+fn digit_separators_are_kept_in_the_token_and_dropped_from_the_value() {
+    assert_eq!(
+        numeric("1_000"),
+        (String::from("1_000"), NumericHint::Integer)
+    );
+    assert!(matches!(literal("1_000"), Literal::Integer(1000)));
+    assert!(matches!(literal("1_000.5"), Literal::FloatingPoint(value) if value == 1000.5));
+}
+
+#[test]
+fn integers_are_64_bit() {
+    assert!(matches!(
+        literal("3000000000"),
+        Literal::Integer(3000000000)
+    ));
+    assert!(matches!(
+        literal("9223372036854775807"),
+        Literal::Integer(i64::MAX)
+    ));
+}
+
+#[test]
+fn integers_out_of_range_are_lexer_errors() {
+    for source in [
+        "99999999999999999999",
+        "9223372036854775808",
+        "1_0000_0000_0000_0000_0000",
+    ] {
+        match Lexer::new(source).get_tokens() {
+            Err(LexerError::NumericLiteralOutOfRange { raw }) => assert_eq!(raw, source),
+            other => panic!(
+                "{}: expected an out of range error, found {:?}",
+                source, other
+            ),
+        }
+    }
+    // Floats never overflow at the lexer.
+    assert_eq!(
+        numeric("99999999999999999999.0").1,
+        NumericHint::FloatingPoint
+    );
+}
+
+#[test]
+fn separators_cannot_start_a_fraction() {
+    match Lexer::new("1._5").get_tokens() {
+        Err(LexerError::NumericLiteralInvalidChar { invalid, .. }) => assert_eq!(invalid, '_'),
+        other => panic!("expected an invalid character error, found {:?}", other),
+    }
+}
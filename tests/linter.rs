@@ -0,0 +1,238 @@
+mod common;
+
+use std::process::Command;
+
+use common::TempDir;
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::linter::config::Config;
+use mini_comp::linter::linter::{lint, Rule};
+use mini_comp::parser::cst::SyntaxTree;
+
+/// The lints for `source` as `line:column rule` strings.
+fn lints(source: &str, config: &Config) -> Vec<String> {
+    let tokens = Lexer::with_comments(source).get_tokens().unwrap();
+    let tree = SyntaxTree::parse(Lexer::new(source).get_cst_tokens().unwrap());
+    lint(&tree, &tokens, config)
+        .into_iter()
+        .map(|lint| format!("{} {}", lint.span, lint.rule))
+        .collect()
+}
+
+fn check(source: &str) -> Vec<String> {
+    lints(source, &Config::default())
+}
+
+#[test]
+fn unused_variables() {
+    assert_eq!(
+        check(
+            "def f(used, unused) {
+    let local = 1;
+    let assigned = 2;
+    assigned = used;
+    let read = 3;
+    read += 1;
+    return read;
+}
+let global = 1;"
+        ),
+        [
+            "1:13 unused-variable",
+            "2:9 unused-variable",
+            "3:9 unused-variable"
+        ]
+    );
+}
+
+#[test]
+fn unreachable_code_points_at_the_statement() {
+    assert_eq!(
+        check(
+            "def f() {
+    return 1;
+    print 2;
+    print 3;
+}
+def g() {
+    throw 1;
+    // a comment is not code
+    {
+        print 4;
+    }
+}
+def h() {
+    return 1;
+    if (true) print 5;
+}"
+        ),
+        [
+            "3:5 unreachable-code",
+            "9:5 unreachable-code",
+            "15:5 unreachable-code"
+        ]
+    );
+}
+
+#[test]
+fn self_assignment_and_comparison() {
+    assert_eq!(
+        check(
+            "let a = 1;
+a = a;
+print a == a;
+print a < (a);
+print a == 1;
+class P { m() { this.x = this.x; this.x = this.y; } }"
+        ),
+        [
+            "2:1 self-assignment",
+            "3:9 self-comparison",
+            "4:9 self-comparison",
+            "6:22 self-assignment"
+        ]
+    );
+}
+
+#[test]
+fn empty_blocks() {
+    assert_eq!(
+        check(
+            "if (true) {}
+for (let i = 0; i < 1; i++) {
+    // only a comment
+}
+def f() {}
+try {} catch (e) {} finally {
+    print 1;
+}
+try { print 2; } finally {}
+{ print 3; }"
+        ),
+        [
+            "1:11 empty-block",
+            "2:29 empty-block",
+            "6:5 empty-block",
+            "6:15 unused-variable",
+            "6:18 empty-block",
+            "9:26 empty-block"
+        ]
+    );
+}
+
+#[test]
+fn shadowed_names() {
+    assert_eq!(
+        check(
+            "let x = 1;
+def f(x) { return x; }
+def g() {
+    let y = 1;
+    { let y = 2; print y; }
+    return y;
+}
+class A { x() {} }"
+        ),
+        ["2:7 shadowed-name", "5:11 shadowed-name"]
+    );
+}
+
+#[test]
+fn infinite_loops() {
+    assert_eq!(
+        check(
+            "def f() {
+    while (true) { print 1; }
+    while ((true)) { return 1; }
+    while (false) {}
+}
+while (true) { def g() { return 1; } }"
+        ),
+        ["2:5 infinite-loop", "4:19 empty-block", "6:1 infinite-loop"]
+    );
+}
+
+#[test]
+fn allow_comments_silence_rules_nearby() {
+    assert_eq!(
+        check(
+            "let a = 1;
+a = a; // lint:allow(self-assignment)
+// lint:allow(self-comparison, empty-block)
+print a == a;
+print a == a;
+/* lint:allow(
+   self-assignment) */
+a = a;"
+        ),
+        ["5:9 self-comparison"]
+    );
+}
+
+#[test]
+fn config_turns_rules_off() {
+    let config = Config::parse(
+        "[rules]
+self-assignment = false
+empty-block = true
+",
+    )
+    .unwrap();
+    assert!(!config.enabled(Rule::SelfAssignment));
+    assert!(config.enabled(Rule::EmptyBlock));
+    assert!(config.enabled(Rule::ShadowedName));
+
+    assert_eq!(
+        lints("let a = 1; a = a; if (a) {}", &config),
+        ["1:26 empty-block"]
+    );
+    assert!(Config::parse("").unwrap().enabled(Rule::UnusedVariable));
+}
+
+#[test]
+fn config_errors() {
+    assert_eq!(
+        Config::parse("[rules]\nno-such-rule = false\n")
+            .unwrap_err()
+            .to_string(),
+        "Unknown lint rule \"no-such-rule\""
+    );
+    assert!(Config::parse("[rules]\nempty-block = \"no\"\n").is_err());
+    assert!(Config::parse("[rules\n").is_err());
+}
+
+#[test]
+fn rule_names_round_trip() {
+    for rule in Rule::ALL {
+        assert_eq!(Rule::from_name(rule.name()), Some(rule));
+    }
+    assert_eq!(Rule::from_name("unused"), None);
+}
+
+#[test]
+fn syntax_errors_are_reported_with_the_other_files() {
+    let dir = TempDir::new("lint-syntax");
+    let invalid = dir.path.join("invalid.comp");
+    let unused = dir.path.join("unused.comp");
+    std::fs::write(&invalid, "print 1 +;\n").unwrap();
+    std::fs::write(&unused, "def f(a) {}\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mini-comp"))
+        .arg("lint")
+        .args([&invalid, &unused])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        format!(
+            "{}:1:10: Invalid Syntax at 1:10, No literal match\n",
+            invalid.display()
+        )
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.starts_with(&format!("{}:1:7: unused-variable: ", unused.display())),
+        "{}",
+        stdout
+    );
+}
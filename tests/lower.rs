@@ -0,0 +1,254 @@
+use mini_comp::ir::ir::{Instr, Module};
+use mini_comp::ir::lower::lower;
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::parser::parser::Parser;
+
+fn module(source: &str) -> Module {
+    let tokens = Lexer::new(source).get_tokens().unwrap();
+    lower(&Parser::new(tokens).parse_program()).unwrap()
+}
+
+fn function(source: &str) -> String {
+    module(source).function("f").unwrap().to_string()
+}
+
+#[test]
+fn if_skips_the_branch() {
+    assert_eq!(
+        function("def f(a) { if (a) print 1; print 2; }"),
+        "def f(%a: any) -> nil {
+bb0:
+    %1: any = copy %a
+    branch %1, bb1, bb2
+bb1:
+    %2: int = const 1
+    print %2
+    jump bb2
+bb2:
+    %3: int = const 2
+    print %3
+    %4: nil = const nil
+    return %4
+}
+"
+    );
+}
+
+#[test]
+fn while_checks_before_each_iteration() {
+    assert_eq!(
+        function("def f(n) { while (n > 0) n = n - 1; return n; }"),
+        "def f(%n: any) -> any {
+bb0:
+    jump bb1
+bb1:
+    %1: any = copy %n
+    %2: int = const 0
+    %3: bool = gt %1, %2
+    branch %3, bb2, bb3
+bb2:
+    %4: any = copy %n
+    %5: int = const 1
+    %6: any = sub %4, %5
+    %n: any = copy %6
+    jump bb1
+bb3:
+    %7: any = copy %n
+    return %7
+}
+"
+    );
+}
+
+#[test]
+fn for_increments_after_the_body() {
+    assert_eq!(
+        function("def f(n) { let s = 0; for (let i = 0; i < n; i++) s += i; return s; }"),
+        "def f(%n: any) -> int {
+bb0:
+    %1: int = const 0
+    %s: int = copy %1
+    %3: int = const 0
+    %i: int = copy %3
+    jump bb1
+bb1:
+    %5: int = copy %i
+    %6: any = copy %n
+    %7: bool = lt %5, %6
+    branch %7, bb2, bb3
+bb2:
+    %8: int = copy %s
+    %9: int = copy %i
+    %10: int = add %8, %9
+    %s: int = copy %10
+    %11: int = copy %i
+    %12: int = const 1
+    %13: int = add %11, %12
+    %i: int = copy %13
+    jump bb1
+bb3:
+    %14: int = copy %s
+    return %14
+}
+"
+    );
+}
+
+#[test]
+fn conditional_joins_both_branches() {
+    assert_eq!(
+        function("def f(a) { return a ? 1 : 2.5; }"),
+        "def f(%a: any) -> any {
+bb0:
+    %1: any = copy %a
+    branch %1, bb1, bb2
+bb1:
+    %3: int = const 1
+    %2: any = copy %3
+    jump bb3
+bb2:
+    %4: float = const 2.5
+    %2: any = copy %4
+    jump bb3
+bb3:
+    return %2
+}
+"
+    );
+}
+
+#[test]
+fn updates_give_the_old_or_new_value() {
+    assert_eq!(
+        function("def f(o) { let i = 1; print i++; print --i; o.x += 2; return o.x; }"),
+        "def f(%o: any) -> any {
+bb0:
+    %1: int = const 1
+    %i: int = copy %1
+    %3: int = copy %i
+    %4: int = const 1
+    %5: int = add %3, %4
+    %i: int = copy %5
+    print %3
+    %6: int = copy %i
+    %7: int = const 1
+    %8: int = sub %6, %7
+    %i: int = copy %8
+    print %8
+    %9: any = copy %o
+    %10: any = get %9.x
+    %11: int = const 2
+    %12: any = add %10, %11
+    set %9.x, %12
+    %13: any = copy %o
+    %14: any = get %13.x
+    return %14
+}
+"
+    );
+}
+
+#[test]
+fn functions_without_return_give_nil() {
+    assert_eq!(
+        function("def f() { }"),
+        "def f() -> nil {
+bb0:
+    %0: nil = const nil
+    return %0
+}
+"
+    );
+}
+
+#[test]
+fn powers_of_integers_are_integers() {
+    assert_eq!(
+        function("def f(a: int, b: int) { return a ** b; }"),
+        "def f(%a: int, %b: int) -> int {
+bb0:
+    %2: int = copy %a
+    %3: int = copy %b
+    %4: int = pow %2, %3
+    return %4
+}
+"
+    );
+}
+
+#[test]
+fn instructions_that_can_fail_keep_their_span() {
+    let module = module("let a = 1;\nprint a +\n  f(a);\ndef f(x) { return x.y; }");
+    let spans = |name: &str| {
+        module
+            .function(name)
+            .unwrap()
+            .blocks
+            .iter()
+            .flat_map(|block| &block.instrs)
+            .filter_map(|instr| Some((instr_name(instr), instr.span()?.to_string())))
+            .collect::<Vec<(&str, String)>>()
+    };
+
+    assert_eq!(
+        spans("<main>"),
+        [
+            ("load_global", String::from("2:7")),
+            ("load_global", String::from("3:5")),
+            ("call", String::from("3:6")),
+            ("add", String::from("2:9")),
+        ]
+    );
+    assert_eq!(spans("f"), [("get", String::from("4:21"))]);
+}
+
+#[test]
+fn finally_runs_on_every_way_out_of_a_try() {
+    assert_eq!(
+        function("def f(a) { try { return g(a); } catch (e) { print e; } finally { print 0; } }"),
+        "def f(%a: any) -> any {
+bb0:
+    try bb3, bb4
+bb1:
+    %11: nil = const nil
+    return %11
+bb2:
+    %10: int = const 0
+    print %10
+    jump bb1
+bb3:
+    %1: any = load_global g
+    %2: any = copy %a
+    %3: any = call_indirect %1(%2)
+    end_try
+    %4: int = const 0
+    print %4
+    return %3
+bb4:
+    %5: any = caught
+    try bb5, bb6
+bb5:
+    %e: any = copy %5
+    %7: any = copy %e
+    print %7
+    end_try
+    jump bb2
+bb6:
+    %8: any = caught
+    %9: int = const 0
+    print %9
+    rethrow %8
+}
+"
+    );
+}
+
+fn instr_name(instr: &Instr) -> &'static str {
+    match instr {
+        Instr::LoadGlobal { .. } => "load_global",
+        Instr::Call { .. } => "call",
+        Instr::Binary { .. } => "add",
+        Instr::GetProperty { .. } => "get",
+        _ => "other",
+    }
+}
@@ -0,0 +1,192 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+use serde_json::{json, Value};
+
+const URI: &str = "file:///test.comp";
+
+/// A language server talking to the test over its stdin and stdout.
+struct Client {
+    server: Child,
+    stdout: BufReader<ChildStdout>,
+    id: i64,
+}
+
+impl Client {
+    fn start() -> Client {
+        let mut server = Command::new(env!("CARGO_BIN_EXE_mini-comp-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdout = BufReader::new(server.stdout.take().unwrap());
+        let mut client = Client {
+            server,
+            stdout,
+            id: 0,
+        };
+
+        let capabilities = client.request("initialize", json!({"capabilities": {}}));
+        assert_eq!(capabilities["capabilities"]["hoverProvider"], true);
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        let stdin = self.server.stdin.as_mut().unwrap();
+        write!(stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            self.stdout.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length: ") {
+                length = value.parse().unwrap();
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({"jsonrpc": "2.0", "method": method, "params": params}));
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.id += 1;
+        self.send(json!({"jsonrpc": "2.0", "id": self.id, "method": method, "params": params}));
+        let response = self.receive();
+        assert_eq!(response["id"], self.id, "{}", response);
+        response["result"].clone()
+    }
+
+    /// Opens the document and returns the diagnostics published for it.
+    fn open(&mut self, text: &str) -> Value {
+        self.notify(
+            "textDocument/didOpen",
+            json!({"textDocument": {"uri": URI, "languageId": "comp", "version": 1, "text": text}}),
+        );
+        let notification = self.receive();
+        assert_eq!(notification["method"], "textDocument/publishDiagnostics");
+        notification["params"]["diagnostics"].clone()
+    }
+
+    fn at(&mut self, method: &str, line: u32, character: u32) -> Value {
+        self.request(
+            method,
+            json!({"textDocument": {"uri": URI}, "position": {"line": line, "character": character}}),
+        )
+    }
+
+    /// Shuts the server down and returns what it wrote to stderr.
+    fn stop(mut self) -> String {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+        let output = self.server.wait_with_output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stderr).unwrap()
+    }
+}
+
+fn range(line: u32, start: u32, end: u32) -> Value {
+    json!({"start": {"line": line, "character": start}, "end": {"line": line, "character": end}})
+}
+
+#[test]
+fn syntax_errors_become_diagnostics_without_output() {
+    let mut client = Client::start();
+
+    let diagnostics = client.open("let a = ;\n");
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(
+        diagnostics[0]["range"]["start"],
+        json!({"line": 0, "character": 8})
+    );
+
+    let diagnostics = client.open("print missing;\n");
+    assert_eq!(diagnostics[0]["severity"], 2);
+    assert_eq!(diagnostics[0]["message"], "'missing' is not declared");
+    assert_eq!(diagnostics[0]["range"], range(0, 6, 13));
+
+    // The panic the parser raises for the syntax error is not printed.
+    assert_eq!(client.stop(), "");
+}
+
+#[test]
+fn navigation_uses_the_resolved_names() {
+    let mut client = Client::start();
+    let diagnostics = client.open(
+        "def add(a: int, b: int) -> int { return a + b; }
+class Point {
+    init(x) { this.x = x; }
+    norm() { return this.x; }
+}
+let total = add(1, 2);
+print total;
+",
+    );
+    assert_eq!(diagnostics, json!([]));
+
+    let definition = client.at("textDocument/definition", 6, 7);
+    assert_eq!(definition["uri"], URI);
+    assert_eq!(definition["range"], range(5, 4, 9));
+
+    let hover = client.at("textDocument/hover", 5, 13);
+    assert_eq!(
+        hover["contents"]["value"],
+        "```\ndef add(a, b)\n```\nfunction"
+    );
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({"textDocument": {"uri": URI}}),
+    );
+    let names = |symbols: &Value| {
+        symbols
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|symbol| symbol["name"].as_str().unwrap().to_string())
+            .collect::<Vec<String>>()
+    };
+    assert_eq!(names(&symbols), ["add", "Point"]);
+    assert_eq!(names(&symbols[1]["children"]), ["init", "norm"]);
+
+    let completion = client.request(
+        "textDocument/completion",
+        json!({"textDocument": {"uri": URI}, "position": {"line": 0, "character": 41}}),
+    );
+    let labels = completion
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect::<Vec<&str>>();
+    assert!(labels.contains(&"return"));
+    assert!(labels.contains(&"a"));
+    assert!(!labels.contains(&"total"));
+
+    assert_eq!(client.stop(), "");
+}
+
+#[test]
+fn unknown_requests_are_answered_with_an_error() {
+    let mut client = Client::start();
+    client.send(json!({"jsonrpc": "2.0", "id": 99, "method": "textDocument/rename", "params": {}}));
+    let response = client.receive();
+    assert_eq!(response["id"], 99);
+    assert_eq!(response["error"]["code"], -32601);
+    client.stop();
+}
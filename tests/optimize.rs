@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+
+use mini_comp::formatter::formatter::Formatter;
+use mini_comp::ir::ir::{Instr, Module};
+use mini_comp::ir::lower::lower;
+use mini_comp::ir::passes::{optimize, Pass};
+use mini_comp::ir::ssa::to_ssa;
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::optimizer::fold::fold_constants;
+use mini_comp::parser::parser::Parser;
+
+fn module(source: &str) -> Module {
+    let tokens = Lexer::new(source).get_tokens().unwrap();
+    lower(&Parser::new(tokens).parse_program()).unwrap()
+}
+
+fn optimized(source: &str, passes: &[Pass]) -> String {
+    let mut module = module(source);
+    optimize(&mut module, passes, |_, _| {});
+    module.function("f").unwrap().to_string()
+}
+
+#[test]
+fn ssa_writes_every_register_once() {
+    for entry in std::fs::read_dir("examples").unwrap() {
+        let path = entry.unwrap().path();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let tokens = Lexer::new(&text).get_tokens().unwrap();
+        let mut module = match lower(&Parser::new(tokens).parse_program()) {
+            Ok(module) => module,
+            Err(_) => continue,
+        };
+
+        for function in &mut module.functions {
+            to_ssa(function);
+            let mut written = HashSet::new();
+            for block in &function.blocks {
+                for dest in block.instrs.iter().filter_map(Instr::dest) {
+                    assert!(written.insert(dest), "{}: {}", path.display(), function);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn loops_get_phis() {
+    let mut module =
+        module("def f(n) { let s = 0; for (let i = 0; i < n; i++) { s = s + i; } return s; }");
+    optimize(&mut module, &[Pass::CopyPropagation], |name, module| {
+        if name == "ssa" {
+            let text = module.function("f").unwrap().to_string();
+            assert_eq!(text.matches(" = phi ").count(), 2, "{}", text);
+        }
+    });
+}
+
+#[test]
+fn dumps_follow_the_passes() {
+    let mut module = module("def f() { return 1 + 2; }");
+    let mut names = vec![];
+    optimize(&mut module, &Pass::for_level(1), |name, _| {
+        names.push(name.to_string())
+    });
+    assert_eq!(names, ["ssa", "copy-prop", "dce"]);
+}
+
+#[test]
+fn level_zero_changes_nothing() {
+    let source = "def f(a) { let x = a; return x; }";
+    let mut module = module(source);
+    optimize(&mut module, &Pass::for_level(0), |_, _| {
+        panic!("no passes to dump")
+    });
+    assert_eq!(module, self::module(source));
+}
+
+#[test]
+fn constant_propagation_folds_branches() {
+    let text = optimized(
+        "def f() { let x = 2; let y = x * 3; if (y > 5) { return y; } return 0; }",
+        &Pass::for_level(2),
+    );
+    assert!(text.contains("const 6"), "{}", text);
+    assert!(!text.contains("branch"), "{}", text);
+    assert!(!text.contains("const 0"), "{}", text);
+}
+
+#[test]
+fn constant_propagation_sees_through_loops() {
+    let text = optimized(
+        "def f(n) { let x = 1; for (let i = 0; i < n; i++) { x = 1; } return x + 1; }",
+        &[Pass::ConstantPropagation, Pass::DeadCode],
+    );
+    assert!(text.contains("const 2"), "{}", text);
+    assert!(!text.contains("add %x"), "{}", text);
+}
+
+#[test]
+fn common_subexpressions_are_computed_once() {
+    let text = optimized(
+        "def f(a, b) { let x = a * b; let y = a * b; return x + y; }",
+        &Pass::for_level(2),
+    );
+    assert_eq!(text.matches(" = mul ").count(), 1, "{}", text);
+}
+
+#[test]
+fn dead_code_keeps_effects() {
+    let text = optimized(
+        "def g() { return 1; } def f(a: float) { let unused = a * 2.0; g(); return nil; }",
+        &Pass::for_level(1),
+    );
+    assert!(!text.contains("mul"), "{}", text);
+    assert!(text.contains("call g()"), "{}", text);
+}
+
+#[test]
+fn integer_overflow_is_not_dead() {
+    let text = optimized(
+        "def f(a: int) { let unused = a * 2; return nil; }",
+        &Pass::for_level(1),
+    );
+    assert!(text.contains("mul"), "{}", text);
+}
+
+#[test]
+fn swapped_variables_are_copied_in_parallel() {
+    let text = optimized(
+        "def f(n) { let a = 1; let b = 2; for (let i = 0; i < n; i++) { let t = a; a = b; b = t; } return a - b; }",
+        &Pass::for_level(2),
+    );
+    // Both values are saved in temporaries before either variable of the
+    // loop is written.
+    let lines = text.lines().collect::<Vec<&str>>();
+    let body = &lines[lines.iter().position(|line| *line == "bb2:").unwrap()..];
+    let first_write = body
+        .iter()
+        .position(|line| line.starts_with("    %a") || line.starts_with("    %b"))
+        .unwrap();
+    let saved = &body[..first_write];
+    assert!(
+        saved.iter().any(|line| line.ends_with("copy %a")),
+        "{}",
+        text
+    );
+    assert!(
+        saved.iter().any(|line| line.ends_with("copy %b")),
+        "{}",
+        text
+    );
+    assert!(text.contains("sub %a, %b"), "{}", text);
+}
+
+/// `source` after constant folding, formatted.
+fn folded(source: &str) -> String {
+    let tokens = Lexer::new(source).get_tokens().unwrap();
+    let mut statements = Parser::new(tokens).parse_program();
+    fold_constants(&mut statements);
+    Formatter::new().format_program(&statements)
+}
+
+#[test]
+fn folding_keeps_the_parentheses_of_negative_numbers() {
+    assert_eq!(
+        folded("let x = 2; print (-2) ** x; print (0 - 2.5) ** x; print (2) ** x;"),
+        "let x = 2;\nprint (-2) ** x;\nprint (-2.5) ** x;\nprint 2 ** x;\n"
+    );
+}
+
+#[test]
+fn identities_need_the_type_of_the_other_operand() {
+    assert_eq!(
+        folded("let i = 1; print i + 0; print 0 + i; print i * 1; print i / 1; print i - 0;"),
+        "let i = 1;\nprint i;\nprint i;\nprint i;\nprint i;\nprint i;\n"
+    );
+
+    // Mixing an int with a float literal makes a float, `-0.0 + 0.0` is `0.0`,
+    // and a float can hold an int that `* 1.0` would turn into a float.
+    for source in [
+        "let i = 1; print i + 0.0;",
+        "let i = 1; print i * 1.0;",
+        "let f = 1.5; print f + 0.0;",
+        "let f = 1.5; print f * 1.0; print f - 0.0; print f / 1.0;",
+        "let c = true; let y = c ? 1 : 1.5; print y * 1.0; print y - 0.0; print y / 1.0;",
+        "def g(x) { return x * 1; }",
+    ] {
+        assert_eq!(folded(source), format(source), "{}", source);
+    }
+
+    // With a type error nothing is known, so the error still happens at runtime.
+    let source = "let s = \"s\"; print s - 0;";
+    assert_eq!(folded(source), format(source));
+}
+
+fn format(source: &str) -> String {
+    let tokens = Lexer::new(source).get_tokens().unwrap();
+    Formatter::new().format_program(&Parser::new(tokens).parse_program())
+}
@@ -0,0 +1,295 @@
+use mini_comp::lexer::lexer::Lexer;
+use mini_comp::parser::expr::{Expr, Literal, UpdateKind};
+use mini_comp::parser::parser::Parser;
+use mini_comp::parser::stmt::Stmt;
+
+/// Parses `print <source>;` and writes the expression with every operator
+/// in parentheses, so `1 + 2 * 3` comes out as `(+ 1 (* 2 3))`.
+fn expr(source: &str) -> String {
+    let tokens = Lexer::new(&format!("print {};", source))
+        .get_tokens()
+        .unwrap();
+    match Parser::new(tokens).parse_program().remove(0) {
+        Stmt::Print(expr) => tree(&expr),
+        other => panic!("expected a print statement, found {:?}", other),
+    }
+}
+
+fn tree(expr: &Expr) -> String {
+    match expr {
+        Expr::BinaryExpr(expr) => format!(
+            "({} {} {})",
+            expr.op.kind,
+            tree(&expr.left),
+            tree(&expr.right)
+        ),
+        Expr::UnaryExpr(expr) => format!("({} {})", expr.op.kind, tree(&expr.right)),
+        Expr::Grouping(expr) => format!("(group {})", tree(&expr.expr)),
+        Expr::Conditional(expr) => format!(
+            "(? {} {} {})",
+            tree(&expr.condition),
+            tree(&expr.then_branch),
+            tree(&expr.else_branch)
+        ),
+        Expr::Literal(Literal::Integer(value)) => value.to_string(),
+        Expr::Literal(Literal::FloatingPoint(value)) => format!("{:?}", value),
+        Expr::Literal(Literal::String(value)) => format!("{:?}", value),
+        Expr::Literal(Literal::Boolean(value)) => value.to_string(),
+        Expr::Literal(Literal::Terminal(_)) => String::from("terminal"),
+        Expr::Variable(name) => name.kind.to_string(),
+        Expr::Assign(expr) => format!("(= {} {})", expr.name.kind, tree(&expr.value)),
+        Expr::Update(expr) => {
+            let kind = match expr.kind {
+                UpdateKind::Prefix => "prefix",
+                UpdateKind::Postfix => "postfix",
+                UpdateKind::Compound => "compound",
+            };
+            format!(
+                "({} {} {} {})",
+                kind,
+                expr.op.kind,
+                tree(&expr.target),
+                tree(&expr.change)
+            )
+        }
+        Expr::Call(expr) => {
+            let mut text = format!("(call {}", tree(&expr.callee));
+            for arg in &expr.args {
+                text += &format!(" {}", tree(arg));
+            }
+            text + ")"
+        }
+        Expr::Lambda(expr) => {
+            let params: Vec<String> = expr.params.iter().map(|p| p.kind.to_string()).collect();
+            format!("(def ({}) {})", params.join(" "), expr.body.len())
+        }
+        Expr::Get(expr) => format!("(. {} {})", tree(&expr.object), expr.name.kind),
+        Expr::Set(expr) => format!(
+            "(= (. {} {}) {})",
+            tree(&expr.object),
+            expr.name.kind,
+            tree(&expr.value)
+        ),
+        Expr::This(_) => String::from("this"),
+        Expr::Super(expr) => format!("(super {})", expr.method.kind),
+    }
+}
+
+#[test]
+fn power_is_right_associative() {
+    assert_eq!(expr("2 ** 3 ** 2"), "(** 2 (** 3 2))");
+    assert_eq!(expr("(2 ** 3) ** 2"), "(** (group (** 2 3)) 2)");
+    assert_eq!(expr("2 * 3 ** 2"), "(* 2 (** 3 2))");
+}
+
+#[test]
+fn power_binds_tighter_than_unary_minus() {
+    assert_eq!(expr("-2 ** 2"), "(- (** 2 2))");
+    assert_eq!(expr("(-2) ** 2"), "(** (group (- 2)) 2)");
+    assert_eq!(expr("2 ** -1"), "(** 2 (- 1))");
+}
+
+#[test]
+fn bitwise_operators_sit_between_comparison_and_arithmetic() {
+    assert_eq!(expr("1 + 2 << 3"), "(<< (+ 1 2) 3)");
+    assert_eq!(expr("a & b << 1"), "(& a (<< b 1))");
+    assert_eq!(expr("a | b ^ c & d"), "(| a (^ b (& c d)))");
+    assert_eq!(expr("a | b == c"), "(== (| a b) c)");
+    assert_eq!(expr("a & 1 < 2"), "(< (& a 1) 2)");
+    assert_eq!(expr("a >> 1 >> 2"), "(>> (>> a 1) 2)");
+}
+
+#[test]
+fn compound_assignments_cover_every_arithmetic_operator() {
+    assert_eq!(expr("x += 1"), "(compound + x 1)");
+    assert_eq!(expr("x -= 1"), "(compound - x 1)");
+    assert_eq!(expr("x *= 2"), "(compound * x 2)");
+    assert_eq!(expr("x /= 2"), "(compound / x 2)");
+    assert_eq!(expr("x %= 2"), "(compound % x 2)");
+    assert_eq!(expr("x *= y += 2"), "(compound * x (compound + y 2))");
+}
+
+#[test]
+fn conditionals_nest_to_the_right() {
+    assert_eq!(expr("a ? b : c ? d : e"), "(? a b (? c d e))");
+    assert_eq!(expr("a ? b ? c : d : e"), "(? a (? b c d) e)");
+    assert_eq!(expr("(a ? b : c) ? d : e"), "(? (group (? a b c)) d e)");
+}
+
+#[test]
+fn conditionals_bind_tighter_than_assignment() {
+    assert_eq!(expr("x = a ? 1 : 2"), "(= x (? a 1 2))");
+    assert_eq!(expr("x += a ? 1 : 2"), "(compound + x (? a 1 2))");
+    assert_eq!(expr("a ? x = 1 : 2"), "(? a (= x 1) 2)");
+    assert_eq!(expr("a == b ? 1 + 2 : 3"), "(? (== a b) (+ 1 2) 3)");
+    assert_eq!(expr("a < b | c ? 1 : 2"), "(? (< a (| b c)) 1 2)");
+}
+
+fn statements(source: &str) -> Vec<Stmt> {
+    let tokens = Lexer::new(source).get_tokens().unwrap();
+    Parser::new(tokens).parse_program()
+}
+
+#[test]
+fn def_with_a_name_declares_a_function() {
+    match &statements("def add(a, b) { return a + b; }")[..] {
+        [Stmt::Function { name, params, .. }] => {
+            assert_eq!(name.kind.to_string(), "add");
+            assert_eq!(params.len(), 2);
+        }
+        other => panic!("expected a function declaration, found {:?}", other),
+    }
+}
+
+#[test]
+fn def_followed_by_a_paren_is_a_lambda() {
+    assert_eq!(expr("def (x) { return x; }"), "(def (x) 1)");
+    assert_eq!(expr("def () {}"), "(def () 0)");
+    assert_eq!(expr("def (x) { return x; }(3)"), "(call (def (x) 1) 3)");
+    assert_eq!(
+        expr("apply(def (a, b) { print a; return b; }, 1)"),
+        "(call apply (def (a b) 2) 1)"
+    );
+
+    match &statements("def (x) { print x; };")[..] {
+        [Stmt::Expression(Expr::Lambda(_))] => {}
+        other => panic!("expected a lambda statement, found {:?}", other),
+    }
+    match &statements("let f = def (x: int) -> int { return x; };")[..] {
+        [Stmt::Let {
+            initilizer: Expr::Lambda(lambda),
+            ..
+        }] => {
+            assert_eq!(lambda.param_types.len(), 1);
+            assert!(lambda.param_types[0].is_some());
+            assert!(lambda.return_type.is_some());
+        }
+        other => panic!("expected a lambda initializer, found {:?}", other),
+    }
+}
+
+fn syntax_error(source: &str) -> String {
+    let tokens = Lexer::new(source).get_tokens().unwrap();
+    match Parser::new(tokens).try_parse_program() {
+        Ok(statements) => panic!("expected a syntax error, parsed {:?}", statements),
+        Err(err) => err.message,
+    }
+}
+
+#[test]
+fn updates_know_whether_they_are_prefix_or_postfix() {
+    assert_eq!(expr("++x"), "(prefix + x 1)");
+    assert_eq!(expr("x++"), "(postfix + x 1)");
+    assert_eq!(expr("--x"), "(prefix - x 1)");
+    assert_eq!(expr("x--"), "(postfix - x 1)");
+    assert_eq!(expr("-x++"), "(- (postfix + x 1))");
+}
+
+#[test]
+fn updates_target_variables_and_properties() {
+    assert_eq!(expr("this.count++"), "(postfix + (. this count) 1)");
+    assert_eq!(expr("--this.count"), "(prefix - (. this count) 1)");
+    assert_eq!(expr("obj.total += 5"), "(compound + (. obj total) 5)");
+    assert_eq!(expr("a.b.c -= 1"), "(compound - (. (. a b) c) 1)");
+    assert_eq!(expr("make().n++"), "(postfix + (. (call make) n) 1)");
+}
+
+#[test]
+fn updates_reject_other_targets() {
+    for source in ["print 1++;", "print ++f();", "print (a)--;"] {
+        let message = syntax_error(source);
+        assert!(
+            message.ends_with("Expected variable or property before '++' or '--'"),
+            "{}: {}",
+            source,
+            message
+        );
+    }
+    assert_eq!(
+        syntax_error("1 += 2;"),
+        "Invalid syntax at 1:3, Expected variable or property before '+='"
+    );
+    assert_eq!(
+        syntax_error("let a = 1;\nf() *= a;"),
+        "Invalid syntax at 2:5, Expected variable or property before '*='"
+    );
+}
+
+#[test]
+fn try_takes_a_catch_a_finally_or_both() {
+    for (source, catch, finally) in [
+        ("try { f(); } catch (e) { print e; }", Some("e"), false),
+        ("try { f(); } finally { g(); }", None, true),
+        (
+            "try { f(); } catch (err) {} finally { g(); }",
+            Some("err"),
+            true,
+        ),
+    ] {
+        match &statements(source)[..] {
+            [Stmt::Try {
+                body,
+                catch_name,
+                catch_body,
+                finally_body,
+            }] => {
+                assert_eq!(body.len(), 1, "{}", source);
+                assert_eq!(
+                    catch_name.as_ref().map(|name| name.kind.to_string()),
+                    catch.map(String::from),
+                    "{}",
+                    source
+                );
+                assert_eq!(catch_body.is_some(), catch.is_some(), "{}", source);
+                assert_eq!(finally_body.is_some(), finally, "{}", source);
+            }
+            other => panic!("{}: expected a try statement, found {:?}", source, other),
+        }
+    }
+}
+
+#[test]
+fn throw_takes_any_expression() {
+    match &statements("throw \"bad \" + 1;")[..] {
+        [Stmt::Throw { value, .. }] => assert_eq!(tree(value), "(+ \"bad \" 1)"),
+        other => panic!("expected a throw statement, found {:?}", other),
+    }
+}
+
+#[test]
+fn try_needs_a_catch_or_a_finally() {
+    assert_eq!(
+        syntax_error("try { f(); }\nprint 1;"),
+        "Invalid syntax at 2:1, Expected catch or finally after try block"
+    );
+}
+
+/// Where `Expr::span` puts `source`, printed after `print ` so it starts
+/// at column 7.
+fn span(source: &str) -> Option<String> {
+    let tokens = Lexer::new(&format!("print {};", source))
+        .get_tokens()
+        .unwrap();
+    match Parser::new(tokens).parse_program().remove(0) {
+        Stmt::Print(expr) => expr.span().map(|span| span.to_string()),
+        other => panic!("expected a print statement, found {:?}", other),
+    }
+}
+
+#[test]
+fn spans_point_at_the_token_that_identifies_an_expression() {
+    assert_eq!(span("a + b"), Some(String::from("1:9")));
+    assert_eq!(span("-a"), Some(String::from("1:7")));
+    assert_eq!(span("(a * b)"), Some(String::from("1:10")));
+    assert_eq!(span("a ? b : c"), Some(String::from("1:7")));
+    assert_eq!(span("x = 1"), Some(String::from("1:7")));
+    assert_eq!(span("x++"), Some(String::from("1:8")));
+    assert_eq!(span("f(a, b)"), Some(String::from("1:13")));
+    assert_eq!(span("def () {}"), Some(String::from("1:7")));
+    assert_eq!(span("a.b.c"), Some(String::from("1:11")));
+    assert_eq!(span("a.b = 1"), Some(String::from("1:9")));
+    assert_eq!(span("this"), Some(String::from("1:7")));
+    assert_eq!(span("super.m"), Some(String::from("1:7")));
+    assert_eq!(span("1.5"), None);
+    assert_eq!(span("\"a\""), None);
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use common::wasm::run;
+use mini_comp::backend::wasm::generate;
+
+fn text(source: &str, level: u32) -> Result<String, String> {
+    generate(&common::module(source, level)).map_err(|err| err.to_string())
+}
+
+fn assert_prints(source: &str, expected: &[&str]) {
+    for level in [0, 2] {
+        assert_eq!(run(source, level), expected, "at --opt-level={}", level);
+    }
+}
+
+#[test]
+fn arithmetic() {
+    assert_prints(
+        "print 1 + 2 * 3; print 7 / 2; print 7 % 3; print 2 ** 10; print 1.5 * 2; print 7.5 % 2; print -(3); print ~5; print 6 & 3;",
+        &["7", "3", "1", "1024", "3.0", "1.5", "-3", "-6", "2"],
+    );
+}
+
+#[test]
+fn comparisons_and_printing() {
+    assert_prints(
+        "print 1 < 2.5; print 3 == 3; print nil == nil; print true != false; print \"hello\"; print nil;",
+        &["true", "true", "true", "true", "hello", "nil"],
+    );
+}
+
+#[test]
+fn functions_and_loops() {
+    assert_prints(
+        "def fib(n: int) -> int {
+             if (n < 2) { return n; }
+             return fib(n - 1) + fib(n - 2);
+         }
+         let total = 0;
+         for (let i = 0; i < 10; i++) { total = total + fib(i); }
+         print total;
+         def half(x: float) { return x / 2; }
+         print half(5);",
+        &["88", "2.5"],
+    );
+}
+
+#[test]
+fn integer_errors_are_reported() {
+    for (source, error) in [
+        ("9223372036854775807 + 1", "Integer overflow in '+'"),
+        ("-9223372036854775807 - 2", "Integer overflow in '-'"),
+        ("4611686018427387904 * 2", "Integer overflow in '*'"),
+        ("-1 * (-9223372036854775807 - 1)", "Integer overflow in '*'"),
+        ("-(-9223372036854775807 - 1)", "Integer overflow in '-'"),
+        ("1 / 0", "Division by zero"),
+        ("1 % 0", "Division by zero"),
+        ("(-9223372036854775807 - 1) / -1", "Integer overflow in '/'"),
+        ("2 ** 63", "Integer overflow in '**'"),
+        ("2 ** -1", "Negative exponent in '**'"),
+        ("1 << 64", "Integer overflow in '<<'"),
+        ("1 << -1", "Integer overflow in '<<'"),
+        ("-8 >> 64", "Integer overflow in '>>'"),
+    ] {
+        // Through a parameter, so that the optimizer leaves it alone.
+        let program = format!(
+            "def f(x: int) -> int {{ return x + 0 * ({}); }} print 1; print f(1);",
+            source
+        );
+        assert_prints(&program, &["1", &format!("Runtime error: {}", error)]);
+    }
+
+    assert_prints(
+        "print 9223372036854775806 + 1; print -3 * 3037000499; print 2 ** 62; print (-2) ** 63; print 1 ** 9223372036854775807; print 1 << 63; print -8 >> 63; print -7 % 2;",
+        &[
+            "9223372036854775807",
+            "-9111001497",
+            "4611686018427387904",
+            "-9223372036854775808",
+            "1",
+            "-9223372036854775808",
+            "-1",
+            "-1",
+        ],
+    );
+}
+
+#[test]
+fn globals_are_undefined_until_assigned() {
+    assert_prints(
+        "def f() -> int { return later; } print 1; print f(); let later = 2;",
+        &["1", "Runtime error: Undefined variable 'later'"],
+    );
+    assert_prints(
+        "let early = 1; def f() -> int { return early; } print f();",
+        &["1"],
+    );
+}
+
+#[test]
+fn untyped_values_are_rejected() {
+    let err = text("def f(x, y: int) { return x; } print f(1, 2);", 0).unwrap_err();
+    assert_eq!(
+        err,
+        "Parameter 'x' of 'f(x, y)' needs a type annotation for the wasm target"
+    );
+
+    let module = common::module("def f() { return nil; }\nlet a = 1 < 2 ? f() : 2;", 0);
+    let err = generate(&module).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "The wasm target cannot represent %3 in '<main>' of type any"
+    );
+    assert_eq!(err.span.unwrap().to_string(), "2:19");
+
+    let err = text("class A {} print A();", 0).unwrap_err();
+    assert_eq!(err, "Class 'A' is not supported by the wasm target");
+
+    let err = text("def f() { try { print 1; } finally { print 2; } }", 0).unwrap_err();
+    assert_eq!(err, "'try' in 'f' is not supported by the wasm target");
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use std::process::{Command, Output};
+
+use common::TempDir;
+use mini_comp::backend::x86_64::generate;
+
+fn assembly(source: &str, level: u32) -> Result<String, String> {
+    generate(&common::module(source, level)).map_err(|err| err.to_string())
+}
+
+/// Assembles and links `source` with the system C compiler and runs it.
+fn run(name: &str, source: &str, level: u32) -> Output {
+    let dir = TempDir::new(&format!("x86_64-{}-{}", name, level));
+    let asm_file = dir.path.join("program.s");
+    let binary = dir.path.join("program");
+    std::fs::write(&asm_file, assembly(source, level).unwrap()).unwrap();
+
+    common::compile(
+        Command::new("cc")
+            .args(["-Wa,--fatal-warnings", "-o"])
+            .arg(&binary)
+            .arg(&asm_file),
+    );
+    Command::new(&binary).output().unwrap()
+}
+
+fn stdout(name: &str, source: &str) -> String {
+    common::stdout(|level| run(name, source, level))
+}
+
+#[test]
+#[cfg_attr(not(has_x86_64_cc), ignore = "needs a C compiler for x86-64 Linux")]
+fn arithmetic_and_comparisons() {
+    let out = stdout(
+        "arithmetic",
+        "print 1 + 2 * 3; print -7 / 2; print -7 % 3; print 2 ** 10; print (-1) ** 3;
+         print 1 << 4; print -16 >> 2; print ~5; print 6 & 3 | 8 ^ 1;
+         print 1 < 2; print 2 <= 1; print nil == nil; print true != false; print 1 == true;
+         print nil; print !nil;",
+    );
+    assert_eq!(
+        out,
+        "7\n-3\n-1\n1024\n-1\n16\n-4\n-6\n11\ntrue\nfalse\ntrue\ntrue\nfalse\nnil\ntrue\n"
+    );
+}
+
+#[test]
+#[cfg_attr(not(has_x86_64_cc), ignore = "needs a C compiler for x86-64 Linux")]
+fn functions_and_loops() {
+    let out = stdout(
+        "functions",
+        "def fib(n: int) -> int {
+             if (n < 2) { return n; }
+             return fib(n - 1) + fib(n - 2);
+         }
+         def add(a: int, b: int, c: int, d: int, e: int, f: int) -> int {
+             return a + b + c + d + e + f;
+         }
+         let total = 0;
+         for (let i = 0; i < 10; i++) { total = total + fib(i); }
+         print total;
+         print add(1, 2, 3, 4, 5, 6);",
+    );
+    assert_eq!(out, "88\n21\n");
+}
+
+#[test]
+#[cfg_attr(not(has_x86_64_cc), ignore = "needs a C compiler for x86-64 Linux")]
+fn runtime_errors_exit_with_70() {
+    let output = run(
+        "error",
+        "print 1; print 9223372036854775807 + 1; print 2;",
+        0,
+    );
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Runtime error: Integer overflow in '+'\n"
+    );
+}
+
+#[test]
+fn unsupported_programs_are_rejected() {
+    assert_eq!(
+        assembly("print 1.5;", 0).unwrap_err(),
+        "The x86-64 target cannot represent %0 in '<main>' of type float"
+    );
+    assert_eq!(
+        assembly("class A {}", 0).unwrap_err(),
+        "Class 'A' is not supported by the x86-64 target"
+    );
+    assert_eq!(
+        assembly("throw 1;", 0).unwrap_err(),
+        "'throw' in '<main>' is not supported by the x86-64 target"
+    );
+}
+
+#[test]
+#[cfg_attr(not(has_x86_64_cc), ignore = "needs a C compiler for x86-64 Linux")]
+fn globals_are_undefined_until_assigned() {
+    let output = run(
+        "undefined",
+        "def f() -> int { return later; } print 1; print f(); let later = 2;",
+        0,
+    );
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Runtime error: Undefined variable 'later'\n"
+    );
+
+    let out = stdout(
+        "defined",
+        "let early = 1; def f() -> int { return early; } print f();",
+    );
+    assert_eq!(out, "1\n");
+}
+
+#[test]
+#[cfg_attr(not(has_x86_64_cc), ignore = "needs a C compiler for x86-64 Linux")]
+fn negative_exponents_are_errors() {
+    let output = run(
+        "exponent",
+        "def f(x: int) -> int { return 2 ** x; } print f(-1);",
+        2,
+    );
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Runtime error: Negative exponent in '**'\n"
+    );
+}